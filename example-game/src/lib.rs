@@ -0,0 +1,169 @@
+/*
+    example-game - a toy game on top of the names pallet
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! An example pallet for a toy game that is played through names, in the
+//! style of the [Xaya](https://xaya.io/) "mover" game.  It shows how game
+//! developers can build on the names pallet, and doubles as an integration
+//! test for its [operation hook](names::hook::OnNameOperation).
+//!
+//! Each name inside the [game's namespace](Trait::Namespace) (e.g. `p/domob`)
+//! is a player on an infinite grid.  Players send moves by updating their
+//! name's value to JSON like `{"d": "k", "n": 5}` (see [`moves`]).  The
+//! pallet receives all name operations through the hook, and stores the
+//! direction and number of steps for valid moves.  At the end of each block,
+//! every moving player goes one step.  Invalid moves are ignored, just like
+//! a Xaya game would.
+//!
+//! The pallet has no extrinsics of its own; all interaction happens through
+//! the names pallet.  To use it, set it as `OnNameOperation` hook of the
+//! names pallet in the runtime.
+
+use frame_support::{
+    decl_event, decl_module, decl_storage,
+    traits::Get,
+};
+use codec::{Decode, Encode};
+use names::{NameData, OperationType};
+use names::hook::OnNameOperation;
+use sp_std::prelude::*;
+
+pub mod moves;
+
+use moves::Direction;
+
+/// The pallet's configuration trait.
+pub trait Trait: names::Trait<Name = Vec<u8>, Value = Vec<u8>> {
+
+    /// The overarching event type.
+    type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+
+    /// Prefix of the names that are players of the game.
+    type Namespace: Get<&'static [u8]>;
+
+    /// Maximum number of steps in a single move.
+    type MaxSteps: Get<u32>;
+
+}
+
+/// The game state of a player.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Default, Encode, Eq, PartialEq)]
+pub struct PlayerState {
+    /// The player's x coordinate.
+    pub x: i64,
+    /// The player's y coordinate.
+    pub y: i64,
+    /// The direction the player is moving in, if any.
+    pub direction: Option<Direction>,
+    /// Remaining steps of the current move.
+    pub steps_left: u32,
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as ExampleGame {
+        /// The state of all players that have made a valid move, by the
+        /// full name (including the namespace).
+        Players get(player): map Vec<u8> => Option<PlayerState>;
+        /// The names of all players that are currently moving.
+        Moving get(moving): Vec<Vec<u8>>;
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        fn deposit_event() = default;
+
+        /// Moves all players by one step at the end of a block.
+        fn on_finalize(_h: T::BlockNumber) {
+            Self::step_players();
+        }
+
+    }
+}
+
+impl<T: Trait> Module<T> {
+
+    /// Moves each moving player one step in their direction.
+    fn step_players() {
+        let mut still_moving = Vec::new();
+        for name in <Moving>::take() {
+            let mut player = match Self::player(&name) {
+                None => continue,
+                Some(p) => p,
+            };
+            let direction = match player.direction {
+                None => continue,
+                Some(d) => d,
+            };
+
+            let (dx, dy) = direction.offset();
+            player.x = player.x.saturating_add(dx);
+            player.y = player.y.saturating_add(dy);
+            player.steps_left = player.steps_left.saturating_sub(1);
+            if player.steps_left == 0 {
+                player.direction = None;
+                Self::deposit_event(Event::PlayerStopped(name.clone(), player.x, player.y));
+            } else {
+                still_moving.push(name.clone());
+            }
+            <Players>::insert(&name, player);
+        }
+        <Moving>::put(still_moving);
+    }
+
+}
+
+impl<T: Trait> OnNameOperation<T> for Module<T> {
+    fn on_name_operation(operation: OperationType, name: &T::Name, data: &NameData<T>) {
+        /* Transfers keep the value, so they would just repeat the last move
+           of the player.  Only explicit value updates are moves.  */
+        if operation == OperationType::Transfer || !name.starts_with(T::Namespace::get()) {
+            return;
+        }
+        let mv = match moves::parse_move(&data.value, T::MaxSteps::get()) {
+            None => return,
+            Some(mv) => mv,
+        };
+
+        let mut player = Self::player(name).unwrap_or_default();
+        if player.direction.is_none() {
+            <Moving>::mutate(|moving| moving.push(name.clone()));
+        }
+        player.direction = Some(mv.direction);
+        player.steps_left = mv.steps;
+        <Players>::insert(name, player);
+
+        Self::deposit_event(Event::MoveAccepted(name.clone(), mv.direction, mv.steps));
+    }
+}
+
+decl_event!(
+    pub enum Event {
+        /// A player sent a valid move with the given direction and
+        /// number of steps.
+        MoveAccepted(Vec<u8>, Direction, u32),
+        /// A player has finished its move at the given coordinates.
+        PlayerStopped(Vec<u8>, i64, i64),
+    }
+);
+
+/// Module with unit tests.
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+/*
+    example-game - a toy game on top of the names pallet
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Parsing of moves, which are the JSON values of player names.  A move
+//! has the form `{"d": "k", "n": 5}`, where `d` is the direction (as one of
+//! the `vi` keys `hjklyubn`) and `n` the number of steps.
+
+use codec::{Decode, Encode};
+use serde::Deserialize;
+
+/// One of the eight directions a player can move in.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+
+    /// Parses a direction from its `vi` key.
+    pub fn from_key(key: &str) -> Option<Direction> {
+        Some(match key {
+            "h" => Direction::Left,
+            "j" => Direction::Down,
+            "k" => Direction::Up,
+            "l" => Direction::Right,
+            "y" => Direction::UpLeft,
+            "u" => Direction::UpRight,
+            "b" => Direction::DownLeft,
+            "n" => Direction::DownRight,
+            _ => return None,
+        })
+    }
+
+    /// Returns the change in x and y coordinates of a single step.
+    pub fn offset(self) -> (i64, i64) {
+        match self {
+            Direction::Left => (-1, 0),
+            Direction::Down => (0, -1),
+            Direction::Up => (0, 1),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, 1),
+            Direction::UpRight => (1, 1),
+            Direction::DownLeft => (-1, -1),
+            Direction::DownRight => (1, -1),
+        }
+    }
+
+}
+
+/// A valid move of a player.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Move {
+    /// The direction to move in.
+    pub direction: Direction,
+    /// The number of steps, which is at least one.
+    pub steps: u32,
+}
+
+/// The move as it appears in JSON.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawMove<'a> {
+    d: &'a str,
+    n: u32,
+}
+
+/// Parses a name value as move.  Returns `None` if the value is not a
+/// valid move, e.g. because it is not JSON, has an unknown direction or
+/// a number of steps that is zero or larger than `max_steps`.
+pub fn parse_move(value: &[u8], max_steps: u32) -> Option<Move> {
+    let raw: RawMove = serde_json::from_slice(value).ok()?;
+    if raw.n == 0 || raw.n > max_steps {
+        return None;
+    }
+    Some(Move {
+        direction: Direction::from_key(raw.d)?,
+        steps: raw.n,
+    })
+}
@@ -0,0 +1,428 @@
+/*
+    example-game - a toy game on top of the names pallet
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+use super::*;
+use crate::moves::{Direction, Move, parse_move};
+
+use frame_support::{
+    assert_ok, impl_outer_event, impl_outer_origin, parameter_types,
+    traits::Randomness,
+    weights::Weight,
+};
+use names::policy::{Composed, FlatFee, NeverExpire};
+use sp_core::H256;
+use sp_runtime::{
+    ModuleId, Perbill,
+    testing::{Header, TestSignature, UintAuthorityId},
+    traits::{BlakeTwo256, Hash, IdentityLookup, OnFinalize},
+};
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+    pub const ExistentialDeposit: u128 = 1000;
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const Namespace: &'static [u8] = b"p/";
+    pub const MaxSteps: u32 = 10;
+    pub const ZeroFee: u128 = 0;
+    pub const HiddenRegistrationFee: u128 = 0;
+    pub const HiddenRegistrationPeriod: u64 = 1;
+    pub const MaxBulkTransfer: u32 = 10;
+    pub const MaxSwapNames: u32 = 10;
+    pub const MaxForceRegistrations: u32 = 3;
+    pub const StatsEraLength: u64 = 100;
+    pub const StatsHistory: u32 = 10;
+    pub const FreeUpdatesPerEra: u32 = 0;
+    pub const MaxTemplates: u32 = 5;
+    pub const ReceiptRetention: u64 = 100;
+    pub const MaxPayoutSplits: u32 = 5;
+    pub const MaxShareHolders: u32 = 10;
+    pub const MigrationWeightLimit: Weight = 1_000_000;
+    pub const DeferredWeightLimit: Weight = 100_000;
+    pub const CommunityAccount: u64 = 777;
+    pub const NamesModuleId: ModuleId = ModuleId(*b"py/names");
+    pub const ExpirationBucketSize: u64 = 1;
+    pub const MaxExpirationsPerBlock: u32 = 10;
+    pub const MaxAutoExpirations: Option<u32> = None;
+    pub const SweepBounty: u128 = 0;
+    pub const MaxValueLookahead: u64 = 100;
+    pub const StrictTransfers: bool = false;
+    pub const ValueDepositPerByte: u128 = 0;
+    pub const MinExpirationBlocks: u64 = 1;
+    pub const ExpirationUpdates: names::ExpirationUpdate = names::ExpirationUpdate::MayShorten;
+    pub const ComplianceWindow: Option<u64> = None;
+    pub const NonComplianceAction: names::ComplianceAction = names::ComplianceAction::Warn;
+    pub const WatchDeposit: u128 = 0;
+    pub const MaxWatchers: u32 = 0;
+    pub const MaxSubvalues: u32 = 0;
+    pub const SubvalueDeposit: u128 = 0;
+    pub const NoteDeposit: u128 = 0;
+    pub const MaxNoteSize: u32 = 64;
+    pub const MaxRedirectDepth: u32 = 4;
+    pub const EnvelopeFee: u128 = 0;
+    pub const MaxEnvelopeSize: u32 = 256;
+    pub const MaxEnvelopes: u32 = 10;
+    pub const MinPreorderDeposit: u128 = 0;
+    pub const PreorderMaturity: u64 = 1;
+    pub const PreorderWindow: u64 = 1;
+    pub const PreorderForfeit: Perbill = Perbill::from_percent(0);
+    pub const FirstUpdatePeriod: Option<u64> = None;
+    pub const FirstUpdateRefund: Perbill = Perbill::from_percent(50);
+    pub const ExpirationQuarantine: u64 = 0;
+    pub const RegistrarCommission: Perbill = Perbill::from_percent(10);
+    pub const MinBackorderBond: u128 = 0;
+    pub const MaxFeeRateAge: u64 = 10;
+    pub const ReportBond: u128 = 500;
+    pub const ReportChallengePeriod: u64 = 10;
+    pub const ArchiveRetention: Option<u64> = None;
+    pub const GuardianConfirmationPeriod: u64 = 10;
+    pub const MaxBackorders: u32 = 10;
+    pub const MaxBulkRenewals: u32 = 3;
+    pub const MaxTopicPrefixes: u32 = 2;
+    pub const MaxDelegatedNamespaces: u32 = 2;
+    pub const MaxSessionDuration: u64 = 100;
+    pub const MaxSessionNames: u32 = 2;
+    pub const MaxInsuredRenewals: u32 = 3;
+    pub const RegistrationIntentsOnly: bool = false;
+    pub const IntentBond: u128 = 10;
+    pub const MaxIntentsPerName: u32 = 10;
+    pub const MaxTotalNames: Option<u32> = None;
+    pub const MaxRegistrationsPerBlock: Option<u32> = None;
+    pub const CongestionMultipliers: &'static [(u32, u32)] = &[];
+}
+impl system::Trait for Test {
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = TestEvent;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+}
+impl balances::Trait for Test {
+    type Balance = u128;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = TestEvent;
+    type DustRemoval = ();
+    type TransferPayment = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type TransferFee = ();
+    type CreationFee = ();
+}
+
+mod game {
+    pub use crate::Event;
+}
+impl_outer_event! {
+    pub enum TestEvent for Test {
+        balances<T>,
+        names<T>,
+        game,
+    }
+}
+
+/// Randomness for testing, which is just a hash of the subject.
+pub struct TestRandomness;
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        BlakeTwo256::hash(subject)
+    }
+}
+
+impl names::Trait for Test {
+
+    type Name = Vec<u8>;
+    type Value = Vec<u8>;
+    type DefaultValue = ();
+
+    type Currency = Balances;
+    type Event = TestEvent;
+
+    type Namehash = ();
+
+    type HiddenRegistrationFee = HiddenRegistrationFee;
+    type HiddenRegistrationPeriod = HiddenRegistrationPeriod;
+
+    type Identity = ();
+    type ForceOrigin = system::EnsureRoot<u64>;
+    type ReportOrigin = system::EnsureRoot<u64>;
+    type Namespaces = names::namespace::BytePrefix;
+    type CommunityAccount = CommunityAccount;
+    type ModuleId = NamesModuleId;
+    type Call = names::Call<Test>;
+    type NameCallFilter = ();
+    type MaxShareHolders = MaxShareHolders;
+    type MaxPayoutSplits = MaxPayoutSplits;
+    type MaxBulkTransfer = MaxBulkTransfer;
+    type MaxSwapNames = MaxSwapNames;
+    type MaxForceRegistrations = MaxForceRegistrations;
+    type MaxTotalNames = MaxTotalNames;
+    type MaxRegistrationsPerBlock = MaxRegistrationsPerBlock;
+    type CongestionMultipliers = CongestionMultipliers;
+    type Randomness = TestRandomness;
+    type StrictTransfers = StrictTransfers;
+    type RegistrationIntentsOnly = RegistrationIntentsOnly;
+    type IntentBond = IntentBond;
+    type MaxIntentsPerName = MaxIntentsPerName;
+
+    type MinPreorderDeposit = MinPreorderDeposit;
+    type PreorderMaturity = PreorderMaturity;
+    type PreorderWindow = PreorderWindow;
+    type PreorderForfeit = PreorderForfeit;
+    type FirstUpdatePeriod = FirstUpdatePeriod;
+    type FirstUpdateRefund = FirstUpdateRefund;
+    type ExpirationQuarantine = ExpirationQuarantine;
+    type RegistrarCommission = RegistrarCommission;
+    type MinBackorderBond = MinBackorderBond;
+    type ReportBond = ReportBond;
+    type ReportChallengePeriod = ReportChallengePeriod;
+    type ArchiveRetention = ArchiveRetention;
+    type GuardianConfirmationPeriod = GuardianConfirmationPeriod;
+    type MaxBackorders = MaxBackorders;
+    type MaxBulkRenewals = MaxBulkRenewals;
+    type MaxTopicPrefixes = MaxTopicPrefixes;
+    type MaxDelegatedNamespaces = MaxDelegatedNamespaces;
+    type MaxSessionDuration = MaxSessionDuration;
+    type MaxSessionNames = MaxSessionNames;
+    type MaxInsuredRenewals = MaxInsuredRenewals;
+
+    type Policy = Composed<FlatFee<ZeroFee, ZeroFee>, NeverExpire, ()>;
+    type ExtraWeight = ();
+    type WeightInfo = ();
+    type FeeConverter = ();
+    type Payment = ();
+    type MaxFeeRateAge = MaxFeeRateAge;
+    type MinExpirationBlocks = MinExpirationBlocks;
+    type ExpirationUpdates = ExpirationUpdates;
+    type ComplianceWindow = ComplianceWindow;
+    type NonComplianceAction = NonComplianceAction;
+    type MaxExpirationsPerBlock = MaxExpirationsPerBlock;
+    type MaxAutoExpirations = MaxAutoExpirations;
+    type SweepBounty = SweepBounty;
+    type MaxValueLookahead = MaxValueLookahead;
+    type ExpirationBucketSize = ExpirationBucketSize;
+    type ValueDepositPerByte = ValueDepositPerByte;
+    type OnOwnerReaped = ();
+    type OnExpiration = ();
+    type ValueCompliance = ();
+    type OnNameOperation = Game;
+    type Signature = TestSignature;
+    type Signer = UintAuthorityId;
+    type Patch = ();
+    type SubKey = Vec<u8>;
+    type MaxSubvalues = MaxSubvalues;
+    type SubvalueDeposit = SubvalueDeposit;
+    type NoteDeposit = NoteDeposit;
+    type MaxNoteSize = MaxNoteSize;
+    type MaxRedirectDepth = MaxRedirectDepth;
+    type EnvelopeFee = EnvelopeFee;
+    type MaxEnvelopeSize = MaxEnvelopeSize;
+    type MaxEnvelopes = MaxEnvelopes;
+    type WatchDeposit = WatchDeposit;
+    type MaxWatchers = MaxWatchers;
+    type Templates = ();
+    type MaxTemplates = MaxTemplates;
+    type Migration = ();
+    type MigrationWeightLimit = MigrationWeightLimit;
+    type DeferredWeightLimit = DeferredWeightLimit;
+    type ReceiptRetention = ReceiptRetention;
+    type StatsEraLength = StatsEraLength;
+    type StatsHistory = StatsHistory;
+    type FreeUpdatesPerEra = FreeUpdatesPerEra;
+
+}
+
+impl Trait for Test {
+    type Event = TestEvent;
+    type Namespace = Namespace;
+    type MaxSteps = MaxSteps;
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+    system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
+
+type Balances = balances::Module<Test>;
+type Names = names::Module<Test>;
+type Game = Module<Test>;
+type System = system::Module<Test>;
+
+/// Ends the current block (which moves the players) and starts
+/// the next one.
+fn next_block() {
+    Game::on_finalize(System::block_number());
+    System::set_block_number(System::block_number() + 1);
+}
+
+/// Returns the x and y coordinates of a player.
+fn position(name: &[u8]) -> (i64, i64) {
+    let p = Game::player(name.to_vec()).expect("player does not exist");
+    (p.x, p.y)
+}
+
+mod parsing {
+    use super::*;
+
+    #[test]
+    fn valid_moves() {
+        assert_eq!(parse_move(br#"{"d": "k", "n": 5}"#, 10), Some(Move {
+            direction: Direction::Up,
+            steps: 5,
+        }));
+        assert_eq!(parse_move(br#"{"n":10,"d":"b"}"#, 10), Some(Move {
+            direction: Direction::DownLeft,
+            steps: 10,
+        }));
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let max = 10;
+        assert_eq!(parse_move(b"", max), None);
+        assert_eq!(parse_move(b"not json", max), None);
+        assert_eq!(parse_move(br#"[1, 2]"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "x", "n": 1}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "kk", "n": 1}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "k"}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "k", "n": 0}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "k", "n": 11}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "k", "n": -1}"#, max), None);
+        assert_eq!(parse_move(br#"{"d": "k", "n": 1, "x": 0}"#, max), None);
+    }
+
+}
+
+mod game_play {
+    use super::*;
+
+    #[test]
+    fn moving() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     br#"{"d": "l", "n": 2}"#.to_vec()));
+            assert_eq!(Game::moving(), vec![b"p/domob".to_vec()]);
+            assert_eq!(position(b"p/domob"), (0, 0));
+
+            next_block();
+            assert_eq!(position(b"p/domob"), (1, 0));
+            next_block();
+            assert_eq!(position(b"p/domob"), (2, 0));
+            assert_eq!(Game::moving(), Vec::<Vec<u8>>::new());
+            assert_eq!(Game::player(b"p/domob".to_vec()).unwrap().direction, None);
+
+            next_block();
+            assert_eq!(position(b"p/domob"), (2, 0));
+
+            let events: Vec<_> = System::events().into_iter().map(|r| r.event).collect();
+            assert!(events.contains(&TestEvent::game(Event::MoveAccepted(
+                b"p/domob".to_vec(), Direction::Right, 2))));
+            assert!(events.contains(&TestEvent::game(Event::PlayerStopped(
+                b"p/domob".to_vec(), 2, 0))));
+        });
+    }
+
+    #[test]
+    fn new_move_replaces_current() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     br#"{"d": "k", "n": 5}"#.to_vec()));
+            next_block();
+            assert_eq!(position(b"p/domob"), (0, 1));
+
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     br#"{"d": "b", "n": 1}"#.to_vec()));
+            assert_eq!(Game::moving(), vec![b"p/domob".to_vec()]);
+            next_block();
+            assert_eq!(position(b"p/domob"), (-1, 0));
+            assert_eq!(Game::moving(), Vec::<Vec<u8>>::new());
+        });
+    }
+
+    #[test]
+    fn invalid_moves_ignored() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     br#"{"d": "j", "n": 3}"#.to_vec()));
+            next_block();
+
+            /* The name is updated, but the game state is not.  */
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     b"invalid".to_vec()));
+            assert_eq!(Names::lookup(b"p/domob".to_vec()).unwrap().value, b"invalid".to_vec());
+            next_block();
+            assert_eq!(position(b"p/domob"), (0, -2));
+
+            assert_ok!(Names::update(Origin::signed(2), b"p/andy".to_vec(),
+                                     br#"{"d": "j", "n": 100}"#.to_vec()));
+            assert_eq!(Game::player(b"p/andy".to_vec()), None);
+        });
+    }
+
+    #[test]
+    fn outside_namespace() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            assert_ok!(Names::update(Origin::signed(1), b"x/domob".to_vec(),
+                                     br#"{"d": "k", "n": 1}"#.to_vec()));
+            assert_eq!(Game::player(b"x/domob".to_vec()), None);
+            assert_eq!(Game::moving(), Vec::<Vec<u8>>::new());
+        });
+    }
+
+    #[test]
+    fn transferred_players() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(1);
+            assert_ok!(Names::update(Origin::signed(1), b"p/domob".to_vec(),
+                                     br#"{"d": "h", "n": 1}"#.to_vec()));
+            next_block();
+
+            /* The player keeps its state when the name is transferred, and
+               the new owner can move it.  */
+            assert_ok!(Names::transfer(Origin::signed(1), b"p/domob".to_vec(), 2));
+            assert_eq!(position(b"p/domob"), (-1, 0));
+            assert_eq!(Game::moving(), Vec::<Vec<u8>>::new());
+            assert_ok!(Names::update(Origin::signed(2), b"p/domob".to_vec(),
+                                     br#"{"d": "y", "n": 1}"#.to_vec()));
+            next_block();
+            assert_eq!(position(b"p/domob"), (-2, 1));
+        });
+    }
+
+}
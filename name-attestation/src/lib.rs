@@ -0,0 +1,113 @@
+/*
+    name-attestation - verify ownership attestations of Substrate names
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Verification of ownership attestations exported by the `names` pallet
+//! through its runtime API.  An attestation is checked against a storage
+//! proof for the block's state root, so that it can be trusted based on
+//! a known block header alone.
+
+use codec::{Decode, Encode};
+use sp_core::{blake2_256, Blake2Hasher, H256};
+use sp_state_machine::{read_proof_check, StorageProof};
+
+/// Prefix of the storage keys in the name database of the names pallet.
+const NAMES_PREFIX: &[u8] = b"TemplateModule Names";
+
+/// Returns the storage key of a name's entry in the name database of the
+/// names pallet.  This is the same as its `name_storage_key` runtime API.
+pub fn names_storage_key<Name: Encode>(name: &Name) -> Vec<u8> {
+    let mut data = NAMES_PREFIX.to_vec();
+    name.encode_to(&mut data);
+    blake2_256(&data).to_vec()
+}
+
+/// An attestation that an account owns a name at some block.  This has
+/// the same encoding as the `OwnershipAttestation` of the names pallet.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+pub struct Attestation<Name, AccountId, BlockNumber> {
+    /// The attested name.
+    pub name: Name,
+    /// The account owning the name.
+    pub owner: AccountId,
+    /// The block at which the name is owned by the account.
+    pub block: BlockNumber,
+    /// The storage key of the name's entry in the name database.
+    pub storage_key: Vec<u8>,
+}
+
+impl<Name: Decode, AccountId: Decode, BlockNumber: Decode>
+    Attestation<Name, AccountId, BlockNumber>
+{
+
+    /// Decodes an attestation as returned by the runtime API.
+    pub fn decode_exported(data: &[u8]) -> Result<Self, &'static str> {
+        Self::decode(&mut &data[..]).map_err(|_| "invalid attestation encoding")
+    }
+
+}
+
+/// Verifies an attestation against the state root of its block, using
+/// a storage proof (as list of trie nodes) for the attestation's key.
+/// `Value` is the type of name values on the chain, which is needed to
+/// decode the name's stored data.  The storage key is not trusted; it
+/// must match the key computed from the attested name.
+pub fn verify<Name, AccountId, BlockNumber, Value>(
+    attestation: &Attestation<Name, AccountId, BlockNumber>,
+    state_root: H256, proof: Vec<Vec<u8>>) -> Result<(), &'static str>
+    where Name: Encode, AccountId: Decode + Eq, Value: Decode
+{
+    let storage_key = names_storage_key(&attestation.name);
+    if storage_key != attestation.storage_key {
+        return Err("storage key does not belong to the name");
+    }
+    let owner = proven_owner::<AccountId, Value>(&storage_key, state_root, proof)?
+        .ok_or("name does not exist in the proven state")?;
+    if owner != attestation.owner {
+        return Err("name is owned by a different account");
+    }
+    Ok(())
+}
+
+/// Determines the owner of a name from a storage proof for the name's
+/// storage key (as returned by the `name_storage_key` runtime API) against
+/// the state root of some block.  Returns `None` if the proof shows that
+/// the name did not exist at that block.  This can be used to check
+/// answers of the `owner_at` runtime API for historical blocks.
+pub fn proven_owner<AccountId, Value>(storage_key: &[u8], state_root: H256,
+                                      proof: Vec<Vec<u8>>)
+    -> Result<Option<AccountId>, &'static str>
+    where AccountId: Decode, Value: Decode
+{
+    let values = read_proof_check::<Blake2Hasher, _>(state_root, StorageProof::new(proof),
+                                                     &[storage_key])
+        .map_err(|_| "invalid storage proof")?;
+    let data = match values.get(storage_key) {
+        Some(Some(data)) => data,
+        Some(None) => return Ok(None),
+        None => return Err("storage proof does not cover the name"),
+    };
+
+    /* The stored name data starts with the value, followed by the owner.  */
+    let (_, owner) = <(Value, AccountId)>::decode(&mut &data[..])
+        .map_err(|_| "invalid name data in the proven state")?;
+    Ok(Some(owner))
+}
+
+/// Module with unit tests.
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,88 @@
+/*
+    name-attestation - verify ownership attestations of Substrate names
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use super::*;
+
+use sp_state_machine::{prove_read, Backend, TestExternalities};
+
+type TestAttestation = Attestation<Vec<u8>, u64, u32>;
+
+/// Builds a state with the given names (and their owners) in the name
+/// database, and returns its root together with a storage proof for the
+/// given key.
+fn prove(names: &[(&[u8], u64)], key: &[u8]) -> (H256, Vec<Vec<u8>>) {
+    let mut ext = TestExternalities::<Blake2Hasher>::default();
+    for (name, owner) in names {
+        let value: Vec<u8> = b"value".to_vec();
+        ext.insert(names_storage_key(&name.to_vec()), (value, *owner).encode());
+    }
+    let backend = ext.as_backend();
+    let root = backend.storage_root(std::iter::empty()).0;
+    let proof = prove_read(backend, &[key]).expect("failed to create storage proof");
+    (root, proof.iter_nodes().collect())
+}
+
+fn attestation() -> TestAttestation {
+    Attestation {
+        name: b"foo".to_vec(),
+        owner: 42,
+        block: 10,
+        storage_key: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn decode_exported() {
+    let att = attestation();
+    assert_eq!(TestAttestation::decode_exported(&att.encode()), Ok(att.clone()));
+    assert!(TestAttestation::decode_exported(&[1, 2]).is_err());
+}
+
+#[test]
+fn empty_proof() {
+    let res = verify::<_, _, _, Vec<u8>>(&attestation(), H256::repeat_byte(1), vec![]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn verify_with_proof() {
+    let mut att = attestation();
+    att.storage_key = names_storage_key(&att.name);
+    let (root, proof) = prove(&[(b"foo", 42), (b"bar", 5)], &att.storage_key);
+    assert_eq!(verify::<_, _, _, Vec<u8>>(&att, root, proof.clone()), Ok(()));
+
+    att.owner = 5;
+    assert_eq!(verify::<_, _, _, Vec<u8>>(&att, root, proof),
+               Err("name is owned by a different account"));
+}
+
+#[test]
+fn key_of_other_name() {
+    /* The attacker owns "bar" and tries to use its proof to claim "foo".  */
+    let mut att = attestation();
+    att.storage_key = names_storage_key(&b"bar".to_vec());
+    let (root, proof) = prove(&[(b"foo", 5), (b"bar", 42)], &att.storage_key);
+    assert_eq!(verify::<_, _, _, Vec<u8>>(&att, root, proof),
+               Err("storage key does not belong to the name"));
+}
+
+#[test]
+fn proven_owner_empty_proof() {
+    let res = proven_owner::<u64, Vec<u8>>(&[1, 2, 3], H256::repeat_byte(1), vec![]);
+    assert!(res.is_err());
+}
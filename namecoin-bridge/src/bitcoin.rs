@@ -0,0 +1,529 @@
+/*
+    namecoin-bridge - mirror Namecoin names into a Substrate chain
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Parsing of the (Bitcoin-derived) Namecoin data structures that are
+//! needed for SPV proofs:  Block headers (including merge-mining data),
+//! transactions and name scripts.  Also the consensus rules for verifying
+//! the proof-of-work and difficulty of headers.
+
+use sp_core::{H256, U256};
+use sp_io::hashing::sha2_256;
+use sp_std::prelude::*;
+
+/// The auxpow chain ID of Namecoin.
+pub const AUXPOW_CHAIN_ID: u32 = 1;
+
+/// Number of blocks between difficulty retargets.
+pub const RETARGET_INTERVAL: u32 = 2016;
+
+/// Targeted duration (in seconds) of a retarget interval.
+pub const TARGET_TIMESPAN: i64 = 14 * 24 * 60 * 60;
+
+/// Version bit that marks a block header as merge-mined with auxpow data.
+const VERSION_AUXPOW: u32 = 1 << 8;
+
+/// Marker that precedes the chain Merkle root in a parent coinbase.
+const MERGED_MINING_HEADER: [u8; 4] = [0xfa, 0xbe, b'm', b'm'];
+
+/// Maximum length of the chain Merkle branch in auxpow data.
+const MAX_CHAIN_MERKLE_BRANCH: usize = 30;
+
+/// Opcodes used in name scripts.
+const OP_NAME_FIRSTUPDATE: u8 = 0x52;
+const OP_NAME_UPDATE: u8 = 0x53;
+const OP_2DROP: u8 = 0x6d;
+const OP_DROP: u8 = 0x75;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+
+/// Computes the double-SHA256 hash used for blocks and transactions.
+pub fn double_sha256(data: &[u8]) -> H256 {
+    H256::from(sha2_256(&sha2_256(data)))
+}
+
+/// Helper for reading serialised data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+
+    fn read(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        if self.data.len() < n {
+            return Err("unexpected end of data");
+        }
+        let (res, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(res)
+    }
+
+    fn read_le(&mut self, n: usize) -> Result<u64, &'static str> {
+        let bytes = self.read(n)?;
+        Ok(bytes.iter().rev().fold(0, |acc, b| (acc << 8) | (*b as u64)))
+    }
+
+    fn read_varint(&mut self) -> Result<u64, &'static str> {
+        match self.read(1)?[0] {
+            0xfd => self.read_le(2),
+            0xfe => self.read_le(4),
+            0xff => self.read_le(8),
+            n => Ok(n as u64),
+        }
+    }
+
+    fn read_var_bytes(&mut self) -> Result<&'a [u8], &'static str> {
+        let len = self.read_varint()?;
+        self.read(len as usize)
+    }
+
+    fn read_hash(&mut self) -> Result<H256, &'static str> {
+        Ok(H256::from_slice(self.read(32)?))
+    }
+
+    fn read_hashes(&mut self) -> Result<Vec<H256>, &'static str> {
+        let len = self.read_varint()?;
+        let mut res = Vec::new();
+        for _ in 0..len {
+            res.push(self.read_hash()?);
+        }
+        Ok(res)
+    }
+
+    /// Reads a transaction in non-witness serialisation.
+    fn read_transaction(&mut self) -> Result<Transaction<'a>, &'static str> {
+        let start = self.data;
+        self.read(4)?;
+
+        let num_in = self.read_varint()?;
+        if num_in == 0 {
+            return Err("witness serialisation is not supported");
+        }
+        let mut inputs = Vec::new();
+        for _ in 0..num_in {
+            self.read(36)?;
+            inputs.push(self.read_var_bytes()?);
+            self.read(4)?;
+        }
+
+        let mut outputs = Vec::new();
+        let num_out = self.read_varint()?;
+        for _ in 0..num_out {
+            self.read(8)?;
+            outputs.push(self.read_var_bytes()?);
+        }
+
+        self.read(4)?;
+        let raw = &start[..start.len() - self.data.len()];
+        Ok(Transaction {
+            txid: double_sha256(raw),
+            inputs: inputs,
+            outputs: outputs,
+        })
+    }
+
+}
+
+/// The parts of a transaction we need.
+struct Transaction<'a> {
+    /// The transaction's ID.
+    txid: H256,
+    /// The scripts of all inputs.
+    inputs: Vec<&'a [u8]>,
+    /// The scripts of all outputs.
+    outputs: Vec<&'a [u8]>,
+}
+
+/// The data we need from a block header.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Eq, PartialEq)]
+pub struct BlockHeader {
+    /// The block's hash.
+    pub hash: H256,
+    /// The block's version, which includes the auxpow flag and chain ID.
+    pub version: u32,
+    /// The hash of the previous block.
+    pub prev: H256,
+    /// The Merkle root of the block's transactions.
+    pub merkle_root: H256,
+    /// The block's timestamp.
+    pub time: u32,
+    /// The block's difficulty target in compact form.
+    pub bits: u32,
+    /// The merge-mining data, if the block is merge-mined.
+    pub auxpow: Option<Box<AuxPow>>,
+}
+
+impl BlockHeader {
+
+    /// Parses a block header.  If the header has the auxpow flag set in its
+    /// version, the 80 bytes of the pure header must be followed by the
+    /// merge-mining data.
+    pub fn parse(data: &[u8]) -> Result<BlockHeader, &'static str> {
+        if data.len() < 80 {
+            return Err("invalid block header size");
+        }
+        let mut reader = Reader { data: data };
+        let mut res = Self::parse_pure(reader.read(80)?)?;
+        if res.version & VERSION_AUXPOW != 0 {
+            res.auxpow = Some(Box::new(AuxPow::read(&mut reader)?));
+        }
+        if !reader.data.is_empty() {
+            return Err("trailing data after block header");
+        }
+        Ok(res)
+    }
+
+    /// Parses the 80 bytes of a pure block header.
+    fn parse_pure(data: &[u8]) -> Result<BlockHeader, &'static str> {
+        let mut reader = Reader { data: data };
+        Ok(BlockHeader {
+            hash: double_sha256(data),
+            version: reader.read_le(4)? as u32,
+            prev: reader.read_hash()?,
+            merkle_root: reader.read_hash()?,
+            time: reader.read_le(4)? as u32,
+            bits: reader.read_le(4)? as u32,
+            auxpow: None,
+        })
+    }
+
+    /// Verifies the header's proof-of-work against its difficulty target,
+    /// which must not be above the given limit (in compact form).  For
+    /// merge-mined blocks, this verifies the auxpow data and the
+    /// proof-of-work of the parent block.  Returns the target.
+    pub fn check_pow(&self, pow_limit: u32) -> Result<U256, &'static str> {
+        let target = compact_to_target(self.bits).ok_or("invalid difficulty target")?;
+        let limit = compact_to_target(pow_limit).ok_or("invalid proof-of-work limit")?;
+        if target > limit {
+            return Err("difficulty target is above the limit");
+        }
+
+        /* Version 1 blocks are from before merge-mining, all others must
+           carry our chain ID.  */
+        if self.version != 1 && self.version >> 16 != AUXPOW_CHAIN_ID {
+            return Err("block has the wrong chain ID");
+        }
+
+        let pow_hash = match &self.auxpow {
+            None => self.hash,
+            Some(auxpow) => {
+                auxpow.check(self.hash)?;
+                auxpow.parent.hash
+            },
+        };
+        if U256::from_little_endian(pow_hash.as_bytes()) > target {
+            return Err("insufficient proof of work");
+        }
+
+        Ok(target)
+    }
+
+}
+
+/// Merge-mining data of a block header, which proves that a block of some
+/// parent chain commits to the header and has enough proof-of-work.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Eq, PartialEq)]
+pub struct AuxPow {
+    /// The ID of the parent block's coinbase transaction.
+    pub coinbase_txid: H256,
+    /// The input script of the parent coinbase, which commits to the
+    /// chain Merkle root.
+    pub coinbase_script: Vec<u8>,
+    /// The Merkle branch of the coinbase in the parent block.
+    pub coinbase_branch: Vec<H256>,
+    /// The index of the coinbase in the parent block.
+    pub coinbase_index: u32,
+    /// The Merkle branch of our block in the chain Merkle tree.
+    pub chain_branch: Vec<H256>,
+    /// The index of our block in the chain Merkle tree.
+    pub chain_index: u32,
+    /// The (pure) header of the parent block.
+    pub parent: BlockHeader,
+}
+
+impl AuxPow {
+
+    /// Reads the auxpow data following a block header.
+    fn read(reader: &mut Reader) -> Result<AuxPow, &'static str> {
+        let coinbase = reader.read_transaction()?;
+        /* The hash of the parent block is redundant with the parent
+           header that follows.  */
+        reader.read_hash()?;
+        let coinbase_branch = reader.read_hashes()?;
+        let coinbase_index = reader.read_le(4)? as u32;
+        let chain_branch = reader.read_hashes()?;
+        let chain_index = reader.read_le(4)? as u32;
+        let parent = BlockHeader::parse_pure(reader.read(80)?)?;
+        Ok(AuxPow {
+            coinbase_txid: coinbase.txid,
+            coinbase_script: coinbase.inputs[0].to_vec(),
+            coinbase_branch: coinbase_branch,
+            coinbase_index: coinbase_index,
+            chain_branch: chain_branch,
+            chain_index: chain_index,
+            parent: parent,
+        })
+    }
+
+    /// Verifies that the parent block commits to the block with the given
+    /// hash.  This does not check the parent's proof-of-work.
+    fn check(&self, hash: H256) -> Result<(), &'static str> {
+        if self.coinbase_index != 0 {
+            return Err("auxpow is not a generate");
+        }
+        if self.parent.version >> 16 == AUXPOW_CHAIN_ID {
+            return Err("auxpow parent has our chain ID");
+        }
+        if self.chain_branch.len() > MAX_CHAIN_MERKLE_BRANCH {
+            return Err("auxpow chain merkle branch too long");
+        }
+
+        let (chain_root, _) = merkle_branch_root(hash, self.chain_index, &self.chain_branch);
+        let (coinbase_root, _) = merkle_branch_root(self.coinbase_txid, self.coinbase_index,
+                                                    &self.coinbase_branch);
+        if coinbase_root != self.parent.merkle_root {
+            return Err("auxpow merkle root incorrect");
+        }
+
+        /* The chain root is committed in the coinbase script in reversed
+           byte order, either right after the merged-mining header or (for
+           legacy parent blocks) within the first 20 bytes.  */
+        let mut root_bytes = chain_root.as_bytes().to_vec();
+        root_bytes.reverse();
+        let script = &self.coinbase_script;
+        let pos = find(script, &root_bytes)
+            .ok_or("auxpow missing chain merkle root in parent coinbase")?;
+        match find(script, &MERGED_MINING_HEADER) {
+            Some(head) => {
+                if find(&script[head + 1..], &MERGED_MINING_HEADER).is_some() {
+                    return Err("multiple merged mining headers in coinbase");
+                }
+                if head + MERGED_MINING_HEADER.len() != pos {
+                    return Err("merged mining header is not just before chain merkle root");
+                }
+            },
+            None => if pos > 20 {
+                return Err("chain merkle root must start in the first 20 bytes of the parent coinbase");
+            },
+        }
+
+        let mut reader = Reader { data: &script[pos + root_bytes.len()..] };
+        let missing = "auxpow missing chain merkle tree size and nonce in parent coinbase";
+        let size = reader.read_le(4).map_err(|_| missing)?;
+        let nonce = reader.read_le(4).map_err(|_| missing)? as u32;
+        if size != 1 << self.chain_branch.len() {
+            return Err("auxpow merkle branch size does not match parent coinbase");
+        }
+        if self.chain_index != expected_index(nonce, AUXPOW_CHAIN_ID, self.chain_branch.len()) {
+            return Err("auxpow wrong index");
+        }
+
+        Ok(())
+    }
+
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Computes the slot in a chain Merkle tree of the given height that
+/// a chain ID has to use for a given nonce.
+fn expected_index(nonce: u32, chain_id: u32, height: usize) -> u32 {
+    let mut rand = nonce;
+    rand = rand.wrapping_mul(1103515245).wrapping_add(12345);
+    rand = rand.wrapping_add(chain_id);
+    rand = rand.wrapping_mul(1103515245).wrapping_add(12345);
+    rand % (1 << height)
+}
+
+/// Decodes a difficulty target from its compact form.  Returns `None` if
+/// the encoded target is zero, negative or overflows.
+pub fn compact_to_target(bits: u32) -> Option<U256> {
+    let size = (bits >> 24) as usize;
+    let word = bits & 0x007f_ffff;
+    if word == 0 || bits & 0x0080_0000 != 0 {
+        return None;
+    }
+    let target = if size <= 3 {
+        U256::from(word >> (8 * (3 - size)))
+    } else {
+        if size > 34 || (word > 0xff && size > 33) || (word > 0xffff && size > 32) {
+            return None;
+        }
+        U256::from(word) << (8 * (size - 3))
+    };
+    if target.is_zero() {
+        return None;
+    }
+    Some(target)
+}
+
+/// Encodes a difficulty target in compact form.
+pub fn target_to_compact(target: U256) -> u32 {
+    let mut size = (target.bits() + 7) / 8;
+    let mut compact = (if size <= 3 {
+        target.low_u64() << (8 * (3 - size))
+    } else {
+        (target >> (8 * (size - 3))).low_u64()
+    }) as u32;
+    if compact & 0x0080_0000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+    compact | (size as u32) << 24
+}
+
+/// Returns the expected amount of work (number of hashes) needed to find
+/// a block with the given target.
+pub fn block_work(target: U256) -> U256 {
+    /* This is 2^256 / (target + 1), which cannot be computed directly
+       with 256-bit numbers.  */
+    (!target / target.saturating_add(U256::one())).saturating_add(U256::one())
+}
+
+/// Computes the difficulty target (in compact form) for the first block
+/// of a new retarget interval, based on the target of the previous block
+/// and the time the last interval took.
+pub fn next_target(bits: u32, timespan: i64, pow_limit: u32) -> Result<u32, &'static str> {
+    let timespan = timespan.max(TARGET_TIMESPAN / 4).min(TARGET_TIMESPAN * 4);
+    let target = compact_to_target(bits).ok_or("invalid difficulty target")?;
+    let limit = compact_to_target(pow_limit).ok_or("invalid proof-of-work limit")?;
+
+    /* Compute target * timespan / TARGET_TIMESPAN, but without overflowing
+       in the intermediate product for large targets.  */
+    let timespan = U256::from(timespan as u64);
+    let divisor = U256::from(TARGET_TIMESPAN as u64);
+    let (quotient, remainder) = (target / divisor, target % divisor);
+    let new_target = quotient.checked_mul(timespan)
+        .and_then(|t| t.checked_add(remainder * timespan / divisor))
+        .map_or(limit, |t| t.min(limit));
+    Ok(target_to_compact(new_target))
+}
+
+/// A name operation extracted from a transaction output.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Eq, PartialEq)]
+pub struct NameOp {
+    /// The name being updated.
+    pub name: Vec<u8>,
+    /// The new value.
+    pub value: Vec<u8>,
+}
+
+/// A single element of a script.
+#[derive(Eq, PartialEq)]
+enum ScriptOp<'a> {
+    /// A data push.
+    Push(&'a [u8]),
+    /// Some other opcode.
+    Code(u8),
+}
+
+/// Splits a script into its individual operations.
+fn parse_script(script: &[u8]) -> Result<Vec<ScriptOp>, &'static str> {
+    let mut reader = Reader { data: script };
+    let mut res = Vec::new();
+    while !reader.data.is_empty() {
+        let op = reader.read(1)?[0];
+        let len = match op {
+            0x00..=0x4b => op as u64,
+            OP_PUSHDATA1 => reader.read_le(1)?,
+            OP_PUSHDATA2 => reader.read_le(2)?,
+            OP_PUSHDATA4 => reader.read_le(4)?,
+            _ => {
+                res.push(ScriptOp::Code(op));
+                continue;
+            },
+        };
+        res.push(ScriptOp::Push(reader.read(len as usize)?));
+    }
+    Ok(res)
+}
+
+/// Extracts the name operation from a script, if it is a
+/// `name_firstupdate` or `name_update` script.
+pub fn parse_name_script(script: &[u8]) -> Option<NameOp> {
+    let ops = parse_script(script).ok()?;
+
+    /* Determine the positions of the name and value pushes, and the
+       drop opcodes that have to follow them.  */
+    let (name, value, drops) = match ops.get(0)? {
+        ScriptOp::Code(OP_NAME_UPDATE) => (1, 2, &[OP_2DROP, OP_DROP][..]),
+        ScriptOp::Code(OP_NAME_FIRSTUPDATE) => (1, 3, &[OP_2DROP, OP_2DROP][..]),
+        _ => return None,
+    };
+    for (i, code) in drops.iter().enumerate() {
+        if ops.get(value + 1 + i)? != &ScriptOp::Code(*code) {
+            return None;
+        }
+    }
+
+    match (ops.get(name)?, ops.get(value)?) {
+        (ScriptOp::Push(n), ScriptOp::Push(v)) => Some(NameOp {
+            name: n.to_vec(),
+            value: v.to_vec(),
+        }),
+        _ => None,
+    }
+}
+
+/// Parses a transaction (in non-witness serialisation) and returns the
+/// name operation in the given output.
+pub fn parse_name_output(tx: &[u8], vout: u32) -> Result<NameOp, &'static str> {
+    let mut reader = Reader { data: tx };
+    let parsed = reader.read_transaction()?;
+    if !reader.data.is_empty() {
+        return Err("trailing data after transaction");
+    }
+
+    parsed.outputs.get(vout as usize)
+        .and_then(|script| parse_name_script(script))
+        .ok_or("output is not a name update")
+}
+
+/// Verifies a Merkle branch for a transaction with the given index in
+/// a block against the block's Merkle root.
+pub fn verify_merkle_branch(txid: H256, index: u32, branch: &[H256],
+                            root: H256) -> bool {
+    let (computed, rest) = merkle_branch_root(txid, index, branch);
+    rest == 0 && computed == root
+}
+
+/// Computes the Merkle root from a leaf with the given index and its
+/// branch.  Also returns what is left of the index after consuming one
+/// bit per level, which is zero if the index fits the branch.
+fn merkle_branch_root(leaf: H256, mut index: u32, branch: &[H256]) -> (H256, u32) {
+    let mut cur = leaf;
+    for sibling in branch {
+        let mut data = Vec::with_capacity(64);
+        if index % 2 == 0 {
+            data.extend_from_slice(cur.as_bytes());
+            data.extend_from_slice(sibling.as_bytes());
+        } else {
+            data.extend_from_slice(sibling.as_bytes());
+            data.extend_from_slice(cur.as_bytes());
+        }
+        cur = double_sha256(&data);
+        index /= 2;
+    }
+    (cur, index)
+}
@@ -0,0 +1,298 @@
+/*
+    namecoin-bridge - mirror Namecoin names into a Substrate chain
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A companion pallet to `names`, which mirrors names from the
+//! [Namecoin](https://www.namecoin.org/) blockchain into the names database
+//! of the Substrate chain, based on SPV proofs against Namecoin block
+//! headers.
+//!
+//! The pallet keeps track of a tree of Namecoin block headers, which starts
+//! at [checkpoints](Module::set_checkpoint) set by a
+//! [configured origin](Trait::RelayOrigin).  Further headers are submitted
+//! by the same origin, but they are fully verified:  Each header must build
+//! on a known block, have the difficulty target required by Namecoin's
+//! retargeting rules and satisfy its proof-of-work.  For merge-mined blocks,
+//! the auxpow data is verified and the proof-of-work is checked on the
+//! parent block.  The pallet accumulates the chain work of all headers and
+//! follows the chain with the most work.  Thus the relay only needs to be
+//! trusted for the checkpoints and for submitting headers at all, not for
+//! which chain is the best one.
+//!
+//! Not verified are the timestamp rules (median time past and the limit on
+//! future timestamps), which Namecoin nodes enforce in addition.
+//!
+//! Anyone can then [mirror](Module::mirror_name) a name by providing
+//! a `name_firstupdate` or `name_update` transaction from Namecoin together
+//! with its Merkle branch in a block that is on the best chain and has enough
+//! confirmations.  The name is then stored in the names pallet, with
+//! a [dedicated prefix](Trait::Namespace) prepended to the Namecoin name.
+//! Mirrored names are owned by the pallet's own account, for which nobody has
+//! a key.  Thus they are read-only on the Substrate chain.
+//!
+//! The namespace must be one of the names pallet's
+//! [reserved namespaces](names::Module::reserved_prefixes), so that users
+//! cannot register names there themselves.  Mirroring fails otherwise, and
+//! also refuses to take over names that are not owned by the bridge.
+//! Mirrored names bypass the name policy.
+
+use frame_support::{
+    decl_event, decl_module, decl_storage, ensure,
+    dispatch::DispatchResult,
+    traits::{EnsureOrigin, Get},
+};
+use codec::{Decode, Encode};
+use sp_core::{H256, U256};
+use sp_runtime::{ModuleId, traits::AccountIdConversion};
+use sp_std::prelude::*;
+use system::ensure_signed;
+
+pub mod bitcoin;
+
+/// The ID used to derive the account that owns all mirrored names.
+const MODULE_ID: ModuleId = ModuleId(*b"nmc/brdg");
+
+/// The pallet's configuration trait.
+pub trait Trait: names::Trait<Name = Vec<u8>, Value = Vec<u8>> {
+
+    /// The overarching event type.
+    type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+
+    /// Origin that is allowed to submit Namecoin block headers.
+    type RelayOrigin: EnsureOrigin<Self::Origin>;
+
+    /// Number of confirmations (including the block itself) a Namecoin
+    /// block needs before names can be mirrored from it.
+    type Confirmations: Get<u32>;
+
+    /// Prefix prepended to Namecoin names when mirroring them.
+    type Namespace: Get<&'static [u8]>;
+
+    /// The maximum difficulty target (i.e. minimum difficulty) in compact
+    /// form.  For Namecoin's main network, this is `0x1d00ffff`.
+    type PowLimit: Get<u32>;
+
+}
+
+/// The data stored for known Namecoin block headers.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct HeaderInfo {
+    /// The block's height.
+    pub height: u32,
+    /// The hash of the previous block.
+    pub prev: H256,
+    /// The Merkle root of the block's transactions.
+    pub merkle_root: H256,
+    /// The block's timestamp.
+    pub time: u32,
+    /// The block's difficulty target in compact form.
+    pub bits: u32,
+    /// The timestamp of the last block before the retarget interval
+    /// this block is in.  This is what the next retarget is based on.
+    pub interval_start: u32,
+    /// The total work of the chain up to and including this block.
+    pub chain_work: U256,
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as NamecoinBridge {
+        /// All known Namecoin block headers by their hash.
+        Headers get(header): map H256 => Option<HeaderInfo>;
+        /// The tip of the chain with the most work.
+        BestBlock get(best_block): H256;
+        /// The height of the best chain's tip.  Confirmations are counted
+        /// with respect to this.
+        BestHeight get(best_height): u32;
+        /// The hashes of all blocks in the best chain by their height.
+        MainChain get(main_chain): map u32 => Option<H256>;
+        /// For each mirrored name (by its Namecoin name), the block height
+        /// of the last update that was mirrored.  Only newer updates can
+        /// be applied on top.
+        LastUpdate get(last_update): map Vec<u8> => Option<u32>;
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        fn deposit_event() = default;
+
+        /// Adds a trusted checkpoint block header at a given height.  This is
+        /// used to start the header chain, and does not need to connect to
+        /// any known block.  Since the preceding chain is not known,
+        /// the checkpoint's total chain work and the timestamp of the last
+        /// block before its retarget interval have to be given as well.
+        /// The header's proof-of-work is still verified.
+        pub fn set_checkpoint(origin, header: Vec<u8>, height: u32,
+                              chain_work: U256, interval_start: u32) -> DispatchResult {
+            T::RelayOrigin::ensure_origin(origin)?;
+            let header = bitcoin::BlockHeader::parse(&header)?;
+            header.check_pow(T::PowLimit::get())?;
+            Self::add_header(header, height, interval_start, chain_work);
+            Ok(())
+        }
+
+        /// Adds a block header that builds on an already known block.
+        /// The header's difficulty target and proof-of-work are verified,
+        /// and it becomes the new best block if its chain has more work
+        /// than the current best chain.
+        pub fn submit_header(origin, header: Vec<u8>) -> DispatchResult {
+            T::RelayOrigin::ensure_origin(origin)?;
+            let header = bitcoin::BlockHeader::parse(&header)?;
+            ensure!(!<Headers>::exists(&header.hash), "block is known already");
+            let prev = match <Headers>::get(&header.prev) {
+                None => return Err("previous block is unknown".into()),
+                Some(info) => info,
+            };
+            let height = prev.height.checked_add(1).ok_or("block height is too large")?;
+
+            let pow_limit = T::PowLimit::get();
+            let (bits, interval_start) = if height % bitcoin::RETARGET_INTERVAL == 0 {
+                let timespan = i64::from(prev.time) - i64::from(prev.interval_start);
+                (bitcoin::next_target(prev.bits, timespan, pow_limit)?, prev.time)
+            } else {
+                (prev.bits, prev.interval_start)
+            };
+            ensure!(header.bits == bits, "incorrect difficulty target");
+            let target = header.check_pow(pow_limit)?;
+
+            let chain_work = prev.chain_work.saturating_add(bitcoin::block_work(target));
+            Self::add_header(header, height, interval_start, chain_work);
+            Ok(())
+        }
+
+        /// Mirrors a name from Namecoin.  `tx` is a transaction (in non-witness
+        /// serialisation) with a name operation in output `vout`.  It must be
+        /// part of the given block, which is proven by the Merkle branch.
+        /// The block must be on the best chain.
+        pub fn mirror_name(origin, block: H256, tx: Vec<u8>, vout: u32,
+                           index: u32, branch: Vec<H256>) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let info = match <Headers>::get(&block) {
+                None => return Err("block is unknown".into()),
+                Some(info) => info,
+            };
+            ensure!(<MainChain>::get(info.height) == Some(block),
+                    "block is not in the best chain");
+            let confirmations = Self::best_height().saturating_sub(info.height).saturating_add(1);
+            ensure!(confirmations >= T::Confirmations::get(),
+                    "block does not have enough confirmations");
+
+            let txid = bitcoin::double_sha256(&tx);
+            ensure!(bitcoin::verify_merkle_branch(txid, index, &branch, info.merkle_root),
+                    "invalid merkle branch");
+            let op = bitcoin::parse_name_output(&tx, vout)?;
+
+            if let Some(h) = <LastUpdate>::get(&op.name) {
+                ensure!(h < info.height, "a newer update has been mirrored already");
+            }
+
+            let mut name = T::Namespace::get().to_vec();
+            name.extend_from_slice(&op.name);
+            ensure!(names::Module::<T>::is_reserved_name(&name),
+                    "bridge namespace is not reserved");
+            if let Some(data) = names::Module::<T>::lookup(&name) {
+                ensure!(data.owner == Self::account_id(), "name is not owned by the bridge");
+            }
+            let name_op = names::Module::<T>::unchecked_operation(name, op.value,
+                                                                 Self::account_id());
+            names::Module::<T>::execute(name_op)?;
+            <LastUpdate>::insert(&op.name, info.height);
+
+            Self::deposit_event(Event::NameMirrored(op.name, info.height));
+            Ok(())
+        }
+
+    }
+}
+
+impl<T: Trait> Module<T> {
+
+    /// Returns the account that owns all mirrored names.
+    pub fn account_id() -> T::AccountId {
+        MODULE_ID.into_account()
+    }
+
+    /// Stores a (verified) block header as known with the given height
+    /// and chain work.  If it has more work than the current best block,
+    /// it becomes the new tip.
+    fn add_header(header: bitcoin::BlockHeader, height: u32,
+                  interval_start: u32, chain_work: U256) {
+        let hash = header.hash;
+        <Headers>::insert(&hash, HeaderInfo {
+            height: height,
+            prev: header.prev,
+            merkle_root: header.merkle_root,
+            time: header.time,
+            bits: header.bits,
+            interval_start: interval_start,
+            chain_work: chain_work,
+        });
+        Self::deposit_event(Event::HeaderAdded(hash, height));
+
+        let is_best = <Headers>::get(Self::best_block())
+            .map_or(true, |best| chain_work > best.chain_work);
+        if is_best {
+            Self::set_best_block(hash, height);
+        }
+    }
+
+    /// Makes the given block the tip of the best chain, and updates the
+    /// main chain index accordingly.  The work done here is proportional
+    /// to the depth of the reorg, which is limited by the number of blocks
+    /// on the new branch that have been submitted (each with proof-of-work).
+    fn set_best_block(hash: H256, height: u32) {
+        for h in height.saturating_add(1)..=Self::best_height() {
+            <MainChain>::remove(h);
+        }
+
+        let mut cur = hash;
+        let mut h = height;
+        while <MainChain>::get(h) != Some(cur) {
+            <MainChain>::insert(h, cur);
+            cur = match <Headers>::get(&cur) {
+                Some(info) if h > 0 && <Headers>::exists(&info.prev) => info.prev,
+                _ => break,
+            };
+            h -= 1;
+        }
+
+        <BestBlock>::put(hash);
+        <BestHeight>::put(height);
+        Self::deposit_event(Event::BestBlockChanged(hash, height));
+    }
+
+}
+
+decl_event!(
+    pub enum Event {
+        /// A Namecoin block header has been added at the given height.
+        HeaderAdded(H256, u32),
+        /// The block at the given height is the new tip of the best chain.
+        BestBlockChanged(H256, u32),
+        /// A name (without the namespace prefix) has been mirrored from
+        /// a Namecoin block at the given height.
+        NameMirrored(Vec<u8>, u32),
+    }
+);
+
+/// Module with unit tests.
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,904 @@
+/*
+    namecoin-bridge - mirror Namecoin names into a Substrate chain
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+use super::*;
+use crate::bitcoin::{
+    block_work, compact_to_target, double_sha256, next_target, parse_name_output,
+    target_to_compact, BlockHeader, NameOp, TARGET_TIMESPAN,
+};
+
+use frame_support::{
+    assert_noop, assert_ok, impl_outer_event, impl_outer_origin, parameter_types,
+    dispatch::DispatchError,
+    traits::Randomness,
+    weights::Weight,
+};
+use names::{NameData, Operation};
+use names::policy::{Composed, FeePolicy, NeverExpire};
+use sp_runtime::{
+    ModuleId, Perbill,
+    testing::{Header, TestSignature, UintAuthorityId},
+    traits::{BlakeTwo256, Hash, IdentityLookup},
+};
+
+impl_outer_origin! {
+    pub enum Origin for Test {}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Test;
+parameter_types! {
+    pub const ExistentialDeposit: u128 = 1000;
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: Weight = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const Confirmations: u32 = 2;
+    pub const Namespace: &'static [u8] = b"nmc/";
+    pub const PowLimit: u32 = 0x207fffff;
+    pub const HiddenRegistrationFee: u128 = 0;
+    pub const HiddenRegistrationPeriod: u64 = 1;
+    pub const MaxBulkTransfer: u32 = 10;
+    pub const MaxSwapNames: u32 = 10;
+    pub const MaxForceRegistrations: u32 = 3;
+    pub const StatsEraLength: u64 = 100;
+    pub const StatsHistory: u32 = 10;
+    pub const FreeUpdatesPerEra: u32 = 0;
+    pub const MaxTemplates: u32 = 5;
+    pub const ReceiptRetention: u64 = 100;
+    pub const MaxPayoutSplits: u32 = 5;
+    pub const MaxShareHolders: u32 = 10;
+    pub const MigrationWeightLimit: Weight = 1_000_000;
+    pub const DeferredWeightLimit: Weight = 100_000;
+    pub const CommunityAccount: u64 = 777;
+    pub const NamesModuleId: ModuleId = ModuleId(*b"py/names");
+    pub const ExpirationBucketSize: u64 = 1;
+    pub const MaxExpirationsPerBlock: u32 = 10;
+    pub const MaxAutoExpirations: Option<u32> = None;
+    pub const SweepBounty: u128 = 0;
+    pub const MaxValueLookahead: u64 = 100;
+    pub const StrictTransfers: bool = false;
+    pub const ValueDepositPerByte: u128 = 0;
+    pub const MinExpirationBlocks: u64 = 1;
+    pub const ExpirationUpdates: names::ExpirationUpdate = names::ExpirationUpdate::MayShorten;
+    pub const ComplianceWindow: Option<u64> = None;
+    pub const NonComplianceAction: names::ComplianceAction = names::ComplianceAction::Warn;
+    pub const WatchDeposit: u128 = 0;
+    pub const MaxWatchers: u32 = 0;
+    pub const MaxSubvalues: u32 = 0;
+    pub const SubvalueDeposit: u128 = 0;
+    pub const NoteDeposit: u128 = 0;
+    pub const MaxNoteSize: u32 = 64;
+    pub const MaxRedirectDepth: u32 = 4;
+    pub const EnvelopeFee: u128 = 0;
+    pub const MaxEnvelopeSize: u32 = 256;
+    pub const MaxEnvelopes: u32 = 10;
+    pub const MinPreorderDeposit: u128 = 0;
+    pub const PreorderMaturity: u64 = 1;
+    pub const PreorderWindow: u64 = 1;
+    pub const PreorderForfeit: Perbill = Perbill::from_percent(0);
+    pub const FirstUpdatePeriod: Option<u64> = None;
+    pub const FirstUpdateRefund: Perbill = Perbill::from_percent(50);
+    pub const ExpirationQuarantine: u64 = 0;
+    pub const RegistrarCommission: Perbill = Perbill::from_percent(10);
+    pub const MinBackorderBond: u128 = 0;
+    pub const MaxFeeRateAge: u64 = 10;
+    pub const ReportBond: u128 = 500;
+    pub const ReportChallengePeriod: u64 = 10;
+    pub const ArchiveRetention: Option<u64> = None;
+    pub const GuardianConfirmationPeriod: u64 = 10;
+    pub const MaxBackorders: u32 = 10;
+    pub const MaxBulkRenewals: u32 = 3;
+    pub const MaxTopicPrefixes: u32 = 2;
+    pub const MaxDelegatedNamespaces: u32 = 2;
+    pub const MaxSessionDuration: u64 = 100;
+    pub const MaxSessionNames: u32 = 2;
+    pub const MaxInsuredRenewals: u32 = 3;
+    pub const RegistrationIntentsOnly: bool = false;
+    pub const IntentBond: u128 = 10;
+    pub const MaxIntentsPerName: u32 = 10;
+    pub const MaxTotalNames: Option<u32> = None;
+    pub const MaxRegistrationsPerBlock: Option<u32> = None;
+    pub const CongestionMultipliers: &'static [(u32, u32)] = &[];
+}
+impl system::Trait for Test {
+    type Origin = Origin;
+    type Call = ();
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = TestEvent;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ();
+}
+impl balances::Trait for Test {
+    type Balance = u128;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = TestEvent;
+    type DustRemoval = ();
+    type TransferPayment = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type TransferFee = ();
+    type CreationFee = ();
+}
+
+mod bridge {
+    pub use crate::Event;
+}
+impl_outer_event! {
+    pub enum TestEvent for Test {
+        balances<T>,
+        names<T>,
+        bridge,
+    }
+}
+
+/// Randomness for testing, which is just a hash of the subject.
+pub struct TestRandomness;
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        BlakeTwo256::hash(subject)
+    }
+}
+
+impl names::Trait for Test {
+
+    type Name = Vec<u8>;
+    type Value = Vec<u8>;
+    type DefaultValue = ();
+
+    type Currency = Balances;
+    type Event = TestEvent;
+
+    type Namehash = ();
+
+    type HiddenRegistrationFee = HiddenRegistrationFee;
+    type HiddenRegistrationPeriod = HiddenRegistrationPeriod;
+
+    type Identity = ();
+    type ForceOrigin = system::EnsureRoot<u64>;
+    type ReportOrigin = system::EnsureRoot<u64>;
+    type Namespaces = names::namespace::BytePrefix;
+    type CommunityAccount = CommunityAccount;
+    type ModuleId = NamesModuleId;
+    type Call = names::Call<Test>;
+    type NameCallFilter = ();
+    type MaxShareHolders = MaxShareHolders;
+    type MaxPayoutSplits = MaxPayoutSplits;
+    type MaxBulkTransfer = MaxBulkTransfer;
+    type MaxSwapNames = MaxSwapNames;
+    type MaxForceRegistrations = MaxForceRegistrations;
+    type MaxTotalNames = MaxTotalNames;
+    type MaxRegistrationsPerBlock = MaxRegistrationsPerBlock;
+    type CongestionMultipliers = CongestionMultipliers;
+    type Randomness = TestRandomness;
+    type StrictTransfers = StrictTransfers;
+    type RegistrationIntentsOnly = RegistrationIntentsOnly;
+    type IntentBond = IntentBond;
+    type MaxIntentsPerName = MaxIntentsPerName;
+
+    type MinPreorderDeposit = MinPreorderDeposit;
+    type PreorderMaturity = PreorderMaturity;
+    type PreorderWindow = PreorderWindow;
+    type PreorderForfeit = PreorderForfeit;
+    type FirstUpdatePeriod = FirstUpdatePeriod;
+    type FirstUpdateRefund = FirstUpdateRefund;
+    type ExpirationQuarantine = ExpirationQuarantine;
+    type RegistrarCommission = RegistrarCommission;
+    type MinBackorderBond = MinBackorderBond;
+    type ReportBond = ReportBond;
+    type ReportChallengePeriod = ReportChallengePeriod;
+    type ArchiveRetention = ArchiveRetention;
+    type GuardianConfirmationPeriod = GuardianConfirmationPeriod;
+    type MaxBackorders = MaxBackorders;
+    type MaxBulkRenewals = MaxBulkRenewals;
+    type MaxTopicPrefixes = MaxTopicPrefixes;
+    type MaxDelegatedNamespaces = MaxDelegatedNamespaces;
+    type MaxSessionDuration = MaxSessionDuration;
+    type MaxSessionNames = MaxSessionNames;
+    type MaxInsuredRenewals = MaxInsuredRenewals;
+
+    type Policy = Composed<NoNamespaceRegistrations, NeverExpire, ()>;
+    type ExtraWeight = ();
+    type WeightInfo = ();
+    type FeeConverter = ();
+    type Payment = ();
+    type MaxFeeRateAge = MaxFeeRateAge;
+    type MinExpirationBlocks = MinExpirationBlocks;
+    type ExpirationUpdates = ExpirationUpdates;
+    type ComplianceWindow = ComplianceWindow;
+    type NonComplianceAction = NonComplianceAction;
+    type MaxExpirationsPerBlock = MaxExpirationsPerBlock;
+    type MaxAutoExpirations = MaxAutoExpirations;
+    type SweepBounty = SweepBounty;
+    type MaxValueLookahead = MaxValueLookahead;
+    type ExpirationBucketSize = ExpirationBucketSize;
+    type ValueDepositPerByte = ValueDepositPerByte;
+    type OnOwnerReaped = ();
+    type OnExpiration = ();
+    type ValueCompliance = ();
+    type OnNameOperation = ();
+    type Signature = TestSignature;
+    type Signer = UintAuthorityId;
+    type Patch = ();
+    type SubKey = Vec<u8>;
+    type MaxSubvalues = MaxSubvalues;
+    type SubvalueDeposit = SubvalueDeposit;
+    type NoteDeposit = NoteDeposit;
+    type MaxNoteSize = MaxNoteSize;
+    type MaxRedirectDepth = MaxRedirectDepth;
+    type EnvelopeFee = EnvelopeFee;
+    type MaxEnvelopeSize = MaxEnvelopeSize;
+    type MaxEnvelopes = MaxEnvelopes;
+    type WatchDeposit = WatchDeposit;
+    type MaxWatchers = MaxWatchers;
+    type Templates = ();
+    type MaxTemplates = MaxTemplates;
+    type Migration = ();
+    type MigrationWeightLimit = MigrationWeightLimit;
+    type DeferredWeightLimit = DeferredWeightLimit;
+    type ReceiptRetention = ReceiptRetention;
+    type StatsEraLength = StatsEraLength;
+    type StatsHistory = StatsHistory;
+    type FreeUpdatesPerEra = FreeUpdatesPerEra;
+
+}
+
+/// Names inside the namespace cannot be registered normally, everything
+/// else is free.
+pub struct NoNamespaceRegistrations;
+impl FeePolicy<Test> for NoNamespaceRegistrations {
+    fn get_name_fee(op: &Operation<Test>) -> Option<u128> {
+        if op.name.starts_with(b"nmc/") {
+            return None;
+        }
+        Some(0)
+    }
+}
+
+impl Trait for Test {
+    type Event = TestEvent;
+    type RelayOrigin = system::EnsureRoot<u64>;
+    type Confirmations = Confirmations;
+    type Namespace = Namespace;
+    type PowLimit = PowLimit;
+}
+
+/// Builds the test externalities with the given names and reserved
+/// namespaces in the names pallet's genesis.
+fn build_ext(names: Vec<(Vec<u8>, Vec<u8>, u64, Option<u64>)>,
+             reserved_prefixes: Vec<Vec<u8>>) -> sp_io::TestExternalities {
+    let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    names::GenesisConfig::<Test> {
+        names: names,
+        system_names: vec![],
+        reserved_prefixes: reserved_prefixes,
+        topic_prefixes: vec![],
+    }.assimilate_storage(&mut t).unwrap();
+    t.into()
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+    build_ext(vec![], vec![Namespace::get().to_vec()])
+}
+
+type Balances = balances::Module<Test>;
+type Names = names::Module<Test>;
+type Mod = Module<Test>;
+
+/// Serialises a pure block header.
+fn pure_header(version: u32, prev: H256, merkle_root: H256,
+               time: u32, bits: u32, nonce: u32) -> Vec<u8> {
+    let mut res = version.to_le_bytes().to_vec();
+    res.extend_from_slice(prev.as_bytes());
+    res.extend_from_slice(merkle_root.as_bytes());
+    res.extend_from_slice(&time.to_le_bytes());
+    res.extend_from_slice(&bits.to_le_bytes());
+    res.extend_from_slice(&nonce.to_le_bytes());
+    res
+}
+
+/// Changes the nonce of a pure block header until its hash satisfies the
+/// given target (if `valid` is true) or does not satisfy it (otherwise).
+/// With the test's proof-of-work limit, about every second hash is valid.
+fn mine(hdr: &mut Vec<u8>, bits: u32, valid: bool) {
+    let target = compact_to_target(bits).unwrap();
+    while (U256::from_little_endian(double_sha256(hdr).as_bytes()) <= target) != valid {
+        let mut nonce = [0; 4];
+        nonce.copy_from_slice(&hdr[76..80]);
+        let nonce = u32::from_le_bytes(nonce) + 1;
+        hdr[76..80].copy_from_slice(&nonce.to_le_bytes());
+    }
+}
+
+/// Constructs a mined block header with the given previous block,
+/// timestamp and difficulty.
+fn mined_header(prev: H256, merkle_root: H256, time: u32, bits: u32) -> Vec<u8> {
+    let mut res = pure_header(1, prev, merkle_root, time, bits, 0);
+    mine(&mut res, bits, true);
+    res
+}
+
+/// Constructs a mined block header with the given previous block
+/// and Merkle root at the minimum difficulty.
+fn header(prev: H256, merkle_root: H256) -> Vec<u8> {
+    mined_header(prev, merkle_root, 0, PowLimit::get())
+}
+
+/// Sets a checkpoint at the given height, which is also the first
+/// block known.
+fn checkpoint(hdr: &[u8], height: u32) {
+    assert_ok!(Mod::set_checkpoint(Origin::ROOT, hdr.to_vec(), height, U256::one(), 0));
+}
+
+/// Constructs a serialised coinbase transaction with the given input
+/// script and no outputs.
+fn coinbase(script: &[u8]) -> Vec<u8> {
+    let mut res = vec![1, 0, 0, 0, 1];
+    res.extend_from_slice(&[0; 36]);
+    res.push(script.len() as u8);
+    res.extend_from_slice(script);
+    res.extend_from_slice(&[0xff; 4]);
+    res.push(0);
+    res.extend_from_slice(&[0; 4]);
+    res
+}
+
+/// Returns a parent coinbase script that commits to the given (reversed)
+/// chain Merkle root in the standard way, for a chain Merkle tree with
+/// just one entry.
+fn merge_mining_script(root: &[u8]) -> Vec<u8> {
+    let mut res = vec![0xfa, 0xbe, b'm', b'm'];
+    res.extend_from_slice(root);
+    res.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+    res
+}
+
+/// Constructs a merge-mined block header on top of `prev`, with auxpow
+/// data for a parent block with the given version.  The parent coinbase's
+/// input script is computed by `script` from the reversed chain Merkle
+/// root, and the parent block satisfies the target if `valid` is true.
+fn merge_mined_header(prev: H256, parent_version: u32,
+                      script: impl Fn(&[u8]) -> Vec<u8>, valid: bool) -> Vec<u8> {
+    let mut res = pure_header(0x0001_0101, prev, H256::default(), 0, PowLimit::get(), 0);
+    let mut root = double_sha256(&res).as_bytes().to_vec();
+    root.reverse();
+
+    let tx = coinbase(&script(&root));
+    let mut parent = pure_header(parent_version, H256::default(), double_sha256(&tx),
+                                 0, PowLimit::get(), 0);
+    mine(&mut parent, PowLimit::get(), valid);
+
+    res.extend_from_slice(&tx);
+    res.extend_from_slice(double_sha256(&parent).as_bytes());
+    /* Empty coinbase and chain Merkle branches, both with index zero.  */
+    res.extend_from_slice(&[0; 5]);
+    res.extend_from_slice(&[0; 5]);
+    res.extend_from_slice(&parent);
+    res
+}
+
+/// Constructs a name_update script.
+fn update_script(name: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut res = vec![0x53, name.len() as u8];
+    res.extend_from_slice(name);
+    res.push(0x4c);
+    res.push(value.len() as u8);
+    res.extend_from_slice(value);
+    res.extend_from_slice(&[0x6d, 0x75, 0x51]);
+    res
+}
+
+/// Constructs a serialised transaction with one input and the
+/// given output scripts.
+fn transaction(scripts: &[Vec<u8>]) -> Vec<u8> {
+    let mut res = vec![0x00, 0x71, 0, 0, 1];
+    res.extend_from_slice(&[0; 36]);
+    res.extend_from_slice(&[0, 0xff, 0xff, 0xff, 0xff]);
+    res.push(scripts.len() as u8);
+    for s in scripts {
+        res.extend_from_slice(&[0; 8]);
+        res.push(s.len() as u8);
+        res.extend_from_slice(s);
+    }
+    res.extend_from_slice(&[0; 4]);
+    res
+}
+
+/// Hashes two Merkle tree nodes together.
+fn merkle_parent(left: H256, right: H256) -> H256 {
+    let mut data = left.as_bytes().to_vec();
+    data.extend_from_slice(right.as_bytes());
+    double_sha256(&data)
+}
+
+/// Sets up a header chain of the given length, where the block at height 10
+/// (the checkpoint) contains the two transactions.  Returns the hash of the
+/// checkpoint block.
+fn setup_chain(txs: (&[u8], &[u8]), len: u32) -> H256 {
+    let root = merkle_parent(double_sha256(txs.0), double_sha256(txs.1));
+    let first = header(H256::default(), root);
+    checkpoint(&first, 10);
+
+    let mut prev = double_sha256(&first);
+    for _ in 1..len {
+        let hdr = header(prev, H256::default());
+        assert_ok!(Mod::submit_header(Origin::ROOT, hdr.clone()));
+        prev = double_sha256(&hdr);
+    }
+
+    double_sha256(&first)
+}
+
+mod parsing {
+    use super::*;
+
+    #[test]
+    fn block_header() {
+        let hdr = pure_header(1, H256::repeat_byte(1), H256::repeat_byte(2), 3, 4, 5);
+        assert_eq!(BlockHeader::parse(&hdr), Ok(BlockHeader {
+            hash: double_sha256(&hdr),
+            version: 1,
+            prev: H256::repeat_byte(1),
+            merkle_root: H256::repeat_byte(2),
+            time: 3,
+            bits: 4,
+            auxpow: None,
+        }));
+        assert!(BlockHeader::parse(&hdr[1..]).is_err());
+
+        let mut trailing = hdr.clone();
+        trailing.push(0);
+        assert_eq!(BlockHeader::parse(&trailing), Err("trailing data after block header"));
+    }
+
+    #[test]
+    fn auxpow_header() {
+        let hdr = merge_mined_header(H256::default(), 0x2000_0000, merge_mining_script, true);
+        let parsed = BlockHeader::parse(&hdr).unwrap();
+        assert_eq!(parsed.hash, double_sha256(&hdr[..80]));
+        let auxpow = parsed.auxpow.unwrap();
+        assert_eq!(auxpow.coinbase_index, 0);
+        assert!(auxpow.chain_branch.is_empty());
+        assert_eq!(auxpow.parent.version, 0x2000_0000);
+        assert_eq!(auxpow.parent.merkle_root, auxpow.coinbase_txid);
+
+        /* The auxpow flag requires the auxpow data to be present.  */
+        assert!(BlockHeader::parse(&hdr[..80]).is_err());
+    }
+
+    #[test]
+    fn compact_targets() {
+        assert_eq!(compact_to_target(0x1d00ffff), Some(U256::from(0xffff) << 208));
+        assert_eq!(target_to_compact(U256::from(0xffff) << 208), 0x1d00ffff);
+        assert_eq!(compact_to_target(0x05009234), Some(U256::from(0x9234_0000u64)));
+        assert_eq!(target_to_compact(U256::from(0x9234_0000u64)), 0x05009234);
+        assert_eq!(target_to_compact(U256::from(0x80)), 0x02008000);
+
+        assert_eq!(compact_to_target(0x01003456), None);
+        assert_eq!(compact_to_target(0x04923456), None);
+        assert_eq!(compact_to_target(0xff123456), None);
+    }
+
+    #[test]
+    fn work_and_retargeting() {
+        /* The work of Bitcoin's genesis block.  */
+        assert_eq!(block_work(compact_to_target(0x1d00ffff).unwrap()),
+                   U256::from(0x1_0001_0001u64));
+
+        /* Bitcoin's retarget at height 32,256.  */
+        assert_eq!(next_target(0x1d00ffff, 1262152739 - 1261130161, 0x1d00ffff),
+                   Ok(0x1d00d86a));
+
+        /* Timespans are clamped, and the target cannot exceed the limit.  */
+        assert_eq!(next_target(0x1d00ffff, TARGET_TIMESPAN, 0x1d00ffff), Ok(0x1d00ffff));
+        assert_eq!(next_target(0x1d00ffff, 10 * TARGET_TIMESPAN, 0x1d00ffff), Ok(0x1d00ffff));
+        assert_eq!(next_target(0x1c00ffff, 10 * TARGET_TIMESPAN, 0x1d00ffff), Ok(0x1c03fffc));
+        assert_eq!(next_target(0x1d00ffff, -1, 0x1d00ffff), Ok(0x1c3fffc0));
+    }
+
+    #[test]
+    fn name_outputs() {
+        let tx = transaction(&[vec![0x51], update_script(b"d/foo", b"bar")]);
+        assert_eq!(parse_name_output(&tx, 1), Ok(NameOp {
+            name: b"d/foo".to_vec(),
+            value: b"bar".to_vec(),
+        }));
+        assert!(parse_name_output(&tx, 0).is_err());
+        assert!(parse_name_output(&tx, 2).is_err());
+        assert!(parse_name_output(&tx[..tx.len() - 1], 1).is_err());
+
+        let firstupdate = vec![0x52, 1, b'x', 1, 0xaa, 1, b'y', 0x6d, 0x6d, 0x51];
+        assert_eq!(parse_name_output(&transaction(&[firstupdate]), 0), Ok(NameOp {
+            name: b"x".to_vec(),
+            value: b"y".to_vec(),
+        }));
+
+        let mut bad = update_script(b"d/foo", b"bar");
+        let len = bad.len();
+        bad[len - 2] = 0x6d;
+        assert!(parse_name_output(&transaction(&[bad]), 0).is_err());
+    }
+
+}
+
+mod headers {
+    use super::*;
+
+    #[test]
+    fn chain() {
+        new_test_ext().execute_with(|| {
+            let first = header(H256::default(), H256::default());
+            assert_noop!(Mod::set_checkpoint(Origin::signed(1), first.clone(), 10,
+                                             U256::one(), 0),
+                         DispatchError::BadOrigin);
+            checkpoint(&first, 10);
+            assert_eq!(Mod::best_height(), 10);
+            assert_eq!(Mod::best_block(), double_sha256(&first));
+
+            let next = header(double_sha256(&first), H256::default());
+            assert_noop!(Mod::submit_header(Origin::signed(1), next.clone()),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::submit_header(Origin::ROOT,
+                                            header(H256::repeat_byte(1), H256::default())),
+                         "previous block is unknown");
+            assert_ok!(Mod::submit_header(Origin::ROOT, next.clone()));
+            assert_noop!(Mod::submit_header(Origin::ROOT, next.clone()),
+                         "block is known already");
+
+            let work = block_work(compact_to_target(PowLimit::get()).unwrap());
+            assert_eq!(Mod::header(double_sha256(&next)), Some(HeaderInfo {
+                height: 11,
+                prev: double_sha256(&first),
+                merkle_root: H256::default(),
+                time: 0,
+                bits: PowLimit::get(),
+                interval_start: 0,
+                chain_work: U256::one() + work,
+            }));
+            assert_eq!(Mod::best_height(), 11);
+            assert_eq!(Mod::best_block(), double_sha256(&next));
+            assert_eq!(Mod::main_chain(10), Some(double_sha256(&first)));
+            assert_eq!(Mod::main_chain(11), Some(double_sha256(&next)));
+        });
+    }
+
+    #[test]
+    fn proof_of_work() {
+        new_test_ext().execute_with(|| {
+            let first = header(H256::default(), H256::default());
+            checkpoint(&first, 10);
+            let prev = double_sha256(&first);
+
+            let mut unmined = pure_header(1, prev, H256::default(), 0, PowLimit::get(), 0);
+            mine(&mut unmined, PowLimit::get(), false);
+            assert_noop!(Mod::submit_header(Origin::ROOT, unmined),
+                         "insufficient proof of work");
+
+            let easier = pure_header(1, prev, H256::default(), 0, 0x2100ffff, 0);
+            assert_noop!(Mod::set_checkpoint(Origin::ROOT, easier, 10, U256::one(), 0),
+                         "difficulty target is above the limit");
+
+            assert_noop!(Mod::submit_header(Origin::ROOT,
+                                            mined_header(prev, H256::default(), 0, 0x2000ffff)),
+                         "incorrect difficulty target");
+
+            let mut wrong_chain = pure_header(2, prev, H256::default(), 0, PowLimit::get(), 0);
+            mine(&mut wrong_chain, PowLimit::get(), true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, wrong_chain),
+                         "block has the wrong chain ID");
+        });
+    }
+
+    #[test]
+    fn retargeting() {
+        new_test_ext().execute_with(|| {
+            /* The checkpoint is the last block of a retarget interval, which
+               took half of the targeted time.  Thus the difficulty doubles
+               in the next block.  */
+            let time = (TARGET_TIMESPAN / 2) as u32;
+            let first = mined_header(H256::default(), H256::default(), time, PowLimit::get());
+            assert_ok!(Mod::set_checkpoint(Origin::ROOT, first.clone(), 2 * 2016 - 1,
+                                           U256::one(), 0));
+            let prev = double_sha256(&first);
+
+            assert_noop!(Mod::submit_header(Origin::ROOT,
+                                            mined_header(prev, H256::default(), time, PowLimit::get())),
+                         "incorrect difficulty target");
+            let next = mined_header(prev, H256::default(), time, 0x203fffff);
+            assert_ok!(Mod::submit_header(Origin::ROOT, next.clone()));
+            let info = Mod::header(double_sha256(&next)).unwrap();
+            assert_eq!(info.interval_start, time);
+
+            /* The new target stays for the rest of the interval.  */
+            let prev = double_sha256(&next);
+            assert_noop!(Mod::submit_header(Origin::ROOT,
+                                            mined_header(prev, H256::default(), time, PowLimit::get())),
+                         "incorrect difficulty target");
+            assert_ok!(Mod::submit_header(Origin::ROOT,
+                                          mined_header(prev, H256::default(), time, 0x203fffff)));
+        });
+    }
+
+    #[test]
+    fn fork_choice() {
+        new_test_ext().execute_with(|| {
+            let first = header(H256::default(), H256::default());
+            checkpoint(&first, 10);
+
+            let a11 = header(double_sha256(&first), H256::repeat_byte(1));
+            let a12 = header(double_sha256(&a11), H256::default());
+            assert_ok!(Mod::submit_header(Origin::ROOT, a11.clone()));
+            assert_ok!(Mod::submit_header(Origin::ROOT, a12.clone()));
+
+            /* A competing branch only takes over once it has more work.  */
+            let b11 = header(double_sha256(&first), H256::repeat_byte(2));
+            let b12 = header(double_sha256(&b11), H256::default());
+            let b13 = header(double_sha256(&b12), H256::default());
+            assert_ok!(Mod::submit_header(Origin::ROOT, b11.clone()));
+            assert_ok!(Mod::submit_header(Origin::ROOT, b12.clone()));
+            assert_eq!(Mod::best_block(), double_sha256(&a12));
+            assert_eq!(Mod::main_chain(11), Some(double_sha256(&a11)));
+
+            assert_ok!(Mod::submit_header(Origin::ROOT, b13.clone()));
+            assert_eq!(Mod::best_block(), double_sha256(&b13));
+            assert_eq!(Mod::best_height(), 13);
+            assert_eq!(Mod::main_chain(10), Some(double_sha256(&first)));
+            assert_eq!(Mod::main_chain(11), Some(double_sha256(&b11)));
+            assert_eq!(Mod::main_chain(12), Some(double_sha256(&b12)));
+            assert_eq!(Mod::main_chain(13), Some(double_sha256(&b13)));
+        });
+    }
+
+    #[test]
+    fn merge_mining() {
+        new_test_ext().execute_with(|| {
+            let first = header(H256::default(), H256::default());
+            checkpoint(&first, 10);
+            let prev = double_sha256(&first);
+
+            let bad_parent = merge_mined_header(prev, 0x2000_0000, merge_mining_script, false);
+            assert_noop!(Mod::submit_header(Origin::ROOT, bad_parent),
+                         "insufficient proof of work");
+            let our_chain = merge_mined_header(prev, 0x0001_0000, merge_mining_script, true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, our_chain),
+                         "auxpow parent has our chain ID");
+
+            let missing = merge_mined_header(prev, 0x2000_0000, |_| vec![0x51; 10], true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, missing),
+                         "auxpow missing chain merkle root in parent coinbase");
+            let late = merge_mined_header(prev, 0x2000_0000, |root| {
+                let mut res = vec![0; 21];
+                res.extend_from_slice(root);
+                res.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+                res
+            }, true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, late),
+                         "chain merkle root must start in the first 20 bytes of the parent coinbase");
+            let twice = merge_mined_header(prev, 0x2000_0000, |root| {
+                let mut res = merge_mining_script(root);
+                res.extend_from_slice(&[0xfa, 0xbe, b'm', b'm']);
+                res
+            }, true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, twice),
+                         "multiple merged mining headers in coinbase");
+            let wrong_size = merge_mined_header(prev, 0x2000_0000, |root| {
+                let mut res = merge_mining_script(root);
+                res[36] = 2;
+                res
+            }, true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, wrong_size),
+                         "auxpow merkle branch size does not match parent coinbase");
+            let truncated = merge_mined_header(prev, 0x2000_0000, |root| {
+                let mut res = merge_mining_script(root);
+                res.truncate(40);
+                res
+            }, true);
+            assert_noop!(Mod::submit_header(Origin::ROOT, truncated),
+                         "auxpow missing chain merkle tree size and nonce in parent coinbase");
+
+            /* Without the merged-mining header, the root can be at the start
+               of the script.  */
+            let legacy = merge_mined_header(prev, 0x2000_0000, |root| {
+                let mut res = root.to_vec();
+                res.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+                res
+            }, true);
+            assert_ok!(Mod::submit_header(Origin::ROOT, legacy.clone()));
+            assert_eq!(Mod::best_block(), double_sha256(&legacy[..80]));
+        });
+    }
+
+}
+
+mod mirroring {
+    use super::*;
+
+    #[test]
+    fn success() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51], update_script(b"d/baz", b"")]);
+            let block = setup_chain((&tx1, &tx2), 2);
+
+            assert_ok!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                        0, vec![double_sha256(&tx2)]));
+            assert_ok!(Mod::mirror_name(Origin::signed(1), block, tx2.clone(), 1,
+                                        1, vec![double_sha256(&tx1)]));
+
+            assert_eq!(Names::lookup(b"nmc/d/foo".to_vec()), Some(NameData::<Test> {
+                value: b"bar".to_vec(),
+                owner: Mod::account_id(),
+                expiration: None,
+                nonce: 0,
+                held_since: 1,
+            }));
+            assert_eq!(Names::lookup(b"nmc/d/baz".to_vec()).unwrap().value, b"".to_vec());
+            assert_eq!(Mod::last_update(b"d/foo".to_vec()), Some(10));
+
+            /* The mirrored names cannot be updated by users, and nobody can
+               register names in the namespace directly.  */
+            assert_noop!(Names::update(Origin::signed(1), b"nmc/d/foo".to_vec(), vec![]),
+                         "non-owner name update");
+            assert_noop!(Names::update(Origin::signed(1), b"nmc/d/new".to_vec(), vec![]),
+                         "name is in a reserved namespace");
+        });
+    }
+
+    #[test]
+    fn invalid_proofs() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let block = setup_chain((&tx1, &tx2), 2);
+            let branch = vec![double_sha256(&tx2)];
+
+            assert_noop!(Mod::mirror_name(Origin::ROOT, block, tx1.clone(), 0, 0, branch.clone()),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::mirror_name(Origin::signed(1), H256::default(), tx1.clone(), 0,
+                                          0, branch.clone()),
+                         "block is unknown");
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          1, branch.clone()),
+                         "invalid merkle branch");
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          0, vec![]),
+                         "invalid merkle branch");
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx2.clone(), 0,
+                                          1, vec![double_sha256(&tx1)]),
+                         "output is not a name update");
+        });
+    }
+
+    #[test]
+    fn confirmations() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let block = setup_chain((&tx1, &tx2), 1);
+            let branch = vec![double_sha256(&tx2)];
+
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0, 0, branch.clone()),
+                         "block does not have enough confirmations");
+        });
+    }
+
+    #[test]
+    fn huge_heights() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let root = merkle_parent(double_sha256(&tx1), double_sha256(&tx2));
+            let first = header(H256::default(), root);
+            checkpoint(&first, u32::max_value());
+            let block = double_sha256(&first);
+
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          0, vec![double_sha256(&tx2)]),
+                         "block does not have enough confirmations");
+            assert_noop!(Mod::submit_header(Origin::ROOT, header(block, H256::default())),
+                         "block height is too large");
+        });
+    }
+
+    #[test]
+    fn stale_blocks() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let block = setup_chain((&tx1, &tx2), 1);
+            let root = merkle_parent(double_sha256(&tx1), double_sha256(&tx2));
+
+            /* A block with the transaction that is confirmed, but then
+               reorged away by a chain with more work.  */
+            let stale = header(block, root);
+            assert_ok!(Mod::submit_header(Origin::ROOT, stale.clone()));
+            assert_ok!(Mod::submit_header(Origin::ROOT,
+                                          header(double_sha256(&stale), H256::default())));
+            let mut prev = block;
+            for _ in 0..3 {
+                let hdr = header(prev, H256::default());
+                assert_ok!(Mod::submit_header(Origin::ROOT, hdr.clone()));
+                prev = double_sha256(&hdr);
+            }
+
+            assert_noop!(Mod::mirror_name(Origin::signed(1), double_sha256(&stale), tx1.clone(),
+                                          0, 0, vec![double_sha256(&tx2)]),
+                         "block is not in the best chain");
+        });
+    }
+
+    #[test]
+    fn namespace_must_be_reserved() {
+        build_ext(vec![], vec![]).execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let block = setup_chain((&tx1, &tx2), 2);
+
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          0, vec![double_sha256(&tx2)]),
+                         "bridge namespace is not reserved");
+        });
+    }
+
+    #[test]
+    fn names_of_other_owners() {
+        let names = vec![(b"nmc/d/foo".to_vec(), b"mine".to_vec(), 5, None)];
+        build_ext(names, vec![Namespace::get().to_vec()]).execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"bar")]);
+            let tx2 = transaction(&[vec![0x51]]);
+            let block = setup_chain((&tx1, &tx2), 2);
+
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          0, vec![double_sha256(&tx2)]),
+                         "name is not owned by the bridge");
+            assert_eq!(Names::lookup(b"nmc/d/foo".to_vec()).unwrap().owner, 5);
+            assert_eq!(Mod::last_update(b"d/foo".to_vec()), None);
+        });
+    }
+
+    #[test]
+    fn ordering() {
+        new_test_ext().execute_with(|| {
+            let tx1 = transaction(&[update_script(b"d/foo", b"old")]);
+            let tx2 = transaction(&[update_script(b"d/foo", b"new")]);
+            let block = setup_chain((&tx1, &tx2), 2);
+
+            <LastUpdate>::insert(b"d/foo".to_vec(), 10);
+            assert_noop!(Mod::mirror_name(Origin::signed(1), block, tx1.clone(), 0,
+                                          0, vec![double_sha256(&tx2)]),
+                         "a newer update has been mirrored already");
+
+            <LastUpdate>::insert(b"d/foo".to_vec(), 9);
+            assert_ok!(Mod::mirror_name(Origin::signed(1), block, tx2.clone(), 0,
+                                        1, vec![double_sha256(&tx1)]));
+            assert_eq!(Names::lookup(b"nmc/d/foo".to_vec()).unwrap().value, b"new".to_vec());
+        });
+    }
+
+}
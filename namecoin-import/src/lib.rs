@@ -0,0 +1,349 @@
+/*
+    namecoin-import - migrate a Namecoin name database to Substrate names
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Conversion of name dumps from Namecoin Core into data that can be used
+//! to bootstrap a Substrate chain with the `names` pallet.
+//!
+//! The input are the JSON results of Namecoin's `name_show` (a single object)
+//! or `name_filter` / `name_scan` (an array of objects) RPC methods.  Several
+//! of them can also simply be concatenated in a single file.
+//!
+//! From those entries, the tool can either produce the list of names for the
+//! pallet's genesis config (which requires a [mapping](OwnerMapping)
+//! from Namecoin addresses to accounts on the new chain), or a Merkle tree
+//! of *claims* that can be used to let the Namecoin owners claim their
+//! names on the new chain themselves.
+
+use codec::Encode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sp_core::hashing::blake2_256;
+
+use std::collections::BTreeMap;
+
+/// A single name with its data as found in the Namecoin dump.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameEntry {
+    /// The name as raw bytes.
+    pub name: Vec<u8>,
+    /// The name's value as raw bytes.
+    pub value: Vec<u8>,
+    /// The address owning the name, if it is a standard script.
+    pub address: Option<String>,
+    /// Number of Namecoin blocks until the name expires.
+    pub expires_in: Option<i64>,
+    /// Whether the name is already expired.
+    pub expired: bool,
+}
+
+/// The JSON format of a name as returned by Namecoin Core's RPC interface.
+#[derive(Deserialize)]
+struct RpcName {
+    name: String,
+    name_encoding: Option<String>,
+    value: String,
+    value_encoding: Option<String>,
+    address: Option<String>,
+    expires_in: Option<i64>,
+    expired: Option<bool>,
+}
+
+/// Decodes a name or value string from the RPC result according to
+/// the encoding that Namecoin Core reports for it.
+fn decode_data(data: &str, encoding: Option<&str>) -> Result<Vec<u8>, String> {
+    match encoding {
+        None | Some("ascii") | Some("utf8") => Ok(data.as_bytes().to_vec()),
+        Some("hex") => hex::decode(data).map_err(|e| format!("invalid hex data: {}", e)),
+        Some(enc) => Err(format!("unsupported data encoding: {}", enc)),
+    }
+}
+
+impl NameEntry {
+
+    /// Converts a single JSON object from the RPC interface.
+    fn from_json(val: Value) -> Result<NameEntry, String> {
+        let raw: RpcName = serde_json::from_value(val)
+            .map_err(|e| format!("invalid name object: {}", e))?;
+        Ok(NameEntry {
+            name: decode_data(&raw.name, raw.name_encoding.as_deref())?,
+            value: decode_data(&raw.value, raw.value_encoding.as_deref())?,
+            address: raw.address,
+            expires_in: raw.expires_in,
+            expired: raw.expired.unwrap_or(false),
+        })
+    }
+
+}
+
+/// Parses a dump of names.  The input may contain any number of JSON
+/// objects (single names) and arrays of them.
+pub fn parse_dump(input: &str) -> Result<Vec<NameEntry>, String> {
+    let mut res = Vec::new();
+    for val in serde_json::Deserializer::from_str(input).into_iter::<Value>() {
+        match val.map_err(|e| format!("invalid JSON: {}", e))? {
+            Value::Array(arr) => {
+                for entry in arr {
+                    res.push(NameEntry::from_json(entry)?);
+                }
+            },
+            obj => res.push(NameEntry::from_json(obj)?),
+        }
+    }
+    Ok(res)
+}
+
+/// Hook for determining which account on the new chain should own
+/// a name from the Namecoin dump.
+pub trait OwnerMapping {
+    /// Returns the account (in whatever string representation the chain
+    /// spec uses, e.g. SS58) that should own the given name, or `None`
+    /// if the name should not be imported.
+    fn owner_for(&self, entry: &NameEntry) -> Option<String>;
+}
+
+/// Simple owner mapping based on a fixed table from Namecoin addresses
+/// to accounts, with an optional fallback account for all other names.
+#[derive(Clone, Debug, Default)]
+pub struct AddressMap {
+    /// The table of addresses to accounts.
+    accounts: BTreeMap<String, String>,
+    /// Account that gets all names of unmapped addresses.
+    fallback: Option<String>,
+}
+
+impl AddressMap {
+
+    /// Constructs the mapping from a JSON object with Namecoin addresses
+    /// as keys and the corresponding accounts as values.
+    pub fn from_json(input: &str) -> Result<AddressMap, String> {
+        let accounts = serde_json::from_str(input)
+            .map_err(|e| format!("invalid address map: {}", e))?;
+        Ok(AddressMap {
+            accounts: accounts,
+            fallback: None,
+        })
+    }
+
+    /// Sets the fallback account that receives all names whose owner
+    /// address is not explicitly mapped.
+    pub fn with_fallback(mut self, account: String) -> AddressMap {
+        self.fallback = Some(account);
+        self
+    }
+
+}
+
+impl OwnerMapping for AddressMap {
+    fn owner_for(&self, entry: &NameEntry) -> Option<String> {
+        entry.address.as_ref()
+            .and_then(|addr| self.accounts.get(addr))
+            .or(self.fallback.as_ref())
+            .cloned()
+    }
+}
+
+/// A name as it will be put into the genesis config.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenesisEntry {
+    /// The name.
+    pub name: Vec<u8>,
+    /// The name's value.
+    pub value: Vec<u8>,
+    /// The account owning the name.
+    pub owner: String,
+    /// The expiration block height on the new chain.
+    pub expiration: Option<u64>,
+}
+
+/// Converts the entries from a dump to genesis names.  Expired names are
+/// dropped, and names for which the owner mapping returns `None` are returned
+/// separately (so that the caller can report them).
+///
+/// The remaining lifetime of each name is multiplied by `block_ratio`, which
+/// should be set to the number of blocks on the new chain per
+/// Namecoin block (e.g. 100 for six-second blocks).
+pub fn to_genesis<M: OwnerMapping>(entries: &[NameEntry], mapping: &M, block_ratio: u64)
+        -> (Vec<GenesisEntry>, Vec<NameEntry>) {
+    let mut res = Vec::new();
+    let mut skipped = Vec::new();
+
+    for entry in entries.iter().filter(|e| !e.expired) {
+        match mapping.owner_for(entry) {
+            None => skipped.push(entry.clone()),
+            Some(owner) => res.push(GenesisEntry {
+                name: entry.name.clone(),
+                value: entry.value.clone(),
+                owner: owner,
+                expiration: entry.expires_in.map(|n| (n.max(1) as u64) * block_ratio),
+            }),
+        }
+    }
+
+    (res, skipped)
+}
+
+/// Formats genesis entries as the `names` pallet's genesis config in JSON
+/// (as it appears in a chain spec).
+pub fn genesis_json(entries: &[GenesisEntry]) -> Value {
+    let names: Vec<Value> = entries.iter()
+        .map(|e| json!([e.name, e.value, e.owner, e.expiration]))
+        .collect();
+    json!({ "names": names })
+}
+
+/// A hash in the claims Merkle tree.
+pub type Hash = [u8; 32];
+
+/// Prefix byte of the hashed data for leaves in the Merkle tree.
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix byte of the hashed data for inner nodes in the Merkle tree.  This
+/// differs from [`LEAF_PREFIX`], so that an inner node can never be passed
+/// off as a leaf (or vice versa).
+const NODE_PREFIX: u8 = 0x01;
+
+/// Computes the Merkle leaf for claiming the given name.  It commits to the
+/// name, its value and the owner address (as string).
+pub fn claim_leaf(entry: &NameEntry) -> Hash {
+    let address = entry.address.clone().unwrap_or_default().into_bytes();
+    let mut data = vec![LEAF_PREFIX];
+    data.extend((&entry.name, &entry.value, address).encode());
+    blake2_256(&data)
+}
+
+/// Hashes two children in the Merkle tree to their parent.
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut data = vec![NODE_PREFIX];
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    blake2_256(&data)
+}
+
+/// A binary Merkle tree over a list of leaves.  If a layer has an odd number
+/// of elements, the last one is promoted unchanged to the next layer.
+pub struct MerkleTree {
+    /// All layers of the tree, starting with the leaves.
+    layers: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+
+    /// Constructs the tree for the given leaves.
+    pub fn new(leaves: Vec<Hash>) -> MerkleTree {
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers.last().unwrap()
+                .chunks(2)
+                .map(|c| match c.get(1) {
+                    Some(right) => hash_pair(&c[0], right),
+                    None => c[0],
+                })
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers: layers }
+    }
+
+    /// Returns the root hash.  For an empty tree, this is all zeros.
+    pub fn root(&self) -> Hash {
+        self.layers.last().unwrap().first().cloned().unwrap_or_default()
+    }
+
+    /// Returns the number of leaves.
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    /// Returns true if the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    /// Returns the proof (the sibling hashes from leaf to root) for the leaf
+    /// with the given index.  Layers in which the node is promoted without
+    /// a sibling do not contribute to the proof.
+    pub fn proof(&self, mut index: usize) -> Vec<Hash> {
+        let mut res = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                res.push(*sibling);
+            }
+            index /= 2;
+        }
+        res
+    }
+
+}
+
+/// Verifies a Merkle proof as returned by [`MerkleTree::proof`] for the leaf
+/// with the given index in a tree of `count` leaves.  The number of leaves
+/// determines in which layers the node is promoted without a sibling.
+pub fn verify_proof(leaf: &Hash, mut index: usize, mut count: usize,
+                    proof: &[Hash], root: &Hash) -> bool {
+    if index >= count {
+        return false;
+    }
+
+    let mut siblings = proof.iter();
+    let mut cur = *leaf;
+    while count > 1 {
+        if index ^ 1 < count {
+            let sibling = match siblings.next() {
+                Some(sibling) => sibling,
+                None => return false,
+            };
+            cur = if index % 2 == 0 {
+                hash_pair(&cur, sibling)
+            } else {
+                hash_pair(sibling, &cur)
+            };
+        }
+        index /= 2;
+        count = (count + 1) / 2;
+    }
+
+    siblings.next().is_none() && cur == *root
+}
+
+/// Builds the claims Merkle tree for all non-expired names and returns
+/// its root and the individual claims with their proofs as JSON.
+pub fn claims_json(entries: &[NameEntry]) -> Value {
+    let active: Vec<&NameEntry> = entries.iter().filter(|e| !e.expired).collect();
+    let leaves: Vec<Hash> = active.iter().map(|e| claim_leaf(e)).collect();
+    let tree = MerkleTree::new(leaves.clone());
+
+    let claims: Vec<Value> = active.iter().enumerate()
+        .map(|(i, e)| json!({
+            "name": hex::encode(&e.name),
+            "value": hex::encode(&e.value),
+            "address": e.address,
+            "leaf": hex::encode(&leaves[i]),
+            "proof": tree.proof(i).iter().map(hex::encode).collect::<Vec<_>>(),
+        }))
+        .collect();
+
+    json!({
+        "root": hex::encode(tree.root()),
+        "count": tree.len(),
+        "claims": claims,
+    })
+}
+
+/// Module with unit tests.
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,100 @@
+/*
+    namecoin-import - migrate a Namecoin name database to Substrate names
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Command-line interface for converting Namecoin name dumps.
+//!
+//! Usage:
+//!
+//!     namecoin-import genesis DUMP [--owners MAP] [--fallback ACCOUNT] [--block-ratio N]
+//!     namecoin-import claims DUMP
+//!
+//! The result is printed as JSON to stdout.
+
+use namecoin_import::{AddressMap, claims_json, genesis_json, parse_dump, to_genesis};
+
+use std::fs;
+use std::process;
+
+const USAGE: &str = "\
+Usage:
+  namecoin-import genesis DUMP [--owners MAP] [--fallback ACCOUNT] [--block-ratio N]
+  namecoin-import claims DUMP";
+
+/// Reads a file into a string.
+fn read_file(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    if args.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let entries = parse_dump(&read_file(&args[1])?)?;
+
+    match args[0].as_str() {
+        "genesis" => {
+            let mut owners = None;
+            let mut fallback = None;
+            let mut block_ratio = 1;
+
+            let mut rest = args[2..].iter();
+            while let Some(flag) = rest.next() {
+                let arg = rest.next().ok_or_else(|| format!("missing argument for {}", flag))?;
+                match flag.as_str() {
+                    "--owners" => owners = Some(read_file(arg)?),
+                    "--fallback" => fallback = Some(arg.clone()),
+                    "--block-ratio" => {
+                        block_ratio = arg.parse().map_err(|_| format!("invalid block ratio: {}", arg))?;
+                    },
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+
+            let mut mapping = match owners {
+                None => AddressMap::default(),
+                Some(json) => AddressMap::from_json(&json)?,
+            };
+            if let Some(account) = fallback {
+                mapping = mapping.with_fallback(account);
+            }
+
+            let (genesis, skipped) = to_genesis(&entries, &mapping, block_ratio);
+            for entry in &skipped {
+                eprintln!("Skipping name without owner: {}", String::from_utf8_lossy(&entry.name));
+            }
+            println!("{}", genesis_json(&genesis));
+        },
+        "claims" => {
+            if args.len() > 2 {
+                return Err(USAGE.to_string());
+            }
+            println!("{}", claims_json(&entries));
+        },
+        _ => return Err(USAGE.to_string()),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(msg) = run(std::env::args().skip(1).collect()) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}
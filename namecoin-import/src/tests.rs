@@ -0,0 +1,169 @@
+/*
+    namecoin-import - migrate a Namecoin name database to Substrate names
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+use super::*;
+
+/// Example output of name_filter with a few names.
+const DUMP: &str = r#"
+[
+  {
+    "name": "d/example",
+    "name_encoding": "ascii",
+    "value": "{\"ip\":\"1.2.3.4\"}",
+    "value_encoding": "ascii",
+    "address": "N1",
+    "expires_in": 1000,
+    "expired": false
+  },
+  {
+    "name": "0001",
+    "name_encoding": "hex",
+    "value": "",
+    "address": "N2",
+    "expires_in": -5,
+    "expired": true
+  }
+]
+{
+  "name": "id/foo",
+  "value": "bar",
+  "address": "N3",
+  "expires_in": 0
+}
+"#;
+
+fn entry(name: &[u8], value: &[u8], address: &str, expires_in: i64, expired: bool) -> NameEntry {
+    NameEntry {
+        name: name.to_vec(),
+        value: value.to_vec(),
+        address: Some(address.to_string()),
+        expires_in: Some(expires_in),
+        expired: expired,
+    }
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(parse_dump(DUMP).unwrap(), vec![
+        entry(b"d/example", b"{\"ip\":\"1.2.3.4\"}", "N1", 1000, false),
+        entry(&[0x00, 0x01], b"", "N2", -5, true),
+        entry(b"id/foo", b"bar", "N3", 0, false),
+    ]);
+}
+
+#[test]
+fn parsing_errors() {
+    assert!(parse_dump("[{\"name\":\"x\"}]").is_err());
+    assert!(parse_dump("{\"name\":\"zz\",\"name_encoding\":\"hex\",\"value\":\"\"}").is_err());
+    assert!(parse_dump("{\"name\":\"x\",\"name_encoding\":\"foo\",\"value\":\"\"}").is_err());
+    assert!(parse_dump("[").is_err());
+}
+
+#[test]
+fn genesis_conversion() {
+    let entries = parse_dump(DUMP).unwrap();
+
+    let mapping = AddressMap::from_json(r#"{"N1": "alice"}"#).unwrap();
+    let (genesis, skipped) = to_genesis(&entries, &mapping, 100);
+    assert_eq!(genesis, vec![GenesisEntry {
+        name: b"d/example".to_vec(),
+        value: b"{\"ip\":\"1.2.3.4\"}".to_vec(),
+        owner: "alice".to_string(),
+        expiration: Some(100_000),
+    }]);
+    assert_eq!(skipped, vec![entries[2].clone()]);
+
+    let mapping = mapping.with_fallback("bob".to_string());
+    let (genesis, skipped) = to_genesis(&entries, &mapping, 1);
+    assert_eq!(genesis.len(), 2);
+    assert_eq!(genesis[1].owner, "bob");
+    assert_eq!(genesis[1].expiration, Some(1));
+    assert!(skipped.is_empty());
+
+    assert_eq!(genesis_json(&genesis[1..]), json!({
+        "names": [[[105, 100, 47, 102, 111, 111], [98, 97, 114], "bob", 1]],
+    }));
+}
+
+#[test]
+fn merkle_proofs() {
+    for n in 1..10 {
+        let leaves: Vec<Hash> = (0..n).map(|i| blake2_256(&[i as u8])).collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let root = tree.root();
+        assert_eq!(tree.len(), n);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(i);
+            assert!(verify_proof(leaf, i, n, &proof, &root));
+            assert!(!verify_proof(&[0; 32], i, n, &proof, &root));
+            assert!(!verify_proof(leaf, i, n + 1, &proof, &root));
+            assert!(!verify_proof(leaf, i + n, n, &proof, &root));
+        }
+    }
+
+    assert!(MerkleTree::new(vec![]).is_empty());
+    assert_eq!(MerkleTree::new(vec![]).root(), [0; 32]);
+}
+
+#[test]
+fn merkle_odd_nodes_promoted() {
+    let leaves: Vec<Hash> = (0..3).map(|i| blake2_256(&[i as u8])).collect();
+    let tree = MerkleTree::new(leaves.clone());
+
+    /* The third leaf has no sibling, so it is promoted as is and only
+       paired with the parent of the first two.  */
+    assert_eq!(tree.proof(2).len(), 1);
+    assert!(verify_proof(&leaves[2], 2, 3, &tree.proof(2), &tree.root()));
+
+    /* Duplicating the last leaf must not yield the same root.  */
+    let mut duplicated = leaves.clone();
+    duplicated.push(leaves[2]);
+    assert_ne!(MerkleTree::new(duplicated).root(), tree.root());
+}
+
+#[test]
+fn merkle_domain_separation() {
+    let entries = parse_dump(DUMP).unwrap();
+    let address = entries[0].address.clone().unwrap_or_default().into_bytes();
+    let mut data = vec![0x00];
+    data.extend((&entries[0].name, &entries[0].value, address).encode());
+    assert_eq!(claim_leaf(&entries[0]), blake2_256(&data));
+
+    /* Inner nodes are hashed with a different prefix, so that they can
+       never be confused with leaves.  */
+    let leaves = vec![claim_leaf(&entries[0]), claim_leaf(&entries[2])];
+    let mut data = vec![0x01];
+    data.extend_from_slice(&leaves[0]);
+    data.extend_from_slice(&leaves[1]);
+    assert_eq!(MerkleTree::new(leaves).root(), blake2_256(&data));
+}
+
+#[test]
+fn claims() {
+    let entries = parse_dump(DUMP).unwrap();
+    let claims = claims_json(&entries);
+    let list = claims["claims"].as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[1]["name"], json!(hex::encode(b"id/foo")));
+    assert_eq!(list[1]["leaf"], json!(hex::encode(claim_leaf(&entries[2]))));
+
+    let root = MerkleTree::new(vec![claim_leaf(&entries[0]), claim_leaf(&entries[2])]).root();
+    assert_eq!(claims["root"], json!(hex::encode(root)));
+    assert_eq!(claims["count"], json!(2));
+}
@@ -0,0 +1,42 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Initial values for names that are registered without an explicit value,
+//! e.g. by a [transfer](crate::Module::transfer) of a name that does not
+//! exist yet or by [revealing](crate::Module::reveal_name) a hidden
+//! registration.
+
+/// Provides the initial value of names registered without a value.
+///
+/// Runtimes can use this to seed a meaningful value that applications
+/// can consume right away, e.g. a JSON stub with the owner's address.
+pub trait DefaultValueProvider<Name, Value, AccountId> {
+
+    /// Returns the value for the given name when it is registered
+    /// for `owner`.
+    fn default_value_for(name: &Name, owner: &AccountId) -> Value;
+
+}
+
+/// The unit type uses the value type's [default](Default) for all names.
+impl<Name, Value: Default, AccountId> DefaultValueProvider<Name, Value, AccountId> for () {
+    fn default_value_for(_name: &Name, _owner: &AccountId) -> Value {
+        Value::default()
+    }
+}
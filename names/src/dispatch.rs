@@ -0,0 +1,45 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Filters for calls that are [dispatched as a name](crate::Module::dispatch_as_name).
+
+/// Decides which calls the owner of a name may dispatch from the name's
+/// sovereign account.
+pub trait CallFilter<Name, Call> {
+
+    /// Returns true if the given call may be dispatched as the name.
+    fn allow_call(name: &Name, call: &Call) -> bool;
+
+}
+
+/// The unit type disables dispatching calls as names altogether.
+impl<Name, Call> CallFilter<Name, Call> for () {
+    fn allow_call(_name: &Name, _call: &Call) -> bool {
+        false
+    }
+}
+
+/// Filter that allows any call to be dispatched as a name.
+pub struct AllowAll;
+
+impl<Name, Call> CallFilter<Name, Call> for AllowAll {
+    fn allow_call(_name: &Name, _call: &Call) -> bool {
+        true
+    }
+}
@@ -0,0 +1,57 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Encrypted values that an owner [publishes](crate::Module::publish_envelope)
+//! for specific readers of a name, e.g. for private messaging.  The pallet
+//! does not know anything about the encryption scheme.  It only checks
+//! the structure of an envelope, and stores the ciphertext under the
+//! reader's public key.
+
+use codec::{Decode, Encode};
+use sp_core::H256;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+/// A value encrypted for a single reader.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct Envelope {
+    /// Public (encryption) key of the reader the value is addressed to.
+    pub recipient: H256,
+    /// The encrypted value.
+    pub ciphertext: Vec<u8>,
+}
+
+impl Envelope {
+
+    /// Checks that the envelope is well-formed, i.e. has a recipient key
+    /// and a non-empty ciphertext of at most `max_size` bytes.
+    pub fn check_structure(&self, max_size: u32) -> Result<(), &'static str> {
+        if self.recipient.is_zero() {
+            return Err("envelope has no recipient");
+        }
+        if self.ciphertext.is_empty() {
+            return Err("envelope has no ciphertext");
+        }
+        if self.ciphertext.len() > max_size as usize {
+            return Err("envelope ciphertext is too large");
+        }
+        Ok(())
+    }
+
+}
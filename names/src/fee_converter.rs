@@ -0,0 +1,50 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Conversion of name fees from a reference unit (e.g. USD) to the native
+//! currency.  This allows registries to keep their prices stable even
+//! if the native token is volatile.
+
+/// Converts name fees quoted in a reference unit to the native currency,
+/// typically based on the rate reported by an oracle.
+///
+/// The fees returned by the [name policy](crate::Trait::Policy) are in the
+/// reference unit, and are converted whenever an operation is checked.
+pub trait FeeConverter<Balance, BlockNumber> {
+
+    /// Converts an amount in the reference unit to the native currency.
+    /// Returns `None` if no conversion rate is available.
+    fn to_native(amount: Balance) -> Option<Balance>;
+
+    /// Returns the block height at which the conversion rate was last
+    /// updated.  Fees are refused if this is older than
+    /// [the maximum age](crate::Trait::MaxFeeRateAge).  `None` means that
+    /// the rate is fixed and never becomes stale.
+    fn last_update() -> Option<BlockNumber>;
+
+}
+
+/// The unit type quotes fees in the native currency directly.
+impl<Balance, BlockNumber> FeeConverter<Balance, BlockNumber> for () {
+    fn to_native(amount: Balance) -> Option<Balance> {
+        Some(amount)
+    }
+    fn last_update() -> Option<BlockNumber> {
+        None
+    }
+}
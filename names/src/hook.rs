@@ -0,0 +1,49 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Hook through which other pallets are notified of executed name
+//! operations, e.g. a game that interprets the values of names in its
+//! namespace as moves.
+
+use crate::{NameData, OperationType, Trait};
+
+/// Called by the names module after each name operation has been executed.
+/// The hook gets the type of the operation, the name and its new data.
+/// It runs after all changes have been written and cannot make the
+/// operation fail.
+pub trait OnNameOperation<T: Trait> {
+
+    /// Processes an executed name operation.
+    fn on_name_operation(operation: OperationType, name: &T::Name, data: &NameData<T>);
+
+}
+
+/// The unit type can be used if nothing needs to be notified.
+impl<T: Trait> OnNameOperation<T> for () {
+    fn on_name_operation(_operation: OperationType, _name: &T::Name, _data: &NameData<T>) {
+    }
+}
+
+/// A pair of hooks notifies both of them (in order).  Nesting pairs allows
+/// to combine any number of hooks.
+impl<T: Trait, A: OnNameOperation<T>, B: OnNameOperation<T>> OnNameOperation<T> for (A, B) {
+    fn on_name_operation(operation: OperationType, name: &T::Name, data: &NameData<T>) {
+        A::on_name_operation(operation, name, data);
+        B::on_name_operation(operation, name, data);
+    }
+}
@@ -0,0 +1,41 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Optional linking of names to on-chain identities (e.g. registrations
+//! in the identity pallet).  This allows explorers to show verified
+//! identity information next to names.
+
+/// Tells the names module whether an account has an identity that
+/// names can be [linked to](crate::Module::link_identity).
+///
+/// A runtime with the identity pallet can implement this by checking
+/// whether `IdentityOf` has an entry (with whatever judgements it
+/// requires) for the account.
+pub trait IdentityProvider<AccountId> {
+
+    /// Returns true if the given account has an identity.
+    fn has_identity(who: &AccountId) -> bool;
+
+}
+
+/// The unit type can be used to disable identity links.
+impl<AccountId> IdentityProvider<AccountId> for () {
+    fn has_identity(_who: &AccountId) -> bool {
+        false
+    }
+}
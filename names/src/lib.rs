@@ -50,14 +50,80 @@
 //! from external code by using [`lookup`](Module::lookup).
 
 use frame_support::{
-    decl_module, decl_storage, decl_event, ensure,
+    debug, decl_module, decl_storage, decl_event, ensure, IsSubType, Parameter,
     dispatch::DispatchResult, dispatch::fmt::Debug,
-    traits::{Currency, ExistenceRequirement, WithdrawReason, WithdrawReasons},
+    traits::{
+        Currency, EnsureOrigin, ExistenceRequirement, Get, Imbalance, LockIdentifier,
+        OnFreeBalanceZero,
+        Randomness, ReservableCurrency, WithdrawReason,
+    },
+    weights::{
+        ClassifyDispatch, DispatchClass, DispatchInfo, GetDispatchInfo, PaysFee,
+        SimpleDispatchInfo, WeighData, Weight,
+    },
 };
-use codec::{Decode, Encode, FullCodec};
-use system::ensure_signed;
-use sp_runtime::traits::CheckedSub;
-use core::cmp::max;
+use codec::{Decode, Encode, EncodeLike, FullCodec, Input, Output};
+use system::{ensure_signed, RawOrigin};
+use sp_core::H256;
+use sp_runtime::{DispatchError, ModuleId, Perbill, RuntimeDebug};
+use sp_runtime::traits::{
+    AccountIdConversion, CheckedAdd, Dispatchable, Hash, IdentifyAccount,
+    SaturatedConversion, Saturating, SignedExtension, Verify, Zero,
+};
+use sp_runtime::transaction_validity::{
+    InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+};
+use sp_io::hashing::blake2_256;
+use sp_std::{marker::PhantomData, prelude::*};
+use core::cmp::{max, min};
+
+pub mod default_value;
+pub mod dispatch;
+pub mod envelope;
+pub mod fee_converter;
+pub mod hook;
+pub mod identity;
+pub mod lock;
+pub mod migration;
+pub mod namehash;
+pub mod namespace;
+pub mod patch;
+pub mod payment;
+pub mod policy;
+pub mod runtime_api;
+pub mod template;
+pub mod weights;
+#[cfg(feature = "std")]
+pub mod xaya;
+
+use default_value::DefaultValueProvider;
+use dispatch::CallFilter;
+use envelope::Envelope;
+use fee_converter::FeeConverter;
+use hook::OnNameOperation;
+use identity::IdentityProvider;
+use lock::NameLocks;
+use migration::NameMigration;
+use namehash::Namehash;
+use namespace::NamespaceParser;
+use patch::ValuePatch;
+use payment::NamePayment;
+use template::ValueTemplate;
+use weights::{OperationWeight, WeightInfo};
+use policy::{
+    ExpirationAction, ExpirationHandler, NamePolicy, ReapedNameAction, ReapedOwnerPolicy,
+    ValueCompliance,
+};
+
+/// Log target used by the pallet.
+const LOG_TARGET: &str = "runtime::names";
+
+/// Type of balances in the currency used for name fees.
+pub type BalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+/// Type of imbalances that result from paying name fees.
+pub type NegativeImbalanceOf<T> =
+    <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
 
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait {
@@ -66,34 +132,354 @@ pub trait Trait: system::Trait {
     type Name: Clone + Debug + Default + Eq + FullCodec;
     /// Type for values associated to names.
     type Value: Clone + Debug + Default + Eq + FullCodec;
+    /// Provides the initial value of names that are registered without
+    /// an explicit value (e.g. by a transfer).  Set this to `()` to use
+    /// the [default](std::default::Default) of the value type.
+    type DefaultValue: DefaultValueProvider<Self::Name, Self::Value, Self::AccountId>;
 
-    /// Type for currency operations (in order to pay for names).
-    type Currency: Currency<Self::AccountId>;
+    /// Type for currency operations (in order to pay for names and
+    /// to reserve deposits).
+    type Currency: ReservableCurrency<Self::AccountId>;
 
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 
-    /// Computes and returns the currency fee the sender has to pay for
-    /// a certain operation.  If `None` is returned, it means that the
-    /// operation is invalid (e.g. the name is too short).
-    fn get_name_fee(op: &Operation<Self>)
-        -> Option<<Self::Currency as Currency<Self::AccountId>>::Balance>;
+    /// The hash function used for the secondary
+    /// [namehash index](Module::resolve_namehash).  This can be set
+    /// to `()` to disable the index.
+    type Namehash: Namehash<Self::Name>;
+
+    /// The fee that has to be paid for a
+    /// [hidden registration](Module::register_hidden).
+    type HiddenRegistrationFee: Get<BalanceOf<Self>>;
+    /// Number of blocks for which a hidden registration reserves a name.
+    /// If it is not revealed before that, the reservation is dropped.
+    type HiddenRegistrationPeriod: Get<Self::BlockNumber>;
+
+    /// Source of on-chain identities that names can be
+    /// [linked to](Module::link_identity).  This can be set to `()`
+    /// to disable identity links.
+    type Identity: IdentityProvider<Self::AccountId>;
+
+    /// Origin (e.g. governance) that is allowed to
+    /// [force changes](Module::force_transfer) to the name database.
+    type ForceOrigin: EnsureOrigin<Self::Origin>;
+    /// Origin (e.g. a council or a token-weighted vote) that
+    /// [decides](Module::decide_report) on reports of squatted names.
+    type ReportOrigin: EnsureOrigin<Self::Origin>;
+    /// Bond that is reserved from the reporter of a squatted name.  It is
+    /// released if the report is upheld, and paid to the name's owner if
+    /// the report is rejected.
+    type ReportBond: Get<BalanceOf<Self>>;
+    /// Number of blocks after a report during which the owner can respond,
+    /// before the report can be decided.
+    type ReportChallengePeriod: Get<Self::BlockNumber>;
+    /// Number of blocks during which the [guardian](Module::set_guardian)
+    /// of a name can confirm a requested transfer.
+    type GuardianConfirmationPeriod: Get<Self::BlockNumber>;
+    /// How names are matched against
+    /// [reserved namespaces](Module::reserved_prefixes).  This can be set
+    /// to `()` if names have no notion of namespaces.
+    type Namespaces: NamespaceParser<Self::Name>;
+    /// Maximum number of [topic prefixes](Module::topic_prefixes).
+    type MaxTopicPrefixes: Get<u32>;
+    /// Maximum number of namespaces [delegated](Module::delegate_namespace)
+    /// to a single account.
+    type MaxDelegatedNamespaces: Get<u32>;
+    /// The community account (e.g. a treasury or governance-controlled
+    /// pallet account) that receives [donated names](Module::donate_name).
+    type CommunityAccount: Get<Self::AccountId>;
+    /// The ID from which the [pallet account](Module::account_id) and the
+    /// accounts of its subsystems (e.g. the
+    /// [insurance pool](Module::insurance_pool)) are derived.
+    type ModuleId: Get<ModuleId>;
+    /// Calls that name owners can [dispatch as their name](Module::dispatch_as_name).
+    type Call: Parameter + Dispatchable<Origin = Self::Origin> + GetDispatchInfo;
+    /// Restricts which calls may be dispatched as a name.  Set this to `()`
+    /// to disable the feature.
+    type NameCallFilter: CallFilter<Self::Name, <Self as Trait>::Call>;
+    /// Maximum number of accounts that can hold shares of a name that is
+    /// [split into shares](Module::split_into_shares).
+    type MaxShareHolders: Get<u32>;
+    /// Maximum number of accounts in the [payout split](Module::set_payout)
+    /// of a name.
+    type MaxPayoutSplits: Get<u32>;
+
+    /// Maximum number of names moved by a single call to
+    /// [`transfer_all_names`](Module::transfer_all_names).
+    type MaxBulkTransfer: Get<u32>;
+    /// Maximum total number of names (on both sides) in a
+    /// [swap offer](Module::propose_swap).
+    type MaxSwapNames: Get<u32>;
+    /// Maximum number of names registered by a single call to
+    /// [`force_register_batch`](Module::force_register_batch).
+    type MaxForceRegistrations: Get<u32>;
+
+    /// Maximum number of names that can exist at the same time, or `None`
+    /// for no limit.  When the limit is reached, new names can only be
+    /// registered once others expire.
+    type MaxTotalNames: Get<Option<u32>>;
+    /// Maximum number of names that can be registered in a single block,
+    /// or `None` for no limit.  This keeps registration floods from
+    /// crowding out updates and renewals of existing names.  Registrations
+    /// beyond the limit fail with [`RetryNextBlock`](OperationError::RetryNextBlock),
+    /// and [`LimitRegistrations`] keeps them out of the current block.
+    type MaxRegistrationsPerBlock: Get<Option<u32>>;
+    /// Congestion pricing for registrations, as list of thresholds for the
+    /// total number of names together with a fee multiplier (in percent)
+    /// that applies when the number of names reaches the threshold.  The
+    /// multiplier is [updated](Module::registration_fee_multiplier) at the
+    /// start of each block.  An empty list disables congestion pricing.
+    type CongestionMultipliers: Get<&'static [(u32, u32)]>;
+
+    /// Source of randomness used to settle conflicting
+    /// [registration intents](Module::register_intent).
+    type Randomness: Randomness<Self::Hash>;
+    /// If true, [`transfer`](Module::transfer) fails for names that do not
+    /// exist, instead of registering them to the recipient.
+    type StrictTransfers: Get<bool>;
+
+    /// If true, names can only be registered through intents (or hidden
+    /// registrations), and not directly with [`update`](Module::update) or
+    /// [`transfer`](Module::transfer).  This makes the outcome of competing
+    /// registrations independent of transaction ordering in the block.
+    type RegistrationIntentsOnly: Get<bool>;
+    /// Bond that is [held](payment::NamePayment::hold) from the sender of
+    /// each [registration intent](Module::register_intent) until the intent
+    /// is settled.  It is returned for valid intents, and forfeited if the
+    /// intent turns out to be invalid at settlement (e.g. because the
+    /// sender cannot pay the fee anymore).
+    type IntentBond: Get<BalanceOf<Self>>;
+    /// Maximum number of [registration intents](Module::register_intent)
+    /// for the same name in a block.
+    type MaxIntentsPerName: Get<u32>;
+
+    /// Minimum deposit for a [preorder](Module::preorder).
+    type MinPreorderDeposit: Get<BalanceOf<Self>>;
+    /// Number of blocks after which a preorder matures, i.e. starts to
+    /// reserve the name exclusively for the preorderer.
+    type PreorderMaturity: Get<Self::BlockNumber>;
+    /// Number of blocks (after maturity) for which a preorder reserves
+    /// the name.
+    type PreorderWindow: Get<Self::BlockNumber>;
+    /// Part of the deposit that is forfeited if a preorder is not used
+    /// to register the name in time.
+    type PreorderForfeit: Get<Perbill>;
+
+    /// If set, newly registered names must receive their
+    /// [first value update](Module::pending_first_update) within this
+    /// number of blocks, or they are released again.
+    type FirstUpdatePeriod: Get<Option<Self::BlockNumber>>;
+    /// Part of the registration fee that is held back until the first
+    /// value update, and refunded if the name is released without it.
+    type FirstUpdateRefund: Get<Perbill>;
+    /// Number of blocks after a name expires before it can be registered
+    /// again.  Otherwise the author of the block in which the name expires
+    /// could always register it first.  Zero disables the quarantine.
+    type ExpirationQuarantine: Get<Self::BlockNumber>;
+    /// Number of blocks for which a [tombstone](Tombstone) of an expired
+    /// name is kept in the [archive](Module::archived), or `None` to not
+    /// archive expired names at all.
+    type ArchiveRetention: Get<Option<Self::BlockNumber>>;
+    /// Part of the name fee that is paid to the
+    /// [registrar](Module::update_via_registrar) of an operation, before
+    /// the rest is handled by the name policy.
+    type RegistrarCommission: Get<Perbill>;
 
-    /// For a given name operation, computes the number of blocks before the
-    /// name will expire again.  If `None` is returned, then the name will
-    /// never expire.
-    fn get_expiration(op: &Operation<Self>) -> Option<Self::BlockNumber>;
+    /// Minimum bond for a [backorder](Module::place_backorder).
+    type MinBackorderBond: Get<BalanceOf<Self>>;
+    /// Maximum number of backorders for a single name.
+    type MaxBackorders: Get<u32>;
+    /// Maximum number of blocks for which a
+    /// [session key](Module::authorize_session_key) can be authorised.
+    type MaxSessionDuration: Get<Self::BlockNumber>;
+    /// Maximum number of names that a single session key can update.
+    type MaxSessionNames: Get<u32>;
+    /// Maximum number of names that a registrar can renew at once with
+    /// [`renew_namespace`](Module::renew_namespace).
+    type MaxBulkRenewals: Get<u32>;
+    /// Maximum number of [insured renewals](Module::insure_name) that a
+    /// name can hold at any time.
+    type MaxInsuredRenewals: Get<u32>;
 
-    /// "Takes ownership" of the fee paid for a name operation.  This
-    /// function can just do nothing to effectively burn the fee, it may
-    /// deposit it to a developer account, or it may give it out to miners.
-    fn deposit_fee(value: <Self::Currency as Currency<Self::AccountId>>::NegativeImbalance);
+    /// The [policy](policy::NamePolicy) for name operations, which
+    /// determines fees and expiration of names.
+    type Policy: NamePolicy<Self>;
+    /// [Extra weight](weights::OperationWeight) that is added to the
+    /// dispatch weight of the basic name operations, e.g. to account for
+    /// an expensive policy.  Set this to `()` to add nothing.
+    type ExtraWeight: OperationWeight<Self::Name, Self::Value>;
+    /// [Base weights](weights::WeightInfo) of the calls.  Set this to `()`
+    /// for the fixed default weights, e.g. in tests.
+    type WeightInfo: WeightInfo;
+    /// [Conversion](fee_converter::FeeConverter) of the fees returned by
+    /// the policy from a reference unit to the native currency.  Set this
+    /// to `()` if the policy quotes fees in the native currency.
+    type FeeConverter: FeeConverter<BalanceOf<Self>, Self::BlockNumber>;
+    /// How name fees are [paid](payment::NamePayment).  Set this to `()`
+    /// to pay them in [the currency](Trait::Currency).
+    type Payment: NamePayment<Self>;
+    /// Maximum age in blocks of the [fee conversion](Trait::FeeConverter)
+    /// rate.  Operations with a fee are refused while the rate is older.
+    type MaxFeeRateAge: Get<Self::BlockNumber>;
+    /// Minimum number of blocks for which a name is registered or renewed
+    /// by an operation.  Operations for which the policy returns a shorter
+    /// expiration are rejected.  This protects against misconfigured
+    /// policies that would quickly fill up the expiration index.
+    type MinExpirationBlocks: Get<Self::BlockNumber>;
+    /// How operations on existing names may change their expiration when
+    /// the policy returns a different period than before, e.g. after the
+    /// policy itself has been changed.
+    type ExpirationUpdates: Get<ExpirationUpdate>;
+    /// Maximum number of names that can be scheduled to expire in the
+    /// same block.  If a block is full, names expire in the next block
+    /// with space instead.  This bounds the work done per block in
+    /// `on_initialize`.
+    type MaxExpirationsPerBlock: Get<u32>;
+    /// Maximum number of expiration index entries that are processed
+    /// automatically in `on_initialize` of their block, or `None` for no
+    /// limit.  Entries beyond it (or all of them, with a limit of zero)
+    /// are left for anyone to [sweep](Module::sweep_expirations).  Until
+    /// then, the expired names stay in the database.
+    type MaxAutoExpirations: Get<Option<u32>>;
+    /// Bounty paid from the [sweep pot](Module::sweep_pot) for each index
+    /// entry processed by [sweeping expirations](Module::sweep_expirations).
+    type SweepBounty: Get<BalanceOf<Self>>;
+    /// If larger than one, expiration heights are rounded up to multiples
+    /// of this.  This reduces the number of distinct blocks in the
+    /// expiration index, and renewals that stay within the same bucket
+    /// need no new index entries.
+    type ExpirationBucketSize: Get<Self::BlockNumber>;
+
+    /// Maximum number of blocks into the future for which a
+    /// [value update can be scheduled](Module::schedule_value).
+    type MaxValueLookahead: Get<Self::BlockNumber>;
+
+    /// Deposit per byte of a name's (encoded) value.  It is reserved from
+    /// whoever sets the value, and reconciled whenever the value changes:
+    /// Growing values require a top-up, shrinking values get a refund.
+    type ValueDepositPerByte: Get<BalanceOf<Self>>;
+
+    /// What [happens](policy::ReapedOwnerPolicy) to names whose owner
+    /// account is reaped.  For this to have an effect, the module has to be
+    /// set as (part of) the `OnFreeBalanceZero` handler of the balances
+    /// module.
+    type OnOwnerReaped: ReapedOwnerPolicy<Self>;
+    /// What [happens](policy::ExpirationHandler) to names when they reach
+    /// their expiration height.  Set this to `()` to just remove them.
+    type OnExpiration: ExpirationHandler<Self>;
+    /// [Content rules](policy::ValueCompliance) that values of names have
+    /// to satisfy once their [compliance window](Trait::ComplianceWindow)
+    /// is over.  Set this to `()` to accept all values.
+    type ValueCompliance: ValueCompliance<Self>;
+    /// Number of blocks after registration during which the value of a
+    /// name need not satisfy the [content rules](Trait::ValueCompliance).
+    /// At the end, names with a non-compliant value are handled according
+    /// to [`NonComplianceAction`](Trait::NonComplianceAction), and from
+    /// then on all operations must leave a compliant value.  `None`
+    /// disables the rules.
+    type ComplianceWindow: Get<Option<Self::BlockNumber>>;
+    /// What happens to names whose value is not compliant at the end of
+    /// their [compliance window](Trait::ComplianceWindow).
+    type NonComplianceAction: Get<ComplianceAction>;
+    /// [Hook](hook::OnNameOperation) that is notified of every executed
+    /// name operation.  Set this to `()` if not needed.
+    type OnNameOperation: OnNameOperation<Self>;
+
+    /// Type of signatures with which owners authorise
+    /// [sponsored updates](Module::sponsored_update).
+    type Signature: Parameter + Verify<Signer = Self::Signer>;
+    /// The signer type corresponding to [signatures](Trait::Signature),
+    /// which must identify accounts.
+    type Signer: IdentifyAccount<AccountId = Self::AccountId>;
+
+    /// How [patch updates](Module::update_patch) are merged into the
+    /// existing value.  This can be set to `()` to disable them.
+    type Patch: ValuePatch<Self::Value>;
+
+    /// Type for the keys of [subvalues](Module::set_subvalue) of a name.
+    type SubKey: Clone + Debug + Eq + FullCodec;
+    /// Maximum number of subvalues a single name can have.
+    type MaxSubvalues: Get<u32>;
+    /// Deposit that is reserved for each subvalue, and released again
+    /// when the subvalue is cleared or the name is removed.
+    type SubvalueDeposit: Get<BalanceOf<Self>>;
+    /// Deposit that is reserved for the [note](Module::set_note) of a name,
+    /// and released again when the note is cleared.
+    type NoteDeposit: Get<BalanceOf<Self>>;
+    /// Maximum size in bytes of the [note](Module::set_note) of a name.
+    type MaxNoteSize: Get<u32>;
+    /// Maximum number of [redirects](Module::set_redirect) that are followed
+    /// when [resolving](Module::resolve) a name.
+    type MaxRedirectDepth: Get<u32>;
+
+    /// Fee for [publishing](Module::publish_envelope) an encrypted envelope.
+    type EnvelopeFee: Get<BalanceOf<Self>>;
+    /// Maximum size (in bytes) of the ciphertext in an envelope.
+    type MaxEnvelopeSize: Get<u32>;
+    /// Maximum number of envelopes a single name can have.
+    type MaxEnvelopes: Get<u32>;
+
+    /// Deposit that is reserved from [watchers](Module::watch_name) of
+    /// a name until they stop watching it.
+    type WatchDeposit: Get<BalanceOf<Self>>;
+    /// Maximum number of accounts that can watch a single name.
+    type MaxWatchers: Get<u32>;
+
+    /// How values are matched against the templates of
+    /// [standing approvals](Module::approve_operator).  Set this to `()`
+    /// to disable operator updates.
+    type Templates: ValueTemplate<Self::Value>;
+    /// Maximum number of templates in a standing approval.
+    type MaxTemplates: Get<u32>;
+
+    /// The [migration](migration::NameMigration) applied to names that are
+    /// [queued](Module::queue_migration) for it.  Set this to `()` if no
+    /// migration is in progress.
+    type Migration: NameMigration<Self>;
+    /// Weight that migrations may consume in each block.  At least one
+    /// name is migrated per block, even if it takes more than this.
+    type MigrationWeightLimit: Get<Weight>;
+    /// Weight that [deferred operations](Module::enqueue) may consume in
+    /// each block.  At least one operation is executed per block, even if
+    /// it takes more than this.
+    type DeferredWeightLimit: Get<Weight>;
+
+    /// Number of blocks for which [receipts](Receipt) of name operations
+    /// are kept before they are pruned.
+    type ReceiptRetention: Get<Self::BlockNumber>;
+    /// Length (in blocks) of the eras for which
+    /// [registrar statistics](RegistrarStats) are kept.
+    type StatsEraLength: Get<Self::BlockNumber>;
+    /// Number of past eras whose statistics are kept.  Older eras are
+    /// overwritten in a ring buffer.
+    type StatsHistory: Get<u32>;
+    /// Number of value updates per name and [era](Trait::StatsEraLength)
+    /// that are exempt from the name fee.  Further updates in the same
+    /// era pay the normal fee.  This allows e.g. frequent game moves while
+    /// still deterring floods of updates.
+    type FreeUpdatesPerEra: Get<u32>;
 
 }
 
+/// Current version of the [`NameData`] encoding.
+const NAME_DATA_VERSION: u8 = 3;
+
 /// All data stored with a name in the database.
+///
+/// The encoding is versioned, so that fields can be added later without
+/// having to migrate all names at once:  The original fields are followed
+/// by a version byte and a length-prefixed extension, which holds the
+/// fields added since (in order of the versions that added them).  Decoding
+/// accepts entries written before versioning (without version and extension),
+/// and ignores extension data of versions newer than known to the runtime.
+///
+/// The versions are:
+/// 1. Empty extension.
+/// 2. Adds the [nonce](NameData::nonce).
+/// 3. Adds [`held_since`](NameData::held_since).
 #[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 pub struct NameData<T: Trait> {
     /// The name's associated value.
     pub value: T::Value,
@@ -101,257 +487,6405 @@ pub struct NameData<T: Trait> {
     pub owner: T::AccountId,
     /// The block number when the name expires or `None` if it does not expire.
     pub expiration: Option<T::BlockNumber>,
+    /// Number of operations executed on the name since it was registered.
+    /// Clients can [assert](Module::set_value_at_nonce) the nonce they
+    /// expect, so that they do not clobber concurrent updates.
+    pub nonce: u64,
+    /// The block since which the current owner holds the name without
+    /// interruption, e.g. for [loyalty pricing](policy::LoyaltyPricing).
+    /// This is zero for names last transferred before it was tracked.
+    pub held_since: T::BlockNumber,
 }
 
-/// Type of a name operation.
+impl<T: Trait> Encode for NameData<T> {
+    fn encode_to<W: Output>(&self, dest: &mut W) {
+        self.value.encode_to(dest);
+        self.owner.encode_to(dest);
+        self.expiration.encode_to(dest);
+        NAME_DATA_VERSION.encode_to(dest);
+        (self.nonce, self.held_since).encode().encode_to(dest);
+    }
+}
+
+impl<T: Trait> EncodeLike for NameData<T> {}
+
+impl<T: Trait> Decode for NameData<T> {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let mut data = NameData::<T> {
+            value: Decode::decode(input)?,
+            owner: Decode::decode(input)?,
+            expiration: Decode::decode(input)?,
+            nonce: 0,
+            held_since: Zero::zero(),
+        };
+
+        /* Entries from before the versioning end right here.  Otherwise,
+           read the fields present in the extension for its version.  Any
+           data after them belongs to later versions and is ignored.  */
+        if input.remaining_len()? == Some(0) {
+            return Ok(data);
+        }
+        let version = u8::decode(input)?;
+        let extension = Vec::<u8>::decode(input)?;
+        let mut extension = &extension[..];
+        if version >= 2 {
+            data.nonce = Decode::decode(&mut extension)?;
+        }
+        if version >= 3 {
+            data.held_since = Decode::decode(&mut extension)?;
+        }
+        Ok(data)
+    }
+}
+
+/// Data stored for a *hidden registration*, i.e. a name that has been
+/// registered only by its hash and not yet revealed.
 #[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Eq, PartialEq)]
-pub enum OperationType {
-    /// This operation registers a name that does not exist yet.
-    Registration,
-    /// This operation updates an existing name.
-    Update,
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct HiddenRegistration<T: Trait> {
+    /// The account that owns the registration.
+    pub owner: T::AccountId,
+    /// The block number at which the registration is dropped if the name
+    /// has not been revealed yet.
+    pub expiration: T::BlockNumber,
 }
 
-/// All data necessary to actually perform a name operation.
-///
-/// This is returned by the
-/// [validation function](Module::check_assuming_signed), and can then
-/// be passed to the [execution function](Module::execute) if a runtime wants
-/// to do its own logic in addition.
-///
-/// A reference to an `Operation` struct is also passed to the [`Trait`](Trait)
-/// functions that need to determine e.g. the name fee for the operation.
+/// Data stored for a [preorder](Module::preorder) of a name by its hash.
 #[cfg_attr(feature = "std", derive(Debug))]
-#[derive(Eq, PartialEq)]
-pub struct Operation<T: Trait> {
-    /// Type of this operation.
-    pub operation: OperationType,
-    /// The name being operated on.
-    pub name: T::Name,
-    /// The value that is being associated to the name.
-    pub value: T::Value,
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct Preorder<T: Trait> {
+    /// The account that made the preorder.
+    pub owner: T::AccountId,
+    /// The reserved deposit.
+    pub deposit: BalanceOf<T>,
+    /// The block from which on the name is reserved.
+    pub matures: T::BlockNumber,
+    /// The block at which the preorder is dropped if the name has not been
+    /// registered by then.
+    pub expiration: T::BlockNumber,
+}
 
-    /// The sender of the name (who pays the name fee).
-    sender: T::AccountId,
-    /// The owner it is sent to.
-    recipient: T::AccountId,
+/// A [subvalue](Module::set_subvalue) stored under a name.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct Subvalue<T: Trait> {
+    /// The value stored for the subkey.
+    pub value: T::Value,
+    /// The account from which the deposit is reserved.
+    pub depositor: T::AccountId,
+    /// The reserved deposit.
+    pub deposit: BalanceOf<T>,
+}
 
-    /// The name fee to pay.
-    fee: <T::Currency as Currency<T::AccountId>>::Balance,
+/// Ownership of a name that is [split into shares](Module::split_into_shares).
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct ShareInfo<T: Trait> {
+    /// The share holders and the number of shares each of them holds.
+    pub holders: Vec<(T::AccountId, u32)>,
+    /// The total number of shares.
+    pub total: u32,
+    /// Fraction of all shares that must approve a value update.
+    pub threshold: Perbill,
 }
 
-decl_storage! {
-    trait Store for Module<T: Trait> as TemplateModule {
-        /// The main mapping from names to [associated data](NameData).
-        Names get(lookup): map T::Name => Option<NameData<T>>;
-        /// All names (as both the second key and the value) that may expire at
-        /// the given block height (first key).  We use this so we can
-        /// efficiently process expirations whenever we process a new block.
-        /// When names are updated, they are not removed from here, though --
-        /// so a name's expiration value in the core database overrules this
-        /// index.
-        Expirations: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+impl<T: Trait> ShareInfo<T> {
+
+    /// Returns the number of shares held by the given account.
+    pub fn shares_of(&self, who: &T::AccountId) -> u32 {
+        self.holders.iter().find(|(a, _)| a == who).map_or(0, |(_, n)| *n)
     }
+
 }
 
-decl_module! {
-    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-        fn deposit_event() = default;
+/// A [report](Module::report_name) that a name violates the naming policy
+/// (e.g. by impersonation).
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct SquattingReport<T: Trait> {
+    /// The account that made the report.
+    pub reporter: T::AccountId,
+    /// The bond reserved from the reporter.
+    pub bond: BalanceOf<T>,
+    /// The block from which on the report can be decided.
+    pub decidable_at: T::BlockNumber,
+}
 
-        /// Tries to update a name with a given value.
-        ///
-        /// If the name does not exist yet, it will be created.  If the name
-        /// exists, then only the current owner can update it.
-        pub fn update(origin, name: T::Name, value: T::Value) -> DispatchResult {
-            let who = ensure_signed(origin)?;
-            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
-            Self::execute(data)?;
-            Ok(())
-        }
+/// A [rent-to-own agreement](Module::offer_rent_to_own) for a name.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct RentAgreement<T: Trait> {
+    /// The tenant, who receives the name after all payments are made.
+    pub tenant: T::AccountId,
+    /// The amount of each installment.
+    pub installment: BalanceOf<T>,
+    /// Number of installments that remain to be paid.
+    pub remaining: u32,
+    /// Number of blocks the tenant has for each installment.
+    pub period: T::BlockNumber,
+    /// The block at which the agreement is cancelled if the next
+    /// installment has not been paid before.
+    pub next_due: T::BlockNumber,
+    /// What happens to the name's data when it is handed over.
+    pub terms: HandoverTerms,
+}
 
-        /// Tries to transfer a name to a given recipient.
-        ///
-        /// If the name does not exist, it will be registered directly to them
-        /// with a [default value](std::default::Default).
-        pub fn transfer(origin, name: T::Name, recipient: T::AccountId) -> DispatchResult {
-            let who = ensure_signed(origin)?;
-            let data = Self::check_assuming_signed(who, name, None, Some(recipient))?;
-            Self::execute(data)?;
-            Ok(())
+/// Terms for the data of a name that is handed over at the end of a
+/// [rent-to-own agreement](Module::offer_rent_to_own), so that the
+/// expectations of both parties are part of the agreement.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub struct HandoverTerms {
+    /// If set, the value is reset to the
+    /// [default value](Trait::DefaultValue) for the new owner, so that
+    /// no stale state is handed over.
+    pub clear_value_on_transfer: bool,
+    /// If not set, all [subvalues](Module::set_subvalue) of the name are
+    /// removed on handover.
+    pub preserve_records: bool,
+}
+
+/// An open offer to [swap names](Module::propose_swap) with a
+/// counterparty, stored under the account that proposed it.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct SwapOffer<T: Trait> {
+    /// The account that can accept the offer.
+    pub counterparty: T::AccountId,
+    /// Names of the proposer that go to the counterparty.  They are
+    /// locked against transfers while the offer is open.
+    pub offered: Vec<T::Name>,
+    /// Names of the counterparty that go to the proposer.
+    pub requested: Vec<T::Name>,
+    /// Amount paid by the proposer to the counterparty in addition, which
+    /// is reserved while the offer is open.
+    pub sweetener: BalanceOf<T>,
+}
+
+/// By default, the name is handed over with all its data.
+impl Default for HandoverTerms {
+    fn default() -> Self {
+        HandoverTerms {
+            clear_value_on_transfer: false,
+            preserve_records: true,
         }
+    }
+}
 
-        /// Processes all names logic required before executing extrinsics
-        /// of a given block.  In concrete terms, this function makes sure that
-        /// names expired in the current block will be removed from the
-        /// database.
-        fn on_initialize(h: T::BlockNumber) {
-            Self::expire_names(h);
+/// Which data attached to a name survives when it changes owner, as
+/// [set](Module::set_retention_policy) by the owner.  This lets sellers
+/// and buyers agree up front on what comes with the name.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub struct RetentionPolicy {
+    /// If set, the [subvalues](Module::set_subvalue) of the name are kept.
+    pub keep_records: bool,
+    /// If set, the [operator](Module::approve_operator) stays approved.
+    pub keep_operator: bool,
+    /// If set, the [insured renewals](Module::insure_name) are kept.
+    pub keep_insurance: bool,
+}
+
+/// How the expiration of an existing name is affected by
+/// operations on it, as [configured](Trait::ExpirationUpdates) for
+/// the runtime.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub enum ExpirationUpdate {
+    /// Every operation sets the expiration from the policy, even if that
+    /// is earlier than before.
+    MayShorten,
+    /// Operations only set the expiration from the policy if that is
+    /// later than before.  Names that never expire keep doing so.
+    ExtendOnly,
+    /// Operations keep the expiration, except for
+    /// [explicit renewals](Module::renew), which set it from the policy.
+    Preserve,
+}
+
+/// What happens to a name whose value does not satisfy the
+/// [content rules](Trait::ValueCompliance) at the end of its
+/// [compliance window](Trait::ComplianceWindow), as
+/// [configured](Trait::NonComplianceAction) for the runtime.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub enum ComplianceAction {
+    /// An event warns about the name, but it stays as is.
+    Warn,
+    /// The name is handled as if it had expired.
+    Expire,
+}
+
+/// By default, records are kept while the operator and insurance, which
+/// were arranged by the previous owner, are cleared.
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_records: true,
+            keep_operator: false,
+            keep_insurance: false,
         }
+    }
+}
+
+/// Kind of change to a name that is reported to its
+/// [watchers](Module::watch_name).
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub enum NameChange {
+    /// The name's value has been updated.
+    Update,
+    /// The name has been transferred to a new owner.
+    Transfer,
+    /// The name has expired or been removed.
+    Removal,
+}
+
+/// The payload of a [sponsored update](Module::sponsored_update), which
+/// is signed by the name's owner.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct SponsoredUpdate<T: Trait> {
+    /// The account authorising the update, i.e. the name's current owner
+    /// or the account registering it.
+    pub owner: T::AccountId,
+    /// The name being updated.
+    pub name: T::Name,
+    /// The new value.
+    pub value: T::Value,
+    /// The owner's [operation nonce](Module::operation_nonce), which
+    /// prevents replaying the payload.
+    pub nonce: u64,
+}
+
+/// The payload of a [signed operation](Module::execute_signed_operation),
+/// which is signed by the name's owner and can then be submitted by
+/// anyone (e.g. a relayer).
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct SignedOperation<T: Trait> {
+    /// The account authorising the operation, i.e. the name's current owner
+    /// or the account registering it.  This account pays the name fee.
+    pub owner: T::AccountId,
+    /// The name being operated on.
+    pub name: T::Name,
+    /// The new value, or `None` to keep the existing one.
+    pub value: Option<T::Value>,
+    /// The new owner, or `None` to keep the name with `owner`.
+    pub recipient: Option<T::AccountId>,
+    /// The owner's [operation nonce](Module::operation_nonce), which
+    /// prevents replaying the payload.
+    pub nonce: u64,
+    /// If set, the operation is only valid while the name has this
+    /// [nonce](NameData::nonce).  This ties a signed order to a specific
+    /// state of the name, so it cannot be executed after any other change.
+    pub name_nonce: Option<u64>,
+}
+
+/// An annotation that the owner of a name attached to it with
+/// [`set_note`](Module::set_note), together with its deposit.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct Note<T: Trait> {
+    /// The note's content, which is opaque to the pallet.
+    pub data: Vec<u8>,
+    /// The account that reserved the deposit.
+    pub depositor: T::AccountId,
+    /// The reserved deposit.
+    pub deposit: BalanceOf<T>,
+}
+
+/// Compact record of an expired name that is kept in the
+/// [archive](Module::archived) for historical lookups.  Only the hash of
+/// the value is stored, so that the archive stays cheap.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct Tombstone<T: Trait> {
+    /// The last owner of the name.
+    pub owner: T::AccountId,
+    /// The hash of the name's last value.
+    pub value_hash: T::Hash,
+    /// The block at which the name expired.
+    pub expired_at: T::BlockNumber,
+}
+
+/// A transfer of a name with a [guardian](Module::set_guardian) that has
+/// been requested by the owner.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct GuardedTransfer<T: Trait> {
+    /// The account that receives the name.
+    pub recipient: T::AccountId,
+    /// The last block in which the guardian can confirm the transfer.
+    pub deadline: T::BlockNumber,
+    /// Whether the guardian has confirmed the transfer.
+    pub confirmed: bool,
+}
+
+/// An oracle's attestation that binds a name to an external identity (e.g.
+/// a Twitter handle or web domain), as needed to register names inside
+/// [attested namespaces](Module::attested_namespaces).
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct IdentityAttestation<T: Trait> {
+    /// The external identity, in a format defined by the oracle.
+    pub identity: Vec<u8>,
+    /// The oracle's signature of the
+    /// [attestation message](Module::attestation_message).
+    pub signature: T::Signature,
+}
+
+/// A registered name that still waits for its
+/// [first value update](Trait::FirstUpdatePeriod).
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct PendingFirstUpdate<T: Trait> {
+    /// The block at which the name is released without a value update.
+    pub deadline: T::BlockNumber,
+    /// The account that paid the registration fee.
+    pub payer: T::AccountId,
+    /// The part of the fee that is held back (reserved from the payer).
+    pub held: BalanceOf<T>,
+}
+
+/// The permissions of a [session key](Module::authorize_session_key).
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct SessionGrant<T: Trait> {
+    /// The last block in which the key can be used.
+    pub expires: T::BlockNumber,
+    /// The names whose value the key can set.
+    pub names: Vec<T::Name>,
+}
+
+/// The payload of a [session update](Module::session_update), which is
+/// signed by a session key of the name's owner.
+#[derive(Clone, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub struct SessionUpdate<T: Trait> {
+    /// The owner of the name, who has authorised the session key.
+    pub owner: T::AccountId,
+    /// The session key that signs the payload.
+    pub key: T::AccountId,
+    /// The name being updated.
+    pub name: T::Name,
+    /// The new value.
+    pub value: T::Value,
+    /// The owner's [operation nonce](Module::operation_nonce), which
+    /// prevents replaying the payload.
+    pub nonce: u64,
+}
 
+/// Dispatch weight of a call that processes a list of items (or up to
+/// a given number of items), which is the given weight per item.
+pub struct WeightPerItem(pub Weight);
+
+impl<'a, I> WeighData<(&'a Vec<I>,)> for WeightPerItem {
+    fn weigh_data(&self, (items,): (&'a Vec<I>,)) -> Weight {
+        self.0.saturating_mul(items.len() as Weight)
     }
 }
 
-impl<T: Trait> Module<T> {
+impl<'a, I> ClassifyDispatch<(&'a Vec<I>,)> for WeightPerItem {
+    fn classify_dispatch(&self, _: (&'a Vec<I>,)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
 
-    /// Returns a withdraw reasons value for the fee payment.
-    fn withdraw_reasons() -> WithdrawReasons {
-        let mut res = WithdrawReasons::none();
-        res.set(WithdrawReason::Fee);
-        res
+impl<'a, A, I> WeighData<(&'a A, &'a Vec<I>)> for WeightPerItem {
+    fn weigh_data(&self, (_, items): (&'a A, &'a Vec<I>)) -> Weight {
+        self.0.saturating_mul(items.len() as Weight)
     }
+}
 
-    /// Checks if a name operation is valid, assuming that we already know
-    /// it was signed by the given account.
-    ///
-    /// Value and recipient are optional.  If the value is missing, we use the
-    /// existing value or the [default value](std::default::Default) if the
-    /// name does not exist yet.  If the recipient is missing, we set it to
-    /// the `sender` account.
-    ///
-    /// This function returns either an error if the operation is not valid,
-    /// or the [data](Operation) that should be passed to
-    /// [`execute`](Module::execute) later on if the transaction is valid.
-    pub fn check_assuming_signed(sender: T::AccountId, name: T::Name,
-                                 value: Option<T::Value>,
-                                 recipient: Option<T::AccountId>) -> Result<Operation<T>, &'static str> {
-        let (typ, old_value) = match <Names<T>>::get(&name) {
-            None => (OperationType::Registration, T::Value::default()),
-            Some(data) => {
-                ensure!(sender == data.owner, "non-owner name update");
-                (OperationType::Update, data.value)
-            },
-        };
+impl<'a, A, I> ClassifyDispatch<(&'a A, &'a Vec<I>)> for WeightPerItem {
+    fn classify_dispatch(&self, _: (&'a A, &'a Vec<I>)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
 
-        let value = match value {
-            None => old_value,
-            Some(new_value) => new_value,
-        };
-        let recipient = match recipient {
-            None => sender.clone(),
-            Some(new_recipient) => new_recipient,
-        };
+impl<'a, A> WeighData<(&'a A, &'a u32)> for WeightPerItem {
+    fn weigh_data(&self, (_, count): (&'a A, &'a u32)) -> Weight {
+        self.0.saturating_mul(*count as Weight)
+    }
+}
 
-        let mut op = Operation::<T> {
-            operation: typ,
-            name: name,
-            value: value,
-            sender: sender,
-            recipient: recipient,
-            fee: <T::Currency as Currency<T::AccountId>>::Balance::default(),
-        };
-        op.fee = match T::get_name_fee(&op) {
-            None => return Err("operation violates name policy"),
-            Some(f) => f,
-        };
+impl<'a, A> ClassifyDispatch<(&'a A, &'a u32)> for WeightPerItem {
+    fn classify_dispatch(&self, _: (&'a A, &'a u32)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
 
-        /* Make sure that we can withdraw the name fee from the sender account.
-           Note that ensure_can_withdraw does not by itself verify the
-           amount against the free balance, but just that the new balance
-           satisfies all locks in place.  Thus we have to do that ourselves.  */
-        let new_balance = match T::Currency::free_balance(&op.sender).checked_sub(&op.fee) {
-            None => return Err("insufficient balance for name fee"),
-            Some(b) => b,
-        };
-        match T::Currency::ensure_can_withdraw(&op.sender, op.fee, Self::withdraw_reasons(), new_balance) {
-            Err(_) => return Err("cannot withdraw name fee from sender"),
-            Ok(_) => (),
-        }
+impl PaysFee for WeightPerItem {}
 
-        Ok(op)
+/// Like [`WeightPerItem`], but for administrative calls, which are
+/// operational.
+pub struct OperationalWeightPerItem(pub Weight);
+
+impl<'a, I> WeighData<(&'a Vec<I>,)> for OperationalWeightPerItem {
+    fn weigh_data(&self, (items,): (&'a Vec<I>,)) -> Weight {
+        self.0.saturating_mul(items.len() as Weight)
     }
+}
 
-    /// Executes the state change (and fires events) for a given
-    /// [name operation](Operation).
-    ///
-    /// This should be called after
-    /// [`check_assuming_signed`](Module::check_assuming_signed) (passing its
-    /// result), and when potential other checks have been done as well.
-    ///
-    /// This function may actually fail (return an error) if the fee withdrawal
-    /// is not possible.  This can happen if some funds were spent externally
-    /// between the call to
-    /// [`check_assuming_signed`](Module::check_assuming_signed) and this
-    /// function.  If that happens, then `execute` will be a noop.
-    pub fn execute(op: Operation<T>) -> DispatchResult {
-        /* As the very first step, handle the name fee.  This makes sure
-           that if withdrawal fails, it will not cause any other changes.  */
-        let imbalance = T::Currency::withdraw(&op.sender, op.fee,
-                                              Self::withdraw_reasons(),
-                                              ExistenceRequirement::AllowDeath)?;
-        T::deposit_fee(imbalance);
-
-        let expiration_blocks = T::get_expiration(&op);
-        let expiration_height = match expiration_blocks {
-            None => None,
-            Some(b) => {
-                /* In the strange case that we are told to use zero blocks for
-                   expiration, make it at least one.  This ensures that we will
-                   actually expire the name in the next block, and not end up
-                   with an index entry from the past that will stick around
-                   forever.  */
-                let b = max(b, T::BlockNumber::from(1));
-                Some(system::Module::<T>::block_number() + b)
-            },
-        };
+impl<'a, I> ClassifyDispatch<(&'a Vec<I>,)> for OperationalWeightPerItem {
+    fn classify_dispatch(&self, _: (&'a Vec<I>,)) -> DispatchClass {
+        DispatchClass::Operational
+    }
+}
 
-        let data = NameData::<T> {
-            value: op.value,
-            owner: op.recipient,
-            expiration: expiration_height,
-        };
+impl PaysFee for OperationalWeightPerItem {}
 
-        <Names<T>>::insert(&op.name, &data);
-        if let Some(h) = expiration_height {
-            <Expirations<T>>::insert(h, &op.name, &op.name);
-        }
+/// Dispatch weight of a call that updates (or registers) a name with
+/// a given value, which is the given base weight plus the
+/// [extra weight](Trait::ExtraWeight) for the operation.
+pub struct ValueOperationWeight<T>(pub Weight, pub DispatchClass, PhantomData<T>);
 
-        match op.operation {
-            OperationType::Registration => {
-                Self::deposit_event(RawEvent::NameRegistered(op.name.clone()));
-            },
-            OperationType::Update => (),
-        }
-        Self::deposit_event(RawEvent::NameUpdated(op.name, data));
+impl<T> ValueOperationWeight<T> {
+    /// Constructs the weight with the given base weight.
+    pub fn new(base: Weight) -> Self {
+        ValueOperationWeight(base, DispatchClass::Normal, PhantomData)
+    }
 
-        Ok(())
+    /// Constructs the weight for an administrative call, which is
+    /// operational.
+    pub fn operational(base: Weight) -> Self {
+        ValueOperationWeight(base, DispatchClass::Operational, PhantomData)
     }
+}
 
-    /// Processes all name expirations for the given block number.
-    fn expire_names(h: T::BlockNumber) {
-        for nm in <Expirations<T>>::iter_prefix(h) {
-            if let Some(data) = <Names<T>>::get(&nm) {
-                match data.expiration {
-                    None => (),
-                    Some(expiration_height) => {
-                        /* Whenever we store an expiration height in a name,
-                           it is guaranteed to be larger than the current
-                           block height.  And when the block height increases,
-                           we first of all remove all names that expire at
-                           that height.  This means that the name's expiration
-                           height will always be not less than h.  */
-                        assert!(expiration_height >= h);
-                        if expiration_height <= h {
-                            <Names<T>>::remove(&nm);
-                            Self::deposit_event(RawEvent::NameExpired(nm));
-                        }
-                    },
-                }
-            }
-        }
-        <Expirations<T>>::remove_prefix(h);
+impl<'a, T: Trait> WeighData<(&'a T::Name, &'a T::Value)> for ValueOperationWeight<T> {
+    fn weigh_data(&self, (name, value): (&'a T::Name, &'a T::Value)) -> Weight {
+        self.0.saturating_add(T::ExtraWeight::extra_weight(name, Some(value)))
     }
+}
 
+impl<'a, T: Trait> ClassifyDispatch<(&'a T::Name, &'a T::Value)> for ValueOperationWeight<T> {
+    fn classify_dispatch(&self, _: (&'a T::Name, &'a T::Value)) -> DispatchClass {
+        self.1
+    }
+}
+
+impl<'a, T: Trait, X> WeighData<(&'a T::Name, &'a T::Value, X)> for ValueOperationWeight<T> {
+    fn weigh_data(&self, (name, value, _): (&'a T::Name, &'a T::Value, X)) -> Weight {
+        self.weigh_data((name, value))
+    }
+}
+
+impl<'a, T: Trait, X> ClassifyDispatch<(&'a T::Name, &'a T::Value, X)>
+    for ValueOperationWeight<T>
+{
+    fn classify_dispatch(&self, _: (&'a T::Name, &'a T::Value, X)) -> DispatchClass {
+        self.1
+    }
+}
+
+impl<T> PaysFee for ValueOperationWeight<T> {}
+
+/// Dispatch weight of a call that transfers a name to a recipient, which
+/// is the given base weight plus the [extra weight](Trait::ExtraWeight)
+/// for the operation.
+pub struct TransferOperationWeight<T>(pub Weight, pub DispatchClass, PhantomData<T>);
+
+impl<T> TransferOperationWeight<T> {
+    /// Constructs the weight with the given base weight.
+    pub fn new(base: Weight) -> Self {
+        TransferOperationWeight(base, DispatchClass::Normal, PhantomData)
+    }
+
+    /// Constructs the weight for an administrative call, which is
+    /// operational.
+    pub fn operational(base: Weight) -> Self {
+        TransferOperationWeight(base, DispatchClass::Operational, PhantomData)
+    }
+}
+
+impl<'a, T: Trait> WeighData<(&'a T::Name, &'a T::AccountId)> for TransferOperationWeight<T> {
+    fn weigh_data(&self, (name, _): (&'a T::Name, &'a T::AccountId)) -> Weight {
+        self.0.saturating_add(T::ExtraWeight::extra_weight(name, None))
+    }
+}
+
+impl<'a, T: Trait> ClassifyDispatch<(&'a T::Name, &'a T::AccountId)>
+    for TransferOperationWeight<T>
+{
+    fn classify_dispatch(&self, _: (&'a T::Name, &'a T::AccountId)) -> DispatchClass {
+        self.1
+    }
+}
+
+impl<T> PaysFee for TransferOperationWeight<T> {}
+
+/// Dispatch weight of a call that performs an operation on the name given
+/// as its first argument, where the new value (if any) is not an argument
+/// itself (e.g. renewals, claims or patches).  It is the given base weight
+/// plus the [extra weight](Trait::ExtraWeight) for the operation without
+/// a value.
+pub struct NameOperationWeight<T>(pub Weight, PhantomData<T>);
+
+impl<T> NameOperationWeight<T> {
+    /// Constructs the weight with the given base weight.
+    pub fn new(base: Weight) -> Self {
+        NameOperationWeight(base, PhantomData)
+    }
+}
+
+impl<'a, T: Trait> WeighData<(&'a T::Name,)> for NameOperationWeight<T> {
+    fn weigh_data(&self, (name,): (&'a T::Name,)) -> Weight {
+        self.0.saturating_add(T::ExtraWeight::extra_weight(name, None))
+    }
+}
+
+impl<'a, T: Trait> ClassifyDispatch<(&'a T::Name,)> for NameOperationWeight<T> {
+    fn classify_dispatch(&self, _: (&'a T::Name,)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+impl<'a, T: Trait, X> WeighData<(&'a T::Name, X)> for NameOperationWeight<T> {
+    fn weigh_data(&self, (name, _): (&'a T::Name, X)) -> Weight {
+        self.weigh_data((name,))
+    }
+}
+
+impl<'a, T: Trait, X> ClassifyDispatch<(&'a T::Name, X)> for NameOperationWeight<T> {
+    fn classify_dispatch(&self, _: (&'a T::Name, X)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+impl<'a, T: Trait, X, Y> WeighData<(&'a T::Name, X, Y)> for NameOperationWeight<T> {
+    fn weigh_data(&self, (name, _, _): (&'a T::Name, X, Y)) -> Weight {
+        self.weigh_data((name,))
+    }
+}
+
+impl<'a, T: Trait, X, Y> ClassifyDispatch<(&'a T::Name, X, Y)> for NameOperationWeight<T> {
+    fn classify_dispatch(&self, _: (&'a T::Name, X, Y)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+impl<T> PaysFee for NameOperationWeight<T> {}
+
+/// Signed payloads that describe a name operation, so that calls taking
+/// them can be [weighed](PayloadOperationWeight) by the operation.
+pub trait OperationPayload<T: Trait> {
+
+    /// Returns the name the operation is on.
+    fn name(&self) -> &T::Name;
+
+    /// Returns the new value, if the operation sets one.
+    fn value(&self) -> Option<&T::Value>;
+
+}
+
+impl<T: Trait> OperationPayload<T> for SponsoredUpdate<T> {
+    fn name(&self) -> &T::Name {
+        &self.name
+    }
+    fn value(&self) -> Option<&T::Value> {
+        Some(&self.value)
+    }
+}
+
+impl<T: Trait> OperationPayload<T> for SignedOperation<T> {
+    fn name(&self) -> &T::Name {
+        &self.name
+    }
+    fn value(&self) -> Option<&T::Value> {
+        self.value.as_ref()
+    }
+}
+
+impl<T: Trait> OperationPayload<T> for SessionUpdate<T> {
+    fn name(&self) -> &T::Name {
+        &self.name
+    }
+    fn value(&self) -> Option<&T::Value> {
+        Some(&self.value)
+    }
+}
+
+/// Dispatch weight of a call that executes the operation of a signed
+/// [payload](OperationPayload), which is the given base weight plus the
+/// [extra weight](Trait::ExtraWeight) for the operation.
+pub struct PayloadOperationWeight<T>(pub Weight, PhantomData<T>);
+
+impl<T> PayloadOperationWeight<T> {
+    /// Constructs the weight with the given base weight.
+    pub fn new(base: Weight) -> Self {
+        PayloadOperationWeight(base, PhantomData)
+    }
+}
+
+impl<'a, T: Trait, P: OperationPayload<T>, X> WeighData<(&'a P, X)>
+    for PayloadOperationWeight<T>
+{
+    fn weigh_data(&self, (payload, _): (&'a P, X)) -> Weight {
+        self.0.saturating_add(T::ExtraWeight::extra_weight(payload.name(), payload.value()))
+    }
+}
+
+impl<'a, T: Trait, P: OperationPayload<T>, X> ClassifyDispatch<(&'a P, X)>
+    for PayloadOperationWeight<T>
+{
+    fn classify_dispatch(&self, _: (&'a P, X)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+impl<T> PaysFee for PayloadOperationWeight<T> {}
+
+/// Dispatch weight of [`transfer_all_names`](Module::transfer_all_names).
+/// The first number is the weight of each transfer, which is charged for
+/// [the maximum number](Trait::MaxBulkTransfer) of names per call.  The
+/// second is the weight of each owned name that is skipped over.
+pub struct BulkTransferWeight<T>(pub Weight, pub Weight, PhantomData<T>);
+
+impl<T> BulkTransferWeight<T> {
+    /// Constructs the weight with the given per-transfer and
+    /// per-skipped-name weights.
+    pub fn new(per_transfer: Weight, per_skipped: Weight) -> Self {
+        BulkTransferWeight(per_transfer, per_skipped, PhantomData)
+    }
+}
+
+impl<'a, T: Trait> WeighData<(&'a T::AccountId, &'a u32)> for BulkTransferWeight<T> {
+    fn weigh_data(&self, (_, skip): (&'a T::AccountId, &'a u32)) -> Weight {
+        let transfers = self.0.saturating_mul(T::MaxBulkTransfer::get() as Weight);
+        transfers.saturating_add(self.1.saturating_mul(*skip as Weight))
+    }
+}
+
+impl<'a, T: Trait> ClassifyDispatch<(&'a T::AccountId, &'a u32)> for BulkTransferWeight<T> {
+    fn classify_dispatch(&self, _: (&'a T::AccountId, &'a u32)) -> DispatchClass {
+        DispatchClass::Normal
+    }
+}
+
+impl<T> PaysFee for BulkTransferWeight<T> {}
+
+/// Dispatch weight of [`dispatch_as_name`](Module::dispatch_as_name),
+/// which is the weight of the inner call plus the given overhead.  The
+/// dispatch class is that of the inner call as well.
+///
+/// In this Substrate version, whether a call pays fees cannot depend on its
+/// arguments.  Thus the outer call always pays, even if the inner one
+/// would not.
+pub struct DispatchAsNameWeight<T>(pub Weight, PhantomData<T>);
+
+impl<T> DispatchAsNameWeight<T> {
+    /// Constructs the weight with the given overhead.
+    pub fn new(overhead: Weight) -> Self {
+        DispatchAsNameWeight(overhead, PhantomData)
+    }
+}
+
+impl<'a, T: Trait> WeighData<(&'a T::Name, &'a Box<<T as Trait>::Call>)>
+    for DispatchAsNameWeight<T>
+{
+    fn weigh_data(&self, (_, call): (&'a T::Name, &'a Box<<T as Trait>::Call>)) -> Weight {
+        self.0.saturating_add(call.get_dispatch_info().weight)
+    }
+}
+
+impl<'a, T: Trait> ClassifyDispatch<(&'a T::Name, &'a Box<<T as Trait>::Call>)>
+    for DispatchAsNameWeight<T>
+{
+    fn classify_dispatch(&self, (_, call): (&'a T::Name, &'a Box<<T as Trait>::Call>))
+        -> DispatchClass
+    {
+        call.get_dispatch_info().class
+    }
+}
+
+impl<T> PaysFee for DispatchAsNameWeight<T> {}
+
+/// The names of an account as exported by
+/// [`export_names`](Module::export_names), as tuples of name, value
+/// and expiration height.
+pub type Portfolio<T> =
+    Vec<(<T as Trait>::Name, <T as Trait>::Value, Option<<T as system::Trait>::BlockNumber>)>;
+
+/// Counters of registry activity during one era, which are kept for
+/// the last [few eras](Trait::StatsHistory) for dashboards.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct RegistrarStats<T: Trait> {
+    /// The era these counters are for.
+    pub era: u32,
+    /// Number of names registered.
+    pub registrations: u32,
+    /// Number of updates (which renew names).
+    pub renewals: u32,
+    /// Number of names that expired.
+    pub expirations: u32,
+    /// Total name fees collected.
+    pub fees: BalanceOf<T>,
+}
+
+impl<T: Trait> RegistrarStats<T> {
+
+    /// Returns empty statistics for the given era.
+    fn empty(era: u32) -> Self {
+        RegistrarStats::<T> {
+            era: era,
+            registrations: 0,
+            renewals: 0,
+            expirations: 0,
+            fees: Zero::zero(),
+        }
+    }
+
+}
+
+impl<T: Trait> Default for RegistrarStats<T> {
+    fn default() -> Self {
+        Self::empty(0)
+    }
+}
+
+/// Attestation that an account owns a name at a certain block, as
+/// [exported](Module::attest_ownership) for other chains and off-chain
+/// services.  It is backed by a storage proof of the name's entry (under
+/// `storage_key`) against the block's state root, and can be verified
+/// with the `name-attestation` crate.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct OwnershipAttestation<T: Trait> {
+    /// The attested name.
+    pub name: T::Name,
+    /// The account owning the name.
+    pub owner: T::AccountId,
+    /// The block at which the name is owned by the account.
+    pub block: T::BlockNumber,
+    /// The storage key of the name's entry in the name database.
+    pub storage_key: Vec<u8>,
+}
+
+/// Type of a name operation.
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq, RuntimeDebug)]
+pub enum OperationType {
+    /// This operation registers a name that does not exist yet.
+    Registration,
+    /// This operation updates an existing name.
+    Update,
+    /// This operation transfers an existing name to a new owner, without
+    /// changing its value.
+    Transfer,
+}
+
+/// Compact record of an executed name operation, which is stored for
+/// [a while](Trait::ReceiptRetention) so that it can be
+/// [queried](Module::receipts) by block and extrinsic index, e.g. to
+/// reconcile registrar revenue.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub struct Receipt<T: Trait> {
+    /// Hash of the name (with the system's hashing function).
+    pub name_hash: T::Hash,
+    /// Type of the operation.
+    pub operation: OperationType,
+    /// The name fee that was paid.
+    pub fee: BalanceOf<T>,
+    /// The account that paid the fee.
+    pub payer: T::AccountId,
+    /// The expiration height set by the operation, if any.
+    pub expiration: Option<T::BlockNumber>,
+    /// The owner before the operation, or `None` for registrations.
+    pub previous_owner: Option<T::AccountId>,
+}
+
+/// Outcome of a [simulated execution](Module::simulate_execute) of a
+/// name operation:  The result of executing it, the state of the name
+/// before and after, the balance changes of the accounts involved and the
+/// events that were emitted.
+#[cfg(feature = "simulation")]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Eq, PartialEq)]
+pub struct Simulation<T: Trait> {
+    /// The result of [`execute`](Module::execute).
+    pub result: DispatchResult,
+    /// The name's data before the operation.
+    pub old_data: Option<NameData<T>>,
+    /// The name's data after the operation.
+    pub new_data: Option<NameData<T>>,
+    /// Free and reserved balances of the sender, payer and recipient
+    /// before and after the operation.
+    pub balances: Vec<(T::AccountId, (BalanceOf<T>, BalanceOf<T>), (BalanceOf<T>, BalanceOf<T>))>,
+    /// The events emitted while executing the operation.
+    pub events: Vec<<T as system::Trait>::Event>,
+}
+
+/// Whether a name can be registered, and if not, why.  This is
+/// returned by [`availability`](Module::availability) for registration UIs.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub enum Availability<BlockNumber> {
+    /// The name can be registered.
+    Available,
+    /// The name is registered, and expires at the given height (if ever).
+    Taken {
+        expires: Option<BlockNumber>,
+    },
+    /// The name does not exist, but is reserved (e.g. by a reserved
+    /// namespace, a hidden registration, a preorder or the quarantine
+    /// after it expired).
+    Reserved,
+    /// The name is registered and [frozen](Module::freeze_name).
+    Frozen,
+    /// The name has expired and is in its grace period until the given
+    /// height.  Only its owner can renew it until then.
+    GracePeriod {
+        until: BlockNumber,
+    },
+    /// The name does not exist, but the name policy does not allow
+    /// registering it.
+    PolicyInvalid,
+}
+
+/// An outstanding process that affects a name, as returned by
+/// [`pending_for`](Module::pending_for).  Each kind of process appears
+/// at most once per name, so that the result is bounded.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Decode, Encode, Eq, PartialEq)]
+pub enum PendingEntry<T: Trait> {
+    /// A [transfer](Module::claim_name) waits to be claimed by the
+    /// given recipient.
+    Transfer {
+        recipient: T::AccountId,
+    },
+    /// A [guarded transfer](Module::request_guarded_transfer) to the
+    /// given recipient waits for the guardian's confirmation until the
+    /// given block.
+    GuardedTransfer {
+        recipient: T::AccountId,
+        deadline: T::BlockNumber,
+    },
+    /// The name is [locked as collateral](Module::lock_as_collateral)
+    /// for the given beneficiary.
+    Collateral {
+        beneficiary: T::AccountId,
+    },
+    /// The given number of [other pallets](lock::NameLocks) hold a lock
+    /// on the name.
+    ExternalLocks(u32),
+    /// The name is offered in a [swap](Module::propose_swap) by the
+    /// given proposer.
+    Swap {
+        proposer: T::AccountId,
+    },
+    /// A [rent-to-own agreement](Module::offer_rent_to_own) runs, with
+    /// the next installment due at the given block.
+    RentToOwn {
+        tenant: T::AccountId,
+        next_due: T::BlockNumber,
+    },
+    /// A [value update is scheduled](Module::schedule_value) for the
+    /// given block.
+    ScheduledValue {
+        at: T::BlockNumber,
+    },
+    /// A [value proposal](Module::propose_value) of a shared name waits
+    /// for approvals, of which it has the given number.
+    ValueProposal {
+        approvals: u32,
+    },
+    /// The name must receive its [first value update](Trait::FirstUpdatePeriod)
+    /// before the given block.
+    FirstUpdate {
+        deadline: T::BlockNumber,
+    },
+    /// A [squatting report](Module::report_name) can be decided from
+    /// the given block.
+    Report {
+        decidable_at: T::BlockNumber,
+    },
+    /// The given number of [registration intents](Module::register_intent)
+    /// compete for the name.
+    Intents(u32),
+    /// The given number of [backorders](Module::place_backorder) wait for
+    /// the name to expire.
+    Backorders(u32),
+    /// The name is in its grace period until the given block.
+    GracePeriod {
+        until: T::BlockNumber,
+    },
+    /// The name is [quarantined](Trait::ExpirationQuarantine) until the
+    /// given block.
+    Quarantine {
+        until: T::BlockNumber,
+    },
+}
+
+/// The lifecycle state of a name, as returned by
+/// [`state_of`](Module::state_of).  This combines everything that decides
+/// what can be done with a name, so that all checks agree on it.  If more
+/// than one applies, the state listed first here takes precedence.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Eq, PartialEq)]
+pub enum NameState<T: Trait> {
+    /// The name is registered, but [frozen](Module::freeze_name).
+    Frozen(NameData<T>),
+    /// The name has expired and is in its grace period, where only its
+    /// owner can renew it.
+    GracePeriod(NameData<T>),
+    /// The name is registered, and a [transfer](Module::claim_name) to
+    /// the given recipient waits to be claimed.
+    PendingTransfer {
+        data: NameData<T>,
+        recipient: T::AccountId,
+    },
+    /// The name is registered and not in any of the special states.
+    Registered(NameData<T>),
+    /// The name does not exist, but is reserved (e.g. by a reserved
+    /// namespace, a hidden registration, a preorder or quarantine).
+    Reserved,
+    /// The name does not exist and is not reserved.
+    Available,
+}
+
+impl<T: Trait> NameState<T> {
+
+    /// Returns the data of the name, if it exists.
+    pub fn data(&self) -> Option<&NameData<T>> {
+        match self {
+            NameState::Frozen(data) => Some(data),
+            NameState::GracePeriod(data) => Some(data),
+            NameState::PendingTransfer { data, .. } => Some(data),
+            NameState::Registered(data) => Some(data),
+            NameState::Reserved | NameState::Available => None,
+        }
+    }
+
+    /// Returns true if the name is frozen.
+    pub fn is_frozen(&self) -> bool {
+        match self {
+            NameState::Frozen(_) => true,
+            _ => false,
+        }
+    }
+
+}
+
+/// Reason why a name could not be [resolved](Module::resolve).
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Decode, Encode, Eq, PartialEq)]
+pub enum ResolveError {
+    /// The name (or the name its redirects lead to) does not exist.
+    NotFound,
+    /// The redirects lead back to a name that was already visited.
+    Cycle,
+    /// There are more redirects than [allowed](Trait::MaxRedirectDepth).
+    TooDeep,
+}
+
+impl ResolveError {
+
+    /// Returns the error as string, e.g. for a `DispatchError`.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ResolveError::NotFound => "name does not exist",
+            ResolveError::Cycle => "redirects form a cycle",
+            ResolveError::TooDeep => "too many redirects",
+        }
+    }
+
+}
+
+/// Reason why a name operation is invalid, as returned by
+/// [`check_detailed`](Module::check_detailed).  Where it helps clients to
+/// fix the operation, the error carries the relevant values.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Encode, Eq, PartialEq)]
+pub enum OperationError<T: Trait> {
+    /// The name is owned by the given account, and the sender is neither
+    /// that account nor the name's controller.
+    OwnedBy(T::AccountId),
+    /// The payer cannot afford the name fee, which is given.
+    RequiredFee(BalanceOf<T>),
+    /// The sender cannot reserve the given amount, which is needed for the
+    /// value deposit (and the name fee if the sender pays it).
+    RequiredDeposit(BalanceOf<T>),
+    /// The name policy's expiration period is shorter than the minimum.
+    ExpirationTooShort {
+        blocks: T::BlockNumber,
+        min: T::BlockNumber,
+    },
+    /// The [limit of registrations](Trait::MaxRegistrationsPerBlock) in
+    /// the current block has been reached.  The registration may succeed
+    /// when retried in a later block.
+    RetryNextBlock,
+    /// Any other error, with its message.
+    Other(&'static str),
+}
+
+impl<T: Trait> OperationError<T> {
+    /// Returns the error message, which is also used for the
+    /// [`DispatchError`] when the operation is submitted anyway.
+    pub fn message(&self) -> &'static str {
+        match self {
+            OperationError::OwnedBy(_) => "non-owner name update",
+            OperationError::RequiredFee(_) => "insufficient balance for name fee",
+            OperationError::RequiredDeposit(_) => "insufficient balance for value deposit",
+            OperationError::ExpirationTooShort { .. } => "name expiration period is too short",
+            OperationError::RetryNextBlock => "too many registrations in this block",
+            OperationError::Other(msg) => msg,
+        }
+    }
+}
+
+impl<T: Trait> From<&'static str> for OperationError<T> {
+    fn from(msg: &'static str) -> Self {
+        OperationError::Other(msg)
+    }
+}
+
+/// All data necessary to actually perform a name operation.
+///
+/// This is returned by the
+/// [validation function](Module::check_assuming_signed), and can then
+/// be passed to the [execution function](Module::execute) if a runtime wants
+/// to do its own logic in addition.
+///
+/// A reference to an `Operation` struct is also passed to the
+/// [name policy](policy::NamePolicy) functions that need to determine e.g.
+/// the name fee for the operation.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Decode, Encode, Eq, PartialEq)]
+pub struct Operation<T: Trait> {
+    /// Type of this operation.
+    pub operation: OperationType,
+    /// The name being operated on.
+    pub name: T::Name,
+    /// The value that is being associated to the name.
+    pub value: T::Value,
+    /// The data stored for the name before this operation, or `None` if the
+    /// name is being registered.  Name policies can use this e.g. to make
+    /// the fee for a renewal depend on the remaining lifetime.
+    pub old_data: Option<NameData<T>>,
+
+    /// The sender of the name operation.  For updates, this is the
+    /// name's current owner.
+    sender: T::AccountId,
+    /// The account that pays the name fee.  This is the sender, except
+    /// for [sponsored operations](Module::sponsored_update).
+    payer: T::AccountId,
+    /// The owner it is sent to.
+    recipient: T::AccountId,
+
+    /// The name fee to pay.
+    fee: <T::Currency as Currency<T::AccountId>>::Balance,
+    /// The [value deposit](Trait::ValueDepositPerByte) that the sender
+    /// holds for the name after the operation.
+    deposit: BalanceOf<T>,
+    /// Whether this update uses one of the name's
+    /// [free updates](Trait::FreeUpdatesPerEra) instead of paying a fee.
+    free_update: bool,
+    /// The registrar (e.g. a wallet front-end) through which the operation
+    /// was submitted, if any.  It receives a
+    /// [commission](Trait::RegistrarCommission) on the name fee.
+    registrar: Option<T::AccountId>,
+    /// Whether this is an [explicit renewal](Module::renew), which sets the
+    /// expiration even if [updates preserve it](ExpirationUpdate::Preserve).
+    renewal: bool,
+    /// Whether the name never expires after the operation, regardless of
+    /// the name policy.  This is used for [donations](Module::donate_name).
+    never_expires: bool,
+}
+
+impl<T: Trait> Operation<T> {
+    /// Sets the registrar through which the operation is submitted, so
+    /// that it receives the [commission](Trait::RegistrarCommission).
+    pub fn set_registrar(&mut self, registrar: Option<T::AccountId>) {
+        self.registrar = registrar;
+    }
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as TemplateModule {
+        /// The main mapping from names to [associated data](NameData).
+        Names get(lookup): map T::Name => Option<NameData<T>>;
+        /// Legacy expiration index, with all names (as both the second key and
+        /// the value) that may expire at the given block height (first key).
+        /// This has been replaced by the
+        /// [expiration queue](Module::expiring_at), and is no longer read
+        /// when processing blocks.  Existing entries are moved over by the
+        /// [`MigrateExpirations`](migration::MigrateExpirations) migration.
+        Expirations: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+        /// All names that may expire at the given block height.  We use this
+        /// so we can efficiently process expirations whenever we process a new
+        /// block.  Each block holds at most
+        /// [a certain number](Trait::MaxExpirationsPerBlock) of names:  The
+        /// bound is enforced when names are scheduled, which moves on to
+        /// later blocks once one is full.  Lowering the limit in a runtime upgrade
+        /// does not shrink entries written before, so it should only be
+        /// lowered together with a migration of the affected blocks.
+        /// When names are updated, they are not removed from here, though --
+        /// so a name's expiration value in the core database overrules this
+        /// index.
+        ExpirationQueue get(expiring_at): map T::BlockNumber => Vec<T::Name>;
+        /// Names that are in their [grace period](policy::ExpirationAction::Grace)
+        /// after expiring.  They are removed when it ends, unless they are
+        /// renewed by an operation before.
+        InGracePeriod get(in_grace_period): map T::Name => bool;
+        /// All names (as second key and value) owned by an account
+        /// (first key).
+        OwnerIndex: double_map T::AccountId, blake2_256(T::Name) => T::Name;
+        /// Secondary index of all names by their
+        /// [namehash](Trait::Namehash).
+        NamehashIndex: map H256 => Option<T::Name>;
+        /// All current [hidden registrations](HiddenRegistration) by the
+        /// hash of the name (as computed by the system's hashing function
+        /// over the encoded name).
+        HiddenNames get(lookup_hidden): map T::Hash => Option<HiddenRegistration<T>>;
+        /// The total number of names currently in the database.
+        TotalNames get(total_names): u32;
+        /// The number of names registered in the current block, for
+        /// enforcing [`MaxRegistrationsPerBlock`](Trait::MaxRegistrationsPerBlock).
+        /// This is reset at the start of each block.
+        RegistrationsThisBlock get(registrations_this_block): u32;
+        /// The current multiplier (in percent) applied to the fees of
+        /// name registrations due to [congestion](Trait::CongestionMultipliers).
+        RegistrationFeeMultiplier get(registration_fee_multiplier): u32 = 100;
+        /// Registration intents submitted in the current block, by name.
+        /// Each intent consists of the sender and the desired value.  There
+        /// are at most [a certain number](Trait::MaxIntentsPerName) per name.
+        RegistrationIntents get(registration_intents): map T::Name => Vec<(T::AccountId, T::Value)>;
+        /// All names with registration intents in the current block.  Each
+        /// entry comes from an intent that paid for its settlement, so the
+        /// list is bounded by the block weight.
+        IntentNames: Vec<T::Name>;
+        /// All current [preorders](Preorder) by the hash of the name (computed
+        /// in the same way as for hidden registrations).
+        Preorders get(lookup_preorder): map T::Hash => Option<Preorder<T>>;
+        /// Hashes of preorders that expire at the given block height.
+        PreorderExpirations: double_map T::BlockNumber, blake2_256(T::Hash) => T::Hash;
+        /// [Backorders](Module::place_backorder) for each name, as accounts
+        /// with their reserved bonds.  They are ordered by priority, i.e.
+        /// by bond (larger first) and then by the time they were placed.
+        Backorders get(backorders): map T::Name => Vec<(T::AccountId, BalanceOf<T>)>;
+
+        /// Premium per [insured renewal](Module::insure_name), as set by
+        /// governance.
+        InsurancePremium get(insurance_premium): BalanceOf<T>;
+        /// For each insured name, the number of renewals that the
+        /// [insurance pool](Module::insurance_pool) still pays for.
+        InsuredRenewals get(insured_renewals): map T::Name => u32;
+        /// What happens to the data of a name when it
+        /// [changes owner](Module::set_retention_policy).
+        RetentionPolicies get(retention_policy): map T::Name => RetentionPolicy;
+
+        /// For each account, the nonce that its next
+        /// [sponsored update](Module::sponsored_update) or
+        /// [signed operation](Module::execute_signed_operation) has to use.
+        OperationNonce get(operation_nonce): map T::AccountId => u64;
+
+        /// Subvalues by name and subkey.
+        Subvalues get(subvalue): double_map T::Name, blake2_256(T::SubKey) => Option<Subvalue<T>>;
+        /// Number of subvalues stored for each name.
+        SubvalueCount get(subvalue_count): map T::Name => u32;
+        /// The subkeys of all subvalues of a name, ordered by their
+        /// encoding.  This allows [listing](Module::records_of) them.
+        SubvalueKeys get(subvalue_keys): map T::Name => Vec<T::SubKey>;
+
+        /// Ciphertexts of [envelopes](Module::publish_envelope) by name and
+        /// the recipient's public key.
+        Envelopes get(envelope_for): double_map T::Name, blake2_256(H256) => Option<Vec<u8>>;
+        /// Number of envelopes stored for each name.
+        EnvelopeCount get(envelope_count): map T::Name => u32;
+
+        /// For each name, the accounts watching it together with their
+        /// reserved deposits.
+        Watchers get(watchers): map T::Name => Vec<(T::AccountId, BalanceOf<T>)>;
+
+        /// The [value deposit](Trait::ValueDepositPerByte) held for each
+        /// name, together with the account it is reserved from.
+        ValueDeposits get(value_deposit): map T::Name => Option<(T::AccountId, BalanceOf<T>)>;
+
+        /// Accounts that want incoming name transfers to be
+        /// [claimed](Module::claim_name) explicitly.
+        RequiresClaim get(requires_claim): map T::AccountId => bool;
+        /// Pending transfers of names to accounts that require claiming.
+        PendingTransfers get(pending_transfer): map T::Name => Option<T::AccountId>;
+        /// Names that are [locked as collateral](Module::lock_as_collateral),
+        /// with the beneficiary that may seize them.
+        Collateral get(collateral_beneficiary): map T::Name => Option<T::AccountId>;
+        /// Open [swap offers](Module::propose_swap) by proposer.  Each
+        /// account can have at most one open offer.
+        SwapOffers get(swap_offer): map T::AccountId => Option<SwapOffer<T>>;
+        /// Names that are offered in a swap, with the proposer of the offer.
+        SwapLocks get(swap_lock): map T::Name => Option<T::AccountId>;
+        /// [Standing approvals](Module::approve_operator) of names, with the
+        /// operator and the hashes of the templates it may apply.
+        Operators get(operator_of): map T::Name => Option<(T::AccountId, Vec<H256>)>;
+        /// Running [rent-to-own agreements](Module::offer_rent_to_own).
+        RentAgreements get(rent_agreement): map T::Name => Option<RentAgreement<T>>;
+        /// Names (as second key and value) whose rent-to-own agreement
+        /// is cancelled at the given block height (first key) unless the
+        /// next installment is paid before.  Entries for installments that
+        /// have been paid are left in place and skipped.
+        RentDue: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+        /// [Scheduled values](Module::schedule_value) of names, with the
+        /// block at which they take effect.
+        ScheduledValues get(scheduled_value): map T::Name => Option<(T::BlockNumber, T::Value)>;
+        /// Names (as second key and value) with a scheduled value that
+        /// takes effect at the given block height (first key).  Entries
+        /// for schedules that have been replaced or cancelled are skipped.
+        ValueActivations: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+        /// Accounts that receive [income](Module::distribute_income) of a
+        /// name instead of its owner, with the fraction each one gets.
+        /// Whatever is not distributed this way goes to the owner.
+        Payouts get(payout_of): map T::Name => Vec<(T::AccountId, Perbill)>;
+        /// [Receipts](Receipt) of the name operations executed in recent
+        /// blocks (first key), by the index of the extrinsic (second key).
+        /// Operations outside of extrinsics (e.g. during block
+        /// initialisation) are stored with `None` as index.
+        Receipts get(receipts): double_map T::BlockNumber, blake2_256(Option<u32>) => Vec<Receipt<T>>;
+        /// Ring buffer of [registrar statistics](RegistrarStats), where each
+        /// era is stored in the slot given by the era modulo the
+        /// [history length](Trait::StatsHistory).
+        Statistics: map u32 => RegistrarStats<T>;
+        /// Number of [free updates](Trait::FreeUpdatesPerEra) each name has
+        /// used, together with the era in which they were used.
+        FreeUpdatesUsed: map T::Name => (u32, u32);
+        /// Names that are [split into shares](Module::split_into_shares).
+        SharedNames get(share_info): map T::Name => Option<ShareInfo<T>>;
+        /// The currently proposed new value for names split into shares,
+        /// together with the accounts that approved it so far.
+        ValueProposals get(value_proposal): map T::Name => Option<(T::Value, Vec<T::AccountId>)>;
+
+        /// For each account, the senders from which it does not accept
+        /// any incoming name transfers.
+        BlockedSenders get(is_sender_blocked): double_map T::AccountId, blake2_256(T::AccountId) => bool;
+        /// Accounts that do not accept incoming name transfers at all.
+        BlocksAllTransfers get(blocks_all_transfers): map T::AccountId => bool;
+        /// Hashes of hidden registrations that expire at the given block
+        /// height, in the same form as the expiration index for names.
+        HiddenExpirations: double_map T::BlockNumber, blake2_256(T::Hash) => T::Hash;
+        /// Registered names that still wait for their
+        /// [first value update](Trait::FirstUpdatePeriod).
+        FirstUpdates get(pending_first_update): map T::Name => Option<PendingFirstUpdate<T>>;
+        /// Names that are released at the given block height unless they
+        /// receive their first value update, in the same form as the
+        /// expiration index for names.
+        FirstUpdateDeadlines: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+
+        /// The block at which the [compliance window](Trait::ComplianceWindow)
+        /// of each name ends.  From then on, its value must satisfy the
+        /// content rules.
+        ComplianceDeadlines get(compliance_deadline): map T::Name => Option<T::BlockNumber>;
+        /// Names whose compliance window ends at the given block height, in
+        /// the same form as the expiration index for names.
+        ComplianceChecks: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+
+        /// Expired names that are in [quarantine](Trait::ExpirationQuarantine),
+        /// with the block at which it ends.
+        QuarantinedUntil get(quarantined_until): map T::Name => Option<T::BlockNumber>;
+        /// Index of quarantined names by the block at which their
+        /// quarantine ends.
+        QuarantineEnds: double_map T::BlockNumber, blake2_256(T::Name) => T::Name;
+
+        /// [Tombstones](Tombstone) of the most recent expiration of names,
+        /// kept for the [archive retention](Trait::ArchiveRetention) period.
+        Archive get(archived): map T::Name => Option<Tombstone<T>>;
+        /// Index of archived names (with the block at which they expired)
+        /// by the block at which their tombstone is pruned.
+        ArchivePruning: double_map T::BlockNumber, blake2_256(T::Name) => (T::Name, T::BlockNumber);
+        /// Optional dedicated signing keys for names.  If set, signatures
+        /// [made for the name](Module::verify_with_signing_key) are checked
+        /// against this key instead of the owner account.  This way, the
+        /// account that holds a name can be kept offline (e.g. in cold
+        /// storage) while a different key is used for day-to-day signing.
+        SigningKeys get(signing_key): map T::Name => Option<T::AccountId>;
+        /// Optional controller accounts of names.  The
+        /// [controller](Module::set_controller) can update the value of a
+        /// name, while only the owner can transfer it.
+        Controllers get(controller): map T::Name => Option<T::AccountId>;
+        /// Names whose owners marked them [ready for sale](Module::set_sale_ready),
+        /// so that their value cannot be changed until they are transferred.
+        SaleReady get(is_sale_ready): map T::Name => bool;
+        /// [Notes](Module::set_note) that owners attached to their names.
+        Notes get(note): map T::Name => Option<Note<T>>;
+        /// [Redirects](Module::set_redirect) of names to other names, which
+        /// are followed when [resolving](Module::resolve) them.
+        Redirects get(redirect): map T::Name => Option<T::Name>;
+        /// Locks that [other pallets](lock::NameLocks) placed on names,
+        /// which prevent transfers while any of them is set.
+        ExternalLocks get(external_locks): map T::Name => Vec<LockIdentifier>;
+        /// Optional guardian accounts of names.  Transfers of a name with a
+        /// [guardian](Module::set_guardian) need to be confirmed by it.
+        Guardians get(guardian): map T::Name => Option<T::AccountId>;
+        /// [Requested transfers](Module::request_guarded_transfer) of names
+        /// with a guardian.
+        GuardedTransfers get(guarded_transfer): map T::Name => Option<GuardedTransfer<T>>;
+        /// [Session keys](Module::authorize_session_key) by owner and key.
+        SessionKeys get(session_key): double_map T::AccountId, blake2_256(T::AccountId)
+            => Option<SessionGrant<T>>;
+        /// Names that are [linked](Module::link_identity) to the identity
+        /// of their owner, with the account holding that identity.
+        IdentityLinks get(identity_of_name): map T::Name => Option<T::AccountId>;
+        /// The reverse of the identity links, i.e. the name linked to
+        /// each account's identity.  Each identity can be linked to
+        /// at most one name.
+        NameOfIdentity get(name_of_identity): map T::AccountId => Option<T::Name>;
+
+        /// Open [reports](Module::report_name) of squatted names.
+        Reports get(report_of): map T::Name => Option<SquattingReport<T>>;
+
+        /// Names that are [system names](Module::set_system_name), i.e.
+        /// owned by the pallet's own account and managed by governance.
+        SystemNames get(is_system_name): map T::Name => bool;
+        /// Namespace prefixes that are reserved.  Names inside them can
+        /// only be registered through the [force origin](Trait::ForceOrigin)
+        /// with [`force_register`](Module::force_register).
+        ReservedPrefixes get(reserved_prefixes) config(): Vec<Vec<u8>>;
+        /// Namespace prefixes for which events about names are also
+        /// indexed under the [topic](Module::namespace_topic) of the prefix.
+        TopicPrefixes get(topic_prefixes) config(): Vec<Vec<u8>>;
+        /// Namespace prefixes together with the oracle account that attests
+        /// registrations inside them.  Names in these namespaces can only be
+        /// registered through [`register_attested`](Module::register_attested).
+        AttestedNamespaces get(attested_namespaces): Vec<(Vec<u8>, T::AccountId)>;
+        /// The hash of the [attestation](IdentityAttestation) with which a name was
+        /// registered.  It is cleared when the name changes owner.
+        Attestations get(attestation_hash): map T::Name => Option<T::Hash>;
+        /// Registrars that manage namespaces, by prefix.  They can
+        /// [renew](Module::renew_namespace) the names inside them.
+        NamespaceRegistrars get(namespace_registrar): map Vec<u8> => Option<T::AccountId>;
+        /// Namespace prefixes whose administration is
+        /// [delegated](Module::delegate_namespace) to an account.
+        DelegatedNamespaces get(delegated_namespaces): map T::AccountId => Vec<Vec<u8>>;
+        /// Names that are [frozen](Module::freeze_name) and cannot be
+        /// changed by their owners.
+        FrozenNames get(is_frozen): map T::Name => bool;
+        /// Accounts that pay no name fees, e.g. faucets, bots and bridge
+        /// relayers, as [set by governance](Module::set_account_fee_exemption).
+        FeeExemptAccounts get(is_fee_exempt_account): map T::AccountId => bool;
+        /// Names whose operations have no name fee, e.g. for system
+        /// maintenance, as [set by governance](Module::set_name_fee_exemption).
+        FeeExemptNames get(is_fee_exempt_name): map T::Name => bool;
+
+        /// Names queued for the [migration](Trait::Migration), by their
+        /// position in the queue.
+        MigrationQueue: map u32 => Option<T::Name>;
+        /// Position of the next name in the migration queue to process.
+        MigrationCursor get(migration_cursor): u32;
+        /// Position one past the last name in the migration queue.
+        MigrationQueueEnd get(migration_queue_end): u32;
+
+        /// Operations [deferred](Module::enqueue) for execution in later
+        /// blocks, by their position in the queue.
+        DeferredQueue: map u32 => Option<Operation<T>>;
+        /// Position of the next deferred operation to execute.
+        DeferredCursor get(deferred_cursor): u32;
+        /// Position one past the last deferred operation.
+        DeferredQueueEnd get(deferred_queue_end): u32;
+    }
+    add_extra_genesis {
+        /// Names that exist already at genesis, as tuples of name, value,
+        /// owner and expiration height.  This can be used to migrate an
+        /// existing name database (e.g. from Namecoin) to a new chain.
+        config(names): Vec<(T::Name, T::Value, T::AccountId, Option<T::BlockNumber>)>;
+        /// [System names](Module::set_system_name) with their values that
+        /// exist at genesis.  They never expire.
+        ///
+        /// Each name may appear only once across both lists, as otherwise
+        /// the indices and total count would be inconsistent.
+        config(system_names): Vec<(T::Name, T::Value)>;
+        build(|config: &GenesisConfig<T>| {
+            for (name, value, owner, expiration) in &config.names {
+                assert!(!<Names<T>>::exists(name), "duplicate name in genesis config");
+                /* Genesis is block zero, so (just like in execute) make sure
+                   that names expire at the earliest in block one.  */
+                let expiration = expiration.map(|h| {
+                    <Module<T>>::schedule_expiration(name, max(h, T::BlockNumber::from(1)))
+                });
+                let data = NameData::<T> {
+                    value: value.clone(),
+                    owner: owner.clone(),
+                    expiration: expiration,
+                    nonce: 0,
+                    held_since: Zero::zero(),
+                };
+                <Names<T>>::insert(name, &data);
+                <OwnerIndex<T>>::insert(owner, name, name);
+                <Module<T>>::add_to_namehash_index(name);
+            }
+            let owner = <Module<T>>::account_id();
+            for (name, value) in &config.system_names {
+                assert!(!<Names<T>>::exists(name), "duplicate name in genesis config");
+                let data = NameData::<T> {
+                    value: value.clone(),
+                    owner: owner.clone(),
+                    expiration: None,
+                    nonce: 0,
+                    held_since: Zero::zero(),
+                };
+                <Names<T>>::insert(name, &data);
+                <OwnerIndex<T>>::insert(&owner, name, name);
+                <SystemNames<T>>::insert(name, true);
+                <Module<T>>::add_to_namehash_index(name);
+            }
+            <TotalNames>::put((config.names.len() + config.system_names.len()) as u32);
+            <RegistrationFeeMultiplier>::put(<Module<T>>::congestion_multiplier());
+        });
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        fn deposit_event() = default;
+
+        /// Tries to update a name with a given value.
+        ///
+        /// If the name does not exist yet, it will be created.  If the name
+        /// exists, then only the current owner can update it.
+        ///
+        /// This is deprecated in favour of the stricter
+        /// [`register`](Module::register) and
+        /// [`set_value`](Module::set_value), which make sure that a typo
+        /// in the name does not accidentally register a new name.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::update())]
+        pub fn update(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_direct_registration(&name)?;
+            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Updates (or registers) a name like [`update`](Module::update), but
+        /// submitted through a registrar (e.g. a wallet front-end).  The
+        /// registrar receives a [commission](Trait::RegistrarCommission)
+        /// on the name fee.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::update_via_registrar())]
+        pub fn update_via_registrar(origin, name: T::Name, value: T::Value,
+                                    registrar: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_direct_registration(&name)?;
+            let mut data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            data.set_registrar(Some(registrar));
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Registers a new name with the given value.  Fails if the name
+        /// exists already.
+        ///
+        /// If `valid_until` is given, the registration fails after that
+        /// block.  This keeps the transaction from being included much
+        /// later than intended (e.g. at a changed price), or replayed
+        /// on a fork.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::register())]
+        pub fn register(origin, name: T::Name, value: T::Value,
+                        valid_until: Option<T::BlockNumber>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_valid_until(valid_until)?;
+            ensure!(<Names<T>>::get(&name).is_none(), "name already exists");
+            Self::ensure_direct_registration(&name)?;
+            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Registers a name inside an [attested namespace](Module::attested_namespaces)
+        /// with an attestation signed by the namespace's oracle.  The hash
+        /// of the attestation is stored with the name.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::register_attested())]
+        pub fn register_attested(origin, name: T::Name, value: T::Value,
+                                 attestation: IdentityAttestation<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_none(), "name already exists");
+            Self::ensure_direct_registration(&name)?;
+            let data = Self::check_attested(who, name.clone(), value, &attestation)?;
+            Self::execute(data)?;
+            let hash = T::Hashing::hash_of(&attestation);
+            <Attestations<T>>::insert(&name, hash);
+            Self::deposit_event(RawEvent::NameAttested(name, hash));
+            Ok(())
+        }
+
+        /// Renews an existing name without changing its value.  Unlike
+        /// other operations, this always sets the expiration from the name
+        /// policy, even if [updates preserve it](ExpirationUpdate::Preserve).
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::renew())]
+        pub fn renew(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            let mut data = Self::check_assuming_signed(who, name, None, None)?;
+            data.renewal = true;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Sets the value of an existing name.  Fails if the name does
+        /// not exist.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::set_value())]
+        pub fn set_value(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Sets the value of an existing name, but only if its
+        /// [nonce](NameData::nonce) is still the expected one.  This protects
+        /// clients against overwriting an update that they have not seen yet.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::set_value_at_nonce())]
+        pub fn set_value_at_nonce(origin, name: T::Name, value: T::Value,
+                                  expected_nonce: u64) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_name_nonce(&name, expected_nonce)?;
+            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Updates the value of an existing name by applying a
+        /// [patch](Trait::Patch) to the current value.  Apart from how the new
+        /// value is determined, this is the same as an [update](Module::update).
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::update_patch())]
+        pub fn update_patch(origin, name: T::Name, patch: Vec<u8>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+            let value = match T::Patch::apply_patch(&data.value, &patch) {
+                None => return Err("invalid value patch".into()),
+                Some(value) => value,
+            };
+            let data = Self::check_assuming_signed(who, name, Some(value), None)?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Schedules an update of the value of a name owned by the sender,
+        /// which takes effect at the start of the given block.  This lets
+        /// e.g. games roll over state at epoch boundaries deterministically.
+        /// The block must be in the future, but at most
+        /// [a certain number](Trait::MaxValueLookahead) of blocks ahead.
+        ///
+        /// The update is checked now and again when it takes effect, at
+        /// which point the owner pays the name fee.  Each name can have one
+        /// scheduled value, which replaces any earlier one.  It is dropped
+        /// if the name changes owner before.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::schedule_value())]
+        pub fn schedule_value(origin, name: T::Name, value: T::Value,
+                              effective_at: T::BlockNumber) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            let h = system::Module::<T>::block_number();
+            ensure!(effective_at > h && effective_at <= h + T::MaxValueLookahead::get(),
+                    "invalid effective block for scheduled value");
+            Self::check_assuming_signed(who, name.clone(), Some(value.clone()), None)?;
+
+            <ScheduledValues<T>>::insert(&name, (effective_at, value));
+            <ValueActivations<T>>::insert(effective_at, &name, &name);
+            Self::deposit_event(RawEvent::ValueScheduled(name, effective_at));
+            Ok(())
+        }
+
+        /// Cancels the scheduled value of a name owned by the sender.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::cancel_scheduled_value())]
+        pub fn cancel_scheduled_value(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner name update"),
+            }
+            ensure!(Self::scheduled_value(&name).is_some(), "no scheduled value for the name");
+            <ScheduledValues<T>>::remove(&name);
+            Ok(())
+        }
+
+        /// Sets the subvalue for the given subkey of a name, so that single
+        /// attributes can be updated in addition to the name's main value.
+        /// Only the name's owner can do this.  New subvalues require a
+        /// [deposit](Trait::SubvalueDeposit), and each name can have at
+        /// most [a certain number](Trait::MaxSubvalues) of them.  Subvalues
+        /// stay with the name when it is transferred, and are removed
+        /// together with it.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_subvalue())]
+        pub fn set_subvalue(origin, name: T::Name, key: T::SubKey,
+                            value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner subvalue update"),
+            }
+
+            let entry = match <Subvalues<T>>::get(&name, &key) {
+                Some(old) => Subvalue::<T> {
+                    value: value,
+                    ..old
+                },
+                None => {
+                    ensure!(Self::subvalue_count(&name) < T::MaxSubvalues::get(),
+                            "too many subvalues");
+                    let deposit = T::SubvalueDeposit::get();
+                    T::Currency::reserve(&who, deposit)?;
+                    <SubvalueCount<T>>::mutate(&name, |n| *n += 1);
+                    <SubvalueKeys<T>>::mutate(&name, |keys| {
+                        let encoded = key.encode();
+                        let pos = keys.iter().position(|k| k.encode() > encoded)
+                            .unwrap_or(keys.len());
+                        keys.insert(pos, key.clone());
+                    });
+                    Subvalue::<T> {
+                        value: value,
+                        depositor: who,
+                        deposit: deposit,
+                    }
+                },
+            };
+            <Subvalues<T>>::insert(&name, &key, entry);
+
+            Self::deposit_event(RawEvent::SubvalueUpdated(name, key));
+            Ok(())
+        }
+
+        /// Removes the subvalue for the given subkey of a name, releasing
+        /// its deposit.  Only the name's owner can do this.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::clear_subvalue())]
+        pub fn clear_subvalue(origin, name: T::Name, key: T::SubKey) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner subvalue update"),
+            }
+
+            let entry = match <Subvalues<T>>::get(&name, &key) {
+                None => return Err("subvalue does not exist".into()),
+                Some(entry) => entry,
+            };
+            T::Currency::unreserve(&entry.depositor, entry.deposit);
+            <Subvalues<T>>::remove(&name, &key);
+            <SubvalueCount<T>>::mutate(&name, |n| *n = n.saturating_sub(1));
+            <SubvalueKeys<T>>::mutate(&name, |keys| keys.retain(|k| *k != key));
+
+            Self::deposit_event(RawEvent::SubvalueCleared(name, key));
+            Ok(())
+        }
+
+        /// Publishes a value encrypted for a specific reader of a name owned
+        /// by the sender.  Only the structure of the envelope is checked,
+        /// and a [fee](Trait::EnvelopeFee) is charged.  An envelope for the
+        /// same recipient replaces the previous one.  Envelopes are removed
+        /// when the name changes owner or is removed.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::publish_envelope())]
+        pub fn publish_envelope(origin, name: T::Name, envelope: Envelope) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner envelope update"),
+            }
+            envelope.check_structure(T::MaxEnvelopeSize::get())?;
+            let exists = <Envelopes<T>>::exists(&name, &envelope.recipient);
+            if !exists {
+                ensure!(Self::envelope_count(&name) < T::MaxEnvelopes::get(),
+                        "too many envelopes");
+            }
+
+            let fee = T::EnvelopeFee::get();
+            if !fee.is_zero() {
+                T::Payment::withdraw(&who, fee, None)?;
+            }
+
+            if !exists {
+                <EnvelopeCount<T>>::mutate(&name, |n| *n += 1);
+            }
+            <Envelopes<T>>::insert(&name, &envelope.recipient, envelope.ciphertext);
+            Self::deposit_event(RawEvent::EnvelopePublished(name, envelope.recipient));
+            Ok(())
+        }
+
+        /// Removes the envelope of a name owned by the sender for the
+        /// given recipient key.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::remove_envelope())]
+        pub fn remove_envelope(origin, name: T::Name, recipient: H256) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner envelope update"),
+            }
+            ensure!(<Envelopes<T>>::exists(&name, &recipient), "envelope does not exist");
+            <Envelopes<T>>::remove(&name, &recipient);
+            <EnvelopeCount<T>>::mutate(&name, |n| *n = n.saturating_sub(1));
+            Ok(())
+        }
+
+        /// Starts watching an existing name, reserving a
+        /// [deposit](Trait::WatchDeposit).  Whenever the name is then updated,
+        /// transferred or removed, a `NameWatchTriggered` event is emitted
+        /// for the sender, so that notification services can follow specific
+        /// names without processing all events.  When the name is removed,
+        /// all watchers are dropped and their deposits released.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::watch_name())]
+        pub fn watch_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+
+            let mut watchers = <Watchers<T>>::get(&name);
+            ensure!(!watchers.iter().any(|(w, _)| *w == who), "name is watched already");
+            ensure!(watchers.len() < T::MaxWatchers::get() as usize, "too many watchers");
+
+            let deposit = T::WatchDeposit::get();
+            T::Currency::reserve(&who, deposit)?;
+            watchers.push((who, deposit));
+            <Watchers<T>>::insert(&name, watchers);
+            Ok(())
+        }
+
+        /// Stops watching a name and releases the deposit.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::unwatch_name())]
+        pub fn unwatch_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut watchers = <Watchers<T>>::get(&name);
+            let pos = match watchers.iter().position(|(w, _)| *w == who) {
+                None => return Err("name is not watched".into()),
+                Some(pos) => pos,
+            };
+
+            let (_, deposit) = watchers.remove(pos);
+            T::Currency::unreserve(&who, deposit);
+            if watchers.is_empty() {
+                <Watchers<T>>::remove(&name);
+            } else {
+                <Watchers<T>>::insert(&name, watchers);
+            }
+            Ok(())
+        }
+
+        /// Tries to transfer a name to a given recipient.
+        ///
+        /// If the name does not exist, it will be registered directly to them
+        /// with a [default value](Trait::DefaultValue), unless
+        /// [strict transfers](Trait::StrictTransfers) are enabled.
+        #[weight = TransferOperationWeight::<T>::new(T::WeightInfo::transfer())]
+        pub fn transfer(origin, name: T::Name, recipient: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            if T::StrictTransfers::get() {
+                ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            }
+            Self::ensure_direct_registration(&name)?;
+            let data = Self::check_assuming_signed(who, name, None, Some(recipient))?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Transfers an existing name to a given recipient.  In contrast to
+        /// [`transfer`](Module::transfer), this always fails if the name
+        /// does not exist.
+        #[weight = TransferOperationWeight::<T>::new(T::WeightInfo::transfer_existing())]
+        pub fn transfer_existing(origin, name: T::Name, recipient: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            let data = Self::check_assuming_signed(who, name, None, Some(recipient))?;
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Sets whether names transferred to the sender should arrive
+        /// immediately, or only be recorded as pending transfers that the
+        /// sender then has to [claim](Module::claim_name).  This lets e.g.
+        /// exchanges avoid receiving unsolicited names.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_transfer_preference())]
+        pub fn set_transfer_preference(origin, require_claim: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            if require_claim {
+                <RequiresClaim<T>>::insert(&who, true);
+            } else {
+                <RequiresClaim<T>>::remove(&who);
+            }
+            Ok(())
+        }
+
+        /// Refuses all future name transfers from `sender` to the
+        /// transaction sender.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::block_sender())]
+        pub fn block_sender(origin, sender: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            <BlockedSenders<T>>::insert(&who, &sender, true);
+            Ok(())
+        }
+
+        /// Accepts name transfers from a previously
+        /// [blocked](Module::block_sender) sender again.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::unblock_sender())]
+        pub fn unblock_sender(origin, sender: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            <BlockedSenders<T>>::remove(&who, &sender);
+            Ok(())
+        }
+
+        /// Sets whether the sender refuses all incoming name transfers
+        /// from other accounts.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_block_all_transfers())]
+        pub fn set_block_all_transfers(origin, block: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            if block {
+                <BlocksAllTransfers<T>>::insert(&who, true);
+            } else {
+                <BlocksAllTransfers<T>>::remove(&who);
+            }
+            Ok(())
+        }
+
+        /// Accepts a pending transfer of a name to the sender.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::claim_name())]
+        pub fn claim_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::pending_transfer(&name).as_ref() == Some(&who),
+                    "no pending transfer to the sender");
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+
+            /* The fee has been paid already for the original transfer.  */
+            Self::execute(Operation::<T> {
+                sender: who.clone(),
+                ..Self::unchecked_transfer(name.clone(), data, who.clone())
+            })?;
+
+            Self::deposit_event(RawEvent::TransferClaimed(name, who));
+            Ok(())
+        }
+
+        /// Cancels the pending transfer of a name owned by the sender.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::cancel_transfer())]
+        pub fn cancel_transfer(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner transfer cancellation"),
+            }
+            ensure!(Self::pending_transfer(&name).is_some(), "no pending transfer");
+            <PendingTransfers<T>>::remove(&name);
+            Ok(())
+        }
+
+        /// Donates a name to the [community account](Trait::CommunityAccount).
+        /// This hands over names that the owner no longer wants to maintain
+        /// (e.g. community infrastructure) to governance.  The donation is
+        /// checked like any other transfer of the name, but it is free,
+        /// and donated names never expire.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::donate_name())]
+        pub fn donate_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner name donation"),
+            }
+            let community = T::CommunityAccount::get();
+            ensure!(community != who, "name is owned by the community already");
+
+            let mut op = Self::check_assuming_signed(who.clone(), name.clone(),
+                                                     None, Some(community))?;
+            op.fee = Zero::zero();
+            op.never_expires = true;
+            Self::execute(op)?;
+
+            Self::deposit_event(RawEvent::NameDonated(name, who));
+            Ok(())
+        }
+
+        /// Locks a name owned by the sender as collateral for the given
+        /// beneficiary, e.g. a lending protocol.  While locked, the name
+        /// cannot be transferred or donated by its owner (but its value can
+        /// still be updated).  The beneficiary can either
+        /// [release](Module::release_collateral) the name again, or
+        /// [seize](Module::claim_collateral) it.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::lock_as_collateral())]
+        pub fn lock_as_collateral(origin, name: T::Name,
+                                  beneficiary: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let state = Self::state_of(&name);
+            match state.data() {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner collateral lock"),
+            }
+            ensure!(who != beneficiary, "cannot lock collateral for the owner");
+            ensure!(!<Collateral<T>>::exists(&name), "name is locked as collateral");
+            ensure!(!<RentAgreements<T>>::exists(&name),
+                    "name is under a rent-to-own agreement");
+            Self::ensure_can_encumber(&name, &state)?;
+
+            <Collateral<T>>::insert(&name, &beneficiary);
+            Self::deposit_event(RawEvent::CollateralLocked(name, beneficiary));
+            Ok(())
+        }
+
+        /// Releases the collateral lock on a name, which must be done by
+        /// the beneficiary (e.g. after a loan has been repaid).
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::release_collateral())]
+        pub fn release_collateral(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::collateral_beneficiary(&name).as_ref() == Some(&who),
+                    "sender is not the collateral beneficiary");
+            <Collateral<T>>::remove(&name);
+            Self::deposit_event(RawEvent::CollateralReleased(name));
+            Ok(())
+        }
+
+        /// Seizes a name locked as collateral, which transfers it to the
+        /// beneficiary of the lock.  The lock is removed in the process.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::claim_collateral())]
+        pub fn claim_collateral(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::collateral_beneficiary(&name).as_ref() == Some(&who),
+                    "sender is not the collateral beneficiary");
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+            Self::ensure_direct_transfer_allowed(&name)?;
+
+            /* The change of ownership clears the lock as owner data.  */
+            Self::execute(Operation::<T> {
+                sender: who.clone(),
+                ..Self::unchecked_transfer(name.clone(), data, who.clone())
+            })?;
+
+            Self::deposit_event(RawEvent::CollateralClaimed(name, who));
+            Ok(())
+        }
+
+        /// Proposes to swap names with a counterparty:  The `offered` names
+        /// of the sender go to the counterparty, and the `requested` names
+        /// of the counterparty to the sender, together with an optional
+        /// `sweetener` paid by the sender.  The offered names are locked
+        /// against transfers and the sweetener is reserved until the offer
+        /// is [accepted](Module::accept_swap) or
+        /// [cancelled](Module::cancel_swap).
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::propose_swap())]
+        pub fn propose_swap(origin, counterparty: T::AccountId, offered: Vec<T::Name>,
+                            requested: Vec<T::Name>,
+                            sweetener: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(who != counterparty, "cannot swap names with oneself");
+            ensure!(!<SwapOffers<T>>::exists(&who), "sender already has an open swap offer");
+            ensure!(!offered.is_empty() || !requested.is_empty(), "swap offer is empty");
+            ensure!(offered.len() + requested.len() <= T::MaxSwapNames::get() as usize,
+                    "too many names in swap offer");
+            for (i, name) in offered.iter().chain(requested.iter()).enumerate() {
+                ensure!(offered.iter().chain(requested.iter()).skip(i + 1).all(|n| n != name),
+                        "duplicate name in swap offer");
+            }
+            Self::check_swap_names(&offered, &who)?;
+            Self::check_swap_names(&requested, &counterparty)?;
+            for name in &offered {
+                ensure!(!<SwapLocks<T>>::exists(name), "name is offered in a swap");
+            }
+
+            T::Currency::reserve(&who, sweetener)?;
+            for name in &offered {
+                <SwapLocks<T>>::insert(name, &who);
+            }
+            <SwapOffers<T>>::insert(&who, SwapOffer::<T> {
+                counterparty: counterparty.clone(),
+                offered: offered,
+                requested: requested,
+                sweetener: sweetener,
+            });
+            Self::deposit_event(RawEvent::SwapProposed(who, counterparty));
+            Ok(())
+        }
+
+        /// Cancels the sender's open swap offer.  This unlocks the
+        /// offered names and releases the sweetener.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::cancel_swap())]
+        pub fn cancel_swap(origin) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let offer = match <SwapOffers<T>>::take(&who) {
+                None => return Err("no open swap offer".into()),
+                Some(offer) => offer,
+            };
+            Self::unlock_swap(&who, &offer);
+            Self::deposit_event(RawEvent::SwapCancelled(who));
+            Ok(())
+        }
+
+        /// Accepts the swap offer of the given proposer, in which the
+        /// sender is the counterparty.  All names and the sweetener change
+        /// hands, or nothing does if any of them cannot.
+        ///
+        /// The weight covers [the maximum number](Trait::MaxSwapNames) of
+        /// names on both sides.  Their [extra weight](Trait::ExtraWeight)
+        /// cannot be included, since the names are not arguments.
+        #[weight = SimpleDispatchInfo::FixedNormal(
+            T::WeightInfo::accept_swap().saturating_mul(T::MaxSwapNames::get() as Weight))]
+        pub fn accept_swap(origin, proposer: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let offer = match <SwapOffers<T>>::get(&proposer) {
+                None => return Err("no open swap offer".into()),
+                Some(offer) => offer,
+            };
+            ensure!(offer.counterparty == who, "sender is not the swap counterparty");
+
+            /* Storage changes cannot be rolled back, so everything is
+               checked before anything is changed.  The name transfers
+               themselves have no fee and cannot fail after this.  */
+            Self::check_swap_names(&offer.offered, &proposer)?;
+            Self::check_swap_names(&offer.requested, &who)?;
+            for name in &offer.requested {
+                ensure!(!<SwapLocks<T>>::exists(name), "name is offered in a swap");
+            }
+
+            <SwapOffers<T>>::remove(&proposer);
+            Self::unlock_swap(&proposer, &offer);
+            if !offer.sweetener.is_zero() {
+                if let Err(e) = T::Currency::transfer(&proposer, &who, offer.sweetener,
+                                                      ExistenceRequirement::KeepAlive) {
+                    /* Nothing else has changed yet, so just restore
+                       the offer.  */
+                    T::Currency::reserve(&proposer, offer.sweetener)?;
+                    for name in &offer.offered {
+                        <SwapLocks<T>>::insert(name, &proposer);
+                    }
+                    <SwapOffers<T>>::insert(&proposer, &offer);
+                    return Err(e);
+                }
+            }
+
+            for name in &offer.offered {
+                Self::settle_swapped_name(name, &who);
+            }
+            for name in &offer.requested {
+                Self::settle_swapped_name(name, &proposer);
+            }
+            Self::deposit_event(RawEvent::SwapCompleted(proposer, who));
+            Ok(())
+        }
+
+        /// Configures how income accruing to a name owned by the sender is
+        /// split.  Each account receives the given fraction, and the rest
+        /// goes to the owner.  An empty list sends everything to the owner.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_payout())]
+        pub fn set_payout(origin, name: T::Name,
+                          payouts: Vec<(T::AccountId, Perbill)>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner payout change"),
+            }
+            ensure!(payouts.len() <= T::MaxPayoutSplits::get() as usize,
+                    "too many payout accounts");
+            let total = payouts.iter().try_fold(0u32, |acc, (_, p)| {
+                acc.checked_add(p.deconstruct())
+                    .filter(|t| *t <= Perbill::one().deconstruct())
+            });
+            ensure!(total.is_some(), "payout fractions exceed the whole");
+
+            if payouts.is_empty() {
+                <Payouts<T>>::remove(&name);
+            } else {
+                <Payouts<T>>::insert(&name, payouts);
+            }
+            Self::deposit_event(RawEvent::PayoutChanged(name));
+            Ok(())
+        }
+
+        /// Pays the given amount to a name.  It is distributed according to
+        /// the name's [payout configuration](Module::set_payout).
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::pay_name())]
+        pub fn pay_name(origin, name: T::Name, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+            let income = T::Currency::withdraw(&who, amount, WithdrawReason::Transfer.into(),
+                                               ExistenceRequirement::KeepAlive)?;
+            Self::distribute_income(&name, income);
+            Self::deposit_event(RawEvent::NamePaid(name, who, amount));
+            Ok(())
+        }
+
+        /// Grants a standing approval for a name owned by the sender:  The
+        /// operator may [update the value](Module::operator_update) of the
+        /// name without further signatures, but only to values that match
+        /// one of the given [templates](Trait::Templates).  This replaces
+        /// any previous approval; an empty template list revokes it.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::approve_operator())]
+        pub fn approve_operator(origin, name: T::Name, operator: T::AccountId,
+                                templates: Vec<H256>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner operator approval"),
+            }
+            ensure!(templates.len() <= T::MaxTemplates::get() as usize, "too many templates");
+
+            if templates.is_empty() {
+                <Operators<T>>::remove(&name);
+            } else {
+                <Operators<T>>::insert(&name, (operator.clone(), templates));
+            }
+            Self::deposit_event(RawEvent::OperatorApproved(name, operator));
+            Ok(())
+        }
+
+        /// Updates the value of a name as its approved operator.  The value
+        /// must match one of the approved templates, and the operator pays
+        /// the name fee.  Any value deposit is reserved from the owner.
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::operator_update())]
+        pub fn operator_update(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let (operator, templates) = match <Operators<T>>::get(&name) {
+                None => return Err("name has no approved operator".into()),
+                Some(approval) => approval,
+            };
+            ensure!(operator == who, "sender is not the approved operator");
+            ensure!(templates.iter().any(|t| T::Templates::matches(&value, t)),
+                    "value does not match any approved template");
+            let owner = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data.owner,
+            };
+
+            let op = Self::check_sponsored(owner, who, name, Some(value), None)?;
+            Self::execute(op)
+        }
+
+        /// Starts a rent-to-own agreement for a name owned by the sender.
+        /// The tenant has to [pay](Module::pay_installment) the given
+        /// number of installments, each within `period` blocks of the
+        /// previous one.  Installments are income of the name, and thus
+        /// follow its [payout configuration](Module::set_payout).
+        ///
+        /// After the last installment, the name is transferred to the
+        /// tenant, with its data handed over according to `terms`.  If an
+        /// installment is missed, the agreement is cancelled and the name
+        /// stays with the owner.  While the agreement runs, the owner cannot
+        /// transfer the name elsewhere.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::offer_rent_to_own())]
+        pub fn offer_rent_to_own(origin, name: T::Name, tenant: T::AccountId,
+                                 installment: BalanceOf<T>, payments: u32,
+                                 period: T::BlockNumber,
+                                 terms: HandoverTerms) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let state = Self::state_of(&name);
+            match state.data() {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner rent-to-own offer"),
+            }
+            ensure!(who != tenant, "cannot rent a name to its owner");
+            ensure!(payments > 0 && !period.is_zero(), "invalid rent-to-own terms");
+            ensure!(!<RentAgreements<T>>::exists(&name),
+                    "name is under a rent-to-own agreement");
+            ensure!(!<Collateral<T>>::exists(&name), "name is locked as collateral");
+            Self::ensure_can_encumber(&name, &state)?;
+
+            let next_due = system::Module::<T>::block_number() + period;
+            <RentAgreements<T>>::insert(&name, RentAgreement::<T> {
+                tenant: tenant.clone(),
+                installment: installment,
+                remaining: payments,
+                period: period,
+                next_due: next_due,
+                terms: terms,
+            });
+            <RentDue<T>>::insert(next_due, &name, &name);
+            Self::deposit_event(RawEvent::RentToOwnStarted(name, tenant));
+            Ok(())
+        }
+
+        /// Pays the next installment of a rent-to-own agreement in which
+        /// the sender is the tenant.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::pay_installment())]
+        pub fn pay_installment(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut agreement = match <RentAgreements<T>>::get(&name) {
+                None => return Err("no rent-to-own agreement for the name".into()),
+                Some(agreement) => agreement,
+            };
+            ensure!(agreement.tenant == who, "sender is not the tenant");
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+            if agreement.remaining == 1 {
+                Self::ensure_direct_transfer_allowed(&name)?;
+            }
+
+            let income = T::Currency::withdraw(&who, agreement.installment,
+                                               WithdrawReason::Transfer.into(),
+                                               ExistenceRequirement::KeepAlive)?;
+            Self::distribute_income(&name, income);
+
+            agreement.remaining -= 1;
+            Self::deposit_event(RawEvent::InstallmentPaid(name.clone(), agreement.remaining));
+            if agreement.remaining > 0 {
+                agreement.next_due = system::Module::<T>::block_number() + agreement.period;
+                <RentDue<T>>::insert(agreement.next_due, &name, &name);
+                <RentAgreements<T>>::insert(&name, agreement);
+                return Ok(());
+            }
+
+            /* The change of ownership removes the agreement.  */
+            let (typ, value) = if agreement.terms.clear_value_on_transfer {
+                (OperationType::Update, T::DefaultValue::default_value_for(&name, &who))
+            } else {
+                (OperationType::Transfer, data.value.clone())
+            };
+            Self::execute(Operation::<T> {
+                operation: typ,
+                value: value,
+                sender: who.clone(),
+                ..Self::unchecked_transfer(name.clone(), data, who.clone())
+            })?;
+            if !agreement.terms.preserve_records {
+                Self::clear_subvalues(&name);
+            }
+            Self::deposit_event(RawEvent::RentToOwnCompleted(name, who));
+            Ok(())
+        }
+
+        /// Splits a name owned by the sender into the given number of shares,
+        /// all of which are initially held by the sender.  The name itself
+        /// is transferred to its [sovereign account](Module::name_account),
+        /// so that it can no longer be updated or transferred directly.
+        ///
+        /// Instead, share holders [propose](Module::propose_value) new
+        /// values, which are applied once holders of at least the
+        /// `threshold` fraction of all shares approve them.  A value
+        /// deposit for the name (if any) is reserved from the sovereign
+        /// account, which must be funded for that.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::split_into_shares())]
+        pub fn split_into_shares(origin, name: T::Name, total: u32,
+                                 threshold: Perbill) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let state = Self::state_of(&name);
+            let data = match state.data() {
+                None => return Err("name does not exist".into()),
+                Some(data) => data.clone(),
+            };
+            ensure!(data.owner == who, "non-owner share split");
+            ensure!(total > 0, "cannot split a name into zero shares");
+            ensure!(!<Collateral<T>>::exists(&name), "name is locked as collateral");
+            ensure!(!<RentAgreements<T>>::exists(&name),
+                    "name is under a rent-to-own agreement");
+            Self::ensure_can_encumber(&name, &state)?;
+
+            Self::execute(Self::unchecked_transfer(name.clone(), data,
+                                                   Self::name_account(&name)))?;
+
+            let mut holders = Vec::new();
+            holders.push((who, total));
+            <SharedNames<T>>::insert(&name, ShareInfo::<T> {
+                holders: holders,
+                total: total,
+                threshold: threshold,
+            });
+            Self::deposit_event(RawEvent::NameSplit(name, total));
+            Ok(())
+        }
+
+        /// Transfers shares of a name from the sender to another account.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::transfer_shares())]
+        pub fn transfer_shares(origin, name: T::Name, recipient: T::AccountId,
+                               amount: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut info = match <SharedNames<T>>::get(&name) {
+                None => return Err("name is not split into shares".into()),
+                Some(info) => info,
+            };
+            ensure!(info.shares_of(&who) >= amount, "insufficient shares");
+
+            for (holder, n) in info.holders.iter_mut() {
+                if *holder == who {
+                    *n -= amount;
+                }
+            }
+            match info.holders.iter_mut().find(|(a, _)| *a == recipient) {
+                Some((_, n)) => *n += amount,
+                None => info.holders.push((recipient.clone(), amount)),
+            }
+            info.holders.retain(|(_, n)| *n > 0);
+            ensure!(info.holders.len() <= T::MaxShareHolders::get() as usize,
+                    "too many share holders");
+
+            <SharedNames<T>>::insert(&name, info);
+            Self::deposit_event(RawEvent::SharesTransferred(name, who, recipient, amount));
+            Ok(())
+        }
+
+        /// Proposes a new value for a name split into shares.  This replaces
+        /// any previous proposal, and counts as approval by the sender.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::propose_value())]
+        pub fn propose_value(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::approve_proposal(who.clone(), name.clone(), value, Vec::new())?;
+            Self::deposit_event(RawEvent::ValueProposed(name, who));
+            Ok(())
+        }
+
+        /// Approves the currently proposed value of a name split into shares.
+        /// If this brings the approvals to the threshold, the value is
+        /// updated, with the sender paying the name fee.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::approve_value())]
+        pub fn approve_value(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let (value, approvers) = match <ValueProposals<T>>::get(&name) {
+                None => return Err("no value proposal for the name".into()),
+                Some(proposal) => proposal,
+            };
+            Self::approve_proposal(who, name, value, approvers)
+        }
+
+        /// Turns a name split into shares back into a normal name.  This is
+        /// possible for an account that holds all shares, and transfers the
+        /// name to it.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::reclaim_name())]
+        pub fn reclaim_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let info = match <SharedNames<T>>::get(&name) {
+                None => return Err("name is not split into shares".into()),
+                Some(info) => info,
+            };
+            ensure!(info.shares_of(&who) == info.total, "sender does not hold all shares");
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+            Self::ensure_direct_transfer_allowed(&name)?;
+
+            /* The change of ownership clears the share data.  */
+            Self::execute(Operation::<T> {
+                sender: who.clone(),
+                ..Self::unchecked_transfer(name.clone(), data, who.clone())
+            })?;
+
+            Self::deposit_event(RawEvent::NameReclaimed(name, who));
+            Ok(())
+        }
+
+        /// Dispatches a call from the sovereign account of a name, which is
+        /// derived from the name itself (see
+        /// [`name_account`](Module::name_account)).  Only the current owner
+        /// of the name can do this, and only for calls allowed by the
+        /// [configured filter](Trait::NameCallFilter).
+        ///
+        /// This allows on-chain resources to be owned by a name rather than
+        /// an account, so that they follow transfers of the name.
+        ///
+        /// The call is weighed as the inner call plus the overhead for
+        /// checking the name, and has the inner call's dispatch class.
+        #[weight = DispatchAsNameWeight::<T>::new(T::WeightInfo::dispatch_as_name())]
+        pub fn dispatch_as_name(origin, name: T::Name,
+                                call: Box<<T as Trait>::Call>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+            ensure!(data.owner == who, "non-owner name dispatch");
+            ensure!(T::NameCallFilter::allow_call(&name, &call),
+                    "call is not allowed for dispatch as a name");
+
+            let account = Self::name_account(&name);
+            call.dispatch(RawOrigin::Signed(account).into())
+        }
+
+        /// Recomputes the derived index entries (owner index, expiration
+        /// queue and namehash index) of a single name from its entry in the
+        /// name database.  An entry for the name in the sender's own part
+        /// of the owner index is removed if the sender does not own it.
+        ///
+        /// Anyone can call this, as it only brings the indices in line
+        /// with the authoritative data.  For a full rebuild, the runtime can
+        /// set [`RepairIndex`](migration::RepairIndex) as
+        /// [migration](Trait::Migration) and queue all names.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::repair_index())]
+        pub fn repair_index(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::repair_index_of(&name, Some(&who));
+            Ok(())
+        }
+
+        /// Transfers all names owned by the sender to a new owner, e.g. to
+        /// migrate to a new key.  Each transfer is subject to the name policy
+        /// and fees just like a normal transfer.
+        ///
+        /// To bound the weight, at most
+        /// [a certain number](Trait::MaxBulkTransfer) of names are looked at
+        /// per call.  If the sender owns more names, the call has to be
+        /// repeated until all are transferred.
+        ///
+        /// Names that cannot be transferred (e.g. because of the name policy
+        /// or a lock) do not abort the call; they are left with the sender
+        /// and reported by a `BulkTransferSkipped` event each.  Since they
+        /// remain in the owner index, the first `skip` names of the sender
+        /// are passed over, so that repeated calls can continue behind all
+        /// names skipped so far.
+        ///
+        /// The weight covers the maximum number of transfers plus each
+        /// skipped name, so that passing over names is not free.
+        #[weight = BulkTransferWeight::<T>::new(T::WeightInfo::transfer_all_names(),
+                                                T::WeightInfo::transfer_all_names_skipped())]
+        pub fn transfer_all_names(origin, recipient: T::AccountId, skip: u32)
+            -> DispatchResult
+        {
+            let who = ensure_signed(origin)?;
+            ensure!(who != recipient, "cannot transfer names to the sender");
+
+            let max = T::MaxBulkTransfer::get() as usize;
+            let mut ops = Vec::new();
+            let mut skipped = Vec::new();
+            for name in <OwnerIndex<T>>::iter_prefix(&who).skip(skip as usize).take(max) {
+                match Self::check_assuming_signed(who.clone(), name.clone(),
+                                                  None, Some(recipient.clone())) {
+                    Ok(op) => ops.push(op),
+                    Err(_) => skipped.push(name),
+                }
+            }
+            Self::ensure_affordable(&ops).map_err(|e| e.message())?;
+            let count = ops.len() as u32;
+            Self::execute_many(ops)?;
+
+            for name in skipped {
+                Self::deposit_event(RawEvent::BulkTransferSkipped(name));
+            }
+            Self::deposit_event(RawEvent::NamesTransferred(who, recipient, count));
+            Ok(())
+        }
+
+        /// Registers a name only by its hash.  The name is then reserved for the
+        /// sender for a [certain number](Trait::HiddenRegistrationPeriod)
+        /// of blocks, without disclosing the name itself publicly.
+        /// Within this period, the sender can [reveal](Module::reveal_name)
+        /// the name to actually register it.
+        ///
+        /// Note that the hash is not salted, so the privacy only holds
+        /// for names that cannot simply be guessed.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::register_hidden())]
+        pub fn register_hidden(origin, hash: T::Hash) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<HiddenNames<T>>::get(&hash).is_none(),
+                    "name hash is registered already");
+
+            let fee = T::HiddenRegistrationFee::get();
+            if !fee.is_zero() {
+                T::Payment::withdraw(&who, fee, None)?;
+            }
+
+            let blocks = max(T::HiddenRegistrationPeriod::get(), T::BlockNumber::from(1));
+            let expiration = system::Module::<T>::block_number() + blocks;
+            <HiddenNames<T>>::insert(&hash, HiddenRegistration::<T> {
+                owner: who,
+                expiration: expiration,
+            });
+            <HiddenExpirations<T>>::insert(expiration, &hash, &hash);
+
+            Self::deposit_event(RawEvent::NameRegisteredHidden(hash));
+            Ok(())
+        }
+
+        /// Reveals a name for which the sender holds a
+        /// [hidden registration](Module::register_hidden).  This registers
+        /// the name (with [default value](Trait::DefaultValue)) just like a
+        /// normal registration
+        /// and makes it publicly resolvable.  As for
+        /// [`register`](Module::register), `valid_until` optionally limits
+        /// the blocks in which this can be included.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::reveal_name())]
+        pub fn reveal_name(origin, name: T::Name,
+                           valid_until: Option<T::BlockNumber>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_valid_until(valid_until)?;
+            let hash = T::Hashing::hash_of(&name);
+            match <HiddenNames<T>>::get(&hash) {
+                None => return Err("no hidden registration for the name".into()),
+                Some(hidden) => ensure!(hidden.owner == who, "non-owner name reveal"),
+            }
+
+            let data = Self::check_assuming_signed(who, name.clone(), None, None)?;
+            Self::execute(data)?;
+
+            Self::deposit_event(RawEvent::NameRevealed(hash, name));
+            Ok(())
+        }
+
+        /// Sets or (with `None`) clears the dedicated signing key of a name.
+        /// Only the name's owner can do this.  The key is cleared
+        /// automatically when the name is transferred or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_signing_key())]
+        pub fn set_signing_key(origin, name: T::Name, key: Option<T::AccountId>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner signing key update"),
+            }
+
+            match &key {
+                None => <SigningKeys<T>>::remove(&name),
+                Some(k) => <SigningKeys<T>>::insert(&name, k),
+            }
+
+            Self::deposit_event(RawEvent::SigningKeyUpdated(name, key));
+            Ok(())
+        }
+
+        /// Sets or (with `None`) clears the controller of a name.  The
+        /// controller can update the value of the name like its owner, but
+        /// cannot transfer it; the owner keeps full control.  This allows
+        /// e.g. a custodian to hold a name while someone else manages it.
+        /// Only the name's owner can do this, and the controller is cleared
+        /// automatically when the name is transferred or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_controller())]
+        pub fn set_controller(origin, name: T::Name,
+                              controller: Option<T::AccountId>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner controller update"),
+            }
+
+            match &controller {
+                None => <Controllers<T>>::remove(&name),
+                Some(c) => <Controllers<T>>::insert(&name, c),
+            }
+
+            Self::deposit_event(RawEvent::ControllerUpdated(name, controller));
+            Ok(())
+        }
+
+        /// Attaches a note to a name owned by the sender, replacing any
+        /// previous one, or clears it with `None`.  Notes are meant for the
+        /// owner's own tooling, e.g. for custodians to tag names with
+        /// internal references.  They are not part of the name's data and
+        /// not returned by [`lookup`](Module::lookup) or any other resolution
+        /// API, but like all chain state they are not secret.  A note requires
+        /// a [deposit](Trait::NoteDeposit), and is cleared automatically
+        /// (releasing the deposit) when the name changes owner or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_note())]
+        pub fn set_note(origin, name: T::Name, note: Option<Vec<u8>>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner note update"),
+            }
+
+            let data = match note {
+                None => {
+                    ensure!(<Notes<T>>::exists(&name), "name has no note");
+                    Self::clear_note(&name);
+                    Self::deposit_event(RawEvent::NoteUpdated(name, false));
+                    return Ok(());
+                },
+                Some(data) => data,
+            };
+            ensure!(data.len() <= T::MaxNoteSize::get() as usize, "note is too large");
+
+            let entry = match <Notes<T>>::get(&name) {
+                Some(old) => Note::<T> {
+                    data: data,
+                    ..old
+                },
+                None => {
+                    let deposit = T::NoteDeposit::get();
+                    T::Currency::reserve(&who, deposit)?;
+                    Note::<T> {
+                        data: data,
+                        depositor: who,
+                        deposit: deposit,
+                    }
+                },
+            };
+            <Notes<T>>::insert(&name, entry);
+
+            Self::deposit_event(RawEvent::NoteUpdated(name, true));
+            Ok(())
+        }
+
+        /// Redirects a name to another name, or (with `None`) removes the
+        /// redirect.  While set, [`resolve`](Module::resolve) and the helpers
+        /// based on it return the data of the target instead, so that e.g.
+        /// short aliases can point to a canonical name without duplicating
+        /// its records.  The target may redirect further, but the whole chain
+        /// must end at an existing name within the
+        /// [maximum depth](Trait::MaxRedirectDepth) and must not lead back
+        /// to the name itself.  The redirect is cleared when the name changes
+        /// owner or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_redirect())]
+        pub fn set_redirect(origin, name: T::Name, target: Option<T::Name>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner redirect update"),
+            }
+
+            match &target {
+                None => {
+                    ensure!(<Redirects<T>>::exists(&name), "name has no redirect");
+                    <Redirects<T>>::remove(&name);
+                },
+                Some(t) => {
+                    Self::check_redirect(&name, t).map_err(|e| e.message())?;
+                    <Redirects<T>>::insert(&name, t);
+                },
+            }
+
+            Self::deposit_event(RawEvent::RedirectUpdated(name, target));
+            Ok(())
+        }
+
+        /// Sets or (with `None`) clears the guardian of a name, e.g. an
+        /// account kept in cold storage.  While a name has a guardian, it
+        /// can only be transferred after the owner
+        /// [requests the transfer](Module::request_guarded_transfer) and
+        /// the guardian [confirms it](Module::confirm_guarded_transfer).
+        /// The owner can add a guardian to a name without one, but once set,
+        /// only the guardian itself can change or remove it.  The guardian
+        /// is cleared when the name changes owner or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_guardian())]
+        pub fn set_guardian(origin, name: T::Name,
+                            guardian: Option<T::AccountId>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let data = <Names<T>>::get(&name).ok_or("name does not exist")?;
+            match Self::guardian(&name) {
+                None => ensure!(data.owner == who, "non-owner guardian update"),
+                Some(current) => ensure!(current == who, "only the guardian can change it"),
+            }
+
+            /* A new guardian must not block the transfers that existing
+               agreements (which it did not confirm) rely on.  */
+            if guardian.is_some() && !<Guardians<T>>::exists(&name) {
+                ensure!(!<Collateral<T>>::exists(&name), "name is locked as collateral");
+                ensure!(!<SwapLocks<T>>::exists(&name), "name is offered in a swap");
+                ensure!(!<RentAgreements<T>>::exists(&name),
+                        "name is under a rent-to-own agreement");
+            }
+
+            match &guardian {
+                None => <Guardians<T>>::remove(&name),
+                Some(g) => <Guardians<T>>::insert(&name, g),
+            }
+            <GuardedTransfers<T>>::remove(&name);
+
+            Self::deposit_event(RawEvent::GuardianUpdated(name, guardian));
+            Ok(())
+        }
+
+        /// Requests the transfer of a name with a guardian to the given
+        /// recipient, replacing any earlier request.  The guardian must
+        /// [confirm](Module::confirm_guarded_transfer) it within the
+        /// [confirmation period](Trait::GuardianConfirmationPeriod).
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::request_guarded_transfer())]
+        pub fn request_guarded_transfer(origin, name: T::Name,
+                                        recipient: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let data = <Names<T>>::get(&name).ok_or("name does not exist")?;
+            ensure!(data.owner == who, "non-owner transfer request");
+            ensure!(Self::guardian(&name).is_some(), "name has no guardian");
+            ensure!(recipient != who, "name is already owned by the recipient");
+
+            let deadline = system::Module::<T>::block_number()
+                + T::GuardianConfirmationPeriod::get();
+            <GuardedTransfers<T>>::insert(&name, GuardedTransfer {
+                recipient: recipient.clone(),
+                deadline: deadline,
+                confirmed: false,
+            });
+
+            Self::deposit_event(RawEvent::GuardedTransferRequested(name, recipient, deadline));
+            Ok(())
+        }
+
+        /// Confirms a [requested transfer](Module::request_guarded_transfer)
+        /// of a name as its guardian, and executes it.
+        #[weight = NameOperationWeight::<T>::new(T::WeightInfo::confirm_guarded_transfer())]
+        pub fn confirm_guarded_transfer(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::guardian(&name) == Some(who), "only the guardian can confirm");
+            let mut request = Self::guarded_transfer(&name).ok_or("no transfer requested")?;
+            ensure!(system::Module::<T>::block_number() <= request.deadline,
+                    "transfer request has expired");
+            let owner = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data.owner,
+            };
+
+            /* The check only lets the transfer through once it is marked
+               as confirmed.  If it still fails, the request is restored.  */
+            let recipient = request.recipient.clone();
+            request.confirmed = true;
+            <GuardedTransfers<T>>::insert(&name, &request);
+            let op = match Self::check_assuming_signed(owner, name.clone(), None,
+                                                       Some(recipient.clone())) {
+                Ok(op) => op,
+                Err(e) => {
+                    request.confirmed = false;
+                    <GuardedTransfers<T>>::insert(&name, &request);
+                    return Err(e.into());
+                },
+            };
+            Self::execute(op)?;
+
+            Self::deposit_event(RawEvent::GuardedTransferConfirmed(name, recipient));
+            Ok(())
+        }
+
+        /// Cancels a [requested transfer](Module::request_guarded_transfer).
+        /// This can be done by the owner of the name or by its guardian.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::cancel_guarded_transfer())]
+        pub fn cancel_guarded_transfer(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let data = <Names<T>>::get(&name).ok_or("name does not exist")?;
+            ensure!(data.owner == who || Self::guardian(&name) == Some(who),
+                    "only the owner or guardian can cancel");
+            ensure!(<GuardedTransfers<T>>::exists(&name), "no transfer requested");
+            <GuardedTransfers<T>>::remove(&name);
+            Self::deposit_event(RawEvent::GuardedTransferCancelled(name));
+            Ok(())
+        }
+
+        /// Marks a name as ready for sale, or clears the mark again.  While
+        /// it is set, the name's value cannot be changed, so that a buyer
+        /// knows what they get.  Transfers (and thus sales) still work,
+        /// and the mark is cleared when the name changes hands.  Only the
+        /// name's owner can do this.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_sale_ready())]
+        pub fn set_sale_ready(origin, name: T::Name, ready: bool) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner sale mark update"),
+            }
+
+            if ready {
+                <SaleReady<T>>::insert(&name, true);
+            } else {
+                <SaleReady<T>>::remove(&name);
+            }
+            Self::deposit_event(RawEvent::SaleReadySet(name, ready));
+            Ok(())
+        }
+
+        /// Sets which data of a name survives its next change of owner, e.g.
+        /// before it is sold.  Only the name's owner can do this.  The
+        /// policy itself is reset to the default for the new owner.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::set_retention_policy())]
+        pub fn set_retention_policy(origin, name: T::Name,
+                                    policy: RetentionPolicy) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner retention policy update"),
+            }
+
+            if policy == RetentionPolicy::default() {
+                <RetentionPolicies<T>>::remove(&name);
+            } else {
+                <RetentionPolicies<T>>::insert(&name, policy);
+            }
+            Self::deposit_event(RawEvent::RetentionPolicySet(name, policy));
+            Ok(())
+        }
+
+        /// Authorises a short-lived session key, which can then set the
+        /// value of the given names through
+        /// [session updates](Module::session_update) until (and including)
+        /// block `expires`.  It cannot do anything else with them.  This
+        /// lets e.g. game clients keep only a low-privilege key in memory.
+        /// Authorising the same key again replaces its permissions.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::authorize_session_key())]
+        pub fn authorize_session_key(origin, key: T::AccountId, expires: T::BlockNumber,
+                                     names: Vec<T::Name>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let h = system::Module::<T>::block_number();
+            ensure!(expires >= h, "session key is already expired");
+            ensure!(expires <= h + T::MaxSessionDuration::get(),
+                    "session key lasts too long");
+            ensure!(!names.is_empty(), "session key has no names");
+            ensure!(names.len() <= T::MaxSessionNames::get() as usize,
+                    "session key has too many names");
+            for name in &names {
+                match <Names<T>>::get(name) {
+                    None => return Err("name does not exist".into()),
+                    Some(data) => ensure!(data.owner == who, "non-owner session key"),
+                }
+            }
+
+            <SessionKeys<T>>::insert(&who, &key, SessionGrant::<T> {
+                expires: expires,
+                names: names,
+            });
+            Self::deposit_event(RawEvent::SessionKeyAuthorized(who, key, expires));
+            Ok(())
+        }
+
+        /// Revokes a session key of the sender before it expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::revoke_session_key())]
+        pub fn revoke_session_key(origin, key: T::AccountId) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<SessionKeys<T>>::exists(&who, &key), "no such session key");
+            <SessionKeys<T>>::remove(&who, &key);
+            Self::deposit_event(RawEvent::SessionKeyRevoked(who, key));
+            Ok(())
+        }
+
+        /// Sets the value of an existing name as authorised by a
+        /// [session key](Module::authorize_session_key) of its owner,
+        /// through a signature of the key on its
+        /// [message](Module::session_update_message).  The
+        /// transaction itself can be sent by anyone, and the name fee is
+        /// paid by the owner.
+        #[weight = PayloadOperationWeight::<T>::new(T::WeightInfo::session_update())]
+        pub fn session_update(origin, payload: SessionUpdate<T>,
+                              signature: T::Signature) -> DispatchResult {
+            ensure_signed(origin)?;
+            ensure!(signature.verify(&Self::session_update_message(&payload)[..],
+                                     &payload.key),
+                    "invalid session key signature");
+            ensure!(payload.nonce == Self::operation_nonce(&payload.owner),
+                    "invalid operation nonce");
+            match <SessionKeys<T>>::get(&payload.owner, &payload.key) {
+                None => return Err("no such session key".into()),
+                Some(grant) => {
+                    ensure!(system::Module::<T>::block_number() <= grant.expires,
+                            "session key is expired");
+                    ensure!(grant.names.contains(&payload.name),
+                            "session key does not cover the name");
+                },
+            }
+
+            ensure!(<Names<T>>::get(&payload.name).is_some(), "name does not exist");
+            let data = Self::check_assuming_signed(payload.owner.clone(), payload.name,
+                                                   Some(payload.value), None)?;
+            <OperationNonce<T>>::insert(&payload.owner, payload.nonce + 1);
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Links a name to the identity of its owner.  If the owner's
+        /// identity was linked to another name before, that link is
+        /// replaced.  The link is removed when the name is transferred
+        /// or expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::link_identity())]
+        pub fn link_identity(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner identity link"),
+            }
+            ensure!(T::Identity::has_identity(&who), "sender has no identity");
+
+            if let Some(old) = <NameOfIdentity<T>>::get(&who) {
+                <IdentityLinks<T>>::remove(&old);
+            }
+            <IdentityLinks<T>>::insert(&name, &who);
+            <NameOfIdentity<T>>::insert(&who, &name);
+
+            Self::deposit_event(RawEvent::IdentityLinked(name, who));
+            Ok(())
+        }
+
+        /// Removes the identity link of a name.  Only the owner can do this.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::unlink_identity())]
+        pub fn unlink_identity(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner identity link"),
+            }
+            ensure!(<IdentityLinks<T>>::get(&name).is_some(), "name is not linked");
+
+            Self::clear_identity_link(&name);
+            Ok(())
+        }
+
+        /// Transfers a name to a new owner, bypassing the ownership checks,
+        /// name policy and fees.  As with a normal transfer, the value is
+        /// kept and the expiration recomputed.  This can only be called by
+        /// the [force origin](Trait::ForceOrigin) or an
+        /// [admin of the name's namespace](Module::administers).
+        ///
+        /// Administrative calls are operational, so that they cannot
+        /// be crowded out by normal transactions in full blocks.
+        #[weight = TransferOperationWeight::<T>::operational(T::WeightInfo::force_transfer())]
+        pub fn force_transfer(origin, name: T::Name, recipient: T::AccountId) -> DispatchResult {
+            Self::ensure_name_admin(origin, &name)?;
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+
+            Self::execute(Self::unchecked_transfer(name, data, recipient))
+        }
+
+        /// Reports a name as violating the naming policy, e.g. because it
+        /// impersonates someone.  This reserves the
+        /// [report bond](Trait::ReportBond) from the sender.  After a
+        /// [challenge period](Trait::ReportChallengePeriod) in which the
+        /// owner can respond off-chain, the [report origin](Trait::ReportOrigin)
+        /// [decides](Module::decide_report) on it.  Each name can have at
+        /// most one open report.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::report_name())]
+        pub fn report_name(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner != who, "cannot report an owned name"),
+            }
+            ensure!(!<Reports<T>>::exists(&name), "name is reported already");
+
+            let bond = T::ReportBond::get();
+            T::Currency::reserve(&who, bond)?;
+            let decidable_at = system::Module::<T>::block_number()
+                + T::ReportChallengePeriod::get();
+            <Reports<T>>::insert(&name, SquattingReport::<T> {
+                reporter: who.clone(),
+                bond: bond,
+                decidable_at: decidable_at,
+            });
+
+            Self::deposit_event(RawEvent::NameReported(name, who));
+            Ok(())
+        }
+
+        /// Decides on the report of a name after its challenge period.  If
+        /// the report is upheld, the name is removed and the reporter's bond
+        /// released.  Otherwise, the bond is paid to the name's owner.
+        /// This can only be called by the [report origin](Trait::ReportOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::decide_report())]
+        pub fn decide_report(origin, name: T::Name, upheld: bool) -> DispatchResult {
+            T::ReportOrigin::ensure_origin(origin)?;
+            let report = match <Reports<T>>::get(&name) {
+                None => return Err("name is not reported".into()),
+                Some(report) => report,
+            };
+            ensure!(system::Module::<T>::block_number() >= report.decidable_at,
+                    "report is still in its challenge period");
+            let data = match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => data,
+            };
+
+            <Reports<T>>::remove(&name);
+            if upheld {
+                T::Currency::unreserve(&report.reporter, report.bond);
+                Self::remove_name(&name);
+                Self::deposit_name_event(&name, RawEvent::NameRemoved(name.clone()));
+                Self::exercise_backorders(&name);
+            } else {
+                let (imbalance, _) = T::Currency::slash_reserved(&report.reporter, report.bond);
+                T::Currency::resolve_creating(&data.owner, imbalance);
+            }
+
+            Self::deposit_event(RawEvent::ReportDecided(name, upheld));
+            Ok(())
+        }
+
+        /// Removes a name from the database, as if it had expired.  This can
+        /// only be called by the [force origin](Trait::ForceOrigin) or an
+        /// [admin of the name's namespace](Module::administers).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::force_remove())]
+        pub fn force_remove(origin, name: T::Name) -> DispatchResult {
+            Self::ensure_name_admin(origin, &name)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+
+            /* The expiration index may still contain the name, but this
+               is fine since expire_names checks the actual name data.  */
+            Self::remove_name(&name);
+            Self::deposit_name_event(&name, RawEvent::NameRemoved(name.clone()));
+            Self::exercise_backorders(&name);
+            Ok(())
+        }
+
+        /// Freezes or unfreezes a name.  While a name is frozen, its owner
+        /// cannot update or transfer it, but administrative calls still
+        /// work.  This can only be called by the [force origin](Trait::ForceOrigin)
+        /// or an [admin of the name's namespace](Module::administers).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::freeze_name())]
+        pub fn freeze_name(origin, name: T::Name, frozen: bool) -> DispatchResult {
+            Self::ensure_name_admin(origin, &name)?;
+            ensure!(<Names<T>>::get(&name).is_some(), "name does not exist");
+
+            if frozen {
+                <FrozenNames<T>>::insert(&name, true);
+            } else {
+                <FrozenNames<T>>::remove(&name);
+            }
+            Self::deposit_event(RawEvent::NameFrozen(name, frozen));
+            Ok(())
+        }
+
+        /// Registers a name for the given owner, bypassing the name policy
+        /// and fees.  This is the only way to register names inside
+        /// [reserved namespaces](Module::reserved_prefixes), and can only be
+        /// called by the [force origin](Trait::ForceOrigin) or an
+        /// [admin of the name's namespace](Module::administers).
+        #[weight = ValueOperationWeight::<T>::operational(T::WeightInfo::force_register())]
+        pub fn force_register(origin, name: T::Name, value: T::Value,
+                              owner: T::AccountId) -> DispatchResult {
+            Self::ensure_name_admin(origin, &name)?;
+            ensure!(<Names<T>>::get(&name).is_none(), "name exists already");
+            Self::execute(Self::unchecked_operation(name, value, owner))
+        }
+
+        /// Registers a batch of names, bypassing the name policy and fees,
+        /// e.g. to import an existing registry after genesis in chunks.  Each
+        /// entry has the name, value, owner and expiration height.  Expiration
+        /// heights in the past are moved to the next block.  The usual events
+        /// are emitted for each name.  None of the names may exist already,
+        /// and nothing is registered if any of them does.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin),
+        /// and registers at most [a certain number](Trait::MaxForceRegistrations)
+        /// of names per call.
+        #[weight = OperationalWeightPerItem(T::WeightInfo::force_register_batch())]
+        pub fn force_register_batch(
+            origin,
+            entries: Vec<(T::Name, T::Value, T::AccountId, Option<T::BlockNumber>)>)
+            -> DispatchResult
+        {
+            T::ForceOrigin::ensure_origin(origin)?;
+            ensure!(entries.len() <= T::MaxForceRegistrations::get() as usize,
+                    "too many names to register");
+            for (i, (name, _, _, _)) in entries.iter().enumerate() {
+                ensure!(<Names<T>>::get(name).is_none(), "name exists already");
+                ensure!(entries[..i].iter().all(|e| e.0 != *name), "duplicate name in batch");
+            }
+
+            for (name, value, owner, expiration) in entries {
+                Self::import_name(name, value, owner, expiration);
+            }
+            Ok(())
+        }
+
+        /// Queues names for the [migration](Trait::Migration).  They are
+        /// processed in the following blocks, in the order they were queued.
+        /// Since the pallet cannot enumerate its name database, the list of
+        /// names has to be assembled off-chain (e.g. from events).
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin),
+        /// and queues at most [a certain number](Trait::MaxBulkTransfer)
+        /// of names per call.
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::queue_migration())]
+        pub fn queue_migration(origin, names: Vec<T::Name>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            ensure!(names.len() <= T::MaxBulkTransfer::get() as usize,
+                    "too many names to queue");
+
+            let mut end = Self::migration_queue_end();
+            for name in names {
+                <MigrationQueue<T>>::insert(end, name);
+                end += 1;
+            }
+            <MigrationQueueEnd>::put(end);
+            Ok(())
+        }
+
+        /// Sets the [total number of names](Module::total_names), e.g. after
+        /// it has been recounted off-chain when repairing the indices.
+        /// This can only be called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::force_total_names())]
+        pub fn force_total_names(origin, total: u32) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            <TotalNames>::put(total);
+            Ok(())
+        }
+
+        /// Sets or (with `None`) clears the registrar that manages the
+        /// namespace with the given prefix.  This can only be called by the
+        /// [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(
+            T::WeightInfo::set_namespace_registrar())]
+        pub fn set_namespace_registrar(origin, prefix: Vec<u8>,
+                                       registrar: Option<T::AccountId>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            match &registrar {
+                None => <NamespaceRegistrars<T>>::remove(&prefix),
+                Some(who) => <NamespaceRegistrars<T>>::insert(&prefix, who),
+            }
+            Self::deposit_event(RawEvent::NamespaceRegistrarSet(prefix, registrar));
+            Ok(())
+        }
+
+        /// Delegates the administration of a namespace prefix to an account
+        /// (e.g. a game studio or a collective's account), or revokes it.
+        /// The admin can then [register](Module::force_register),
+        /// [transfer](Module::force_transfer), [remove](Module::force_remove)
+        /// and [freeze](Module::freeze_name) names inside the namespace,
+        /// without involving governance for each action.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::delegate_namespace())]
+        pub fn delegate_namespace(origin, prefix: Vec<u8>, admin: T::AccountId,
+                                  delegated: bool) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            let mut prefixes = Self::delegated_namespaces(&admin);
+            let pos = prefixes.iter().position(|p| *p == prefix);
+            match (delegated, pos) {
+                (true, None) => {
+                    ensure!(prefixes.len() < T::MaxDelegatedNamespaces::get() as usize,
+                            "too many delegated namespaces");
+                    prefixes.push(prefix.clone());
+                },
+                (false, Some(pos)) => {
+                    prefixes.remove(pos);
+                },
+                _ => (),
+            }
+
+            if prefixes.is_empty() {
+                <DelegatedNamespaces<T>>::remove(&admin);
+            } else {
+                <DelegatedNamespaces<T>>::insert(&admin, prefixes);
+            }
+            Self::deposit_event(RawEvent::NamespaceDelegated(prefix, admin, delegated));
+            Ok(())
+        }
+
+        /// Sets the [topic prefixes](Module::topic_prefixes), replacing the
+        /// previous list.  Events about names inside one of them are indexed
+        /// under its [topic](Module::namespace_topic), so that e.g. a game
+        /// backend can subscribe to `g/mygame` without decoding all events.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_topic_prefixes())]
+        pub fn set_topic_prefixes(origin, prefixes: Vec<Vec<u8>>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            ensure!(prefixes.len() <= T::MaxTopicPrefixes::get() as usize,
+                    "too many topic prefixes");
+            <TopicPrefixes>::put(&prefixes);
+            Self::deposit_event(RawEvent::TopicPrefixesSet(prefixes));
+            Ok(())
+        }
+
+        /// Sets or (with `None`) clears the oracle that attests registrations
+        /// inside the namespace with the given prefix.  This can only be
+        /// called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(
+            T::WeightInfo::set_attestation_oracle())]
+        pub fn set_attestation_oracle(origin, prefix: Vec<u8>,
+                                      oracle: Option<T::AccountId>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            <AttestedNamespaces<T>>::mutate(|namespaces| {
+                namespaces.retain(|(p, _)| *p != prefix);
+                if let Some(who) = &oracle {
+                    namespaces.push((prefix.clone(), who.clone()));
+                }
+            });
+            Self::deposit_event(RawEvent::AttestationOracleSet(prefix, oracle));
+            Ok(())
+        }
+
+        /// Adds an account to the fee exemption list, or removes it.
+        /// Operations paid by an exempt account have no name fee, but
+        /// still need to be valid according to the name policy.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(
+            T::WeightInfo::set_account_fee_exemption())]
+        pub fn set_account_fee_exemption(origin, account: T::AccountId,
+                                         exempt: bool) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            if exempt {
+                <FeeExemptAccounts<T>>::insert(&account, true);
+            } else {
+                <FeeExemptAccounts<T>>::remove(&account);
+            }
+            Self::deposit_event(RawEvent::AccountFeeExemptionSet(account, exempt));
+            Ok(())
+        }
+
+        /// Adds a name to the fee exemption list, or removes it.  Operations
+        /// on an exempt name have no name fee, whoever pays for them.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(
+            T::WeightInfo::set_name_fee_exemption())]
+        pub fn set_name_fee_exemption(origin, name: T::Name, exempt: bool) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            if exempt {
+                <FeeExemptNames<T>>::insert(&name, true);
+            } else {
+                <FeeExemptNames<T>>::remove(&name);
+            }
+            Self::deposit_event(RawEvent::NameFeeExemptionSet(name, exempt));
+            Ok(())
+        }
+
+        /// Creates or updates a *system name*.  System names are owned by the
+        /// pallet's [own account](Module::account_id), for which nobody has
+        /// a key, and never expire.  Thus no user can ever control them, and
+        /// chains can use them for canonical entries like "treasury".
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin),
+        /// which is also the only way to change the value afterwards.  Names
+        /// that exist already can only be updated if they are system names.
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_system_name())]
+        pub fn set_system_name(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            if <Names<T>>::get(&name).is_some() {
+                ensure!(Self::is_system_name(&name), "name is not a system name");
+            }
+
+            Self::import_name(name.clone(), value, Self::account_id(), None);
+            <SystemNames<T>>::insert(&name, true);
+            Ok(())
+        }
+
+        /// Imports names (e.g. [exported](Module::export_names) from another
+        /// chain) for the given owner, bypassing the name policy and fees.
+        /// `data` is a SCALE-encoded [portfolio](Portfolio).  Existing names
+        /// are overwritten.  Expiration heights are kept, except that they
+        /// are moved to the next block if they are in the past already.
+        ///
+        /// This can only be called by the [force origin](Trait::ForceOrigin),
+        /// and imports at most [a certain number](Trait::MaxBulkTransfer)
+        /// of names per call.
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::import_names())]
+        pub fn import_names(origin, owner: T::AccountId, data: Vec<u8>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            let portfolio = Portfolio::<T>::decode(&mut &data[..])
+                .map_err(|_| "invalid portfolio data")?;
+            ensure!(portfolio.len() <= T::MaxBulkTransfer::get() as usize,
+                    "too many names to import");
+
+            for (name, value, expiration) in portfolio {
+                Self::import_name(name, value, owner.clone(), expiration);
+            }
+            Ok(())
+        }
+
+        /// Submits an intent to register a name with the given value.  All
+        /// intents for the same name within one block are settled at the
+        /// end of the block, where the winner is chosen deterministically
+        /// based on a hash of the name, sender and on-chain randomness.
+        /// Thus the order of transactions within the block does not matter.
+        /// Only the winner pays the registration fee.
+        ///
+        /// Each intent holds a [bond](Trait::IntentBond) until settlement,
+        /// and at most [a certain number](Trait::MaxIntentsPerName) of
+        /// intents can be submitted per name and block.  Since `on_finalize`
+        /// cannot account for weight in this Substrate version, the weight
+        /// of this call includes its share of the settlement (checking and
+        /// possibly executing the registration).
+        #[weight = ValueOperationWeight::<T>::new(T::WeightInfo::register_intent())]
+        pub fn register_intent(origin, name: T::Name, value: T::Value) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Names<T>>::get(&name).is_none(), "name exists already");
+
+            let mut intents = <RegistrationIntents<T>>::get(&name);
+            ensure!(intents.iter().all(|(sender, _)| *sender != who),
+                    "intent submitted already");
+            ensure!(intents.len() < T::MaxIntentsPerName::get() as usize,
+                    "too many intents for the name");
+            /* Make sure the registration would be valid right now, and
+               that the sender can pay the fee on top of the bond.  It is
+               checked again when settling.  */
+            let op = Self::check_assuming_signed(who.clone(), name.clone(),
+                                                 Some(value.clone()), None)?;
+            let bond = T::IntentBond::get();
+            T::Payment::check(&who, op.fee.saturating_add(bond)).map_err(|e| e.message())?;
+            T::Payment::hold(&who, bond)?;
+
+            if intents.is_empty() {
+                <IntentNames<T>>::mutate(|names| names.push(name.clone()));
+            }
+            intents.push((who.clone(), value));
+            <RegistrationIntents<T>>::insert(&name, intents);
+
+            Self::deposit_event(RawEvent::RegistrationIntent(name, who));
+            Ok(())
+        }
+
+        /// Preorders a name by its hash, reserving the given deposit.  After
+        /// a [maturation period](Trait::PreorderMaturity), the name is then
+        /// reserved exclusively for the sender for
+        /// [some blocks](Trait::PreorderWindow), in which they can register
+        /// it normally.  Doing so releases the deposit.  If the name is not
+        /// registered in time, [part](Trait::PreorderForfeit) of the deposit
+        /// is forfeited.
+        ///
+        /// This is a lighter alternative to
+        /// [hidden registrations](Module::register_hidden), as the name
+        /// itself need not be revealed separately.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::preorder())]
+        pub fn preorder(origin, hash: T::Hash, deposit: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(<Preorders<T>>::get(&hash).is_none(), "name hash is preordered already");
+            ensure!(deposit >= T::MinPreorderDeposit::get(), "preorder deposit is too low");
+            T::Payment::hold(&who, deposit)?;
+
+            let matures = system::Module::<T>::block_number() + T::PreorderMaturity::get();
+            let expiration = matures + max(T::PreorderWindow::get(), T::BlockNumber::from(1));
+            <Preorders<T>>::insert(&hash, Preorder::<T> {
+                owner: who,
+                deposit: deposit,
+                matures: matures,
+                expiration: expiration,
+            });
+            <PreorderExpirations<T>>::insert(expiration, &hash, &hash);
+
+            Self::deposit_event(RawEvent::NamePreordered(hash));
+            Ok(())
+        }
+
+        /// Places a backorder on a name that is currently owned by someone
+        /// else, [holding](payment::NamePayment::hold) the given bond.  When
+        /// the name expires and is removed, the backorders are processed in
+        /// priority order (larger bonds first), and the first backorderer for
+        /// which the registration is valid gets the name.  Their bond is paid
+        /// as additional fee, while all other bonds are released.
+        ///
+        /// This lets users queue up for a name without running bots that
+        /// try to snipe it in the block where it expires.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::place_backorder())]
+        pub fn place_backorder(origin, name: T::Name, bond: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner != who, "cannot backorder an owned name"),
+            }
+            ensure!(bond >= T::MinBackorderBond::get(), "backorder bond is too low");
+
+            let mut backorders = <Backorders<T>>::get(&name);
+            ensure!(!backorders.iter().any(|(w, _)| *w == who), "name is backordered already");
+            ensure!(backorders.len() < T::MaxBackorders::get() as usize, "too many backorders");
+
+            T::Payment::hold(&who, bond)?;
+            let pos = backorders.iter().position(|(_, b)| *b < bond)
+                .unwrap_or(backorders.len());
+            backorders.insert(pos, (who.clone(), bond));
+            <Backorders<T>>::insert(&name, backorders);
+
+            Self::deposit_event(RawEvent::BackorderPlaced(name, who, bond));
+            Ok(())
+        }
+
+        /// Cancels a backorder of the sender and releases its bond.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::cancel_backorder())]
+        pub fn cancel_backorder(origin, name: T::Name) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let mut backorders = <Backorders<T>>::get(&name);
+            let pos = match backorders.iter().position(|(w, _)| *w == who) {
+                None => return Err("no backorder for the name".into()),
+                Some(pos) => pos,
+            };
+
+            let (_, bond) = backorders.remove(pos);
+            T::Payment::refund(&who, bond);
+            if backorders.is_empty() {
+                <Backorders<T>>::remove(&name);
+            } else {
+                <Backorders<T>>::insert(&name, backorders);
+            }
+
+            Self::deposit_event(RawEvent::BackorderRefunded(name, who, bond));
+            Ok(())
+        }
+
+        /// Insures a name against expiration for the given number of
+        /// renewals.  The [premium](Module::insurance_premium) for each of
+        /// them is paid into the [insurance pool](Module::insurance_pool),
+        /// which then pays the renewal if the name expires.  Premiums are
+        /// not refunded, also not if the name changes owner.
+        #[weight = SimpleDispatchInfo::FixedNormal(T::WeightInfo::insure_name())]
+        pub fn insure_name(origin, name: T::Name, renewals: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            match <Names<T>>::get(&name) {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == who, "non-owner name insurance"),
+            }
+            ensure!(renewals > 0, "no renewals to insure");
+            let total = Self::insured_renewals(&name).saturating_add(renewals);
+            ensure!(total <= T::MaxInsuredRenewals::get(), "too many insured renewals");
+
+            let premium = Self::insurance_premium()
+                .saturating_mul(BalanceOf::<T>::from(renewals));
+            if !premium.is_zero() {
+                T::Currency::transfer(&who, &Self::insurance_pool(), premium,
+                                      ExistenceRequirement::KeepAlive)?;
+            }
+            <InsuredRenewals<T>>::insert(&name, total);
+
+            Self::deposit_event(RawEvent::NameInsured(name, total, premium));
+            Ok(())
+        }
+
+        /// Sets the [premium](Module::insurance_premium) for each insured
+        /// renewal.  This can only be called by the
+        /// [force origin](Trait::ForceOrigin).
+        #[weight = SimpleDispatchInfo::FixedOperational(T::WeightInfo::set_insurance_premium())]
+        pub fn set_insurance_premium(origin, premium: BalanceOf<T>) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+            <InsurancePremium<T>>::put(premium);
+            Ok(())
+        }
+
+        /// Renews names inside a namespace on behalf of their owners, with
+        /// the name fees paid by the sender.  The sender must be the
+        /// [registrar](Module::namespace_registrar) of the namespace, e.g.
+        /// a custodial registrar that manages the names of its clients.
+        ///
+        /// Each renewal is an update that keeps the current value.  At most
+        /// [`MaxBulkRenewals`](Trait::MaxBulkRenewals) names can be renewed
+        /// at once, and either all of them are renewed or none.  The names
+        /// are given explicitly, since the name database cannot be iterated
+        /// by namespace.
+        #[weight = WeightPerItem(T::WeightInfo::renew_namespace())]
+        pub fn renew_namespace(origin, prefix: Vec<u8>, names: Vec<T::Name>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::namespace_registrar(&prefix) == Some(who.clone()),
+                    "sender is not the registrar of the namespace");
+            ensure!(names.len() <= T::MaxBulkRenewals::get() as usize,
+                    "too many names to renew");
+
+            let mut ops: Vec<Operation<T>> = Vec::with_capacity(names.len());
+            for name in names {
+                ensure!(T::Namespaces::has_prefix(&name, &prefix), "name is outside the namespace");
+                ensure!(ops.iter().all(|op| op.name != name), "duplicate name in batch");
+                let owner = match <Names<T>>::get(&name) {
+                    None => return Err("name does not exist".into()),
+                    Some(data) => data.owner,
+                };
+                ops.push(Self::check_sponsored(owner, who.clone(), name, None, None)?);
+            }
+            Self::ensure_affordable(&ops).map_err(|e| e.message())?;
+
+            let count = ops.len() as u32;
+            Self::execute_many(ops)?;
+            Self::deposit_event(RawEvent::NamespaceRenewed(prefix, count));
+            Ok(())
+        }
+
+        /// Performs an update (or registration) of a name on behalf of its
+        /// owner, with the name fee paid by the sender instead.  The owner
+        /// authorises the update by signing its
+        /// [message](Module::sponsored_update_message).  This allows e.g.
+        /// onboarding users that have no balance yet.
+        #[weight = PayloadOperationWeight::<T>::new(T::WeightInfo::sponsored_update())]
+        pub fn sponsored_update(origin, payload: SponsoredUpdate<T>,
+                                signature: T::Signature) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::check_owner_authorisation(&payload.owner, payload.nonce,
+                                            &Self::sponsored_update_message(&payload),
+                                            &signature)?;
+
+            Self::ensure_direct_registration(&payload.name)?;
+            let data = Self::check_sponsored(payload.owner.clone(), who, payload.name,
+                                             Some(payload.value), None)?;
+            <OperationNonce<T>>::insert(&payload.owner, payload.nonce + 1);
+            Self::execute(data)?;
+            Ok(())
+        }
+
+        /// Executes a name operation that has been authorised by the owner
+        /// through a signature on its
+        /// [message](Module::signed_operation_message).  The transaction
+        /// itself can be sent by anyone, e.g. a relayer, so that the owning
+        /// key need not be used to sign transactions directly.  The name
+        /// fee is paid by the owner.
+        #[weight = PayloadOperationWeight::<T>::new(T::WeightInfo::execute_signed_operation())]
+        pub fn execute_signed_operation(origin, payload: SignedOperation<T>,
+                                        signature: T::Signature) -> DispatchResult {
+            ensure_signed(origin)?;
+            Self::do_signed_operation(payload, signature)
+        }
+
+        /// Executes a batch of [signed operations](Module::execute_signed_operation),
+        /// e.g. to let a relayer submit many of them at once.  Items that
+        /// fail are skipped (with an event reporting their index), and do not
+        /// affect the other items.  The weight is proportional to the
+        /// number of items.
+        #[weight = WeightPerItem(T::WeightInfo::execute_signed_batch())]
+        pub fn execute_signed_batch(origin,
+                                    items: Vec<(SignedOperation<T>, T::Signature)>) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let mut executed = 0u32;
+            for (index, (payload, signature)) in items.into_iter().enumerate() {
+                match Self::do_signed_operation(payload, signature) {
+                    Ok(_) => executed += 1,
+                    Err(_) => Self::deposit_event(RawEvent::SignedOperationFailed(index as u32)),
+                }
+            }
+
+            Self::deposit_event(RawEvent::SignedBatchExecuted(executed));
+            Ok(())
+        }
+
+        /// Processes up to `max` entries of the expiration index for a past
+        /// block, which were not processed automatically because of the
+        /// [limit](Trait::MaxAutoExpirations) in `on_initialize`.  Anyone can
+        /// call this, and receives a [bounty](Trait::SweepBounty) for each
+        /// processed entry from the [sweep pot](Module::sweep_pot), as far
+        /// as its funds allow.
+        #[weight = WeightPerItem(T::WeightInfo::sweep_expirations())]
+        pub fn sweep_expirations(origin, block: T::BlockNumber, max: u32) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(block < system::Module::<T>::block_number(), "can only sweep past blocks");
+            ensure!(max > 0, "nothing to sweep");
+            ensure!(max <= T::MaxExpirationsPerBlock::get(), "too many expirations to sweep");
+            ensure!(<ExpirationQueue<T>>::exists(block), "no expirations to sweep");
+
+            let (expired, stale, left) = Self::process_expirations(block, Some(max));
+            let processed = expired + stale;
+            let pot = Self::sweep_pot();
+            let bounty = T::SweepBounty::get().saturating_mul(BalanceOf::<T>::from(processed));
+            let mut paid = min(bounty, T::Currency::free_balance(&pot));
+            if !paid.is_zero() && T::Currency::transfer(&pot, &who, paid,
+                                                        ExistenceRequirement::AllowDeath).is_err() {
+                paid = Zero::zero();
+            }
+
+            Self::deposit_event(RawEvent::ExpirationsSwept(who, block, processed, left, paid));
+            Ok(())
+        }
+
+        /// Settles all registration intents of the block.
+        fn on_finalize(_h: T::BlockNumber) {
+            Self::settle_intents();
+        }
+
+        /// Processes all names logic required before executing extrinsics
+        /// of a given block.  In concrete terms, this function makes sure that
+        /// names expired in the current block will be removed from the
+        /// database.
+        fn on_initialize(h: T::BlockNumber) {
+            <RegistrationsThisBlock>::kill();
+            Self::expire_names(h);
+            Self::release_unused_names(h);
+            Self::check_compliance(h);
+            Self::end_quarantines(h);
+            Self::prune_archive(h);
+            Self::expire_hidden(h);
+            Self::expire_preorders(h);
+            Self::step_migration();
+            Self::process_deferred();
+            Self::prune_receipts(h);
+            Self::cancel_overdue_rents(h);
+            Self::activate_scheduled_values(h);
+            <RegistrationFeeMultiplier>::put(Self::congestion_multiplier());
+        }
+
+    }
+}
+
+impl<T: Trait> Module<T> {
+
+    /// Returns the pallet's own account, which owns all
+    /// [system names](Module::set_system_name).
+    pub fn account_id() -> T::AccountId {
+        T::ModuleId::get().into_account()
+    }
+
+    /// Returns the account of a subsystem of the pallet that holds funds,
+    /// e.g. a pool.  It is derived from the [module ID](Trait::ModuleId),
+    /// so that all funds held by the pallet can be attributed to it.
+    pub fn sub_account_id(subsystem: &[u8]) -> T::AccountId {
+        T::ModuleId::get().into_sub_account(subsystem)
+    }
+
+    /// Returns the account of the pot from which the bounties for
+    /// [sweeping expirations](Module::sweep_expirations) are paid.  Anyone
+    /// can fund it by transferring to it.
+    pub fn sweep_pot() -> T::AccountId {
+        Self::sub_account_id(b"sweep")
+    }
+
+    /// Returns the account of the insurance pool, which holds the
+    /// premiums paid for [insured names](Module::insure_name) and pays
+    /// their renewals.
+    pub fn insurance_pool() -> T::AccountId {
+        Self::sub_account_id(b"insurance")
+    }
+
+    /// Checks if a name operation is valid, assuming that we already know
+    /// it was signed by the given account.
+    ///
+    /// Value and recipient are optional.  If the value is missing, we use the
+    /// existing value or the [default value](Trait::DefaultValue) for the
+    /// recipient if the name does not exist yet.  If the recipient is missing, we set it to
+    /// the `sender` account.
+    ///
+    /// This function returns either an error if the operation is not valid,
+    /// or the [data](Operation) that should be passed to
+    /// [`execute`](Module::execute) later on if the transaction is valid.
+    pub fn check_assuming_signed(sender: T::AccountId, name: T::Name,
+                                 value: Option<T::Value>,
+                                 recipient: Option<T::AccountId>) -> Result<Operation<T>, &'static str> {
+        Self::check_sponsored(sender.clone(), sender, name, value, recipient)
+    }
+
+    /// Checks a batch of name operations that are meant to be
+    /// [executed together](Module::execute_many), e.g. for a game result
+    /// that touches several names.  Each item consists of the sender, name
+    /// and optional value and recipient like for
+    /// [`check_assuming_signed`](Module::check_assuming_signed).
+    ///
+    /// All operations are validated against the current state, so each name
+    /// can appear only once in a batch.  In addition, each account must be
+    /// able to afford everything it is charged in the batch at once:  the
+    /// total fee of each payer (including any
+    /// [first-update](Trait::FirstUpdatePeriod) hold, which is part of the
+    /// fee) and the total increase of the
+    /// [value deposits](Trait::ValueDepositPerByte) of each sender.  If this
+    /// returns the operations, they can all be executed.
+    pub fn check_many_assuming_signed(
+        items: Vec<(T::AccountId, T::Name, Option<T::Value>, Option<T::AccountId>)>)
+        -> Result<Vec<Operation<T>>, &'static str>
+    {
+        let mut ops: Vec<Operation<T>> = Vec::with_capacity(items.len());
+        for (sender, name, value, recipient) in items {
+            ensure!(ops.iter().all(|op| op.name != name), "duplicate name in batch");
+            ops.push(Self::check_assuming_signed(sender, name, value, recipient)?);
+        }
+        Self::ensure_affordable(&ops).map_err(|e| e.message())?;
+
+        Ok(ops)
+    }
+
+    /// Checks that the accounts involved in the given operations can afford
+    /// all of them together:  Each sender has to reserve the increase of
+    /// its [value deposits](Trait::ValueDepositPerByte), and each payer has
+    /// to pay its name fees.  An account that does both must be able to
+    /// reserve its deposits on top of its fees.
+    fn ensure_affordable<'a, I>(ops: I) -> Result<(), OperationError<T>>
+        where I: IntoIterator<Item = &'a Operation<T>>, T: 'a
+    {
+        let mut fees: Vec<(T::AccountId, BalanceOf<T>)> = Vec::new();
+        let mut deposits: Vec<(T::AccountId, BalanceOf<T>)> = Vec::new();
+        for op in ops {
+            Self::add_to_total(&mut fees, &op.payer, op.fee).ok_or("name fee overflow")?;
+            let deposit = Self::deposit_increase(op);
+            if !deposit.is_zero() {
+                Self::add_to_total(&mut deposits, &op.sender, deposit)
+                    .ok_or("value deposit overflow")?;
+            }
+        }
+
+        for (who, deposit) in &deposits {
+            let fee = fees.iter()
+                .find(|(payer, _)| payer == who)
+                .map_or(Zero::zero(), |(_, fee)| *fee);
+            let needed = deposit.saturating_add(fee);
+            ensure!(T::Currency::can_reserve(who, needed),
+                    OperationError::RequiredDeposit(needed));
+        }
+        for (payer, fee) in fees {
+            T::Payment::check(&payer, fee)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds an amount to the total of an account in the given list.
+    /// Returns `None` if the total overflows.
+    fn add_to_total(totals: &mut Vec<(T::AccountId, BalanceOf<T>)>, who: &T::AccountId,
+                    amount: BalanceOf<T>) -> Option<()> {
+        match totals.iter_mut().find(|(account, _)| account == who) {
+            Some((_, total)) => *total = total.checked_add(&amount)?,
+            None => totals.push((who.clone(), amount)),
+        }
+        Some(())
+    }
+
+    /// Executes a batch of operations returned by
+    /// [`check_many_assuming_signed`](Module::check_many_assuming_signed).
+    ///
+    /// Storage transactions are not available in this Substrate version, so
+    /// operations executed before a failing one are *not* rolled back.  The
+    /// all-or-nothing semantics hold only because the batch has been checked
+    /// as a whole before (including all fees, holds and deposits per
+    /// account), and only if nothing else changed in between.
+    pub fn execute_many(ops: Vec<Operation<T>>) -> DispatchResult {
+        for op in ops {
+            Self::execute(op)?;
+        }
+        Ok(())
+    }
+
+    /// Checks if a name operation is valid, assuming that it was authorised
+    /// by `sender` and that the name fee is paid by `payer`.  Apart from
+    /// the fee payment, this is the same as
+    /// [`check_assuming_signed`](Module::check_assuming_signed).
+    pub fn check_sponsored(sender: T::AccountId, payer: T::AccountId, name: T::Name,
+                           value: Option<T::Value>,
+                           recipient: Option<T::AccountId>) -> Result<Operation<T>, &'static str> {
+        Self::check_detailed(sender, payer, name, value, recipient).map_err(|e| e.message())
+    }
+
+    /// Checks an operation like [`check_sponsored`](Module::check_sponsored),
+    /// but returns a [structured error](OperationError) with context (e.g.
+    /// the required fee) if it is invalid.  This is what the `check_operation`
+    /// runtime API reports to clients.
+    pub fn check_detailed(sender: T::AccountId, payer: T::AccountId, name: T::Name,
+                          value: Option<T::Value>,
+                          recipient: Option<T::AccountId>) -> Result<Operation<T>, OperationError<T>> {
+        Self::check_operation(sender, payer, name, value, recipient, None)
+    }
+
+    /// Checks the registration of a name inside an
+    /// [attested namespace](Module::attested_namespaces) by the given sender.
+    /// This is like [`check_assuming_signed`](Module::check_assuming_signed),
+    /// but also verifies the oracle's attestation for the name.
+    pub fn check_attested(sender: T::AccountId, name: T::Name, value: T::Value,
+                          attestation: &IdentityAttestation<T>) -> Result<Operation<T>, &'static str> {
+        Self::check_operation(sender.clone(), sender, name, Some(value), None, Some(attestation))
+            .map_err(|e| e.message())
+    }
+
+    /// Returns the message that the oracle signs to attest that the given
+    /// account may register a name for the given external identity.
+    pub fn attestation_message(name: &T::Name, identity: &[u8], registrant: &T::AccountId) -> Vec<u8> {
+        (b"names/attestation", name, identity, registrant).encode()
+    }
+
+    /// Returns the message that the owner of a name signs to authorise a
+    /// [signed operation](Module::execute_signed_operation).  It is bound
+    /// to this chain by its genesis hash, so that signatures cannot be
+    /// replayed on other chains that share accounts.
+    pub fn signed_operation_message(payload: &SignedOperation<T>) -> Vec<u8> {
+        (b"names/signed-op", Self::genesis_hash(), payload).encode()
+    }
+
+    /// Returns the message that the owner of a name signs to authorise a
+    /// [sponsored update](Module::sponsored_update), bound to this chain
+    /// like [signed operations](Module::signed_operation_message).
+    pub fn sponsored_update_message(payload: &SponsoredUpdate<T>) -> Vec<u8> {
+        (b"names/sponsored", Self::genesis_hash(), payload).encode()
+    }
+
+    /// Returns the message that a session key signs to authorise a
+    /// [session update](Module::session_update), bound to this chain
+    /// like [signed operations](Module::signed_operation_message).
+    pub fn session_update_message(payload: &SessionUpdate<T>) -> Vec<u8> {
+        (b"names/session", Self::genesis_hash(), payload).encode()
+    }
+
+    /// Returns the hash of the genesis block, which binds signed messages
+    /// to this chain.
+    fn genesis_hash() -> T::Hash {
+        system::Module::<T>::block_hash(T::BlockNumber::zero())
+    }
+
+    /// Returns the oracle that attests registrations of the given name, if
+    /// it is inside an [attested namespace](Module::attested_namespaces).
+    pub fn attestation_oracle(name: &T::Name) -> Option<T::AccountId> {
+        Self::attested_namespaces().into_iter()
+            .find(|(prefix, _)| T::Namespaces::has_prefix(name, prefix))
+            .map(|(_, oracle)| oracle)
+    }
+
+    /// Checks an operation as [`check_detailed`](Module::check_detailed)
+    /// does, with an optional attestation for registrations.
+    fn check_operation(sender: T::AccountId, payer: T::AccountId, name: T::Name,
+                       value: Option<T::Value>, recipient: Option<T::AccountId>,
+                       attestation: Option<&IdentityAttestation<T>>) -> Result<Operation<T>, OperationError<T>> {
+        let state = Self::state_of(&name);
+        let old_data = state.data().cloned();
+        let (typ, old_value) = match &old_data {
+            None => {
+                /* Names reserved by a hidden registration can only be
+                   registered by the owner of that registration.  */
+                let hash = T::Hashing::hash_of(&name);
+                if let Some(hidden) = <HiddenNames<T>>::get(&hash) {
+                    ensure!(sender == hidden.owner,
+                            "name is reserved by a hidden registration");
+                }
+                if let Some(preorder) = <Preorders<T>>::get(&hash) {
+                    let h = system::Module::<T>::block_number();
+                    ensure!(sender == preorder.owner || h < preorder.matures,
+                            "name is reserved by a preorder");
+                }
+                ensure!(!Self::is_reserved_name(&name), "name is in a reserved namespace");
+                ensure!(Self::quarantined_until(&name).is_none(),
+                        "name is quarantined after expiration");
+                if let Some(max) = T::MaxTotalNames::get() {
+                    ensure!(Self::total_names() < max, "maximum number of names reached");
+                }
+                ensure!(Self::can_register_in_block(), OperationError::RetryNextBlock);
+                if let Some(oracle) = Self::attestation_oracle(&name) {
+                    let attestation = attestation.ok_or("name requires an oracle attestation")?;
+                    let msg = Self::attestation_message(&name, &attestation.identity, &sender);
+                    ensure!(attestation.signature.verify(&msg[..], &oracle),
+                            "invalid oracle attestation");
+                }
+                (OperationType::Registration, None)
+            },
+            Some(data) => {
+                ensure!(sender == data.owner || Self::controller(&name) == Some(sender.clone()),
+                        OperationError::OwnedBy(data.owner.clone()));
+                ensure!(!state.is_frozen(), "name is frozen");
+                (OperationType::Update, Some(data.value.clone()))
+            },
+        };
+
+        /* The controller of a name can update it, but the name stays with
+           its owner.  For everyone else, the sender is the owner.  */
+        let owner = match &old_data {
+            Some(data) => data.owner.clone(),
+            None => sender.clone(),
+        };
+        let recipient = match recipient {
+            None => owner.clone(),
+            Some(new_recipient) => new_recipient,
+        };
+        if sender != owner {
+            ensure!(recipient == owner, "controller cannot transfer the name");
+        }
+        let value = match (value, old_value) {
+            (Some(new_value), _) => new_value,
+            (None, Some(old_value)) => old_value,
+            (None, None) => T::DefaultValue::default_value_for(&name, &recipient),
+        };
+        if let Some(data) = &old_data {
+            ensure!(data.value == value || !Self::is_sale_ready(&name),
+                    "name value is locked for sale");
+        }
+        if let (Some(_), Some(deadline)) = (&old_data, Self::compliance_deadline(&name)) {
+            ensure!(system::Module::<T>::block_number() < deadline
+                        || T::ValueCompliance::is_compliant(&name, &value),
+                    "value does not satisfy the content rules");
+        }
+        if recipient != owner {
+            ensure!(!<Collateral<T>>::exists(&name), "name is locked as collateral");
+            ensure!(!<SwapLocks<T>>::exists(&name), "name is offered in a swap");
+            ensure!(!<RentAgreements<T>>::exists(&name),
+                    "name is under a rent-to-own agreement");
+            ensure!(!<ExternalLocks<T>>::exists(&name), "name is locked by another pallet");
+            if <Guardians<T>>::exists(&name) {
+                let confirmed = Self::guarded_transfer(&name)
+                    .map_or(false, |t| t.confirmed && t.recipient == recipient);
+                ensure!(confirmed, "transfer needs confirmation by the guardian");
+            }
+            ensure!(!Self::blocks_all_transfers(&recipient)
+                        && !Self::is_sender_blocked(&recipient, &sender),
+                    "recipient does not accept transfers from the sender");
+        }
+        let typ = match &old_data {
+            Some(data) if recipient != owner && data.value == value => OperationType::Transfer,
+            _ => typ,
+        };
+
+        let mut op = Operation::<T> {
+            operation: typ,
+            name: name,
+            value: value,
+            old_data: old_data,
+            sender: sender,
+            payer: payer,
+            recipient: recipient,
+            fee: <T::Currency as Currency<T::AccountId>>::Balance::default(),
+            deposit: Zero::zero(),
+            free_update: false,
+            registrar: None,
+            renewal: false,
+            never_expires: false,
+        };
+        let exempt = Self::is_fee_exempt_account(&op.payer) || Self::is_fee_exempt_name(&op.name);
+        op.fee = match T::Policy::get_name_fee(&op) {
+            None => {
+                debug::debug!(target: LOG_TARGET, "policy rejected {:?} of name {:?}",
+                              op.operation, T::Hashing::hash_of(&op.name));
+                return Err("operation violates name policy".into());
+            },
+            Some(_) if exempt => Zero::zero(),
+            Some(f) => f,
+        };
+        if !op.fee.is_zero() {
+            op.fee = Self::convert_fee(op.fee)?;
+        }
+        if let Some(blocks) = T::Policy::get_expiration(&op) {
+            let min = T::MinExpirationBlocks::get();
+            ensure!(blocks >= min, OperationError::ExpirationTooShort { blocks, min });
+        }
+        if op.operation == OperationType::Registration {
+            let multiplier = Self::registration_fee_multiplier();
+            op.fee = op.fee.saturating_mul(BalanceOf::<T>::from(multiplier))
+                / BalanceOf::<T>::from(100u32);
+        }
+        if op.operation == OperationType::Update && !op.fee.is_zero()
+                && Self::free_updates_left(&op.name) > 0 {
+            op.fee = Zero::zero();
+            op.free_update = true;
+        }
+
+        /* Determine the value deposit and make sure that the sender can
+           reserve whatever is not yet held by them, and the payer can pay
+           the name fee on top.  */
+        let size = BalanceOf::<T>::from(op.value.encoded_size() as u32);
+        op.deposit = T::ValueDepositPerByte::get().saturating_mul(size);
+        Self::ensure_affordable(core::iter::once(&op))?;
+
+        debug::debug!(target: LOG_TARGET, "checked {:?} of name {:?} with fee {:?}",
+                      op.operation, T::Hashing::hash_of(&op.name), op.fee);
+        Ok(op)
+    }
+
+    /// Verifies and executes a single
+    /// [signed operation](Module::execute_signed_operation).  All checks are
+    /// done before any state is changed.
+    fn do_signed_operation(payload: SignedOperation<T>, signature: T::Signature) -> DispatchResult {
+        Self::check_owner_authorisation(&payload.owner, payload.nonce,
+                                        &Self::signed_operation_message(&payload), &signature)?;
+
+        Self::ensure_direct_registration(&payload.name)?;
+        if let Some(expected) = payload.name_nonce {
+            Self::ensure_name_nonce(&payload.name, expected)?;
+        }
+        let data = Self::check_assuming_signed(payload.owner.clone(), payload.name,
+                                               payload.value, payload.recipient)?;
+        <OperationNonce<T>>::insert(&payload.owner, payload.nonce + 1);
+        Self::execute(data)
+    }
+
+    /// Checks that the current block is not after `valid_until` (if given).
+    fn ensure_valid_until(valid_until: Option<T::BlockNumber>) -> DispatchResult {
+        if let Some(h) = valid_until {
+            ensure!(system::Module::<T>::block_number() <= h,
+                    "transaction is past its validity window");
+        }
+        Ok(())
+    }
+
+    /// Checks that a name exists and has the given [nonce](NameData::nonce).
+    fn ensure_name_nonce(name: &T::Name, expected: u64) -> DispatchResult {
+        match <Names<T>>::get(name) {
+            None => Err("name does not exist".into()),
+            Some(data) => {
+                ensure!(data.nonce == expected, "name nonce mismatch");
+                Ok(())
+            },
+        }
+    }
+
+    /// Verifies that `signature` is a valid signature of `owner` on the
+    /// encoded payload of an owner-authorised operation, and that the
+    /// payload uses the owner's current
+    /// [operation nonce](Module::operation_nonce).
+    fn check_owner_authorisation(owner: &T::AccountId, nonce: u64, payload: &[u8],
+                                 signature: &T::Signature) -> Result<(), &'static str> {
+        ensure!(signature.verify(payload, owner), "invalid owner signature");
+        ensure!(nonce == Self::operation_nonce(owner), "invalid operation nonce");
+        Ok(())
+    }
+
+    /// Constructs an operation that sets a name to the given value and
+    /// owner, without any validation and without a name fee.
+    ///
+    /// This is meant for other pallets that manage names on behalf of some
+    /// external system (e.g. mirroring names from another blockchain), and
+    /// that need to bypass the name policy and ownership checks.  The result
+    /// should be passed to [`execute`](Module::execute).
+    pub fn unchecked_operation(name: T::Name, value: T::Value,
+                               owner: T::AccountId) -> Operation<T> {
+        let old_data = <Names<T>>::get(&name);
+        let typ = match old_data {
+            None => OperationType::Registration,
+            Some(_) => OperationType::Update,
+        };
+
+        Operation::<T> {
+            operation: typ,
+            name: name,
+            value: value,
+            old_data: old_data,
+            sender: owner.clone(),
+            payer: owner.clone(),
+            recipient: owner,
+            fee: <T::Currency as Currency<T::AccountId>>::Balance::default(),
+            deposit: Zero::zero(),
+            free_update: false,
+            registrar: None,
+            renewal: false,
+            never_expires: false,
+        }
+    }
+
+    /// Constructs a transfer of an existing name (with the given current
+    /// data) to `recipient` that can be passed to [`execute`](Module::execute)
+    /// without any checks.  The name's value is kept, and there is no fee.
+    ///
+    /// The transfer is made on behalf of the current owner, so it becomes
+    /// [pending](Module::pending_transfer) if the recipient requires
+    /// claiming.  Callers where the recipient takes the name itself set
+    /// the operation's `sender` to it.
+    pub fn unchecked_transfer(name: T::Name, data: NameData<T>,
+                              recipient: T::AccountId) -> Operation<T> {
+        Operation::<T> {
+            operation: OperationType::Transfer,
+            name: name,
+            value: data.value.clone(),
+            sender: data.owner.clone(),
+            payer: data.owner.clone(),
+            old_data: Some(data),
+            recipient: recipient,
+            fee: Zero::zero(),
+            deposit: Zero::zero(),
+            free_update: false,
+            registrar: None,
+            renewal: false,
+            never_expires: false,
+        }
+    }
+
+    /// Executes the state change (and fires events) for a given
+    /// [name operation](Operation).
+    ///
+    /// This should be called after
+    /// [`check_assuming_signed`](Module::check_assuming_signed) (passing its
+    /// result), and when potential other checks have been done as well.
+    ///
+    /// This function may actually fail (return an error) if the value deposit
+    /// cannot be reserved or the fee cannot be held or withdrawn.  This can
+    /// happen if some funds were spent externally between the call to
+    /// [`check_assuming_signed`](Module::check_assuming_signed) and this
+    /// function.  These steps are done before anything else, and whatever
+    /// was already reserved or held is returned if a later one fails.  Thus
+    /// `execute` is a noop if it fails.
+    pub fn execute(op: Operation<T>) -> DispatchResult {
+        debug::debug!(target: LOG_TARGET, "executing {:?} of name {:?}",
+                      op.operation, T::Hashing::hash_of(&op.name));
+
+        /* As the very first steps, reserve the value deposit and handle
+           the name fee.  These are the only steps that can fail, and the
+           deposit (and fee hold) is released again if a later one of them
+           fails, as storage changes are not rolled back.  This makes
+           sure that a failure will not cause any other changes.  If there
+           is no fee (e.g. for unchecked operations), we skip this
+           completely, so that the sender account need not even exist.  */
+        let deposit = Self::deposit_increase(&op);
+        if !deposit.is_zero() {
+            T::Currency::reserve(&op.sender, deposit)?;
+        }
+        let release_deposit = || {
+            if !deposit.is_zero() {
+                T::Currency::unreserve(&op.sender, deposit);
+            }
+        };
+        let first_update = match (op.operation, T::FirstUpdatePeriod::get()) {
+            (OperationType::Registration, Some(blocks)) => Some(blocks),
+            _ => None,
+        };
+        let held = match first_update {
+            Some(_) => T::FirstUpdateRefund::get() * op.fee,
+            None => Zero::zero(),
+        };
+        if !held.is_zero() {
+            if let Err(e) = T::Payment::hold(&op.payer, held) {
+                release_deposit();
+                return Err(e);
+            }
+        }
+        if op.fee > held {
+            let commission = match T::Payment::withdraw(&op.payer, op.fee - held,
+                                                        op.registrar.as_ref()) {
+                Ok(commission) => commission,
+                Err(e) => {
+                    if !held.is_zero() {
+                        T::Payment::refund(&op.payer, held);
+                    }
+                    release_deposit();
+                    return Err(e);
+                },
+            };
+            if let (Some(registrar), false) = (&op.registrar, commission.is_zero()) {
+                Self::deposit_event(RawEvent::RegistrarCommissionPaid(
+                    op.name.clone(), registrar.clone(), commission));
+            }
+        }
+        Self::reconcile_value_deposit(&op.name, &op.sender, op.deposit, deposit);
+        if op.free_update {
+            let era = Self::current_era();
+            <FreeUpdatesUsed<T>>::mutate(&op.name, |(used_era, used)| {
+                if *used_era != era {
+                    *used_era = era;
+                    *used = 0;
+                }
+                *used += 1;
+            });
+        }
+
+        let expiration_blocks = T::Policy::get_expiration(&op);
+        let kept = if op.never_expires {
+            Some(None)
+        } else {
+            Self::keep_expiration(&op, expiration_blocks)
+        };
+        let expiration_height = match kept {
+            Some(old) => old,
+            None => match expiration_blocks {
+                None => None,
+                Some(b) => {
+                    /* In the strange case that we are told to use zero blocks
+                       for expiration, make it at least one.  This ensures that
+                       we will actually expire the name in the next block, and
+                       not end up with an index entry from the past that will
+                       stick around forever.  */
+                    let b = max(b, T::BlockNumber::from(1));
+                    let h = system::Module::<T>::block_number() + b;
+                    Some(Self::schedule_expiration(&op.name, h))
+                },
+            },
+        };
+
+        /* If the recipient requires incoming transfers to be claimed, the
+           name stays with its current owner (or the sender for new names)
+           and the transfer becomes pending instead.  */
+        let old = <Names<T>>::get(&op.name);
+        let nonce = old.as_ref().map_or(0, |d| d.nonce.saturating_add(1));
+        let old_held_since = old.as_ref().map(|d| d.held_since);
+        let old_owner = old.map(|d| d.owner);
+        let (owner, pending) = if op.recipient != op.sender && Self::requires_claim(&op.recipient) {
+            (old_owner.clone().unwrap_or_else(|| op.sender.clone()), Some(op.recipient))
+        } else {
+            (op.recipient, None)
+        };
+
+        let held_since = match old_held_since {
+            Some(h) if old_owner.as_ref() == Some(&owner) => h,
+            _ => system::Module::<T>::block_number(),
+        };
+        let data = NameData::<T> {
+            value: op.value,
+            owner: owner,
+            expiration: expiration_height,
+            nonce: nonce,
+            held_since: held_since,
+        };
+        <Names<T>>::insert(&op.name, &data);
+        <InGracePeriod<T>>::remove(&op.name);
+        Self::store_receipt(Receipt::<T> {
+            name_hash: T::Hashing::hash_of(&op.name),
+            operation: op.operation,
+            fee: op.fee,
+            payer: op.payer.clone(),
+            expiration: data.expiration,
+            previous_owner: old_owner.clone(),
+        });
+        Self::record_statistics(|stats| {
+            match op.operation {
+                OperationType::Registration => {
+                    stats.registrations = stats.registrations.saturating_add(1);
+                },
+                OperationType::Update => stats.renewals = stats.renewals.saturating_add(1),
+                OperationType::Transfer => (),
+            }
+            stats.fees = stats.fees.saturating_add(op.fee);
+        });
+
+        /* Update the owner index if the owner changes.  Signing keys and
+           identity links belong to the current owner as well, so make sure
+           they do not carry over to a new owner.  Other data is kept or
+           cleared according to the name's retention policy.  */
+        let owner_changed = old_owner.as_ref() != Some(&data.owner);
+        if owner_changed {
+            if let Some(old) = &old_owner {
+                <OwnerIndex<T>>::remove(old, &op.name);
+            }
+            <OwnerIndex<T>>::insert(&data.owner, &op.name, &op.name);
+            let retention = Self::retention_policy(&op.name);
+            let operator = <Operators<T>>::get(&op.name);
+            let insured = Self::insured_renewals(&op.name);
+            Self::clear_owner_data(&op.name);
+            if old_owner.is_some() {
+                Self::apply_retention(&op.name, retention, operator, insured);
+            }
+        }
+        if let Some(recipient) = pending {
+            <PendingTransfers<T>>::insert(&op.name, &recipient);
+            Self::deposit_event(RawEvent::TransferPending(op.name.clone(), recipient));
+        }
+
+        match op.operation {
+            OperationType::Registration => {
+                <TotalNames>::mutate(|n| *n = n.saturating_add(1));
+                <RegistrationsThisBlock>::mutate(|n| *n = n.saturating_add(1));
+                let hash = T::Hashing::hash_of(&op.name);
+                <HiddenNames<T>>::remove(&hash);
+                if let Some(preorder) = <Preorders<T>>::get(&hash) {
+                    if preorder.owner == op.sender {
+                        T::Payment::refund(&preorder.owner, preorder.deposit);
+                        <Preorders<T>>::remove(&hash);
+                        Self::deposit_event(RawEvent::PreorderExercised(hash));
+                    }
+                }
+                Self::add_to_namehash_index(&op.name);
+                if let Some(window) = T::ComplianceWindow::get() {
+                    let deadline = system::Module::<T>::block_number()
+                        + max(window, T::BlockNumber::from(1));
+                    <ComplianceDeadlines<T>>::insert(&op.name, deadline);
+                    <ComplianceChecks<T>>::insert(deadline, &op.name, &op.name);
+                }
+                if let Some(blocks) = first_update {
+                    let deadline = system::Module::<T>::block_number()
+                        + max(blocks, T::BlockNumber::from(1));
+                    <FirstUpdates<T>>::insert(&op.name, PendingFirstUpdate::<T> {
+                        deadline: deadline,
+                        payer: op.payer.clone(),
+                        held: held,
+                    });
+                    <FirstUpdateDeadlines<T>>::insert(deadline, &op.name, &op.name);
+                }
+                Self::deposit_name_event(&op.name, RawEvent::NameRegistered(op.name.clone()));
+            },
+            OperationType::Update | OperationType::Transfer => {
+                let value_changed = op.old_data.as_ref().map_or(false, |d| d.value != data.value);
+                if op.operation == OperationType::Update && value_changed {
+                    Self::complete_first_update(&op.name);
+                }
+                let change = if owner_changed { NameChange::Transfer } else { NameChange::Update };
+                Self::notify_watchers(&op.name, change);
+                if let (OperationType::Transfer, Some(old)) = (&op.operation, old_owner) {
+                    if owner_changed {
+                        let event = RawEvent::NameTransferred(op.name.clone(), old,
+                                                              data.owner.clone());
+                        Self::deposit_name_event(&op.name, event);
+                    }
+                }
+            },
+        }
+        T::OnNameOperation::on_name_operation(op.operation, &op.name, &data);
+        Self::deposit_name_event(&op.name, RawEvent::NameUpdated(op.name.clone(), data));
+
+        Ok(())
+    }
+
+    /// Executes a name operation like [`execute`](Module::execute) and
+    /// reports what it did, for validating e.g. a change of the name policy
+    /// against real operations before it is deployed.
+    ///
+    /// This Substrate version has no storage transactions, so the changes
+    /// are *not* rolled back here.  The function must only be used where
+    /// the storage overlay is discarded afterwards, e.g. from test
+    /// externalities or a runtime API call, but never in a block.  It is
+    /// only available with the `simulation` feature for that reason.
+    #[cfg(feature = "simulation")]
+    pub fn simulate_execute(op: Operation<T>) -> Simulation<T> {
+        let mut accounts = vec![op.sender.clone()];
+        for who in &[&op.payer, &op.recipient] {
+            if !accounts.contains(who) {
+                accounts.push((*who).clone());
+            }
+        }
+        let balances_of = |who: &T::AccountId| {
+            (T::Currency::free_balance(who), T::Currency::reserved_balance(who))
+        };
+        let before: Vec<_> = accounts.iter().map(balances_of).collect();
+
+        let name = op.name.clone();
+        let old_data = <Names<T>>::get(&name);
+        let num_events = system::Module::<T>::events().len();
+
+        let result = Self::execute(op);
+
+        let events = system::Module::<T>::events().into_iter()
+            .skip(num_events)
+            .map(|r| r.event)
+            .collect();
+        let balances = accounts.into_iter().zip(before)
+            .map(|(who, before)| {
+                let after = balances_of(&who);
+                (who, before, after)
+            })
+            .collect();
+
+        Simulation {
+            result: result,
+            old_data: old_data,
+            new_data: <Names<T>>::get(&name),
+            balances: balances,
+            events: events,
+        }
+    }
+
+    /// Signals that an operation on a name was rejected by a runtime hook,
+    /// e.g. a custom value validator, by emitting an `OperationRejected`
+    /// event with a runtime-defined reason code.  This gives off-chain
+    /// tooling a uniform way to see such rejections across runtimes.
+    ///
+    /// The given error is returned, so that callers can write e.g.
+    /// `return Err(Names::reject_operation(&name, 1, "invalid value").into())`.
+    pub fn reject_operation(name: &T::Name, reason_code: u32,
+                            err: &'static str) -> &'static str {
+        debug::debug!(target: LOG_TARGET, "operation on {:?} rejected ({}): {}",
+                      T::Hashing::hash_of(name), reason_code, err);
+        Self::deposit_name_event(name, RawEvent::OperationRejected(name.clone(), reason_code));
+        err
+    }
+
+    /// Returns the event topic for the namespace with the given
+    /// [topic prefix](Module::topic_prefixes).  It is the hash of the
+    /// SCALE-encoded prefix.
+    pub fn namespace_topic(prefix: &[u8]) -> T::Hash {
+        T::Hashing::hash_of(&prefix)
+    }
+
+    /// Deposits an event about a name.  It is indexed under the
+    /// [topics](Module::namespace_topic) of all topic prefixes that
+    /// the name falls into.
+    fn deposit_name_event(name: &T::Name, event: Event<T>) {
+        let topics: Vec<T::Hash> = Self::topic_prefixes().iter()
+            .filter(|prefix| T::Namespaces::has_prefix(name, prefix))
+            .map(|prefix| Self::namespace_topic(prefix))
+            .collect();
+        let event = <T as Trait>::Event::from(event).into();
+        system::Module::<T>::deposit_event_indexed(&topics, event);
+    }
+
+    /// Queues an operation (e.g. as returned by
+    /// [`check_assuming_signed`](Module::check_assuming_signed)) for
+    /// execution in a later block instead of executing it right away.
+    /// Queued operations are executed in order at the start of the
+    /// following blocks, within a [weight limit](Trait::DeferredWeightLimit)
+    /// per block.  This lets runtimes apply name changes e.g. at an epoch
+    /// boundary, or spread heavy batches over several blocks.
+    ///
+    /// An operation is dropped (with a `DeferredOperationFailed` event) if
+    /// the name has changed since it was queued, or if its execution fails.
+    pub fn enqueue(op: Operation<T>) {
+        let end = Self::deferred_queue_end();
+        Self::deposit_event(RawEvent::OperationDeferred(op.name.clone(), end));
+        <DeferredQueue<T>>::insert(end, op);
+        <DeferredQueueEnd>::put(end + 1);
+    }
+
+    /// Executes deferred operations until the
+    /// [weight limit](Trait::DeferredWeightLimit) for the block is reached.
+    /// Each operation weighs the same as the extrinsic for it (without
+    /// the extra arguments of calls).
+    fn process_deferred() {
+        let end = Self::deferred_queue_end();
+        let mut cursor = Self::deferred_cursor();
+        if cursor >= end {
+            return;
+        }
+
+        let limit = T::DeferredWeightLimit::get();
+        let mut used: Weight = 0;
+        let mut processed = 0u32;
+        while cursor < end && (processed == 0 || used < limit) {
+            if let Some(op) = <DeferredQueue<T>>::take(cursor) {
+                let value = match op.operation {
+                    OperationType::Transfer => None,
+                    _ => Some(&op.value),
+                };
+                let weight = T::ExtraWeight::extra_weight(&op.name, value);
+                used = used.saturating_add(weight).saturating_add(10_000);
+
+                let name = op.name.clone();
+                let res: DispatchResult = if <Names<T>>::get(&name) != op.old_data {
+                    Err("name has changed since the operation was deferred".into())
+                } else {
+                    Self::execute(op)
+                };
+                if res.is_err() {
+                    Self::deposit_event(RawEvent::DeferredOperationFailed(name));
+                }
+            }
+            cursor += 1;
+            processed += 1;
+        }
+
+        /* Just like for the migration queue, positions start from
+           zero again once the queue is empty.  */
+        if cursor >= end {
+            <DeferredCursor>::kill();
+            <DeferredQueueEnd>::kill();
+        } else {
+            <DeferredCursor>::put(cursor);
+        }
+        Self::deposit_event(RawEvent::DeferredOperationsProcessed(processed, end - cursor));
+    }
+
+    /// Looks up a name by its hash as defined by the configured
+    /// [namehash function](Trait::Namehash).  This allows tools that address
+    /// names by their ENS namehash to resolve them on this chain.
+    pub fn resolve_namehash(hash: H256) -> Option<T::Name> {
+        <NamehashIndex<T>>::get(hash)
+    }
+
+    /// Follows the [redirects](Module::set_redirect) starting at a name,
+    /// and returns the name they end at together with its data.  A name
+    /// without redirect resolves to itself.
+    pub fn resolve(name: &T::Name) -> Result<(T::Name, NameData<T>), ResolveError> {
+        let mut visited = Vec::new();
+        let mut current = name.clone();
+        while let Some(target) = Self::redirect(&current) {
+            if visited.len() >= T::MaxRedirectDepth::get() as usize {
+                return Err(ResolveError::TooDeep);
+            }
+            visited.push(current);
+            if visited.contains(&target) {
+                return Err(ResolveError::Cycle);
+            }
+            current = target;
+        }
+        match <Names<T>>::get(&current) {
+            None => Err(ResolveError::NotFound),
+            Some(data) => Ok((current, data)),
+        }
+    }
+
+    /// Returns the owner of the name that a name [resolves](Module::resolve)
+    /// to, e.g. as the address to send payments for the name to.
+    pub fn resolve_address(name: &T::Name) -> Result<T::AccountId, ResolveError> {
+        Self::resolve(name).map(|(_, data)| data.owner)
+    }
+
+    /// Checks that redirecting a name to the target would give a valid
+    /// chain of redirects, i.e. one that does not lead back to the name,
+    /// is short enough and ends at an existing name.
+    fn check_redirect(name: &T::Name, target: &T::Name) -> Result<(), ResolveError> {
+        let mut depth = 1;
+        let mut current = target.clone();
+        loop {
+            if current == *name {
+                return Err(ResolveError::Cycle);
+            }
+            if depth > T::MaxRedirectDepth::get() {
+                return Err(ResolveError::TooDeep);
+            }
+            match Self::redirect(&current) {
+                None => break,
+                Some(next) => current = next,
+            }
+            depth += 1;
+        }
+        ensure!(<Names<T>>::exists(&current), ResolveError::NotFound);
+        Ok(())
+    }
+
+    /// Verifies that a message has been signed by the current owner of
+    /// a name.  This allows off-chain services to authenticate users by
+    /// their names ("log in with your name").  Returns false if the name
+    /// does not exist or the signature is invalid.
+    ///
+    /// The signature type can be anything that verifies against the chain's
+    /// account IDs, e.g. `MultiSignature` for sr25519, ed25519 and ecdsa keys.
+    pub fn verify_name_signature<S>(name: &T::Name, message: &[u8], signature: &S) -> bool
+        where S: Verify, S::Signer: IdentifyAccount<AccountId = T::AccountId>
+    {
+        match <Names<T>>::get(name) {
+            None => false,
+            Some(data) => signature.verify(message, &data.owner),
+        }
+    }
+
+    /// Verifies that a message has been signed for a name.  If the name
+    /// has a [dedicated signing key](Module::set_signing_key), the signature
+    /// is checked against that key.  Otherwise, this is the same as
+    /// [`verify_name_signature`](Module::verify_name_signature).
+    pub fn verify_with_signing_key<S>(name: &T::Name, message: &[u8], signature: &S) -> bool
+        where S: Verify, S::Signer: IdentifyAccount<AccountId = T::AccountId>
+    {
+        match <SigningKeys<T>>::get(name) {
+            None => Self::verify_name_signature(name, message, signature),
+            Some(key) => signature.verify(message, &key),
+        }
+    }
+
+    /// Cancels all rent-to-own agreements whose next installment is due
+    /// at the given block and has not been paid.
+    fn cancel_overdue_rents(h: T::BlockNumber) {
+        for name in <RentDue<T>>::iter_prefix(h) {
+            if let Some(agreement) = <RentAgreements<T>>::get(&name) {
+                if agreement.next_due == h {
+                    <RentAgreements<T>>::remove(&name);
+                    Self::deposit_event(RawEvent::RentToOwnCancelled(name));
+                }
+            }
+        }
+        <RentDue<T>>::remove_prefix(h);
+    }
+
+    /// Applies all scheduled values that take effect at the given block.
+    /// The update is executed as an operation by the name's owner.
+    fn activate_scheduled_values(h: T::BlockNumber) {
+        for name in <ValueActivations<T>>::iter_prefix(h) {
+            let value = match <ScheduledValues<T>>::get(&name) {
+                Some((at, value)) if at == h => value,
+                _ => continue,
+            };
+            <ScheduledValues<T>>::remove(&name);
+
+            let owner = match <Names<T>>::get(&name) {
+                None => continue,
+                Some(data) => data.owner,
+            };
+            let res = Self::check_assuming_signed(owner, name.clone(), Some(value), None)
+                .map_err(DispatchError::from)
+                .and_then(Self::execute);
+            match res {
+                Ok(_) => Self::deposit_event(RawEvent::ScheduledValueActivated(name)),
+                Err(_) => Self::deposit_event(RawEvent::ScheduledValueFailed(name)),
+            }
+        }
+        <ValueActivations<T>>::remove_prefix(h);
+    }
+
+    /// Returns the current era for the [statistics](RegistrarStats).
+    fn current_era() -> u32 {
+        let len = max(T::StatsEraLength::get(), T::BlockNumber::from(1));
+        (system::Module::<T>::block_number() / len).saturated_into()
+    }
+
+    /// Updates the statistics of the current era.  If its slot in the ring
+    /// buffer still holds an older era, that is overwritten.
+    fn record_statistics<F: FnOnce(&mut RegistrarStats<T>)>(f: F) {
+        let history = T::StatsHistory::get();
+        if history == 0 {
+            return;
+        }
+        let era = Self::current_era();
+        <Statistics<T>>::mutate(era % history, |stats| {
+            if stats.era != era {
+                *stats = RegistrarStats::<T>::empty(era);
+            }
+            f(stats);
+        });
+    }
+
+    /// Returns the number of [free updates](Trait::FreeUpdatesPerEra) that
+    /// a name has left in the current era.
+    pub fn free_updates_left(name: &T::Name) -> u32 {
+        let (era, used) = <FreeUpdatesUsed<T>>::get(name);
+        let used = if era == Self::current_era() { used } else { 0 };
+        T::FreeUpdatesPerEra::get().saturating_sub(used)
+    }
+
+    /// Returns the statistics of all eras that are still kept, ordered
+    /// from the oldest to the current era.  Eras without any activity
+    /// are included with zero counters.
+    pub fn statistics() -> Vec<RegistrarStats<T>> {
+        let history = T::StatsHistory::get();
+        if history == 0 {
+            return Vec::new();
+        }
+        let era = Self::current_era();
+        let first = era.saturating_sub(history - 1);
+        (first..=era).map(|e| {
+            let stats = <Statistics<T>>::get(e % history);
+            if stats.era == e {
+                stats
+            } else {
+                RegistrarStats::<T>::empty(e)
+            }
+        }).collect()
+    }
+
+    /// Stores the receipt of an operation executed in the current block.
+    fn store_receipt(receipt: Receipt<T>) {
+        let h = system::Module::<T>::block_number();
+        let index = system::Module::<T>::extrinsic_index();
+        <Receipts<T>>::mutate(h, index, |receipts| receipts.push(receipt));
+    }
+
+    /// Removes all receipts that have passed the
+    /// [retention period](Trait::ReceiptRetention) at the given block.
+    fn prune_receipts(h: T::BlockNumber) {
+        let retention = T::ReceiptRetention::get();
+        if h > retention {
+            <Receipts<T>>::remove_prefix(h - retention);
+        }
+    }
+
+    /// Brings the derived index entries of a name in line with its entry
+    /// in the name database.  If `who` is given, a stale entry of the
+    /// name in that account's part of the owner index is removed as well.
+    pub fn repair_index_of(name: &T::Name, who: Option<&T::AccountId>) {
+        let data = <Names<T>>::get(name);
+        if let Some(who) = who {
+            if data.as_ref().map(|d| &d.owner) != Some(who) {
+                <OwnerIndex<T>>::remove(who, name);
+            }
+        }
+
+        match data {
+            None => {
+                if let Some(hash) = T::Namehash::namehash(name) {
+                    if <NamehashIndex<T>>::get(hash).as_ref() == Some(name) {
+                        <NamehashIndex<T>>::remove(hash);
+                    }
+                }
+            },
+            Some(data) => {
+                <OwnerIndex<T>>::insert(&data.owner, name, name);
+                Self::add_to_namehash_index(name);
+                if let Some(h) = data.expiration {
+                    <ExpirationQueue<T>>::mutate(h, |names| {
+                        if !names.contains(name) {
+                            names.push(name.clone());
+                        }
+                    });
+                }
+            },
+        }
+
+        Self::deposit_event(RawEvent::IndexRepaired(name.clone()));
+    }
+
+    /// Distributes income of a name (e.g. from payments to the name or
+    /// rentals) according to its [payout configuration](Module::set_payout).
+    /// If the name does not exist, the income is burnt.
+    pub fn distribute_income(name: &T::Name, mut income: NegativeImbalanceOf<T>) {
+        let owner = match <Names<T>>::get(name) {
+            None => return,
+            Some(data) => data.owner,
+        };
+        let total = income.peek();
+        for (who, fraction) in Self::payout_of(name) {
+            let (part, rest) = income.split(fraction * total);
+            T::Currency::resolve_creating(&who, part);
+            income = rest;
+        }
+        T::Currency::resolve_creating(&owner, income);
+    }
+
+    /// Adds the approval of `who` to a proposed value for a name split into
+    /// shares.  If the approvals reach the threshold, the value update is
+    /// executed (with `who` paying the fee) and the proposal removed.
+    /// Otherwise, the proposal is stored with the new approval.
+    fn approve_proposal(who: T::AccountId, name: T::Name, value: T::Value,
+                        mut approvers: Vec<T::AccountId>) -> DispatchResult {
+        let info = match <SharedNames<T>>::get(&name) {
+            None => return Err("name is not split into shares".into()),
+            Some(info) => info,
+        };
+        ensure!(info.shares_of(&who) > 0, "sender holds no shares");
+        if !approvers.contains(&who) {
+            approvers.push(who.clone());
+        }
+
+        /* Shares are counted at the time of the approval, so approvals of
+           accounts that have transferred their shares away are void.  */
+        let approved: u32 = approvers.iter().map(|a| info.shares_of(a)).sum();
+        if Perbill::from_rational_approximation(approved, info.total) < info.threshold {
+            <ValueProposals<T>>::insert(&name, (value, approvers));
+            return Ok(());
+        }
+
+        let op = Self::check_sponsored(Self::name_account(&name), who, name.clone(),
+                                       Some(value), None)?;
+        Self::execute(op)?;
+        <ValueProposals<T>>::remove(&name);
+        Ok(())
+    }
+
+    /// Returns the sovereign account of a name, from which its owner can
+    /// [dispatch calls](Module::dispatch_as_name).  The account is derived
+    /// from the name alone, so it stays the same when the name is
+    /// transferred, and nobody has a key for it.
+    pub fn name_account(name: &T::Name) -> T::AccountId {
+        let entropy = (T::ModuleId::get().0, b"name", name).using_encoded(blake2_256);
+        T::AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+    }
+
+    /// Ensures that the origin is signed by the current owner of the given
+    /// name, and returns that account.  Other pallets can use this to gate
+    /// their own extrinsics on owning a name.
+    pub fn ensure_name_owner(origin: T::Origin, name: &T::Name)
+        -> Result<T::AccountId, DispatchError>
+    {
+        let who = ensure_signed(origin)?;
+        match <Names<T>>::get(name) {
+            None => Err("name does not exist".into()),
+            Some(data) => {
+                ensure!(data.owner == who, "caller does not own the name");
+                Ok(who)
+            },
+        }
+    }
+
+    /// Returns an attestation that the name is owned by its current owner
+    /// at the current block, or `None` if the name does not exist.
+    pub fn attest_ownership(name: &T::Name) -> Option<OwnershipAttestation<T>> {
+        <Names<T>>::get(name).map(|data| OwnershipAttestation::<T> {
+            name: name.clone(),
+            owner: data.owner,
+            block: system::Module::<T>::block_number(),
+            storage_key: Self::name_storage_key(name),
+        })
+    }
+
+    /// Returns the storage key of a name's entry in the name database.
+    /// A state proof for this key at some block shows who owned the name
+    /// then, or that it did not exist.
+    pub fn name_storage_key(name: &T::Name) -> Vec<u8> {
+        <Names<T>>::hashed_key_for(name)
+    }
+
+    /// Returns the block height at which a name will expire, or `None` if
+    /// the name does not exist or never expires.
+    pub fn next_expiration(name: &T::Name) -> Option<T::BlockNumber> {
+        <Names<T>>::get(name).and_then(|data| data.expiration)
+    }
+
+    /// Checks whether a name will still be active at the given block
+    /// height, assuming it is not updated in the meantime.  This lets other
+    /// pallets rely on a name until some future block, e.g. when accepting
+    /// it as collateral.
+    ///
+    /// If the name will get a [grace period](policy::ExpirationAction::Grace)
+    /// when it expires, it stays with its owner and is thus considered
+    /// active until the grace period ends.
+    pub fn active_at(name: &T::Name, block: T::BlockNumber) -> bool {
+        let data = match <Names<T>>::get(name) {
+            None => return false,
+            Some(data) => data,
+        };
+        let h = match data.expiration {
+            None => return true,
+            Some(h) => h,
+        };
+        if block < h {
+            return true;
+        }
+        if Self::in_grace_period(name) {
+            return false;
+        }
+        match T::OnExpiration::expiration_action(name, &data) {
+            ExpirationAction::Grace(blocks) => block < h + max(blocks, T::BlockNumber::from(1)),
+            _ => false,
+        }
+    }
+
+    /// Checks that all names of one side of a
+    /// [swap offer](Module::propose_swap) are owned by the given account
+    /// and can be transferred by the swap.
+    fn check_swap_names(names: &[T::Name], owner: &T::AccountId) -> DispatchResult {
+        for name in names {
+            let state = Self::state_of(name);
+            match state.data() {
+                None => return Err("name does not exist".into()),
+                Some(data) => ensure!(data.owner == *owner, "swapped name has a different owner"),
+            }
+            ensure!(!<Collateral<T>>::exists(name), "name is locked as collateral");
+            ensure!(!<RentAgreements<T>>::exists(name),
+                    "name is under a rent-to-own agreement");
+            Self::ensure_can_encumber(name, &state)?;
+        }
+        Ok(())
+    }
+
+    /// Unlocks the offered names of a swap offer and releases the
+    /// sweetener.  The offer itself must be removed by the caller.
+    fn unlock_swap(proposer: &T::AccountId, offer: &SwapOffer<T>) {
+        for name in &offer.offered {
+            if Self::swap_lock(name).as_ref() == Some(proposer) {
+                <SwapLocks<T>>::remove(name);
+            }
+        }
+        T::Currency::unreserve(proposer, offer.sweetener);
+    }
+
+    /// Transfers a name to its new owner as part of settling a swap.  The
+    /// name must have been checked with
+    /// [`check_swap_names`](Module::check_swap_names) before.
+    fn settle_swapped_name(name: &T::Name, recipient: &T::AccountId) {
+        let data = match <Names<T>>::get(name) {
+            None => return,
+            Some(data) => data,
+        };
+
+        Self::execute_automatic_transfer(name.clone(), data, recipient.clone());
+    }
+
+    /// Returns all outstanding processes that affect the given name, e.g.
+    /// so that UIs can show that it has a pending claim or rent agreement.
+    pub fn pending_for(name: &T::Name) -> Vec<PendingEntry<T>> {
+        let mut res = Vec::new();
+        if let Some(recipient) = Self::pending_transfer(name) {
+            res.push(PendingEntry::Transfer { recipient });
+        }
+        if let Some(request) = Self::guarded_transfer(name) {
+            res.push(PendingEntry::GuardedTransfer {
+                recipient: request.recipient,
+                deadline: request.deadline,
+            });
+        }
+        if let Some(beneficiary) = Self::collateral_beneficiary(name) {
+            res.push(PendingEntry::Collateral { beneficiary });
+        }
+        let locks = Self::external_locks(name).len() as u32;
+        if locks > 0 {
+            res.push(PendingEntry::ExternalLocks(locks));
+        }
+        if let Some(proposer) = Self::swap_lock(name) {
+            res.push(PendingEntry::Swap { proposer });
+        }
+        if let Some(agreement) = Self::rent_agreement(name) {
+            res.push(PendingEntry::RentToOwn {
+                tenant: agreement.tenant,
+                next_due: agreement.next_due,
+            });
+        }
+        if let Some((at, _)) = Self::scheduled_value(name) {
+            res.push(PendingEntry::ScheduledValue { at });
+        }
+        if let Some((_, approvals)) = Self::value_proposal(name) {
+            res.push(PendingEntry::ValueProposal { approvals: approvals.len() as u32 });
+        }
+        if let Some(pending) = Self::pending_first_update(name) {
+            res.push(PendingEntry::FirstUpdate { deadline: pending.deadline });
+        }
+        if let Some(report) = Self::report_of(name) {
+            res.push(PendingEntry::Report { decidable_at: report.decidable_at });
+        }
+        let intents = Self::registration_intents(name).len() as u32;
+        if intents > 0 {
+            res.push(PendingEntry::Intents(intents));
+        }
+        let backorders = Self::backorders(name).len() as u32;
+        if backorders > 0 {
+            res.push(PendingEntry::Backorders(backorders));
+        }
+        if Self::in_grace_period(name) {
+            if let Some(until) = <Names<T>>::get(name).and_then(|d| d.expiration) {
+                res.push(PendingEntry::GracePeriod { until });
+            }
+        }
+        if let Some(until) = Self::quarantined_until(name) {
+            res.push(PendingEntry::Quarantine { until });
+        }
+        res
+    }
+
+    /// Returns the lifecycle [state](NameState) of a name.  All checks on
+    /// the status of a name should be based on this, so that they agree
+    /// with each other.
+    pub fn state_of(name: &T::Name) -> NameState<T> {
+        let data = match <Names<T>>::get(name) {
+            Some(data) => data,
+            None => {
+                let hash = T::Hashing::hash_of(name);
+                let h = system::Module::<T>::block_number();
+                let preordered = match <Preorders<T>>::get(&hash) {
+                    Some(preorder) => h >= preorder.matures,
+                    None => false,
+                };
+                if Self::is_reserved_name(name) || <HiddenNames<T>>::exists(&hash) || preordered
+                        || Self::quarantined_until(name).is_some() {
+                    return NameState::Reserved;
+                }
+                return NameState::Available;
+            },
+        };
+
+        if Self::is_frozen(name) {
+            return NameState::Frozen(data);
+        }
+        if Self::in_grace_period(name) {
+            return NameState::GracePeriod(data);
+        }
+        match Self::pending_transfer(name) {
+            Some(recipient) => NameState::PendingTransfer {
+                data: data,
+                recipient: recipient,
+            },
+            None => NameState::Registered(data),
+        }
+    }
+
+    /// Ensures that the owner of a name in the given state can put it under
+    /// a new lock or agreement (e.g. collateral, rent-to-own or a swap),
+    /// i.e. that it is neither frozen nor has a pending transfer, and that
+    /// the lock or agreement can later transfer the name.
+    fn ensure_can_encumber(name: &T::Name, state: &NameState<T>) -> DispatchResult {
+        match state {
+            NameState::Frozen(_) => return Err("name is frozen".into()),
+            NameState::PendingTransfer { .. } => return Err("name has a pending transfer".into()),
+            _ => (),
+        }
+        Self::ensure_direct_transfer_allowed(name)
+    }
+
+    /// Ensures that a name can be transferred by an operation that is
+    /// executed without [`check_assuming_signed`](Module::check_assuming_signed)
+    /// (e.g. when collateral is claimed).  Names with a
+    /// [guardian](Module::set_guardian) can only be transferred with its
+    /// confirmation, and names with an [external lock](lock::NameLocks)
+    /// not at all, so neither can.
+    fn ensure_direct_transfer_allowed(name: &T::Name) -> DispatchResult {
+        ensure!(!<Guardians<T>>::exists(name), "transfer needs confirmation by the guardian");
+        ensure!(!<ExternalLocks<T>>::exists(name), "name is locked by another pallet");
+        Ok(())
+    }
+
+    /// Returns the [availability](Module::availability) of each of the
+    /// given names, in the same order.  This lets registration UIs check
+    /// many candidates (e.g. suggested alternatives) with a single query.
+    pub fn check_available(names: &[T::Name]) -> Vec<Availability<T::BlockNumber>> {
+        names.iter().map(Self::availability).collect()
+    }
+
+    /// Returns whether the given name can be registered, or the reason
+    /// why not.  This gives registration UIs a precise status, but does
+    /// not check anything about a particular sender (like their balance).
+    pub fn availability(name: &T::Name) -> Availability<T::BlockNumber> {
+        match Self::state_of(name) {
+            NameState::Frozen(_) => return Availability::Frozen,
+            NameState::GracePeriod(data) => return Availability::GracePeriod {
+                until: data.expiration.unwrap_or_default(),
+            },
+            NameState::PendingTransfer { data, .. } | NameState::Registered(data) => {
+                return Availability::Taken {
+                    expires: data.expiration,
+                };
+            },
+            NameState::Reserved => return Availability::Reserved,
+            NameState::Available => (),
+        }
+
+        let who = T::AccountId::default();
+        let value = T::DefaultValue::default_value_for(name, &who);
+        let op = Self::unchecked_operation(name.clone(), value, who);
+        if T::Policy::get_name_fee(&op).is_none() {
+            return Availability::PolicyInvalid;
+        }
+
+        Availability::Available
+    }
+
+    /// Converts a name fee from the reference unit of the policy to the
+    /// native currency, using the [fee converter](Trait::FeeConverter).
+    /// This fails if no rate is available or if it is stale.
+    fn convert_fee(fee: BalanceOf<T>) -> Result<BalanceOf<T>, &'static str> {
+        if let Some(updated) = T::FeeConverter::last_update() {
+            let age = system::Module::<T>::block_number().saturating_sub(updated);
+            ensure!(age <= T::MaxFeeRateAge::get(), "fee conversion rate is stale");
+        }
+        match T::FeeConverter::to_native(fee) {
+            None => Err("no fee conversion rate available"),
+            Some(native) => Ok(native),
+        }
+    }
+
+    /// Removes a name and all data attached to it from the database.
+    fn remove_name(name: &T::Name) {
+        if let Some(data) = <Names<T>>::get(name) {
+            <OwnerIndex<T>>::remove(&data.owner, name);
+            <TotalNames>::mutate(|n| *n = n.saturating_sub(1));
+        }
+        <Names<T>>::remove(name);
+        <InGracePeriod<T>>::remove(name);
+        <FreeUpdatesUsed<T>>::remove(name);
+        <FrozenNames<T>>::remove(name);
+        <ExternalLocks<T>>::remove(name);
+        <ComplianceDeadlines<T>>::remove(name);
+        Self::complete_first_update(name);
+        Self::clear_owner_data(name);
+        Self::clear_subvalues(name);
+        Self::release_value_deposit(name);
+        if let Some(report) = <Reports<T>>::take(name) {
+            T::Currency::unreserve(&report.reporter, report.bond);
+        }
+        Self::notify_watchers(name, NameChange::Removal);
+        for (who, deposit) in <Watchers<T>>::take(name) {
+            T::Currency::unreserve(&who, deposit);
+        }
+        if let Some(hash) = T::Namehash::namehash(name) {
+            <NamehashIndex<T>>::remove(hash);
+        }
+    }
+
+    /// Returns the part of the [value deposit](Trait::ValueDepositPerByte)
+    /// of an operation that its sender does not hold yet for the name, and
+    /// that has to be reserved when it is executed.
+    fn deposit_increase(op: &Operation<T>) -> BalanceOf<T> {
+        let held = match <ValueDeposits<T>>::get(&op.name) {
+            Some((who, amount)) if who == op.sender => amount,
+            _ => Zero::zero(),
+        };
+        op.deposit.saturating_sub(held)
+    }
+
+    /// Adjusts the [value deposit](Trait::ValueDepositPerByte) of a name,
+    /// so that afterwards `sender` holds exactly `amount` for it.  The
+    /// increase (as per [`deposit_increase`](Module::deposit_increase))
+    /// must have been reserved already and is passed as `reserved`.  A
+    /// deposit held by some other account is released.
+    fn reconcile_value_deposit(name: &T::Name, sender: &T::AccountId,
+                               amount: BalanceOf<T>, reserved: BalanceOf<T>) {
+        let old = <ValueDeposits<T>>::get(name);
+        if !reserved.is_zero() {
+            Self::deposit_event(RawEvent::ValueDepositReserved(name.clone(), sender.clone(),
+                                                               reserved));
+        }
+        match old {
+            Some((who, held)) if who == *sender => {
+                if held > amount {
+                    T::Currency::unreserve(sender, held - amount);
+                    Self::deposit_event(RawEvent::ValueDepositReleased(name.clone(), who,
+                                                                       held - amount));
+                }
+            },
+            Some(_) => Self::release_value_deposit(name),
+            None => (),
+        }
+
+        if amount.is_zero() {
+            <ValueDeposits<T>>::remove(name);
+        } else {
+            <ValueDeposits<T>>::insert(name, (sender.clone(), amount));
+        }
+    }
+
+    /// Releases the full value deposit of a name, if any.
+    fn release_value_deposit(name: &T::Name) {
+        if let Some((who, amount)) = <ValueDeposits<T>>::take(name) {
+            T::Currency::unreserve(&who, amount);
+            Self::deposit_event(RawEvent::ValueDepositReleased(name.clone(), who, amount));
+        }
+    }
+
+    /// Removes the note of a name (if any) and releases its deposit.
+    fn clear_note(name: &T::Name) {
+        if let Some(note) = <Notes<T>>::take(name) {
+            T::Currency::unreserve(&note.depositor, note.deposit);
+        }
+    }
+
+    /// Emits the events for a change to a name to all of its watchers.
+    fn notify_watchers(name: &T::Name, change: NameChange) {
+        for (who, _) in <Watchers<T>>::get(name) {
+            Self::deposit_event(RawEvent::NameWatchTriggered(who, name.clone(), change));
+        }
+    }
+
+    /// Returns the envelope of a name for the given recipient key, if any.
+    pub fn envelope(name: &T::Name, recipient: &H256) -> Option<Envelope> {
+        Self::envelope_for(name, recipient).map(|ciphertext| Envelope {
+            recipient: *recipient,
+            ciphertext: ciphertext,
+        })
+    }
+
+    /// Removes all envelopes of a name.
+    fn clear_envelopes(name: &T::Name) {
+        <Envelopes<T>>::remove_prefix(name);
+        <EnvelopeCount<T>>::remove(name);
+    }
+
+    /// Removes all subvalues of a name and releases their deposits.
+    fn clear_subvalues(name: &T::Name) {
+        for entry in <Subvalues<T>>::iter_prefix(name) {
+            T::Currency::unreserve(&entry.depositor, entry.deposit);
+        }
+        <Subvalues<T>>::remove_prefix(name);
+        <SubvalueCount<T>>::remove(name);
+        <SubvalueKeys<T>>::remove(name);
+    }
+
+    /// Returns all subvalues of a name with their subkeys, ordered by the
+    /// encoding of the subkeys.  The order is deterministic, so that
+    /// resolvers can compare results from different nodes directly.
+    pub fn records_of(name: &T::Name) -> Vec<(T::SubKey, T::Value)> {
+        Self::subvalue_keys(name).into_iter().filter_map(|key| {
+            <Subvalues<T>>::get(name, &key).map(|entry| (key, entry.value))
+        }).collect()
+    }
+
+    /// Returns the storage keys of a name's data, its subkey index and all
+    /// its subvalues.  Requesting a state proof for these keys lets a light
+    /// client fetch and verify the name's entire profile in one round trip.
+    pub fn record_proof_keys(name: &T::Name) -> Vec<Vec<u8>> {
+        let keys = Self::subvalue_keys(name);
+        let mut res = Vec::with_capacity(keys.len() + 2);
+        res.push(<Names<T>>::hashed_key_for(name));
+        res.push(<SubvalueKeys<T>>::hashed_key_for(name));
+        for key in keys {
+            res.push(<Subvalues<T>>::hashed_key_for(name, &key));
+        }
+        res
+    }
+
+    /// Removes all data attached to a name that is specific to its
+    /// current owner.  This is done when the name changes owner or expires.
+    /// A system name that is moved away from the pallet account (e.g. by
+    /// a forced transfer) is no longer a system name afterwards.
+    fn clear_owner_data(name: &T::Name) {
+        <SigningKeys<T>>::remove(name);
+        <Controllers<T>>::remove(name);
+        <SaleReady<T>>::remove(name);
+        <SwapLocks<T>>::remove(name);
+        <Guardians<T>>::remove(name);
+        <GuardedTransfers<T>>::remove(name);
+        Self::clear_note(name);
+        <Redirects<T>>::remove(name);
+        <Attestations<T>>::remove(name);
+        <SystemNames<T>>::remove(name);
+        <PendingTransfers<T>>::remove(name);
+        <Collateral<T>>::remove(name);
+        <Payouts<T>>::remove(name);
+        <RentAgreements<T>>::remove(name);
+        <Operators<T>>::remove(name);
+        <ScheduledValues<T>>::remove(name);
+        <InsuredRenewals<T>>::remove(name);
+        <RetentionPolicies<T>>::remove(name);
+        <SharedNames<T>>::remove(name);
+        Self::clear_envelopes(name);
+        <ValueProposals<T>>::remove(name);
+        Self::clear_identity_link(name);
+    }
+
+    /// Applies the [retention policy](Module::retention_policy) of a name
+    /// that changed owner, after its owner data has been cleared.  The
+    /// operator and insured renewals from before are restored if the
+    /// policy keeps them.  If anything was cleared, an event says what.
+    fn apply_retention(name: &T::Name, retention: RetentionPolicy,
+                       operator: Option<(T::AccountId, Vec<H256>)>, insured: u32) {
+        let records = !retention.keep_records && Self::subvalue_count(name) > 0;
+        if records {
+            Self::clear_subvalues(name);
+        }
+
+        let had_operator = operator.is_some();
+        match operator {
+            Some(operator) if retention.keep_operator => <Operators<T>>::insert(name, operator),
+            _ => (),
+        }
+        if retention.keep_insurance && insured > 0 {
+            <InsuredRenewals<T>>::insert(name, insured);
+        }
+
+        let operator_cleared = had_operator && !retention.keep_operator;
+        let insurance_cleared = insured > 0 && !retention.keep_insurance;
+        if records || operator_cleared || insurance_cleared {
+            Self::deposit_event(RawEvent::OwnerDataCleared(name.clone(), records,
+                                                           operator_cleared,
+                                                           insurance_cleared));
+        }
+    }
+
+    /// Removes the identity link of a name (in both directions), if any.
+    fn clear_identity_link(name: &T::Name) {
+        if let Some(who) = <IdentityLinks<T>>::get(name) {
+            <IdentityLinks<T>>::remove(name);
+            <NameOfIdentity<T>>::remove(&who);
+            Self::deposit_event(RawEvent::IdentityUnlinked(name.clone(), who));
+        }
+    }
+
+    /// Returns an error if names must be registered through intents, and
+    /// the given name does not exist yet.
+    fn ensure_direct_registration(name: &T::Name) -> DispatchResult {
+        if T::RegistrationIntentsOnly::get() && <Names<T>>::get(name).is_none() {
+            return Err("names must be registered through intents".into());
+        }
+        Ok(())
+    }
+
+    /// Settles all registration intents of the current block.  For each
+    /// name, the intents are ordered by a hash of name, sender and a random
+    /// seed.  The first of them that is (still) valid wins.  The
+    /// [bonds](Trait::IntentBond) of intents that turn out invalid before
+    /// that are forfeited, and all others are returned.
+    fn settle_intents() {
+        let seed = T::Randomness::random(b"names/intents");
+        let bond = T::IntentBond::get();
+        for name in <IntentNames<T>>::take() {
+            let mut intents = <RegistrationIntents<T>>::take(&name);
+            intents.sort_by_key(|(who, _)| {
+                T::Hashing::hash_of(&(&name, who, &seed)).as_ref().to_vec()
+            });
+
+            let mut settled = false;
+            for (who, value) in intents {
+                /* The name may have been registered in the same block
+                   already, e.g. through a hidden registration.  */
+                if settled || <Names<T>>::get(&name).is_some() {
+                    settled = true;
+                    T::Payment::refund(&who, bond);
+                    continue;
+                }
+                let executed = Self::check_assuming_signed(who.clone(), name.clone(),
+                                                           Some(value), None)
+                    .map_err(DispatchError::from)
+                    .and_then(Self::execute);
+                match executed {
+                    Ok(()) => {
+                        settled = true;
+                        T::Payment::refund(&who, bond);
+                        Self::deposit_event(RawEvent::RegistrationIntentSettled(name.clone(), who));
+                    },
+                    Err(_) => T::Payment::settle(&who, bond),
+                }
+            }
+        }
+    }
+
+    /// Computes the registration fee multiplier (in percent) for the
+    /// current total number of names.
+    fn congestion_multiplier() -> u32 {
+        let total = Self::total_names();
+        T::CongestionMultipliers::get().iter()
+            .filter(|(threshold, _)| *threshold <= total)
+            .map(|(_, multiplier)| *multiplier)
+            .max()
+            .unwrap_or(100)
+    }
+
+    /// Returns true if another name may be registered in the current block
+    /// without exceeding [`MaxRegistrationsPerBlock`](Trait::MaxRegistrationsPerBlock).
+    pub fn can_register_in_block() -> bool {
+        match T::MaxRegistrationsPerBlock::get() {
+            None => true,
+            Some(max) => Self::registrations_this_block() < max,
+        }
+    }
+
+    /// Checks the validity of a call of this module in the transaction pool
+    /// with respect to the per-block limit of registrations.  If the call
+    /// would register a name but the limit is already reached, it is
+    /// reported as exhausting the block's resources, so that it stays
+    /// out of the current block and is retried in the next one.
+    pub fn validate_registration(call: &Call<T>) -> TransactionValidity {
+        if let Some(name) = call.registered_name() {
+            if !<Names<T>>::exists(name) && !Self::can_register_in_block() {
+                return Err(InvalidTransaction::ExhaustsResources.into());
+            }
+        }
+        Ok(ValidTransaction::default())
+    }
+
+    /// Returns all names owned by the given account (in no particular order).
+    pub fn names_of(owner: &T::AccountId) -> Vec<T::Name> {
+        <OwnerIndex<T>>::iter_prefix(owner).collect()
+    }
+
+    /// Checks whether an account administers a name, i.e. whether the name
+    /// is inside one of the namespaces [delegated](Module::delegate_namespace)
+    /// to it.
+    pub fn administers(who: &T::AccountId, name: &T::Name) -> bool {
+        Self::delegated_namespaces(who).iter()
+            .any(|prefix| T::Namespaces::has_prefix(name, prefix))
+    }
+
+    /// Ensures that the origin is either the [force origin](Trait::ForceOrigin)
+    /// or a signed account that [administers](Module::administers) the name.
+    fn ensure_name_admin(origin: T::Origin, name: &T::Name) -> DispatchResult {
+        let origin = match T::ForceOrigin::try_origin(origin) {
+            Ok(_) => return Ok(()),
+            Err(origin) => origin,
+        };
+        let who = ensure_signed(origin)?;
+        ensure!(Self::administers(&who, name), DispatchError::BadOrigin);
+        Ok(())
+    }
+
+    /// Checks whether a name is inside one of the
+    /// [reserved namespaces](Module::reserved_prefixes).
+    pub fn is_reserved_name(name: &T::Name) -> bool {
+        Self::reserved_prefixes().iter().any(|prefix| T::Namespaces::has_prefix(name, prefix))
+    }
+
+    /// Returns all names owned by the given account together with their
+    /// values and expiration heights.
+    pub fn export_names(owner: &T::AccountId) -> Portfolio<T> {
+        Self::names_of(owner).into_iter()
+            .filter_map(|name| {
+                let data = <Names<T>>::get(&name)?;
+                Some((name, data.value, data.expiration))
+            })
+            .collect()
+    }
+
+    /// Stores an imported name with the given data directly in the database.
+    fn import_name(name: T::Name, value: T::Value, owner: T::AccountId,
+                   expiration: Option<T::BlockNumber>) {
+        let next_block = system::Module::<T>::block_number() + T::BlockNumber::from(1);
+        let old = <Names<T>>::get(&name);
+        let held_since = match &old {
+            Some(d) if d.owner == owner => d.held_since,
+            _ => system::Module::<T>::block_number(),
+        };
+        let data = NameData::<T> {
+            value: value,
+            owner: owner,
+            expiration: expiration.map(|h| Self::schedule_expiration(&name, max(h, next_block))),
+            nonce: old.as_ref().map_or(0, |d| d.nonce.saturating_add(1)),
+            held_since: held_since,
+        };
+
+        match old {
+            None => {
+                <TotalNames>::mutate(|n| *n = n.saturating_add(1));
+                Self::add_to_namehash_index(&name);
+                Self::deposit_name_event(&name, RawEvent::NameRegistered(name.clone()));
+            },
+            Some(old) => {
+                if old.owner != data.owner {
+                    <OwnerIndex<T>>::remove(&old.owner, &name);
+                    Self::clear_owner_data(&name);
+                }
+            },
+        }
+
+        <Names<T>>::insert(&name, &data);
+        <OwnerIndex<T>>::insert(&data.owner, &name, &name);
+        Self::deposit_name_event(&name, RawEvent::NameUpdated(name.clone(), data));
+    }
+
+    /// Executes an [unchecked transfer](Module::unchecked_transfer) that
+    /// is not triggered by a call, e.g. when settling a swap or on
+    /// expiration.  The transfer has no fee and should not fail; if it
+    /// does anyway, it is dropped with a `TransferFailed` event.
+    fn execute_automatic_transfer(name: T::Name, data: NameData<T>,
+                                  recipient: T::AccountId) {
+        let op = Self::unchecked_transfer(name.clone(), data, recipient.clone());
+        if let Err(err) = Self::execute(op) {
+            debug::debug!(target: LOG_TARGET, "transfer of {:?} failed: {:?}",
+                          T::Hashing::hash_of(&name), err);
+            Self::deposit_event(RawEvent::TransferFailed(name, recipient));
+        }
+    }
+
+    /// Applies the [configured action](Trait::OnOwnerReaped) to a name whose
+    /// owner account has been reaped.
+    fn handle_reaped_name(name: T::Name, data: NameData<T>) {
+        match T::OnOwnerReaped::reaped_name_action(&name, &data) {
+            ReapedNameAction::Keep => (),
+            ReapedNameAction::Transfer(recipient) => {
+                Self::execute_automatic_transfer(name, data, recipient);
+            },
+            ReapedNameAction::Expire(blocks) => {
+                /* Just like for normal operations, names expire at the
+                   earliest in the next block.  */
+                let blocks = max(blocks, T::BlockNumber::from(1));
+                let mut h = system::Module::<T>::block_number() + blocks;
+                if let Some(old) = data.expiration {
+                    h = min(h, old);
+                }
+                let data = NameData::<T> {
+                    expiration: Some(Self::schedule_expiration(&name, h)),
+                    ..data
+                };
+                <Names<T>>::insert(&name, &data);
+                Self::deposit_name_event(&name, RawEvent::NameUpdated(name.clone(), data));
+            },
+        }
+    }
+
+    /// Migrates queued names until the
+    /// [weight limit](Trait::MigrationWeightLimit) for the block is reached.
+    fn step_migration() {
+        let end = Self::migration_queue_end();
+        let mut cursor = Self::migration_cursor();
+        if cursor >= end {
+            return;
+        }
+
+        let limit = T::MigrationWeightLimit::get();
+        let mut used: Weight = 0;
+        let mut processed = 0u32;
+        while cursor < end && (processed == 0 || used < limit) {
+            if let Some(name) = <MigrationQueue<T>>::take(cursor) {
+                used = used.saturating_add(T::Migration::migrate_name(&name));
+            }
+            cursor += 1;
+            processed += 1;
+        }
+
+        /* Once the queue is empty, reset it so that positions start
+           from zero again for the next migration.  */
+        if cursor >= end {
+            <MigrationCursor>::kill();
+            <MigrationQueueEnd>::kill();
+        } else {
+            <MigrationCursor>::put(cursor);
+        }
+        debug::debug!(target: LOG_TARGET, "migrated {} names with weight {}, {} left",
+                      processed, used, end - cursor);
+        Self::deposit_event(RawEvent::NamesMigrated(processed, end - cursor));
+    }
+
+    /// Adds a newly registered name to the namehash index (if it should
+    /// be indexed at all).
+    fn add_to_namehash_index(name: &T::Name) {
+        if let Some(hash) = T::Namehash::namehash(name) {
+            <NamehashIndex<T>>::insert(hash, name);
+        }
+    }
+
+    /// Processes the name expirations for the given block number, up to
+    /// the [automatic limit](Trait::MaxAutoExpirations).
+    fn expire_names(h: T::BlockNumber) {
+        let (expired, stale, left) = Self::process_expirations(h, T::MaxAutoExpirations::get());
+        debug::debug!(target: LOG_TARGET,
+                      "block {:?}: {} names expired, {} stale index entries, {} left",
+                      h, expired, stale, left);
+        if expired > 0 || stale > 0 || left > 0 {
+            Self::deposit_event(RawEvent::ExpirationsProcessed(expired, stale, left));
+        }
+    }
+
+    /// Processes up to `limit` (or all, if `None`) entries of the
+    /// expiration index for block `h`.  Entries beyond the limit stay in
+    /// the index.  Returns the number of names that expired, the number
+    /// of stale entries and the number of entries left.
+    fn process_expirations(h: T::BlockNumber, limit: Option<u32>) -> (u32, u32, u32) {
+        let mut entries = <ExpirationQueue<T>>::take(h);
+        let left = match limit {
+            Some(n) if (n as usize) < entries.len() => entries.split_off(n as usize),
+            _ => Vec::new(),
+        };
+        if !left.is_empty() {
+            <ExpirationQueue<T>>::insert(h, &left);
+        }
+
+        let mut expired = 0u32;
+        let mut stale = 0u32;
+        for nm in entries {
+            let data = match <Names<T>>::get(&nm) {
+                None => {
+                    stale += 1;
+                    continue;
+                },
+                Some(data) => data,
+            };
+            match data.expiration {
+                /* Names are normally processed exactly at their expiration
+                   height.  But entries that were left over and are swept
+                   later may belong to names that expired even earlier (and
+                   have another index entry there).  Those expire right
+                   away as well, at their actual height.  */
+                Some(expiration_height) if expiration_height <= h => {
+                    debug::trace!(target: LOG_TARGET, "name {:?} expired",
+                                  T::Hashing::hash_of(&nm));
+                    Self::handle_expired_name(nm, data, expiration_height);
+                    expired += 1;
+                },
+                _ => stale += 1,
+            }
+        }
+
+        if expired > 0 {
+            Self::record_statistics(|stats| {
+                stats.expirations = stats.expirations.saturating_add(expired);
+            });
+        }
+        (expired, stale, left.len() as u32)
+    }
+
+    /// Renews an expired name on behalf of its owner with the fee paid
+    /// by the [insurance pool](Module::insurance_pool), if the name has
+    /// insured renewals left.  Returns true if the name was renewed.
+    fn claim_insurance(name: &T::Name, data: &NameData<T>) -> bool {
+        let remaining = Self::insured_renewals(name);
+        if remaining == 0 {
+            return false;
+        }
+
+        let res = Self::check_sponsored(data.owner.clone(), Self::insurance_pool(),
+                                        name.clone(), None, None)
+            .and_then(|op| Self::execute(op).map_err(|_| "renewal failed"));
+        if let Err(err) = res {
+            debug::debug!(target: LOG_TARGET, "insured renewal of {:?} failed: {}",
+                          T::Hashing::hash_of(name), err);
+            return false;
+        }
+
+        if remaining > 1 {
+            <InsuredRenewals<T>>::insert(name, remaining - 1);
+        } else {
+            <InsuredRenewals<T>>::remove(name);
+        }
+        Self::deposit_event(RawEvent::InsuranceClaimed(name.clone(), remaining - 1));
+        true
+    }
+
+    /// Applies the [configured action](Trait::OnExpiration) to a name that
+    /// has reached its expiration height `h`.  Names whose grace period
+    /// ends are always removed.  `h` may be in the past if the expiration
+    /// is [swept](Module::sweep_expirations) late.  Follow-up work (like
+    /// the end of a grace period or quarantine) is then scheduled relative
+    /// to `h` as usual, but never before the next block.
+    fn handle_expired_name(name: T::Name, data: NameData<T>, h: T::BlockNumber) {
+        if Self::claim_insurance(&name, &data) {
+            return;
+        }
+
+        let action = if Self::in_grace_period(&name) {
+            ExpirationAction::Delete
+        } else {
+            T::OnExpiration::expiration_action(&name, &data)
+        };
+
+        match action {
+            ExpirationAction::Delete => Self::remove_expired_name(&name, &data, h),
+            ExpirationAction::Grace(blocks) => {
+                let blocks = max(blocks, T::BlockNumber::from(1));
+                let until = Self::schedule_expiration(&name, Self::follow_up_height(h + blocks));
+                let data = NameData::<T> {
+                    expiration: Some(until),
+                    ..data
+                };
+                <Names<T>>::insert(&name, &data);
+                <InGracePeriod<T>>::insert(&name, true);
+                Self::deposit_event(RawEvent::NameInGracePeriod(name, until));
+            },
+            ExpirationAction::Transfer(recipient) => {
+                Self::execute_automatic_transfer(name, data, recipient);
+            },
+        }
+    }
+
+    /// Removes a name that expired at the given block, and does the
+    /// follow-up work of the expiration (backorders, quarantine and the
+    /// archive).  Unlike [`handle_expired_name`](Module::handle_expired_name),
+    /// this neither claims insurance nor asks the expiration policy, so the
+    /// name is removed in any case.
+    fn remove_expired_name(name: &T::Name, data: &NameData<T>, h: T::BlockNumber) {
+        Self::remove_name(name);
+        Self::deposit_name_event(name, RawEvent::NameExpired(name.clone()));
+        Self::exercise_backorders(name);
+        Self::quarantine(name, h);
+        Self::archive(name, data, h);
+    }
+
+    /// Returns the height at which follow-up work for an expiration should
+    /// be scheduled, given the intended height.  This is the intended
+    /// height, unless it is not after the current block (which happens for
+    /// late sweeps).  Then it is the next block, since work scheduled for
+    /// past blocks would never be processed.
+    fn follow_up_height(h: T::BlockNumber) -> T::BlockNumber {
+        max(h, system::Module::<T>::block_number() + T::BlockNumber::from(1))
+    }
+
+    /// Adds a name to the expiration queue for the given block (rounded up
+    /// to the [bucket size](Trait::ExpirationBucketSize)), or the
+    /// next block after it that has space left.  Returns the block at
+    /// which the name will actually expire.
+    fn schedule_expiration(name: &T::Name, h: T::BlockNumber) -> T::BlockNumber {
+        let limit = max(T::MaxExpirationsPerBlock::get(), 1) as usize;
+        let mut h = h;
+        let bucket = T::ExpirationBucketSize::get();
+        if bucket > T::BlockNumber::from(1) {
+            let rem = h % bucket;
+            if !rem.is_zero() {
+                h = h + (bucket - rem);
+            }
+        }
+        loop {
+            let mut names = <ExpirationQueue<T>>::get(h);
+            if names.contains(name) {
+                return h;
+            }
+            if names.len() < limit {
+                names.push(name.clone());
+                <ExpirationQueue<T>>::insert(h, names);
+                return h;
+            }
+            h = h + T::BlockNumber::from(1);
+        }
+    }
+
+    /// Decides whether an operation on an existing name keeps the old
+    /// expiration instead of the one from the policy (given as number of
+    /// blocks), according to [`ExpirationUpdates`](Trait::ExpirationUpdates).
+    /// Returns the old expiration to keep, if so.  An old expiration that
+    /// is not in the future (e.g. during a grace period) is never kept.
+    fn keep_expiration(op: &Operation<T>, blocks: Option<T::BlockNumber>)
+        -> Option<Option<T::BlockNumber>>
+    {
+        let old = match &op.old_data {
+            None => return None,
+            Some(data) => data.expiration,
+        };
+        let h = system::Module::<T>::block_number();
+        if let Some(old_height) = old {
+            if old_height <= h {
+                return None;
+            }
+        }
+
+        match T::ExpirationUpdates::get() {
+            ExpirationUpdate::MayShorten => None,
+            ExpirationUpdate::Preserve if !op.renewal => Some(old),
+            ExpirationUpdate::Preserve => None,
+            ExpirationUpdate::ExtendOnly => match (old, blocks) {
+                (None, _) => Some(None),
+                (Some(_), None) => None,
+                (Some(old_height), Some(b)) => {
+                    if h + max(b, T::BlockNumber::from(1)) > old_height {
+                        None
+                    } else {
+                        Some(old)
+                    }
+                },
+            },
+        }
+    }
+
+    /// Marks the first value update of a name as done, if it was still
+    /// pending.  The held back part of the registration fee is paid.
+    fn complete_first_update(name: &T::Name) {
+        if let Some(pending) = <FirstUpdates<T>>::take(name) {
+            <FirstUpdateDeadlines<T>>::remove(pending.deadline, name);
+            T::Payment::settle(&pending.payer, pending.held);
+        }
+    }
+
+    /// Releases all names whose deadline for the
+    /// [first value update](Trait::FirstUpdatePeriod) is the given block
+    /// height, refunding the held back part of their registration fee.
+    fn release_unused_names(h: T::BlockNumber) {
+        for name in <FirstUpdateDeadlines<T>>::iter_prefix(h) {
+            match <FirstUpdates<T>>::get(&name) {
+                Some(pending) if pending.deadline == h => {
+                    <FirstUpdates<T>>::remove(&name);
+                    T::Payment::refund(&pending.payer, pending.held);
+                    Self::remove_name(&name);
+                    Self::deposit_event(RawEvent::NameReleased(name, pending.held));
+                },
+                _ => (),
+            }
+        }
+        <FirstUpdateDeadlines<T>>::remove_prefix(h);
+    }
+
+    /// Checks the values of all names whose
+    /// [compliance window](Trait::ComplianceWindow) ends at the given block
+    /// height.  Names with a non-compliant value are reported, and expired
+    /// if [configured](Trait::NonComplianceAction).
+    fn check_compliance(h: T::BlockNumber) {
+        for name in <ComplianceChecks<T>>::iter_prefix(h) {
+            /* The name may have been removed and registered again since,
+               with a new deadline.  */
+            if Self::compliance_deadline(&name) != Some(h) {
+                continue;
+            }
+            let data = match <Names<T>>::get(&name) {
+                None => continue,
+                Some(data) => data,
+            };
+            if T::ValueCompliance::is_compliant(&name, &data.value) {
+                continue;
+            }
+
+            let expire = T::NonComplianceAction::get() == ComplianceAction::Expire;
+            Self::deposit_event(RawEvent::ValueNotCompliant(name.clone(), expire));
+            if expire {
+                Self::remove_expired_name(&name, &data, h);
+            }
+        }
+        <ComplianceChecks<T>>::remove_prefix(h);
+    }
+
+    /// Puts a name that has just expired into
+    /// [quarantine](Trait::ExpirationQuarantine), unless it has been
+    /// registered again already through a backorder.
+    fn quarantine(name: &T::Name, h: T::BlockNumber) {
+        let blocks = T::ExpirationQuarantine::get();
+        if blocks.is_zero() || <Names<T>>::get(name).is_some() {
+            return;
+        }
+
+        let until = Self::follow_up_height(h + blocks);
+        <QuarantinedUntil<T>>::insert(name, until);
+        <QuarantineEnds<T>>::insert(until, name, name);
+    }
+
+    /// Stores a tombstone for a name that expired at the given block,
+    /// replacing any earlier one, if [archival](Trait::ArchiveRetention)
+    /// is enabled.
+    fn archive(name: &T::Name, data: &NameData<T>, h: T::BlockNumber) {
+        let retention = match T::ArchiveRetention::get() {
+            None => return,
+            Some(blocks) => blocks,
+        };
+
+        <Archive<T>>::insert(name, Tombstone {
+            owner: data.owner.clone(),
+            value_hash: T::Hashing::hash_of(&data.value),
+            expired_at: h,
+        });
+        <ArchivePruning<T>>::insert(Self::follow_up_height(h + retention), name,
+                                    (name.clone(), h));
+    }
+
+    /// Removes all tombstones whose retention period ends at the given
+    /// block.  Tombstones that were replaced by a later expiration of the
+    /// same name are kept.
+    fn prune_archive(h: T::BlockNumber) {
+        for (name, expired_at) in <ArchivePruning<T>>::iter_prefix(h) {
+            if Self::archived(&name).map(|t| t.expired_at) == Some(expired_at) {
+                <Archive<T>>::remove(&name);
+            }
+        }
+        <ArchivePruning<T>>::remove_prefix(h);
+    }
+
+    /// Ends the quarantine of all names whose quarantine ends at the given
+    /// block number, so that they can be registered again.
+    fn end_quarantines(h: T::BlockNumber) {
+        for name in <QuarantineEnds<T>>::iter_prefix(h) {
+            if Self::quarantined_until(&name) == Some(h) {
+                <QuarantinedUntil<T>>::remove(&name);
+            }
+        }
+        <QuarantineEnds<T>>::remove_prefix(h);
+    }
+
+    /// Removes all hidden registrations that expire at the given
+    /// block number.
+    fn expire_hidden(h: T::BlockNumber) {
+        for hash in <HiddenExpirations<T>>::iter_prefix(h) {
+            /* The hidden registration may have been revealed (and thus
+               removed) already.  Otherwise it expires now, since hidden
+               registrations are never renewed.  */
+            if <HiddenNames<T>>::get(&hash).is_some() {
+                <HiddenNames<T>>::remove(&hash);
+                Self::deposit_event(RawEvent::HiddenRegistrationExpired(hash));
+            }
+        }
+        <HiddenExpirations<T>>::remove_prefix(h);
+    }
+
+    /// Processes the backorders of a name that has just been removed.  The
+    /// first backorderer (in priority order) for which the registration
+    /// is valid gets the name and pays their bond as fee.  All other
+    /// bonds are released.
+    fn exercise_backorders(name: &T::Name) {
+        let mut claimed = false;
+        for (who, bond) in <Backorders<T>>::take(name) {
+            if !claimed {
+                claimed = match Self::check_assuming_signed(who.clone(), name.clone(),
+                                                            None, None) {
+                    Err(_) => false,
+                    Ok(op) => Self::execute(op).is_ok(),
+                };
+                if claimed {
+                    T::Payment::settle(&who, bond);
+                    Self::deposit_event(RawEvent::BackorderExercised(name.clone(), who));
+                    continue;
+                }
+            }
+
+            T::Payment::refund(&who, bond);
+            Self::deposit_event(RawEvent::BackorderRefunded(name.clone(), who, bond));
+        }
+    }
+
+    /// Removes all preorders that expire at the given block number (and
+    /// have not been exercised), forfeiting part of their deposit.
+    fn expire_preorders(h: T::BlockNumber) {
+        for hash in <PreorderExpirations<T>>::iter_prefix(h) {
+            if let Some(preorder) = <Preorders<T>>::get(&hash) {
+                let forfeit = T::PreorderForfeit::get() * preorder.deposit;
+                T::Payment::settle(&preorder.owner, forfeit);
+                T::Payment::refund(&preorder.owner, preorder.deposit - forfeit);
+
+                <Preorders<T>>::remove(&hash);
+                Self::deposit_event(RawEvent::PreorderExpired(hash));
+            }
+        }
+        <PreorderExpirations<T>>::remove_prefix(h);
+    }
+
+}
+
+/// Origin check that succeeds for signed origins from the current owner of
+/// a fixed name (e.g. a DAO's name), and returns the owner account.  This
+/// can be used as `EnsureOrigin` in the configuration of other pallets.
+pub struct EnsureNameOwner<T, Name>(PhantomData<(T, Name)>);
+
+impl<T, Name, O> EnsureOrigin<O> for EnsureNameOwner<T, Name>
+    where T: Trait, Name: Get<T::Name>,
+          O: Into<Result<RawOrigin<T::AccountId>, O>> + From<RawOrigin<T::AccountId>>
+{
+    type Success = T::AccountId;
+    fn try_origin(o: O) -> Result<Self::Success, O> {
+        o.into().and_then(|o| match o {
+            RawOrigin::Signed(who) => {
+                match <Names<T>>::get(Name::get()) {
+                    Some(ref data) if data.owner == who => Ok(who),
+                    _ => Err(O::from(RawOrigin::Signed(who))),
+                }
+            },
+            r => Err(O::from(r)),
+        })
+    }
+}
+
+impl<T: Trait> OnFreeBalanceZero<T::AccountId> for Module<T> {
+    fn on_free_balance_zero(who: &T::AccountId) {
+        for name in Self::names_of(who) {
+            if let Some(data) = <Names<T>>::get(&name) {
+                Self::handle_reaped_name(name, data);
+            }
+        }
+    }
+}
+
+impl<T: Trait> NameLocks<T::Name> for Module<T> {
+    fn set_external_lock(name: &T::Name, id: LockIdentifier) -> DispatchResult {
+        ensure!(<Names<T>>::get(name).is_some(), "name does not exist");
+        let mut locks = Self::external_locks(name);
+        if !locks.contains(&id) {
+            locks.push(id);
+            <ExternalLocks<T>>::insert(name, locks);
+            Self::deposit_event(RawEvent::ExternalLockSet(name.clone(), id));
+        }
+        Ok(())
+    }
+
+    fn remove_external_lock(name: &T::Name, id: LockIdentifier) {
+        let mut locks = Self::external_locks(name);
+        let len = locks.len();
+        locks.retain(|l| *l != id);
+        if locks.len() == len {
+            return;
+        }
+        if locks.is_empty() {
+            <ExternalLocks<T>>::remove(name);
+        } else {
+            <ExternalLocks<T>>::insert(name, locks);
+        }
+        Self::deposit_event(RawEvent::ExternalLockRemoved(name.clone(), id));
+    }
+
+    fn is_externally_locked(name: &T::Name) -> bool {
+        <ExternalLocks<T>>::exists(name)
+    }
+}
+
+/// Helpers for classifying calls of the module.  These can be used by the
+/// runtime to build call filters, e.g. for proxy types that are allowed to
+/// manage names on behalf of an account, but only in a restricted way.
+impl<T: Trait> Call<T> {
+
+    /// Returns the name updated by the call if it is one of the basic
+    /// operations that register a name when it does not exist yet.
+    pub fn registered_name(&self) -> Option<&T::Name> {
+        match self {
+            Call::update(name, ..) => Some(name),
+            Call::update_via_registrar(name, ..) => Some(name),
+            Call::register(name, ..) => Some(name),
+            Call::register_attested(name, ..) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the call is any operation that changes names
+    /// owned by the sender (including transfers).
+    pub fn is_name_management_call(&self) -> bool {
+        match self {
+            Call::update(..) => true,
+            Call::update_via_registrar(..) => true,
+            Call::register(..) => true,
+            Call::register_attested(..) => true,
+            Call::set_value(..) => true,
+            Call::renew(..) => true,
+            Call::set_value_at_nonce(..) => true,
+            Call::update_patch(..) => true,
+            Call::schedule_value(..) => true,
+            Call::cancel_scheduled_value(..) => true,
+            Call::set_subvalue(..) => true,
+            Call::clear_subvalue(..) => true,
+            Call::publish_envelope(..) => true,
+            Call::remove_envelope(..) => true,
+            Call::watch_name(..) => true,
+            Call::unwatch_name(..) => true,
+            Call::transfer(..) => true,
+            Call::transfer_existing(..) => true,
+            Call::set_transfer_preference(..) => true,
+            Call::claim_name(..) => true,
+            Call::cancel_transfer(..) => true,
+            Call::block_sender(..) => true,
+            Call::unblock_sender(..) => true,
+            Call::set_block_all_transfers(..) => true,
+            Call::transfer_all_names(..) => true,
+            Call::donate_name(..) => true,
+            Call::lock_as_collateral(..) => true,
+            Call::propose_swap(..) => true,
+            Call::cancel_swap(..) => true,
+            Call::accept_swap(..) => true,
+            Call::set_payout(..) => true,
+            Call::approve_operator(..) => true,
+            Call::offer_rent_to_own(..) => true,
+            Call::split_into_shares(..) => true,
+            Call::transfer_shares(..) => true,
+            Call::propose_value(..) => true,
+            Call::approve_value(..) => true,
+            Call::reclaim_name(..) => true,
+            Call::register_hidden(..) => true,
+            Call::reveal_name(..) => true,
+            Call::register_intent(..) => true,
+            Call::preorder(..) => true,
+            Call::place_backorder(..) => true,
+            Call::cancel_backorder(..) => true,
+            Call::insure_name(..) => true,
+            Call::sponsored_update(..) => true,
+            Call::execute_signed_operation(..) => true,
+            Call::execute_signed_batch(..) => true,
+            Call::set_signing_key(..) => true,
+            Call::set_controller(..) => true,
+            Call::set_sale_ready(..) => true,
+            Call::set_note(..) => true,
+            Call::set_redirect(..) => true,
+            Call::set_guardian(..) => true,
+            Call::request_guarded_transfer(..) => true,
+            Call::confirm_guarded_transfer(..) => true,
+            Call::cancel_guarded_transfer(..) => true,
+            Call::set_retention_policy(..) => true,
+            Call::authorize_session_key(..) => true,
+            Call::revoke_session_key(..) => true,
+            Call::session_update(..) => true,
+            Call::link_identity(..) => true,
+            Call::unlink_identity(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the call is cheap to execute, i.e. only reads and
+    /// writes a small, constant number of storage entries and does not
+    /// perform a name operation (with fees and expiration scheduling).
+    pub fn is_read_cheap(&self) -> bool {
+        self.properties().0
+    }
+
+    /// Returns true if the call may withdraw, reserve or move funds of
+    /// any account (e.g. for name fees or deposits).  Calls that dispatch
+    /// other calls count as touching currency.
+    pub fn touches_currency(&self) -> bool {
+        self.properties().1
+    }
+
+    /// Returns true if the call can only be made by the
+    /// [force origin](Trait::ForceOrigin), i.e. is a governance operation.
+    pub fn is_admin(&self) -> bool {
+        self.properties().2
+    }
+
+    /// Returns the classification of the call as `(cheap, currency, admin)`.
+    /// This matches every call explicitly (without a catch-all arm), so
+    /// that new calls cannot be added without classifying them.
+    fn properties(&self) -> (bool, bool, bool) {
+        match self {
+            Call::update(..) => (false, true, false),
+            Call::update_via_registrar(..) => (false, true, false),
+            Call::register(..) => (false, true, false),
+            Call::register_attested(..) => (false, true, false),
+            Call::set_value(..) => (false, true, false),
+            Call::renew(..) => (false, true, false),
+            Call::set_value_at_nonce(..) => (false, true, false),
+            Call::update_patch(..) => (false, true, false),
+            Call::schedule_value(..) => (true, true, false),
+            Call::cancel_scheduled_value(..) => (true, false, false),
+            Call::set_subvalue(..) => (true, true, false),
+            Call::clear_subvalue(..) => (true, true, false),
+            Call::publish_envelope(..) => (true, true, false),
+            Call::remove_envelope(..) => (true, false, false),
+            Call::watch_name(..) => (true, true, false),
+            Call::unwatch_name(..) => (true, true, false),
+            Call::transfer(..) => (false, true, false),
+            Call::transfer_existing(..) => (false, true, false),
+            Call::set_transfer_preference(..) => (true, false, false),
+            Call::block_sender(..) => (true, false, false),
+            Call::unblock_sender(..) => (true, false, false),
+            Call::set_block_all_transfers(..) => (true, false, false),
+            Call::claim_name(..) => (false, true, false),
+            Call::cancel_transfer(..) => (true, false, false),
+            Call::donate_name(..) => (false, true, false),
+            Call::lock_as_collateral(..) => (true, false, false),
+            Call::release_collateral(..) => (true, false, false),
+            Call::claim_collateral(..) => (false, true, false),
+            Call::propose_swap(..) => (false, true, false),
+            Call::cancel_swap(..) => (true, true, false),
+            Call::accept_swap(..) => (false, true, false),
+            Call::set_payout(..) => (true, false, false),
+            Call::pay_name(..) => (true, true, false),
+            Call::approve_operator(..) => (true, false, false),
+            Call::operator_update(..) => (false, true, false),
+            Call::offer_rent_to_own(..) => (true, false, false),
+            Call::pay_installment(..) => (false, true, false),
+            Call::split_into_shares(..) => (false, true, false),
+            Call::transfer_shares(..) => (true, false, false),
+            Call::propose_value(..) => (false, true, false),
+            Call::approve_value(..) => (false, true, false),
+            Call::reclaim_name(..) => (false, true, false),
+            Call::dispatch_as_name(..) => (false, true, false),
+            Call::repair_index(..) => (true, false, false),
+            Call::transfer_all_names(..) => (false, true, false),
+            Call::register_hidden(..) => (true, true, false),
+            Call::reveal_name(..) => (false, true, false),
+            Call::set_signing_key(..) => (true, false, false),
+            Call::set_controller(..) => (true, false, false),
+            Call::set_sale_ready(..) => (true, false, false),
+            Call::set_note(..) => (true, true, false),
+            Call::set_redirect(..) => (true, false, false),
+            Call::set_guardian(..) => (true, false, false),
+            Call::request_guarded_transfer(..) => (true, false, false),
+            Call::confirm_guarded_transfer(..) => (false, true, false),
+            Call::cancel_guarded_transfer(..) => (true, false, false),
+            Call::set_retention_policy(..) => (true, false, false),
+            Call::authorize_session_key(..) => (true, false, false),
+            Call::revoke_session_key(..) => (true, false, false),
+            Call::session_update(..) => (false, true, false),
+            Call::link_identity(..) => (true, false, false),
+            Call::unlink_identity(..) => (true, false, false),
+            Call::force_transfer(..) => (false, true, true),
+            Call::report_name(..) => (true, true, false),
+            Call::decide_report(..) => (false, true, true),
+            Call::force_remove(..) => (false, true, true),
+            Call::force_register_batch(..) => (false, false, true),
+            Call::force_register(..) => (false, false, true),
+            Call::queue_migration(..) => (false, false, true),
+            Call::force_total_names(..) => (true, false, true),
+            Call::set_namespace_registrar(..) => (true, false, true),
+            Call::set_topic_prefixes(..) => (true, false, true),
+            Call::set_attestation_oracle(..) => (true, false, true),
+            Call::set_account_fee_exemption(..) => (true, false, true),
+            Call::set_name_fee_exemption(..) => (true, false, true),
+            Call::delegate_namespace(..) => (true, false, true),
+            Call::freeze_name(..) => (true, false, true),
+            Call::renew_namespace(..) => (false, true, false),
+            Call::set_system_name(..) => (false, false, true),
+            Call::import_names(..) => (false, false, true),
+            Call::register_intent(..) => (false, true, false),
+            Call::preorder(..) => (true, true, false),
+            Call::place_backorder(..) => (true, true, false),
+            Call::cancel_backorder(..) => (true, true, false),
+            Call::insure_name(..) => (true, true, false),
+            Call::set_insurance_premium(..) => (true, false, true),
+            Call::sponsored_update(..) => (false, true, false),
+            Call::execute_signed_operation(..) => (false, true, false),
+            Call::execute_signed_batch(..) => (false, true, false),
+            Call::sweep_expirations(..) => (false, true, false),
+            Call::__PhantomItem(..) => unreachable!("__PhantomItem should never be used"),
+        }
+    }
+
+    /// Returns true if the call only updates the value of a name, and
+    /// never changes its ownership.  A proxy restricted to this can keep
+    /// the names of an account up-to-date, but cannot give them away.
+    ///
+    /// Note that this includes registrations of new names through
+    /// [`update`](Module::update) and [`register`](Module::register),
+    /// which are always made to the sender.
+    pub fn is_value_update_call(&self) -> bool {
+        match self {
+            Call::update(..) => true,
+            Call::update_via_registrar(..) => true,
+            Call::register(..) => true,
+            Call::register_attested(..) => true,
+            Call::set_value(..) => true,
+            Call::set_value_at_nonce(..) => true,
+            Call::update_patch(..) => true,
+            Call::schedule_value(..) => true,
+            Call::set_subvalue(..) => true,
+            Call::clear_subvalue(..) => true,
+            _ => false,
+        }
+    }
+
+}
+
+/// Signed extension that enforces the
+/// [per-block limit of registrations](Trait::MaxRegistrationsPerBlock)
+/// already in the transaction pool.  Registrations that would exceed it are
+/// [invalid](Module::validate_registration) until the next block, so that
+/// block authors do not waste space on them.  Add this to the runtime's
+/// `SignedExtra` to use it.
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct LimitRegistrations<T: Trait + Send + Sync>(PhantomData<T>);
+
+impl<T: Trait + Send + Sync> LimitRegistrations<T> {
+    /// Constructs the extension.
+    pub fn new() -> Self {
+        LimitRegistrations(PhantomData)
+    }
+}
+
+impl<T: Trait + Send + Sync> Debug for LimitRegistrations<T> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        write!(f, "LimitRegistrations")
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+        Ok(())
+    }
+}
+
+impl<T: Trait + Send + Sync> SignedExtension for LimitRegistrations<T>
+    where <T as system::Trait>::Call: IsSubType<Module<T>, T>
+{
+    const IDENTIFIER: &'static str = "LimitRegistrations";
+    type AccountId = T::AccountId;
+    type Call = <T as system::Trait>::Call;
+    type AdditionalSigned = ();
+    type DispatchInfo = DispatchInfo;
+    type Pre = ();
+
+    fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+        Ok(())
+    }
+
+    fn validate(&self, _who: &Self::AccountId, call: &Self::Call,
+                _info: Self::DispatchInfo, _len: usize) -> TransactionValidity {
+        match call.is_sub_type() {
+            Some(call) => Module::<T>::validate_registration(call),
+            None => Ok(ValidTransaction::default()),
+        }
+    }
 }
 
 decl_event!(
-    pub enum Event<T> where Name = <T as Trait>::Name, NameData = NameData<T> {
+    pub enum Event<T> where Name = <T as Trait>::Name, Value = <T as Trait>::Value,
+                            NameData = NameData<T>,
+                            Hash = <T as system::Trait>::Hash,
+                            AccountId = <T as system::Trait>::AccountId,
+                            BlockNumber = <T as system::Trait>::BlockNumber,
+                            SubKey = <T as Trait>::SubKey,
+                            Balance = BalanceOf<T> {
         /// Event when a name is newly created.
         NameRegistered(Name),
         /// Event when a name is updated (or created).
         NameUpdated(Name, NameData),
+        /// Event when a name is transferred from one owner to another
+        /// (without changing its value).
+        NameTransferred(Name, AccountId, AccountId),
+        /// Event when a name has been [donated](Module::donate_name) by the
+        /// given account to the community.  The name no longer expires,
+        /// even if the preceding `NameUpdated` event says otherwise.
+        NameDonated(Name, AccountId),
+        /// Event when a name is locked as collateral for a beneficiary.
+        CollateralLocked(Name, AccountId),
+        /// Event when the collateral lock on a name is released.
+        CollateralReleased(Name),
+        /// Event when the beneficiary seizes a name locked as collateral.
+        CollateralClaimed(Name, AccountId),
+        /// Event when an account proposes a swap of names with the
+        /// given counterparty.
+        SwapProposed(AccountId, AccountId),
+        /// Event when an account cancels its swap offer.
+        SwapCancelled(AccountId),
+        /// Event when the counterparty accepts a swap offer, with the
+        /// proposer and the counterparty.
+        SwapCompleted(AccountId, AccountId),
+        /// Event when the payout configuration of a name is changed.
+        PayoutChanged(Name),
+        /// Event when an account pays the given amount to a name.
+        NamePaid(Name, AccountId, Balance),
+        /// Event when the standing approval of a name for an operator is
+        /// changed (or revoked).
+        OperatorApproved(Name, AccountId),
+        /// Event when a rent-to-own agreement for a name is started with
+        /// the given tenant.
+        RentToOwnStarted(Name, AccountId),
+        /// Event when an installment is paid, with the number of remaining
+        /// installments.
+        InstallmentPaid(Name, u32),
+        /// Event when the last installment is paid and the name
+        /// transferred to the tenant.
+        RentToOwnCompleted(Name, AccountId),
+        /// Event when a rent-to-own agreement is cancelled because an
+        /// installment was missed.
+        RentToOwnCancelled(Name),
+        /// Event when a name is split into the given number of shares.
+        NameSplit(Name, u32),
+        /// Event when shares of a name are transferred (from, to, amount).
+        SharesTransferred(Name, AccountId, AccountId, u32),
+        /// Event when a new value is proposed for a name split into shares.
+        ValueProposed(Name, AccountId),
+        /// Event when a name split into shares is reclaimed by an account
+        /// holding all shares.
+        NameReclaimed(Name, AccountId),
         /// Event when a name expires and is removed from the database.
         NameExpired(Name),
+        /// Event when a name has expired, but is kept with its owner for
+        /// a [grace period](policy::ExpirationAction::Grace) until the
+        /// given block height.
+        NameInGracePeriod(Name, BlockNumber),
+        /// Event when the value of a name does not satisfy the
+        /// [content rules](Trait::ValueCompliance) at the end of its
+        /// compliance window, and whether the name is expired for it.
+        ValueNotCompliant(Name, bool),
+        /// Event when the expiration index for a block has been processed,
+        /// with the number of names that expired, the number of stale index
+        /// entries (e.g. for names that have been renewed) that were skipped,
+        /// and the number of entries left for processing in later blocks.
+        /// This is only emitted if there were any entries.
+        ExpirationsProcessed(u32, u32, u32),
+        /// Event when an account [swept](Module::sweep_expirations) the
+        /// expiration index of a block, with the number of entries processed,
+        /// the number still left and the bounty paid.
+        ExpirationsSwept(AccountId, BlockNumber, u32, u32, Balance),
+        /// Event when a block has processed part of the
+        /// [migration queue](Module::queue_migration), with the number of
+        /// names migrated and the number still queued.
+        NamesMigrated(u32, u32),
+        /// Event when the value of a name was truncated by the
+        /// [bounded-types migration](migration::TruncateOversized), with
+        /// the original value.
+        ValueTruncated(Name, Value),
+        /// Event when the [bounded-types migration](migration::TruncateOversized)
+        /// found a name that is too long and needs a governance decision.
+        OversizedName(Name),
+        /// Event when an operation on a name is [deferred](Module::enqueue),
+        /// with its position in the queue.
+        OperationDeferred(Name, u32),
+        /// Event when a runtime hook [rejected](Module::reject_operation)
+        /// an operation on a name, with the hook's reason code.
+        OperationRejected(Name, u32),
+        /// Event when a deferred operation on a name has been dropped,
+        /// because the name changed in the meantime or the execution failed.
+        DeferredOperationFailed(Name),
+        /// Event when a block has executed deferred operations, with the
+        /// number of operations processed and the number still queued.
+        DeferredOperationsProcessed(u32, u32),
+        /// Event when a name is reported as squatted by the given account.
+        NameReported(Name, AccountId),
+        /// Event when the report of a name has been decided, with whether
+        /// it was upheld (and the name thus removed).
+        ReportDecided(Name, bool),
+        /// Event when a name is released because it did not receive its
+        /// first value update in time, with the refunded amount.
+        NameReleased(Name, Balance),
+        /// Event when a name is forcibly removed from the database.
+        NameRemoved(Name),
+        /// Event when the [index entries](Module::repair_index) of a name
+        /// have been recomputed.
+        IndexRepaired(Name),
+        /// Event when a name is registered only by its hash.
+        NameRegisteredHidden(Hash),
+        /// Event when a hidden registration is revealed (and thus the
+        /// name registered for real).
+        NameRevealed(Hash, Name),
+        /// Event when a hidden registration has not been revealed in time
+        /// and is dropped.
+        HiddenRegistrationExpired(Hash),
+        /// Event when a name is preordered by its hash.
+        NamePreordered(Hash),
+        /// Event when a preordered name is registered by the preorderer.
+        PreorderExercised(Hash),
+        /// Event when a preorder has not been used in time and is dropped.
+        PreorderExpired(Hash),
+        /// Event when an account places a backorder on a name with the
+        /// given bond.
+        BackorderPlaced(Name, AccountId, Balance),
+        /// Event when a backorder is exercised, i.e. the name has been
+        /// registered for the backorderer.
+        BackorderExercised(Name, AccountId),
+        /// Event when a backorder is cancelled or not exercised, and its
+        /// bond is released.
+        BackorderRefunded(Name, AccountId, Balance),
+        /// Event when a name is insured, with its total number of insured
+        /// renewals and the premium paid.
+        NameInsured(Name, u32, Balance),
+        /// Event when the registrar of a namespace is set or cleared.
+        NamespaceRegistrarSet(Vec<u8>, Option<AccountId>),
+        /// Event when the [topic prefixes](Module::topic_prefixes) are set.
+        TopicPrefixesSet(Vec<Vec<u8>>),
+        /// Event when the oracle of an attested namespace is set or cleared.
+        AttestationOracleSet(Vec<u8>, Option<AccountId>),
+        /// Event when a name is registered with an oracle attestation,
+        /// which has the given hash.
+        NameAttested(Name, Hash),
+        /// Event when an account is added to (true) or removed from (false)
+        /// the fee exemption list.
+        AccountFeeExemptionSet(AccountId, bool),
+        /// Event when a name is added to (true) or removed from (false)
+        /// the fee exemption list.
+        NameFeeExemptionSet(Name, bool),
+        /// Event when the administration of a namespace is delegated to an
+        /// account (`true`) or revoked (`false`).
+        NamespaceDelegated(Vec<u8>, AccountId, bool),
+        /// Event when a name is frozen (`true`) or unfrozen (`false`).
+        NameFrozen(Name, bool),
+        /// Event when the registrar of an operation on a name is paid its
+        /// [commission](Trait::RegistrarCommission).
+        RegistrarCommissionPaid(Name, AccountId, Balance),
+        /// Event when the [retention policy](Module::set_retention_policy)
+        /// of a name is set.
+        RetentionPolicySet(Name, RetentionPolicy),
+        /// Event when a name changed owner and some of its data was cleared
+        /// according to its retention policy.  The flags say whether the
+        /// records, the operator and the insured renewals were cleared.
+        OwnerDataCleared(Name, bool, bool, bool),
+        /// Event when a registrar renews the given number of names in
+        /// its namespace.
+        NamespaceRenewed(Vec<u8>, u32),
+        /// Event when an expired name is renewed by the insurance pool,
+        /// with the number of insured renewals left.
+        InsuranceClaimed(Name, u32),
+        /// Event when the dedicated signing key of a name is set or cleared.
+        SigningKeyUpdated(Name, Option<AccountId>),
+        /// Event when the controller of a name is set or cleared.
+        ControllerUpdated(Name, Option<AccountId>),
+        /// Event when a name is marked as ready for sale (or the mark
+        /// is cleared).
+        SaleReadySet(Name, bool),
+        /// Event when another pallet places a lock with the given ID on a
+        /// name.
+        ExternalLockSet(Name, LockIdentifier),
+        /// Event when a lock of another pallet is removed from a name.
+        ExternalLockRemoved(Name, LockIdentifier),
+        /// Event when the owner of a name sets its note (`true`) or
+        /// clears it (`false`).  The note itself is not included.
+        NoteUpdated(Name, bool),
+        /// Event when the redirect of a name is set or cleared.
+        RedirectUpdated(Name, Option<Name>),
+        /// Event when the guardian of a name is set or cleared.
+        GuardianUpdated(Name, Option<AccountId>),
+        /// Event when the owner of a name with a guardian requests to
+        /// transfer it to the given recipient.  The guardian can confirm
+        /// until the given block.
+        GuardedTransferRequested(Name, AccountId, BlockNumber),
+        /// Event when the guardian of a name confirms its transfer to
+        /// the given recipient.
+        GuardedTransferConfirmed(Name, AccountId),
+        /// Event when a requested transfer of a name with a guardian
+        /// is cancelled.
+        GuardedTransferCancelled(Name),
+        /// Event when an owner authorises a session key until the given block.
+        SessionKeyAuthorized(AccountId, AccountId, BlockNumber),
+        /// Event when an owner revokes a session key.
+        SessionKeyRevoked(AccountId, AccountId),
+        /// Event when a name is linked to the identity of an account.
+        IdentityLinked(Name, AccountId),
+        /// Event when the identity link of a name is removed.
+        IdentityUnlinked(Name, AccountId),
+        /// Event when a number of names have been moved from one owner to
+        /// another by a [bulk transfer](Module::transfer_all_names).
+        NamesTransferred(AccountId, AccountId, u32),
+        /// Event when a name could not be moved by a
+        /// [bulk transfer](Module::transfer_all_names) and stays with its owner.
+        BulkTransferSkipped(Name),
+        /// Event when an intent to register a name is submitted.
+        RegistrationIntent(Name, AccountId),
+        /// Event when the registration intents for a name are settled,
+        /// with the account that won the name.
+        RegistrationIntentSettled(Name, AccountId),
+        /// Event when the item with the given index in a
+        /// [batch of signed operations](Module::execute_signed_batch) failed.
+        SignedOperationFailed(u32),
+        /// Event when a batch of signed operations has been processed, with
+        /// the number of items that were executed successfully.
+        SignedBatchExecuted(u32),
+        /// Event when a value update of a name is scheduled to take effect
+        /// at the given block.
+        ValueScheduled(Name, BlockNumber),
+        /// Event when the scheduled value of a name has taken effect.
+        ScheduledValueActivated(Name),
+        /// Event when the scheduled value of a name could not be applied
+        /// (e.g. because the owner cannot pay the fee) and is dropped.
+        ScheduledValueFailed(Name),
+        /// Event when a subvalue of a name is set.
+        SubvalueUpdated(Name, SubKey),
+        /// Event when a subvalue of a name is cleared.
+        SubvalueCleared(Name, SubKey),
+        /// Event when an envelope of a name for the given recipient key
+        /// is published.
+        EnvelopePublished(Name, H256),
+        /// Event for a watcher of a name when the name has changed.
+        NameWatchTriggered(AccountId, Name, NameChange),
+        /// Event when additional value deposit is reserved for a name.
+        ValueDepositReserved(Name, AccountId, Balance),
+        /// Event when (part of) the value deposit for a name is released.
+        ValueDepositReleased(Name, AccountId, Balance),
+        /// Event when a name is transferred to an account that requires
+        /// claiming, and the transfer is thus pending.
+        TransferPending(Name, AccountId),
+        /// Event when a pending transfer of a name is claimed.
+        TransferClaimed(Name, AccountId),
+        /// Event when an automatic transfer of a name to the given account
+        /// (e.g. on expiration or when settling a swap) failed and has
+        /// been dropped.
+        TransferFailed(Name, AccountId),
     }
 );
 
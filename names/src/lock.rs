@@ -0,0 +1,44 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Interface through which other pallets can lock names, e.g. an escrow
+//! or court pallet that needs a name to stay with its owner while it holds
+//! a case open.
+
+use frame_support::dispatch::DispatchResult;
+use frame_support::traits::LockIdentifier;
+
+/// Lets other pallets place locks on names.  While a name has any lock,
+/// it cannot be transferred.  Each lock is identified by the pallet that
+/// holds it, so that several pallets can lock the same name independently.
+///
+/// This is implemented by the names [module](crate::Module) and meant to
+/// be used as an associated type in the other pallet's trait.
+pub trait NameLocks<Name> {
+
+    /// Places the lock with the given ID on a name.  Fails if the name does
+    /// not exist.  Setting a lock that is already in place has no effect.
+    fn set_external_lock(name: &Name, id: LockIdentifier) -> DispatchResult;
+
+    /// Removes the lock with the given ID from a name, if it is set.
+    fn remove_external_lock(name: &Name, id: LockIdentifier);
+
+    /// Returns true if the name has any external lock.
+    fn is_externally_locked(name: &Name) -> bool;
+
+}
@@ -0,0 +1,163 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Storage migrations of names that are too large to run in a single block.
+//! Names that need migrating are [queued](crate::Module::queue_migration)
+//! (the pallet cannot enumerate its name map by itself), and are then
+//! processed in `on_initialize` of the following blocks, up to
+//! [a certain weight](crate::Trait::MigrationWeightLimit) per block.
+
+use crate::{BalanceOf, Expirations, Module, NameData, Names, RawEvent, Trait, ValueDeposits};
+use codec::Encode;
+use frame_support::{traits::Get, weights::Weight};
+use sp_runtime::traits::{Saturating, Zero};
+use sp_std::{marker::PhantomData, prelude::*};
+
+/// A migration that is applied to each queued name individually.
+pub trait NameMigration<T: Trait> {
+
+    /// Migrates the given name (if necessary), and returns the weight
+    /// this consumed.
+    fn migrate_name(name: &T::Name) -> Weight;
+
+}
+
+/// The unit type can be used if there is no migration.
+impl<T: Trait> NameMigration<T> for () {
+    fn migrate_name(_name: &T::Name) -> Weight {
+        0
+    }
+}
+
+/// Two migrations can be combined, in which case both are applied to
+/// each queued name in order.
+impl<T: Trait, A: NameMigration<T>, B: NameMigration<T>> NameMigration<T> for (A, B) {
+    fn migrate_name(name: &T::Name) -> Weight {
+        A::migrate_name(name).saturating_add(B::migrate_name(name))
+    }
+}
+
+/// Weight of migrating a single name with [`TruncateOversized`].
+const TRUNCATE_OVERSIZED_WEIGHT: Weight = 10_000;
+
+/// Migration that prepares the switch to length-bounded name and value
+/// types, for runtimes that use byte strings for both.  Values longer than
+/// the given limit are truncated to it, and the part of their
+/// [value deposit](crate::Trait::ValueDepositPerByte) that is no longer
+/// needed is released.  The original value is kept in the `ValueTruncated`
+/// event.
+///
+/// Names that are themselves longer than their limit cannot be re-encoded
+/// without changing their key.  They are left untouched and only reported
+/// with an `OversizedName` event, so that governance can decide how to deal
+/// with them before the types are switched.  Only names that have been
+/// [queued](crate::Module::queue_migration) by the
+/// [force origin](crate::Trait::ForceOrigin) are migrated at all.
+pub struct TruncateOversized<MaxName, MaxValue>(PhantomData<(MaxName, MaxValue)>);
+
+impl<T, MaxName, MaxValue> NameMigration<T> for TruncateOversized<MaxName, MaxValue>
+    where T: Trait<Name = Vec<u8>, Value = Vec<u8>>, MaxName: Get<u32>, MaxValue: Get<u32>
+{
+    fn migrate_name(name: &T::Name) -> Weight {
+        let mut data = match <Names<T>>::get(name) {
+            None => return TRUNCATE_OVERSIZED_WEIGHT,
+            Some(data) => data,
+        };
+        if name.len() > MaxName::get() as usize {
+            Module::<T>::deposit_event(RawEvent::OversizedName(name.clone()));
+            return TRUNCATE_OVERSIZED_WEIGHT;
+        }
+
+        let max = MaxValue::get() as usize;
+        if data.value.len() > max {
+            let original = data.value.clone();
+            data.value.truncate(max);
+            <Names<T>>::insert(name, &data);
+
+            if let Some((who, held)) = <ValueDeposits<T>>::get(name) {
+                let size = BalanceOf::<T>::from(data.value.encoded_size() as u32);
+                let amount = T::ValueDepositPerByte::get().saturating_mul(size);
+                if amount < held {
+                    Module::<T>::reconcile_value_deposit(name, &who, amount, Zero::zero());
+                }
+            }
+            Module::<T>::deposit_event(RawEvent::ValueTruncated(name.clone(), original));
+        }
+
+        TRUNCATE_OVERSIZED_WEIGHT
+    }
+}
+
+/// Weight of migrating a single name with [`RepairIndex`].
+const REPAIR_INDEX_WEIGHT: Weight = 10_000;
+
+/// Migration that [repairs the index entries](crate::Module::repair_index)
+/// of each queued name.  This can be used to rebuild the indices of all
+/// names if they have drifted.
+pub struct RepairIndex;
+
+impl<T: Trait> NameMigration<T> for RepairIndex {
+    fn migrate_name(name: &T::Name) -> Weight {
+        Module::<T>::repair_index_of(name, None);
+        REPAIR_INDEX_WEIGHT
+    }
+}
+
+/// Weight of migrating a single name with [`MigrateExpirations`].
+const MIGRATE_EXPIRATIONS_WEIGHT: Weight = 20_000;
+
+/// Migration that moves the entry of each queued name from the legacy
+/// expiration index into the [expiration queue](crate::Module::expiring_at).
+/// The legacy index is no longer processed, so all names with entries in it
+/// (which can be enumerated off-chain) have to be
+/// [queued](crate::Module::queue_migration) with the upgrade that
+/// introduces this migration.
+///
+/// Only the entry at the name's current expiration is moved.  Entries at
+/// other heights are stale and would have been ignored anyway.  If that
+/// height has passed already (because the migration got to the name late)
+/// or its queue is full, the name is scheduled for the next block with
+/// space instead, and its expiration is updated accordingly.
+pub struct MigrateExpirations;
+
+impl<T: Trait> NameMigration<T> for MigrateExpirations {
+    fn migrate_name(name: &T::Name) -> Weight {
+        let data = match <Names<T>>::get(name) {
+            None => return MIGRATE_EXPIRATIONS_WEIGHT,
+            Some(data) => data,
+        };
+        let h = match data.expiration {
+            Some(h) if <Expirations<T>>::exists(h, name) => h,
+            _ => return MIGRATE_EXPIRATIONS_WEIGHT,
+        };
+
+        <Expirations<T>>::remove(h, name);
+        let scheduled = Module::<T>::schedule_expiration(name, Module::<T>::follow_up_height(h));
+        if scheduled != h {
+            let data = NameData::<T> {
+                expiration: Some(scheduled),
+                ..data
+            };
+            <Names<T>>::insert(name, &data);
+            Module::<T>::deposit_name_event(name, RawEvent::NameUpdated(name.clone(), data));
+        }
+
+        MIGRATE_EXPIRATIONS_WEIGHT
+    }
+}
@@ -0,0 +1,67 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Support for a secondary index of names by their
+//! [ENS](https://ens.domains/) *namehash*.  This allows tools written for ENS
+//! (which address names by namehash) to resolve entries on the chain.
+
+use sp_core::H256;
+use sp_io::hashing::keccak_256;
+
+/// Computes the hash under which a name is put into the
+/// [namehash index](crate::Module::resolve_namehash).
+pub trait Namehash<Name> {
+
+    /// Returns the hash for the given name, or `None` if the name should
+    /// not be put into the index at all.
+    fn namehash(name: &Name) -> Option<H256>;
+
+}
+
+/// The unit type can be used to disable the namehash index.
+impl<Name> Namehash<Name> for () {
+    fn namehash(_name: &Name) -> Option<H256> {
+        None
+    }
+}
+
+/// The namehash algorithm as defined in
+/// [EIP-137](https://eips.ethereum.org/EIPS/eip-137) for ENS.  Names are
+/// split into labels at dots.  The labels are hashed as raw bytes, i.e.
+/// no normalisation is done.
+pub struct EnsNamehash;
+
+impl<Name: AsRef<[u8]>> Namehash<Name> for EnsNamehash {
+    fn namehash(name: &Name) -> Option<H256> {
+        let name = name.as_ref();
+        let mut node = [0u8; 32];
+        if name.is_empty() {
+            return Some(H256::from(node));
+        }
+
+        for label in name.rsplit(|c| *c == b'.') {
+            let mut data = [0u8; 64];
+            data[..32].copy_from_slice(&node);
+            data[32..].copy_from_slice(&keccak_256(label));
+            node = keccak_256(&data);
+        }
+
+        Some(H256::from(node))
+    }
+}
@@ -0,0 +1,48 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Matching of names against namespace prefixes, which is used for
+//! [reserved namespaces](crate::Module::reserved_prefixes).
+
+/// Determines whether a name falls into the namespace given by a prefix.
+pub trait NamespaceParser<Name> {
+
+    /// Returns true if the name is inside the namespace denoted by the
+    /// given prefix (e.g. `sys/`).
+    fn has_prefix(name: &Name, prefix: &[u8]) -> bool;
+
+}
+
+/// The unit type can be used if names have no notion of namespaces.  Then
+/// no name is ever inside a reserved namespace.
+impl<Name> NamespaceParser<Name> for () {
+    fn has_prefix(_name: &Name, _prefix: &[u8]) -> bool {
+        false
+    }
+}
+
+/// Namespaces for names that are byte strings, where a name is inside
+/// a namespace if its raw bytes start with the prefix.
+pub struct BytePrefix;
+
+impl<Name: AsRef<[u8]>> NamespaceParser<Name> for BytePrefix {
+    fn has_prefix(name: &Name, prefix: &[u8]) -> bool {
+        name.as_ref().starts_with(prefix)
+    }
+}
@@ -0,0 +1,39 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Optional support for partial updates of values.  For chains whose
+//! values are structured documents (e.g. JSON), this avoids having to
+//! upload the full value again just to change a single field.
+
+/// Merges a patch into an existing value for
+/// [patch updates](crate::Module::update_patch).  The format of patches
+/// is entirely up to the implementation, e.g. JSON merge patches.
+pub trait ValuePatch<Value> {
+
+    /// Applies the patch to the given value and returns the resulting
+    /// new value, or `None` if the patch is invalid.
+    fn apply_patch(value: &Value, patch: &[u8]) -> Option<Value>;
+
+}
+
+/// The unit type can be used to disable patch updates.
+impl<Value> ValuePatch<Value> for () {
+    fn apply_patch(_value: &Value, _patch: &[u8]) -> Option<Value> {
+        None
+    }
+}
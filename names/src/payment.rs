@@ -0,0 +1,119 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Payment of name fees.  By default fees are paid in the pallet's
+//! [currency](crate::Trait::Currency), but chains with other accounting
+//! (e.g. pure holds or a fungibles pallet) can replace this completely.
+
+use crate::{BalanceOf, OperationError, Trait};
+use crate::policy::NamePolicy;
+
+use frame_support::dispatch::DispatchResult;
+use frame_support::traits::{
+    Currency, ExistenceRequirement, Get, Imbalance, ReservableCurrency,
+    WithdrawReason, WithdrawReasons,
+};
+use sp_runtime::DispatchError;
+use sp_runtime::traits::{CheckedSub, Zero};
+
+/// How name fees are paid.  The pallet [checks](NamePayment::check) that
+/// the payer can afford the fee of an operation before it executes anything,
+/// and then either [withdraws](NamePayment::withdraw) the fee directly or
+/// [holds](NamePayment::hold) part of it until it is
+/// [settled](NamePayment::settle) or [refunded](NamePayment::refund)
+/// (e.g. for the [first update](crate::Trait::FirstUpdatePeriod)).
+///
+/// The unit type implements this with the pallet's currency, paying fees
+/// to the [name policy](NamePolicy::deposit_fee).
+pub trait NamePayment<T: Trait> {
+
+    /// Checks that the payer can pay a fee of the given amount.
+    fn check(payer: &T::AccountId, fee: BalanceOf<T>) -> Result<(), OperationError<T>>;
+
+    /// Takes a fee from the payer.  If a registrar is given, it receives
+    /// its [commission](crate::Trait::RegistrarCommission) out of the fee.
+    /// Returns the commission that was paid.
+    fn withdraw(payer: &T::AccountId, fee: BalanceOf<T>,
+                registrar: Option<&T::AccountId>) -> Result<BalanceOf<T>, DispatchError>;
+
+    /// Holds an amount of the payer, which is later either settled as fee
+    /// or refunded.
+    fn hold(payer: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult;
+
+    /// Pays an amount held from the payer as fee.
+    fn settle(payer: &T::AccountId, amount: BalanceOf<T>);
+
+    /// Returns an amount held from the payer back to it.
+    fn refund(payer: &T::AccountId, amount: BalanceOf<T>);
+
+}
+
+/// Returns a withdraw reasons value for the fee payment.
+fn withdraw_reasons() -> WithdrawReasons {
+    let mut res = WithdrawReasons::none();
+    res.set(WithdrawReason::Fee);
+    res
+}
+
+impl<T: Trait> NamePayment<T> for () {
+
+    fn check(payer: &T::AccountId, fee: BalanceOf<T>) -> Result<(), OperationError<T>> {
+        /* Note that ensure_can_withdraw does not by itself verify the
+           amount against the free balance, but just that the new balance
+           satisfies all locks in place.  Thus we have to do that ourselves.  */
+        let new_balance = match T::Currency::free_balance(payer).checked_sub(&fee) {
+            None => return Err(OperationError::RequiredFee(fee)),
+            Some(b) => b,
+        };
+        match T::Currency::ensure_can_withdraw(payer, fee, withdraw_reasons(), new_balance) {
+            Err(_) => Err("cannot withdraw name fee from sender".into()),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    fn withdraw(payer: &T::AccountId, fee: BalanceOf<T>,
+                registrar: Option<&T::AccountId>) -> Result<BalanceOf<T>, DispatchError> {
+        let mut imbalance = T::Currency::withdraw(payer, fee, withdraw_reasons(),
+                                                  ExistenceRequirement::AllowDeath)?;
+        let mut commission = Zero::zero();
+        if let Some(registrar) = registrar {
+            commission = T::RegistrarCommission::get() * imbalance.peek();
+            let (paid, rest) = imbalance.split(commission);
+            imbalance = rest;
+            if !commission.is_zero() {
+                T::Currency::resolve_creating(registrar, paid);
+            }
+        }
+        T::Policy::deposit_fee(imbalance);
+        Ok(commission)
+    }
+
+    fn hold(payer: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+        T::Currency::reserve(payer, amount)
+    }
+
+    fn settle(payer: &T::AccountId, amount: BalanceOf<T>) {
+        let (imbalance, _) = T::Currency::slash_reserved(payer, amount);
+        T::Policy::deposit_fee(imbalance);
+    }
+
+    fn refund(payer: &T::AccountId, amount: BalanceOf<T>) {
+        T::Currency::unreserve(payer, amount);
+    }
+
+}
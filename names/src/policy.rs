@@ -0,0 +1,458 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Name policies, i.e. the rules that determine which name operations are
+//! allowed, what fee they cost, when names expire and what happens to
+//! the fees paid.
+//!
+//! A runtime can either implement [`NamePolicy`] directly, or compose one
+//! from a [fee part](FeePolicy), an [expiration part](ExpirationPolicy) and
+//! an `OnUnbalanced` handler for the fees using [`Composed`].  This module
+//! provides some common building blocks for that.
+
+use crate::{BalanceOf, NameData, NegativeImbalanceOf, Operation, OperationType, Trait};
+use codec::Encode;
+use frame_support::traits::{Get, OnUnbalanced};
+use sp_runtime::traits::{Saturating, UniqueSaturatedInto, Zero};
+use sp_io::hashing::blake2_256;
+use sp_std::marker::PhantomData;
+
+/// The full policy for name operations.
+pub trait NamePolicy<T: Trait> {
+
+    /// Computes and returns the currency fee the sender has to pay for
+    /// a certain operation.  If `None` is returned, it means that the
+    /// operation is invalid (e.g. the name is too short).
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>>;
+
+    /// For a given name operation, computes the number of blocks before the
+    /// name will expire again.  If `None` is returned, then the name will
+    /// never expire.
+    fn get_expiration(op: &Operation<T>) -> Option<T::BlockNumber>;
+
+    /// "Takes ownership" of the fee paid for a name operation.  This
+    /// function can just do nothing to effectively burn the fee, it may
+    /// deposit it to a developer account, or it may give it out to miners.
+    fn deposit_fee(value: NegativeImbalanceOf<T>);
+
+}
+
+/// The part of a name policy that determines fees (and thus also which
+/// operations are valid at all).
+pub trait FeePolicy<T: Trait> {
+    /// See [`NamePolicy::get_name_fee`].
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>>;
+}
+
+/// The part of a name policy that determines expiration.
+pub trait ExpirationPolicy<T: Trait> {
+    /// See [`NamePolicy::get_expiration`].
+    fn get_expiration(op: &Operation<T>) -> Option<T::BlockNumber>;
+}
+
+/// A name policy composed of separate parts for fees, expiration and
+/// the handling of paid fees.  For instance, `()` can be used for the
+/// latter to burn all fees.
+pub struct Composed<Fee, Expiration, Deposit>(PhantomData<(Fee, Expiration, Deposit)>);
+
+impl<T, Fee, Expiration, Deposit> NamePolicy<T> for Composed<Fee, Expiration, Deposit>
+    where T: Trait, Fee: FeePolicy<T>, Expiration: ExpirationPolicy<T>,
+          Deposit: OnUnbalanced<NegativeImbalanceOf<T>>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        Fee::get_name_fee(op)
+    }
+
+    fn get_expiration(op: &Operation<T>) -> Option<T::BlockNumber> {
+        Expiration::get_expiration(op)
+    }
+
+    fn deposit_fee(value: NegativeImbalanceOf<T>) {
+        Deposit::on_unbalanced(value);
+    }
+}
+
+/// Fee policy that allows all operations and charges fixed fees for
+/// registrations and updates (including transfers).
+pub struct FlatFee<Registration, Update>(PhantomData<(Registration, Update)>);
+
+impl<T, Registration, Update> FeePolicy<T> for FlatFee<Registration, Update>
+    where T: Trait, Registration: Get<BalanceOf<T>>, Update: Get<BalanceOf<T>>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        Some(match op.operation {
+            OperationType::Registration => Registration::get(),
+            OperationType::Update | OperationType::Transfer => Update::get(),
+        })
+    }
+}
+
+/// Fee policy where the registration fee depends on the length (in bytes)
+/// of the name.  `Tiers` is a list of minimum name lengths together with
+/// the registration fee for names of at least that length.  The tier with
+/// the largest minimum length that a name reaches applies.  Names shorter
+/// than all tiers are invalid.  Updates and transfers cost a fixed fee.
+pub struct LengthTiered<Tiers, Update>(PhantomData<(Tiers, Update)>);
+
+impl<T, Tiers, Update> FeePolicy<T> for LengthTiered<Tiers, Update>
+    where T: Trait, T::Name: AsRef<[u8]>,
+          Tiers: Get<&'static [(usize, BalanceOf<T>)]>, Update: Get<BalanceOf<T>>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        let len = op.name.as_ref().len();
+        let (_, fee) = Tiers::get().iter()
+            .filter(|(min_len, _)| *min_len <= len)
+            .max_by_key(|(min_len, _)| *min_len)?;
+
+        Some(match op.operation {
+            OperationType::Registration => *fee,
+            OperationType::Update | OperationType::Transfer => Update::get(),
+        })
+    }
+}
+
+/// Fee policy that charges a premium for short names, similar to what
+/// ENS does to price scarce short names.  Names of at least `Threshold`
+/// bytes cost `Base` to register.  For each byte a name is shorter than
+/// that, the fee is multiplied by `Factor`.  For instance, with a base fee
+/// of 100, factor 10 and threshold 5, names of four bytes cost 1'000 and
+/// single-byte names cost 1'000'000.  The empty name is invalid.  Updates
+/// cost a fixed fee.
+pub struct ShortNamePremium<Base, Factor, Threshold, Update>(
+    PhantomData<(Base, Factor, Threshold, Update)>
+);
+
+impl<T, Base, Factor, Threshold, Update> FeePolicy<T>
+    for ShortNamePremium<Base, Factor, Threshold, Update>
+    where T: Trait, T::Name: AsRef<[u8]>,
+          Base: Get<BalanceOf<T>>, Factor: Get<u32>, Threshold: Get<u32>,
+          Update: Get<BalanceOf<T>>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        let len = op.name.as_ref().len();
+        if len == 0 {
+            return None;
+        }
+
+        Some(match op.operation {
+            OperationType::Registration => {
+                let factor = BalanceOf::<T>::from(Factor::get());
+                let missing = (Threshold::get() as usize).saturating_sub(len);
+                (0..missing).fold(Base::get(), |fee, _| fee.saturating_mul(factor))
+            },
+            OperationType::Update | OperationType::Transfer => Update::get(),
+        })
+    }
+}
+
+/// Classes of names by the characters they are made of.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum NameClass {
+    /// Only ASCII characters.
+    Ascii,
+    /// Valid UTF-8 with non-ASCII characters, which is not emoji-only.
+    Unicode,
+    /// Valid UTF-8 consisting only of emoji.
+    Emoji,
+    /// Arbitrary bytes that are not valid UTF-8.
+    Binary,
+}
+
+/// Determines the [class](NameClass) of a name, so that policies can
+/// e.g. price or forbid classes differently.
+pub trait NameClassifier<Name> {
+    /// Returns the class of the given name.
+    fn classify(name: &Name) -> NameClass;
+}
+
+/// Default classifier for names that are (supposed to be) UTF-8 strings.
+/// Emoji are detected based on the main Unicode blocks of pictographs,
+/// together with the joiners, variation selectors and modifiers used
+/// in emoji sequences.  The empty name counts as ASCII.
+pub struct Utf8Classifier;
+
+impl Utf8Classifier {
+
+    /// Returns true if the character is a pictograph (i.e. an emoji
+    /// on its own).
+    fn is_pictograph(c: char) -> bool {
+        match c as u32 {
+            0x1F000..=0x1FAFF => true,
+            0x2600..=0x27BF => true,
+            0x2B00..=0x2BFF => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the character is only used as part of emoji
+    /// sequences (joiner, variation selector, keycap, tags).
+    fn is_emoji_component(c: char) -> bool {
+        match c as u32 {
+            0x200D | 0xFE0F | 0x20E3 => true,
+            0xE0020..=0xE007F => true,
+            _ => false,
+        }
+    }
+
+}
+
+impl<Name: AsRef<[u8]>> NameClassifier<Name> for Utf8Classifier {
+    fn classify(name: &Name) -> NameClass {
+        let bytes = name.as_ref();
+        if bytes.is_ascii() {
+            return NameClass::Ascii;
+        }
+        let s = match core::str::from_utf8(bytes) {
+            Err(_) => return NameClass::Binary,
+            Ok(s) => s,
+        };
+
+        let emoji_only = s.chars().any(Self::is_pictograph)
+            && s.chars().all(|c| Self::is_pictograph(c) || Self::is_emoji_component(c));
+        if emoji_only {
+            NameClass::Emoji
+        } else {
+            NameClass::Unicode
+        }
+    }
+}
+
+/// Fee policy that adjusts the registration fees of an inner policy based
+/// on the [class](NameClass) of the name.  `Multipliers` lists the allowed
+/// classes together with a multiplier (in percent) for the registration
+/// fee.  Names of classes that are not listed are invalid.  Updates are
+/// charged as by the inner policy.
+pub struct ByNameClass<Classifier, Inner, Multipliers>(
+    PhantomData<(Classifier, Inner, Multipliers)>
+);
+
+impl<T, Classifier, Inner, Multipliers> FeePolicy<T> for ByNameClass<Classifier, Inner, Multipliers>
+    where T: Trait, Classifier: NameClassifier<T::Name>, Inner: FeePolicy<T>,
+          Multipliers: Get<&'static [(NameClass, u32)]>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        let class = Classifier::classify(&op.name);
+        let (_, multiplier) = Multipliers::get().iter().find(|(c, _)| *c == class)?;
+
+        let fee = Inner::get_name_fee(op)?;
+        Some(match op.operation {
+            OperationType::Registration => {
+                fee.saturating_mul(BalanceOf::<T>::from(*multiplier))
+                    / BalanceOf::<T>::from(100u32)
+            },
+            OperationType::Update | OperationType::Transfer => fee,
+        })
+    }
+}
+
+/// Fee policy that adjusts the update fees of an inner policy based on how
+/// long the current owner has [held](NameData::held_since) the name without
+/// interruption.  `Steps` lists multipliers (in percent) for each full
+/// `Period` of blocks the name has been held, starting with names held
+/// for less than one period.  Owners holding a name longer than listed get
+/// the last multiplier.  Registrations and transfers are charged as by the
+/// inner policy, and so are updates if `Steps` is empty.
+pub struct LoyaltyPricing<Inner, Period, Steps>(PhantomData<(Inner, Period, Steps)>);
+
+impl<T, Inner, Period, Steps> FeePolicy<T> for LoyaltyPricing<Inner, Period, Steps>
+    where T: Trait, Inner: FeePolicy<T>,
+          Period: Get<T::BlockNumber>, Steps: Get<&'static [u32]>
+{
+    fn get_name_fee(op: &Operation<T>) -> Option<BalanceOf<T>> {
+        let fee = Inner::get_name_fee(op)?;
+        let held_since = match (&op.operation, &op.old_data) {
+            (OperationType::Update, Some(data)) => data.held_since,
+            _ => return Some(fee),
+        };
+
+        let steps = Steps::get();
+        let last = match steps.len().checked_sub(1) {
+            None => return Some(fee),
+            Some(last) => last,
+        };
+        let period = Period::get();
+        let held = system::Module::<T>::block_number().saturating_sub(held_since);
+        let index = if period.is_zero() {
+            last
+        } else {
+            let periods: u32 = (held / period).unique_saturated_into();
+            sp_std::cmp::min(periods as usize, last)
+        };
+
+        Some(fee.saturating_mul(BalanceOf::<T>::from(steps[index]))
+             / BalanceOf::<T>::from(100u32))
+    }
+}
+
+/// Expiration policy under which names never expire.
+pub struct NeverExpire;
+
+impl<T: Trait> ExpirationPolicy<T> for NeverExpire {
+    fn get_expiration(_op: &Operation<T>) -> Option<T::BlockNumber> {
+        None
+    }
+}
+
+/// Expiration policy where all names expire a fixed number of blocks
+/// after their last update.
+pub struct FixedPeriod<Blocks>(PhantomData<Blocks>);
+
+impl<T: Trait, Blocks: Get<T::BlockNumber>> ExpirationPolicy<T> for FixedPeriod<Blocks> {
+    fn get_expiration(_op: &Operation<T>) -> Option<T::BlockNumber> {
+        Some(Blocks::get())
+    }
+}
+
+/// Expiration policy that spreads out the expirations of an inner policy,
+/// so that names registered together (e.g. on launch day) do not all expire
+/// in the same block again later.  Up to `MaxJitter` blocks are added to
+/// each expiration.  The offset is derived from a hash of the name and the
+/// current block, so that it is deterministic but differs between names.
+pub struct Jittered<Inner, MaxJitter>(PhantomData<(Inner, MaxJitter)>);
+
+impl<T, Inner, MaxJitter> ExpirationPolicy<T> for Jittered<Inner, MaxJitter>
+    where T: Trait, Inner: ExpirationPolicy<T>, MaxJitter: Get<T::BlockNumber>
+{
+    fn get_expiration(op: &Operation<T>) -> Option<T::BlockNumber> {
+        let blocks = Inner::get_expiration(op)?;
+        let max_jitter: u32 = MaxJitter::get().unique_saturated_into();
+        if max_jitter == 0 {
+            return Some(blocks);
+        }
+
+        let now = system::Module::<T>::block_number();
+        let hash = (&op.name, now).using_encoded(blake2_256);
+        let mut raw = [0u8; 4];
+        raw.copy_from_slice(&hash[0..4]);
+        let jitter = u32::from_le_bytes(raw) % max_jitter.saturating_add(1);
+
+        Some(blocks.saturating_add(T::BlockNumber::from(jitter)))
+    }
+}
+
+/// What to do with a name whose owner account has been reaped.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Eq, PartialEq)]
+pub enum ReapedNameAction<AccountId, BlockNumber> {
+    /// Keep the name as it is.  Since nobody can control the reaped account
+    /// anymore, the name will just linger until it expires naturally.
+    Keep,
+    /// Transfer the name to the given account (e.g. a treasury or
+    /// governance account).
+    Transfer(AccountId),
+    /// Let the name expire after the given number of blocks (or at its
+    /// original expiration, if that is earlier).
+    Expire(BlockNumber),
+}
+
+/// Policy for names whose [owner account is reaped](crate::Trait::OnOwnerReaped).
+pub trait ReapedOwnerPolicy<T: Trait> {
+    /// Returns what should happen to the given name.
+    fn reaped_name_action(name: &T::Name, data: &NameData<T>)
+        -> ReapedNameAction<T::AccountId, T::BlockNumber>;
+}
+
+/// The unit type keeps all names of reaped accounts.
+impl<T: Trait> ReapedOwnerPolicy<T> for () {
+    fn reaped_name_action(_name: &T::Name, _data: &NameData<T>)
+        -> ReapedNameAction<T::AccountId, T::BlockNumber>
+    {
+        ReapedNameAction::Keep
+    }
+}
+
+/// Transfers all names of reaped accounts to a fallback account.
+pub struct TransferTo<Fallback>(PhantomData<Fallback>);
+
+impl<T: Trait, Fallback: Get<T::AccountId>> ReapedOwnerPolicy<T> for TransferTo<Fallback> {
+    fn reaped_name_action(_name: &T::Name, _data: &NameData<T>)
+        -> ReapedNameAction<T::AccountId, T::BlockNumber>
+    {
+        ReapedNameAction::Transfer(Fallback::get())
+    }
+}
+
+/// Lets all names of reaped accounts expire after a given number of blocks.
+pub struct ExpireAfter<Blocks>(PhantomData<Blocks>);
+
+impl<T: Trait, Blocks: Get<T::BlockNumber>> ReapedOwnerPolicy<T> for ExpireAfter<Blocks> {
+    fn reaped_name_action(_name: &T::Name, _data: &NameData<T>)
+        -> ReapedNameAction<T::AccountId, T::BlockNumber>
+    {
+        ReapedNameAction::Expire(Blocks::get())
+    }
+}
+
+/// What to do with a name when it reaches its expiration height.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Eq, PartialEq)]
+pub enum ExpirationAction<AccountId, BlockNumber> {
+    /// Remove the name from the database.
+    Delete,
+    /// Keep the name with its owner for a grace period of the given number
+    /// of blocks, during which the owner can still renew it.  If that does
+    /// not happen, the name is removed at the end of the grace period.
+    Grace(BlockNumber),
+    /// Transfer the name to the given account instead of removing it, e.g.
+    /// to the account of a pallet that auctions off lapsed names.  The new
+    /// expiration is determined by the name policy, as for any transfer.
+    Transfer(AccountId),
+}
+
+/// Policy for names that [reach their expiration](crate::Trait::OnExpiration).
+pub trait ExpirationHandler<T: Trait> {
+    /// Returns what should happen to the given name.
+    fn expiration_action(name: &T::Name, data: &NameData<T>)
+        -> ExpirationAction<T::AccountId, T::BlockNumber>;
+}
+
+/// The unit type removes all expired names.
+impl<T: Trait> ExpirationHandler<T> for () {
+    fn expiration_action(_name: &T::Name, _data: &NameData<T>)
+        -> ExpirationAction<T::AccountId, T::BlockNumber>
+    {
+        ExpirationAction::Delete
+    }
+}
+
+/// Content rules that the value of a name has to satisfy from its
+/// [compliance deadline](crate::Trait::ComplianceWindow) on, e.g. that it
+/// includes a field verified by some registry.
+pub trait ValueCompliance<T: Trait> {
+    /// Returns true if the value satisfies the rules for the given name.
+    fn is_compliant(name: &T::Name, value: &T::Value) -> bool;
+}
+
+/// The unit type accepts all values.
+impl<T: Trait> ValueCompliance<T> for () {
+    fn is_compliant(_name: &T::Name, _value: &T::Value) -> bool {
+        true
+    }
+}
+
+/// Gives all expired names a grace period of the given number of blocks.
+pub struct GracePeriod<Blocks>(PhantomData<Blocks>);
+
+impl<T: Trait, Blocks: Get<T::BlockNumber>> ExpirationHandler<T> for GracePeriod<Blocks> {
+    fn expiration_action(_name: &T::Name, _data: &NameData<T>)
+        -> ExpirationAction<T::AccountId, T::BlockNumber>
+    {
+        ExpirationAction::Grace(Blocks::get())
+    }
+}
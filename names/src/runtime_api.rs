@@ -0,0 +1,95 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Runtime API for querying the names pallet, e.g. through RPC.
+
+use codec::Codec;
+use sp_core::H256;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+    /// Queries of the names database that are not just direct
+    /// storage lookups.
+    pub trait NamesApi<Name, AccountId, Signature, BlockNumber>
+        where Name: Codec, AccountId: Codec, Signature: Codec, BlockNumber: Codec
+    {
+        /// Returns the name with the given
+        /// [namehash](crate::Module::resolve_namehash), if any.
+        fn resolve_namehash(hash: H256) -> Option<Name>;
+        /// [Resolves](crate::Module::resolve) a name by following its
+        /// redirects, and returns the final name with its SCALE-encoded value.
+        fn resolve(name: Name) -> Result<(Name, Vec<u8>), crate::ResolveError>;
+        /// Returns the owner of the name that a name
+        /// [resolves](crate::Module::resolve_address) to.
+        fn resolve_address(name: Name) -> Result<AccountId, crate::ResolveError>;
+        /// Checks whether a message has been
+        /// [signed by the owner](crate::Module::verify_name_signature)
+        /// of the given name.
+        fn verify_name_signature(name: Name, message: Vec<u8>, signature: Signature) -> bool;
+        /// Returns all names of an account as SCALE-encoded
+        /// [portfolio](crate::Portfolio), which can be
+        /// [imported](crate::Module::import_names) e.g. on another chain.
+        fn export_names(owner: AccountId) -> Vec<u8>;
+        /// Returns the [expiration height](crate::Module::next_expiration)
+        /// of a name, if it exists and expires.
+        fn next_expiration(name: Name) -> Option<BlockNumber>;
+        /// Checks whether a name will [still be active](crate::Module::active_at)
+        /// at the given block height.
+        fn active_at(name: Name, block: BlockNumber) -> bool;
+        /// Returns the SCALE-encoded [receipts](crate::Receipt) of all name
+        /// operations executed by the given extrinsic of a block.
+        fn receipts(block: BlockNumber, extrinsic: Option<u32>) -> Vec<u8>;
+        /// Returns a SCALE-encoded [attestation](crate::OwnershipAttestation)
+        /// of the name's current owner, if the name exists.
+        fn attest_ownership(name: Name) -> Option<Vec<u8>>;
+        /// Returns the owner of a name, if it exists.  When called at the
+        /// hash of a past block (on a node that keeps archive state), this
+        /// gives the owner at that block.  The answer can be checked against
+        /// the block's state root with a proof for the
+        /// [storage key](crate::Module::name_storage_key) of the name.
+        fn owner_at(name: Name) -> Option<AccountId>;
+        /// Returns the [storage key](crate::Module::name_storage_key) of
+        /// a name's entry in the name database.
+        fn name_storage_key(name: Name) -> Vec<u8>;
+        /// Returns the SCALE-encoded [registrar statistics](crate::RegistrarStats)
+        /// of all eras that are [still kept](crate::Module::statistics).
+        fn statistics() -> Vec<u8>;
+        /// Returns the SCALE-encoded [records](crate::Module::records_of)
+        /// (subkeys and subvalues) of a name.
+        fn records_of(name: Name) -> Vec<u8>;
+        /// Returns the [storage keys](crate::Module::record_proof_keys) for
+        /// a state proof of a name's data and all its records.
+        fn record_proof_keys(name: Name) -> Vec<Vec<u8>>;
+        /// Checks whether the given account could perform an operation on
+        /// a name, with an optional SCALE-encoded value and an optional
+        /// recipient.  Returns the SCALE-encoded
+        /// [structured error](crate::OperationError) if not.
+        fn check_operation(sender: AccountId, name: Name, value: Option<Vec<u8>>,
+                           recipient: Option<AccountId>) -> Result<(), Vec<u8>>;
+        /// Returns whether a name [is available](crate::Module::availability)
+        /// for registration, or why not.
+        fn availability(name: Name) -> crate::Availability<BlockNumber>;
+        /// Returns the [availability](crate::Module::check_available) of
+        /// each of the given names, in the same order.
+        fn check_available(names: Vec<Name>) -> Vec<crate::Availability<BlockNumber>>;
+        /// Returns the SCALE-encoded [pending processes](crate::Module::pending_for)
+        /// (transfers, locks, agreements and so on) that affect a name.
+        fn pending_for(name: Name) -> Vec<u8>;
+    }
+}
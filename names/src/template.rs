@@ -0,0 +1,53 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Value templates for [standing approvals](crate::Module::approve_operator).
+//! An owner can allow an operator to update a name's value without
+//! further signatures, but only to values matching one of a set of
+//! templates, each identified by a hash (e.g. of a schema).
+
+use codec::Encode;
+use sp_core::H256;
+use sp_io::hashing::blake2_256;
+
+/// Decides whether a value matches a template.
+pub trait ValueTemplate<Value> {
+
+    /// Returns true if the value matches the template with the given hash.
+    fn matches(value: &Value, template: &H256) -> bool;
+
+}
+
+/// The unit type can be used to disable operator updates.  Then no value
+/// ever matches any template.
+impl<Value> ValueTemplate<Value> for () {
+    fn matches(_value: &Value, _template: &H256) -> bool {
+        false
+    }
+}
+
+/// Templates that each allow exactly one value, identified by the
+/// Blake2 hash of its encoding.
+pub struct ExactValue;
+
+impl<Value: Encode> ValueTemplate<Value> for ExactValue {
+    fn matches(value: &Value, template: &H256) -> bool {
+        value.using_encoded(blake2_256) == template.0
+    }
+}
@@ -28,9 +28,11 @@ use frame_support::{
 };
 use system::{EventRecord, Phase};
 use sp_runtime::{
-    traits::{BlakeTwo256, IdentityLookup}, testing::Header, Perbill,
+    traits::{BlakeTwo256, IdentityLookup},
+    testing::{Header, TestSignature, UintAuthorityId}, ModuleId, Perbill,
 };
 use crate::{Module, Trait};
+use std::cell::RefCell;
 
 impl_outer_origin! {
     pub enum Origin for Test {}
@@ -49,6 +51,58 @@ parameter_types! {
     pub const MaximumBlockWeight: Weight = 1024;
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const HiddenRegistrationFee: u128 = 50;
+    pub const HiddenRegistrationPeriod: u64 = 10;
+    pub const MaxBulkTransfer: u32 = 2;
+    pub const MaxSwapNames: u32 = 3;
+    pub const MaxForceRegistrations: u32 = 3;
+    pub const StatsEraLength: u64 = 10;
+    pub const StatsHistory: u32 = 3;
+    pub const FreeUpdatesPerEra: u32 = 2;
+    pub const MaxTemplates: u32 = 2;
+    pub const ReceiptRetention: u64 = 10;
+    pub const MaxPayoutSplits: u32 = 2;
+    pub const MaxShareHolders: u32 = 3;
+    pub const MigrationWeightLimit: Weight = 25;
+    pub const DeferredWeightLimit: Weight = 15_000;
+    pub const CommunityAccount: u64 = 777;
+    pub const NamesModuleId: ModuleId = ModuleId(*b"py/names");
+    pub const MaxExpirationsPerBlock: u32 = 3;
+    pub const SweepBounty: u128 = 5;
+    pub const MaxValueLookahead: u64 = 10;
+    pub const MinExpirationBlocks: u64 = 1;
+    pub const WatchDeposit: u128 = 5;
+    pub const MaxWatchers: u32 = 2;
+    pub const MaxSubvalues: u32 = 2;
+    pub const SubvalueDeposit: u128 = 10;
+    pub const NoteDeposit: u128 = 20;
+    pub const MaxNoteSize: u32 = 8;
+    pub const MaxRedirectDepth: u32 = 3;
+    pub const EnvelopeFee: u128 = 20;
+    pub const MaxEnvelopeSize: u32 = 4;
+    pub const MaxEnvelopes: u32 = 2;
+    pub const MinPreorderDeposit: u128 = 100;
+    pub const IntentBond: u128 = 10;
+    pub const MaxIntentsPerName: u32 = 5;
+    pub const PreorderMaturity: u64 = 5;
+    pub const PreorderWindow: u64 = 10;
+    pub const PreorderForfeit: Perbill = Perbill::from_percent(50);
+    pub const FirstUpdateRefund: Perbill = Perbill::from_percent(50);
+    pub const RegistrarCommission: Perbill = Perbill::from_percent(10);
+    pub const MinBackorderBond: u128 = 10;
+    pub const MaxFeeRateAge: u64 = 10;
+    pub const ReportBond: u128 = 500;
+    pub const ReportChallengePeriod: u64 = 10;
+    pub const GuardianConfirmationPeriod: u64 = 10;
+    pub const MaxBackorders: u32 = 2;
+    pub const MaxBulkRenewals: u32 = 3;
+    pub const MaxTopicPrefixes: u32 = 2;
+    pub const MaxDelegatedNamespaces: u32 = 2;
+    pub const MaxSessionDuration: u64 = 100;
+    pub const MaxSessionNames: u32 = 2;
+    pub const MaxInsuredRenewals: u32 = 3;
+    pub const MaxTotalNames: Option<u32> = Some(20);
+    pub const CongestionMultipliers: &'static [(u32, u32)] = &[(5, 200), (10, 1000)];
 }
 impl system::Trait for Test {
     type Origin = Origin;
@@ -70,7 +124,7 @@ impl system::Trait for Test {
 }
 impl balances::Trait for Test {
     type Balance = u128;
-    type OnFreeBalanceZero = ();
+    type OnFreeBalanceZero = Mod;
     type OnNewAccount = ();
     type Event = TestEvent;
     type DustRemoval = ();
@@ -90,34 +144,489 @@ impl_outer_event! {
     }
 }
 
+/// Namehash function for testing:  Names below 1'000 are indexed with their
+/// number as hash, larger names are not indexed.
+pub struct TestNamehash;
+impl namehash::Namehash<u64> for TestNamehash {
+    fn namehash(name: &u64) -> Option<H256> {
+        if *name >= 1_000 {
+            return None;
+        }
+        Some(H256::from_low_u64_be(*name))
+    }
+}
+
+/// Identity provider for testing:  Accounts below 100 have an identity.
+pub struct TestIdentity;
+impl identity::IdentityProvider<u64> for TestIdentity {
+    fn has_identity(who: &u64) -> bool {
+        *who < 100
+    }
+}
+
+/// Value patches for testing:  The patch bytes are added to the value,
+/// and empty patches are invalid.
+pub struct TestPatch;
+impl patch::ValuePatch<u64> for TestPatch {
+    fn apply_patch(value: &u64, patch: &[u8]) -> Option<u64> {
+        if patch.is_empty() {
+            return None;
+        }
+        Some(patch.iter().fold(*value, |acc, b| acc + *b as u64))
+    }
+}
+
+/// Namespaces for testing:  A name is inside a namespace if its decimal
+/// representation starts with the prefix.
+pub struct TestNamespaces;
+impl namespace::NamespaceParser<u64> for TestNamespaces {
+    fn has_prefix(name: &u64, prefix: &[u8]) -> bool {
+        name.to_string().as_bytes().starts_with(prefix)
+    }
+}
+
+/// Call filter for testing:  Only value updates can be dispatched as a name.
+pub struct TestCallFilter;
+impl dispatch::CallFilter<u64, Call<Test>> for TestCallFilter {
+    fn allow_call(_name: &u64, call: &Call<Test>) -> bool {
+        call.is_value_update_call()
+    }
+}
+
+/// Default values for testing:  Names from 900 to 999 are registered with
+/// their owner as value, all others with zero.
+pub struct TestDefaultValue;
+impl default_value::DefaultValueProvider<u64, u64, u64> for TestDefaultValue {
+    fn default_value_for(name: &u64, owner: &u64) -> u64 {
+        match *name {
+            900..=999 => *owner,
+            _ => 0,
+        }
+    }
+}
+
+/// Extra weight for testing:  Operations on names from 300 to 399 weigh
+/// an additional 1'000, and updates add their value on top.
+pub struct TestExtraWeight;
+impl weights::OperationWeight<u64, u64> for TestExtraWeight {
+    fn extra_weight(name: &u64, value: Option<&u64>) -> Weight {
+        match *name {
+            300..=399 => 1_000 + value.map_or(0, |v| *v as Weight),
+            _ => 0,
+        }
+    }
+}
+
+/// Base weights for testing, which are distinct for all entries so that
+/// tests can tell which entry the weight of a call is based on.
+pub struct TestWeights;
+
+macro_rules! test_weights {
+    ($($entry:ident,)*) => {
+        /// Names of all entries of the weight info, in order.
+        const WEIGHT_ENTRIES: &[&str] = &[$(stringify!($entry)),*];
+
+        impl weights::WeightInfo for TestWeights {
+            $(fn $entry() -> Weight { test_weight(stringify!($entry)) })*
+        }
+    };
+}
+
+test_weights!(
+    update, update_via_registrar, register, register_attested, renew, set_value,
+    set_value_at_nonce, update_patch, schedule_value, cancel_scheduled_value, set_subvalue,
+    clear_subvalue, publish_envelope, remove_envelope, watch_name, unwatch_name, transfer,
+    transfer_existing, set_transfer_preference, block_sender, unblock_sender,
+    set_block_all_transfers, claim_name, cancel_transfer, donate_name, lock_as_collateral,
+    release_collateral, claim_collateral, propose_swap, cancel_swap, accept_swap, set_payout,
+    pay_name, approve_operator, operator_update, offer_rent_to_own, pay_installment,
+    split_into_shares, transfer_shares, propose_value, approve_value, reclaim_name,
+    dispatch_as_name, repair_index, transfer_all_names, transfer_all_names_skipped,
+    register_hidden, reveal_name, set_signing_key, set_controller, set_note, set_redirect,
+    set_guardian, request_guarded_transfer, confirm_guarded_transfer, cancel_guarded_transfer,
+    set_sale_ready, set_retention_policy, authorize_session_key, revoke_session_key,
+    session_update, link_identity, unlink_identity, force_transfer, report_name, decide_report,
+    force_remove, freeze_name, force_register, force_register_batch, queue_migration,
+    force_total_names, set_namespace_registrar, delegate_namespace, set_topic_prefixes,
+    set_attestation_oracle, set_account_fee_exemption, set_name_fee_exemption, set_system_name,
+    import_names, register_intent, preorder, place_backorder, cancel_backorder, insure_name,
+    set_insurance_premium, renew_namespace, sponsored_update, execute_signed_operation,
+    execute_signed_batch, sweep_expirations,
+);
+
+/// Returns the test weight of the entry with the given name.  None of them
+/// is equal to the default weight of calls without a weight annotation.
+fn test_weight(entry: &str) -> Weight {
+    let pos = WEIGHT_ENTRIES.iter().position(|e| *e == entry).unwrap();
+    20_000 + 100 * pos as Weight
+}
+
+/// Value templates for testing:  The template with low number `n`
+/// matches all values that are multiples of `n`.
+pub struct TestTemplates;
+impl template::ValueTemplate<u64> for TestTemplates {
+    fn matches(value: &u64, template: &H256) -> bool {
+        let n = template.to_low_u64_be();
+        n > 0 && value % n == 0
+    }
+}
+
+/// Randomness for testing, which is just a hash of the subject.
+pub struct TestRandomness;
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        BlakeTwo256::hash(subject)
+    }
+}
+
+thread_local! {
+    static INTENTS_ONLY: RefCell<bool> = RefCell::new(false);
+    static VALUE_DEPOSIT: RefCell<u128> = RefCell::new(0);
+    static STRICT_TRANSFERS: RefCell<bool> = RefCell::new(false);
+    static BUCKET_SIZE: RefCell<u64> = RefCell::new(1);
+    static MIGRATED: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+    static FEE_RATE: RefCell<Option<(u128, u64)>> = RefCell::new(None);
+    static FIRST_UPDATE: RefCell<Option<u64>> = RefCell::new(None);
+    static QUARANTINE: RefCell<u64> = RefCell::new(0);
+    static EXPIRATION_UPDATES: RefCell<ExpirationUpdate> = RefCell::new(ExpirationUpdate::MayShorten);
+    static REGISTRATIONS_PER_BLOCK: RefCell<Option<u32>> = RefCell::new(None);
+    static ARCHIVE_RETENTION: RefCell<Option<u64>> = RefCell::new(None);
+    static AUTO_EXPIRATIONS: RefCell<Option<u32>> = RefCell::new(None);
+    static COMPLIANCE: RefCell<(Option<u64>, ComplianceAction)>
+        = RefCell::new((None, ComplianceAction::Warn));
+    static EXECUTED_OPERATIONS: RefCell<Vec<(OperationType, u64, u64)>> = RefCell::new(Vec::new());
+}
+
+/// Whether registrations must go through intents.  This can be changed
+/// by tests with set_intents_only.
+pub struct IntentsOnly;
+impl Get<bool> for IntentsOnly {
+    fn get() -> bool {
+        INTENTS_ONLY.with(|v| *v.borrow())
+    }
+}
+
+fn set_intents_only(value: bool) {
+    INTENTS_ONLY.with(|v| *v.borrow_mut() = value);
+}
+
+/// Value deposit per byte, which can be changed by tests with
+/// set_value_deposit.
+pub struct ValueDepositPerByte;
+impl Get<u128> for ValueDepositPerByte {
+    fn get() -> u128 {
+        VALUE_DEPOSIT.with(|v| *v.borrow())
+    }
+}
+
+fn set_value_deposit(value: u128) {
+    VALUE_DEPOSIT.with(|v| *v.borrow_mut() = value);
+}
+
+/// Whether transfers of non-existing names are refused.  This can be
+/// changed by tests with set_strict_transfers.
+pub struct StrictTransfers;
+impl Get<bool> for StrictTransfers {
+    fn get() -> bool {
+        STRICT_TRANSFERS.with(|v| *v.borrow())
+    }
+}
+
+fn set_strict_transfers(value: bool) {
+    STRICT_TRANSFERS.with(|v| *v.borrow_mut() = value);
+}
+
+/// Migration for testing, which just records the migrated names.  Each
+/// name costs a weight of 10.
+pub struct TestMigration;
+impl migration::NameMigration<Test> for TestMigration {
+    fn migrate_name(name: &u64) -> Weight {
+        MIGRATED.with(|v| v.borrow_mut().push(*name));
+        10
+    }
+}
+
+fn take_migrated() -> Vec<u64> {
+    MIGRATED.with(|v| v.replace(Vec::new()))
+}
+
+/// Bucket size for expirations, which can be changed by tests with
+/// set_bucket_size.
+pub struct ExpirationBucketSize;
+impl Get<u64> for ExpirationBucketSize {
+    fn get() -> u64 {
+        BUCKET_SIZE.with(|v| *v.borrow())
+    }
+}
+
+fn set_bucket_size(value: u64) {
+    BUCKET_SIZE.with(|v| *v.borrow_mut() = value);
+}
+
+/// Period for the first value update of new names, which can be changed
+/// by tests with set_first_update_period.
+pub struct FirstUpdatePeriod;
+impl Get<Option<u64>> for FirstUpdatePeriod {
+    fn get() -> Option<u64> {
+        FIRST_UPDATE.with(|v| *v.borrow())
+    }
+}
+
+fn set_first_update_period(value: Option<u64>) {
+    FIRST_UPDATE.with(|v| *v.borrow_mut() = value);
+}
+
+/// Quarantine of expired names, which can be changed by tests with
+/// set_quarantine.
+pub struct ExpirationQuarantine;
+impl Get<u64> for ExpirationQuarantine {
+    fn get() -> u64 {
+        QUARANTINE.with(|v| *v.borrow())
+    }
+}
+
+fn set_quarantine(value: u64) {
+    QUARANTINE.with(|v| *v.borrow_mut() = value);
+}
+
+pub struct ExpirationUpdates;
+impl Get<ExpirationUpdate> for ExpirationUpdates {
+    fn get() -> ExpirationUpdate {
+        EXPIRATION_UPDATES.with(|v| *v.borrow())
+    }
+}
+
+fn set_expiration_updates(value: ExpirationUpdate) {
+    EXPIRATION_UPDATES.with(|v| *v.borrow_mut() = value);
+}
+
+/// Compliance window and action for non-compliant values, which can be
+/// changed by tests with set_compliance.
+pub struct ComplianceWindow;
+impl Get<Option<u64>> for ComplianceWindow {
+    fn get() -> Option<u64> {
+        COMPLIANCE.with(|v| v.borrow().0)
+    }
+}
+
+pub struct NonComplianceAction;
+impl Get<ComplianceAction> for NonComplianceAction {
+    fn get() -> ComplianceAction {
+        COMPLIANCE.with(|v| v.borrow().1)
+    }
+}
+
+fn set_compliance(window: Option<u64>, action: ComplianceAction) {
+    COMPLIANCE.with(|v| *v.borrow_mut() = (window, action));
+}
+
+/// Content rules for testing, which require values to be even.
+pub struct TestCompliance;
+impl policy::ValueCompliance<Test> for TestCompliance {
+    fn is_compliant(_name: &u64, value: &u64) -> bool {
+        value % 2 == 0
+    }
+}
+
+pub struct MaxRegistrationsPerBlock;
+impl Get<Option<u32>> for MaxRegistrationsPerBlock {
+    fn get() -> Option<u32> {
+        REGISTRATIONS_PER_BLOCK.with(|v| *v.borrow())
+    }
+}
+
+fn set_registrations_per_block(value: Option<u32>) {
+    REGISTRATIONS_PER_BLOCK.with(|v| *v.borrow_mut() = value);
+}
+
+pub struct ArchiveRetention;
+impl Get<Option<u64>> for ArchiveRetention {
+    fn get() -> Option<u64> {
+        ARCHIVE_RETENTION.with(|v| *v.borrow())
+    }
+}
+
+fn set_archive_retention(value: Option<u64>) {
+    ARCHIVE_RETENTION.with(|v| *v.borrow_mut() = value);
+}
+
+/// Number of expirations processed automatically per block.  This can be
+/// changed by tests with set_auto_expirations.
+pub struct AutoExpirations;
+impl Get<Option<u32>> for AutoExpirations {
+    fn get() -> Option<u32> {
+        AUTO_EXPIRATIONS.with(|v| *v.borrow())
+    }
+}
+
+fn set_auto_expirations(value: Option<u32>) {
+    AUTO_EXPIRATIONS.with(|v| *v.borrow_mut() = value);
+}
+
+/// Operation hook for testing, which records the type, name and new owner
+/// of all executed operations.
+pub struct TestOperationHook;
+impl hook::OnNameOperation<Test> for TestOperationHook {
+    fn on_name_operation(operation: OperationType, name: &u64, data: &NameData<Test>) {
+        EXECUTED_OPERATIONS.with(|v| v.borrow_mut().push((operation, *name, data.owner)));
+    }
+}
+
+fn executed_operations() -> Vec<(OperationType, u64, u64)> {
+    EXECUTED_OPERATIONS.with(|v| v.borrow().clone())
+}
+
+/// Fee conversion for testing:  Fees are scaled by a percentage that is
+/// set together with the block of its last update by set_fee_rate.
+/// Without a rate, fees are taken as they are.
+pub struct TestFeeConverter;
+impl fee_converter::FeeConverter<u128, u64> for TestFeeConverter {
+    fn to_native(amount: u128) -> Option<u128> {
+        match FEE_RATE.with(|v| *v.borrow()) {
+            None => Some(amount),
+            Some((0, _)) => None,
+            Some((percent, _)) => Some(amount * percent / 100),
+        }
+    }
+    fn last_update() -> Option<u64> {
+        FEE_RATE.with(|v| v.borrow().map(|(_, h)| h))
+    }
+}
+
+fn set_fee_rate(percent: u128, updated: u64) {
+    FEE_RATE.with(|v| *v.borrow_mut() = Some((percent, updated)));
+}
+
 impl Trait for Test {
 
     type Name = u64;
     type Value = u64;
+    type DefaultValue = TestDefaultValue;
 
     type Currency = Balances;
     type Event = TestEvent;
 
-    fn get_name_fee(op: &Operation<Self>) -> Option<u128> {
+    type Namehash = TestNamehash;
+
+    type HiddenRegistrationFee = HiddenRegistrationFee;
+    type HiddenRegistrationPeriod = HiddenRegistrationPeriod;
+
+    type Identity = TestIdentity;
+    type ForceOrigin = system::EnsureRoot<u64>;
+    type ReportOrigin = system::EnsureRoot<u64>;
+    type Namespaces = TestNamespaces;
+    type CommunityAccount = CommunityAccount;
+    type ModuleId = NamesModuleId;
+    type Call = Call<Test>;
+    type NameCallFilter = TestCallFilter;
+    type MaxShareHolders = MaxShareHolders;
+    type MaxPayoutSplits = MaxPayoutSplits;
+    type MaxBulkTransfer = MaxBulkTransfer;
+    type MaxSwapNames = MaxSwapNames;
+    type MaxForceRegistrations = MaxForceRegistrations;
+    type MaxTotalNames = MaxTotalNames;
+    type MaxRegistrationsPerBlock = MaxRegistrationsPerBlock;
+    type CongestionMultipliers = CongestionMultipliers;
+    type Randomness = TestRandomness;
+    type StrictTransfers = StrictTransfers;
+    type RegistrationIntentsOnly = IntentsOnly;
+    type IntentBond = IntentBond;
+    type MaxIntentsPerName = MaxIntentsPerName;
+
+    type MinPreorderDeposit = MinPreorderDeposit;
+    type PreorderMaturity = PreorderMaturity;
+    type PreorderWindow = PreorderWindow;
+    type PreorderForfeit = PreorderForfeit;
+    type FirstUpdatePeriod = FirstUpdatePeriod;
+    type FirstUpdateRefund = FirstUpdateRefund;
+    type ExpirationQuarantine = ExpirationQuarantine;
+    type RegistrarCommission = RegistrarCommission;
+    type MinBackorderBond = MinBackorderBond;
+    type ReportBond = ReportBond;
+    type ReportChallengePeriod = ReportChallengePeriod;
+    type ArchiveRetention = ArchiveRetention;
+    type GuardianConfirmationPeriod = GuardianConfirmationPeriod;
+    type MaxBackorders = MaxBackorders;
+    type MaxBulkRenewals = MaxBulkRenewals;
+    type MaxTopicPrefixes = MaxTopicPrefixes;
+    type MaxDelegatedNamespaces = MaxDelegatedNamespaces;
+    type MaxSessionDuration = MaxSessionDuration;
+    type MaxSessionNames = MaxSessionNames;
+    type MaxInsuredRenewals = MaxInsuredRenewals;
+
+    type Policy = TestPolicy;
+    type ExtraWeight = TestExtraWeight;
+    type WeightInfo = TestWeights;
+    type FeeConverter = TestFeeConverter;
+    type Payment = ();
+    type MaxFeeRateAge = MaxFeeRateAge;
+    type MinExpirationBlocks = MinExpirationBlocks;
+    type ExpirationUpdates = ExpirationUpdates;
+    type ComplianceWindow = ComplianceWindow;
+    type NonComplianceAction = NonComplianceAction;
+    type MaxExpirationsPerBlock = MaxExpirationsPerBlock;
+    type MaxAutoExpirations = AutoExpirations;
+    type SweepBounty = SweepBounty;
+    type MaxValueLookahead = MaxValueLookahead;
+    type ExpirationBucketSize = ExpirationBucketSize;
+    type ValueDepositPerByte = ValueDepositPerByte;
+    type OnOwnerReaped = TestReapedOwnerPolicy;
+    type OnExpiration = TestExpirationHandler;
+    type ValueCompliance = TestCompliance;
+    type OnNameOperation = TestOperationHook;
+    type Signature = TestSignature;
+    type Signer = UintAuthorityId;
+    type Patch = TestPatch;
+    type SubKey = u64;
+    type MaxSubvalues = MaxSubvalues;
+    type SubvalueDeposit = SubvalueDeposit;
+    type NoteDeposit = NoteDeposit;
+    type MaxNoteSize = MaxNoteSize;
+    type MaxRedirectDepth = MaxRedirectDepth;
+    type EnvelopeFee = EnvelopeFee;
+    type MaxEnvelopeSize = MaxEnvelopeSize;
+    type MaxEnvelopes = MaxEnvelopes;
+    type WatchDeposit = WatchDeposit;
+    type MaxWatchers = MaxWatchers;
+    type Templates = TestTemplates;
+    type MaxTemplates = MaxTemplates;
+    type Migration = TestMigration;
+    type MigrationWeightLimit = MigrationWeightLimit;
+    type DeferredWeightLimit = DeferredWeightLimit;
+    type ReceiptRetention = ReceiptRetention;
+    type StatsEraLength = StatsEraLength;
+    type StatsHistory = StatsHistory;
+    type FreeUpdatesPerEra = FreeUpdatesPerEra;
+
+}
+
+/// Name policy used in the tests.
+pub struct TestPolicy;
+impl policy::NamePolicy<Test> for TestPolicy {
+
+    fn get_name_fee(op: &Operation<Test>) -> Option<u128> {
         /* Just some dummy policy for valid operations.  */
         if op.value > 1_000 {
             return None
         }
 
+        /* Updates of names from 400 to 499 cost a fee, so that free
+           updates can be tested with them.  */
         Some(match op.operation {
             OperationType::Registration => 100,
-            OperationType::Update => 0,
+            OperationType::Update if op.name >= 400 && op.name < 500 => 10,
+            OperationType::Update | OperationType::Transfer => 0,
         })
     }
 
-    fn get_expiration(op: &Operation<Self>) -> Option<u64> {
+    fn get_expiration(op: &Operation<Test>) -> Option<u64> {
         if op.value == 0 {
             return None;
         }
         return Some(op.name);
     }
 
-    fn deposit_fee(neg: <Self::Currency as Currency<u64>>::NegativeImbalance) {
+    fn deposit_fee(neg: NegativeImbalanceOf<Test>) {
         let value = neg.peek();
         let pos = Balances::deposit_creating(&FEE_RECEIVER, value);
         let result = pos.offset(neg).ok().expect("fee balances offset failed");
@@ -126,6 +635,41 @@ impl Trait for Test {
 
 }
 
+/// Account that receives names of reaped accounts.
+const REAPED_FALLBACK: u64 = 999;
+
+/// Policy for names of reaped accounts:  Names from 500 to 599 are
+/// transferred to the fallback account, names from 600 to 699 expire
+/// in five blocks.  All others are kept.
+pub struct TestReapedOwnerPolicy;
+impl policy::ReapedOwnerPolicy<Test> for TestReapedOwnerPolicy {
+    fn reaped_name_action(name: &u64, _data: &NameData<Test>)
+        -> policy::ReapedNameAction<u64, u64>
+    {
+        match *name {
+            500..=599 => policy::ReapedNameAction::Transfer(REAPED_FALLBACK),
+            600..=699 => policy::ReapedNameAction::Expire(5),
+            _ => policy::ReapedNameAction::Keep,
+        }
+    }
+}
+
+/// Handler for expired names:  Names from 700 to 799 get a grace period
+/// of five blocks, names from 800 to 899 are transferred to the fallback
+/// account.  All others are deleted.
+pub struct TestExpirationHandler;
+impl policy::ExpirationHandler<Test> for TestExpirationHandler {
+    fn expiration_action(name: &u64, _data: &NameData<Test>)
+        -> policy::ExpirationAction<u64, u64>
+    {
+        match *name {
+            700..=799 => policy::ExpirationAction::Grace(5),
+            800..=899 => policy::ExpirationAction::Transfer(REAPED_FALLBACK),
+            _ => policy::ExpirationAction::Delete,
+        }
+    }
+}
+
 fn new_test_ext() -> sp_io::TestExternalities {
     system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
 }
@@ -150,7 +694,7 @@ fn expect_balance(account: u64, expected: u128) {
 /// Returns the list of names from the expiration index for a given
 /// block height.  The return value is sorted.
 fn get_expiring_names(h: u64) -> Vec<u64> {
-    let mut names: Vec<u64> = <Expirations<Test>>::iter_prefix(h).collect();
+    let mut names = Mod::expiring_at(h);
     names.sort();
     names
 }
@@ -176,6 +720,8 @@ mod extrinsics {
                 value: 42,
                 owner: 10,
                 expiration: Some(101),
+                nonce: 0,
+                held_since: 1,
             }));
             assert_eq!(<Names<Test>>::get(200), None);
             expect_balance(FEE_RECEIVER, 1100);
@@ -198,6 +744,8 @@ mod extrinsics {
                 value: 50,
                 owner: 10,
                 expiration: Some(101),
+                nonce: 1,
+                held_since: 1,
             }));
             expect_balance(FEE_RECEIVER, 1100);
             expect_balance(10, 4900);
@@ -220,6 +768,8 @@ mod extrinsics {
                 value: 99,
                 owner: 40,
                 expiration: Some(101),
+                nonce: 2,
+                held_since: 1,
             }));
             expect_balance(FEE_RECEIVER, 1100);
             expect_balance(10, 4900);
@@ -251,6 +801,8 @@ mod lookup {
                 value: 42,
                 owner: 10,
                 expiration: Some(101),
+                nonce: 0,
+                held_since: 1,
             }));
         });
     }
@@ -271,9 +823,16 @@ mod check_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 0,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 100,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
@@ -286,13 +845,30 @@ mod check_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 42,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 20,
                 fee: 100,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
 
+    #[test]
+    fn registration_default_value() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert_eq!(Mod::check_assuming_signed(10, 900, None, None).unwrap().value, 10);
+            assert_eq!(Mod::check_assuming_signed(10, 900, None, Some(20)).unwrap().value, 20);
+            assert_eq!(Mod::check_assuming_signed(10, 900, Some(42), None).unwrap().value, 42);
+        });
+    }
+
     #[test]
     fn update_nonowner() {
         new_test_ext().execute_with(|| {
@@ -300,6 +876,8 @@ mod check_function {
                 value: 42,
                 owner: 20,
                 expiration: None,
+                nonce: 0,
+                held_since: 0,
             });
             assert_noop!(Mod::check_assuming_signed(10, 100, None, None), "non-owner name update");
         });
@@ -312,14 +890,29 @@ mod check_function {
                 value: 42,
                 owner: 10,
                 expiration: None,
+                nonce: 0,
+                held_since: 0,
             });
             assert_ok!(Mod::check_assuming_signed(10, 100, None, None), Operation {
                 operation: OperationType::Update,
                 name: 100,
                 value: 42,
+                old_data: Some(NameData::<Test> {
+                    value: 42,
+                    owner: 10,
+                    expiration: None,
+                    nonce: 0,
+                    held_since: 0,
+                }),
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
@@ -331,14 +924,29 @@ mod check_function {
                 value: 42,
                 owner: 10,
                 expiration: None,
+                nonce: 0,
+                held_since: 0,
             });
             assert_ok!(Mod::check_assuming_signed(10, 100, Some(50), Some(20)), Operation {
                 operation: OperationType::Update,
                 name: 100,
                 value: 50,
+                old_data: Some(NameData::<Test> {
+                    value: 42,
+                    owner: 10,
+                    expiration: None,
+                    nonce: 0,
+                    held_since: 0,
+                }),
                 sender: 10,
+                payer: 10,
                 recipient: 20,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
@@ -375,9 +983,16 @@ mod check_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 50,
+                old_data: None,
                 sender: ok_account,
+                payer: ok_account,
                 recipient: 20,
                 fee: 100,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
@@ -392,9 +1007,16 @@ mod check_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 1_000,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 100,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             });
         });
     }
@@ -414,28 +1036,46 @@ mod execute_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 42,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_eq!(<Names<Test>>::get(100), Some(NameData::<Test> {
                 value: 42,
                 owner: 10,
                 expiration: Some(101),
+                nonce: 0,
+                held_since: 1,
             }));
 
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 100,
                 value: 50,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 20,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_eq!(<Names<Test>>::get(100), Some(NameData::<Test> {
                 value: 50,
                 owner: 20,
                 expiration: Some(101),
+                nonce: 1,
+                held_since: 1,
             }));
         });
     }
@@ -443,18 +1083,24 @@ mod execute_function {
     #[test]
     fn stores_expiration() {
         new_test_ext().execute_with(|| {
-            <Expirations<Test>>::insert(90, 5, 5);
-            <Expirations<Test>>::insert(100, 10, 10);
-            <Expirations<Test>>::insert(100, 20, 20);
+            <ExpirationQueue<Test>>::insert(90, vec![5]);
+            <ExpirationQueue<Test>>::insert(100, vec![10, 20]);
 
             System::set_block_number(70);
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 20,
                 value: 10,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
 
             System::set_block_number(80);
@@ -462,41 +1108,68 @@ mod execute_function {
                 operation: OperationType::Update,
                 name: 20,
                 value: 10,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 30,
                 value: 0,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 40,
                 value: 100,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
 
             assert_eq!(<Names<Test>>::get(20), Some(NameData::<Test> {
                 value: 10,
                 owner: 10,
                 expiration: Some(100),
+                nonce: 0,
+                held_since: 70,
             }));
             assert_eq!(<Names<Test>>::get(30), Some(NameData::<Test> {
                 value: 0,
                 owner: 10,
                 expiration: None,
+                nonce: 0,
+                held_since: 80,
             }));
             assert_eq!(<Names<Test>>::get(40), Some(NameData::<Test> {
                 value: 100,
                 owner: 10,
                 expiration: Some(120),
+                nonce: 0,
+                held_since: 80,
             }));
 
             assert_eq!(get_expiring_names(90), vec![5, 20]);
@@ -513,15 +1186,24 @@ mod execute_function {
                 operation: OperationType::Update,
                 name: 0,
                 value: 10,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
 
             assert_eq!(<Names<Test>>::get(0), Some(NameData::<Test> {
                 value: 10,
                 owner: 10,
                 expiration: Some(71),
+                nonce: 0,
+                held_since: 70,
             }));
 
             assert_eq!(get_expiring_names(71), vec![0]);
@@ -537,9 +1219,16 @@ mod execute_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 50,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 50,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             expect_balance(FEE_RECEIVER, 1050);
             expect_balance(10, 4950);
@@ -550,9 +1239,16 @@ mod execute_function {
                 operation: OperationType::Update,
                 name: 100,
                 value: 60,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 20,
                 fee: 5000,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }), DispatchError::Module {
                 index: 0,
                 error: 3,
@@ -566,14 +1262,23 @@ mod execute_function {
                 operation: OperationType::Update,
                 name: 100,
                 value: 70,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 4000,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_eq!(<Names<Test>>::get(100), Some(NameData::<Test> {
                 value: 70,
                 owner: 10,
                 expiration: Some(101),
+                nonce: 1,
+                held_since: 1,
             }));
             expect_balance(FEE_RECEIVER, 5050);
             expect_balance(10, 0);
@@ -581,6 +1286,87 @@ mod execute_function {
         });
     }
 
+    #[test]
+    fn unchecked_operation() {
+        new_test_ext().execute_with(|| {
+            /* The sender account does not exist, which is fine since
+               there is no fee to withdraw.  */
+            assert_eq!(Mod::unchecked_operation(100, 42, 10), Operation {
+                operation: OperationType::Registration,
+                name: 100,
+                value: 42,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            });
+            assert_ok!(Mod::execute(Mod::unchecked_operation(100, 42, 10)));
+            assert_eq!(<Names<Test>>::get(100), Some(NameData::<Test> {
+                value: 42,
+                owner: 10,
+                expiration: Some(101),
+                nonce: 0,
+                held_since: 1,
+            }));
+
+            assert_eq!(Mod::unchecked_operation(100, 50, 20), Operation {
+                operation: OperationType::Update,
+                name: 100,
+                value: 50,
+                old_data: Some(NameData::<Test> {
+                    value: 42,
+                    owner: 10,
+                    expiration: Some(101),
+                    nonce: 0,
+                    held_since: 1,
+                }),
+                sender: 20,
+                payer: 20,
+                recipient: 20,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            });
+        });
+    }
+
+    #[test]
+    fn unchecked_transfer() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(Mod::execute(Mod::unchecked_operation(100, 42, 10)));
+            let data = <Names<Test>>::get(100).unwrap();
+
+            assert_eq!(Mod::unchecked_transfer(100, data.clone(), 20), Operation {
+                operation: OperationType::Transfer,
+                name: 100,
+                value: 42,
+                old_data: Some(data.clone()),
+                sender: 10,
+                payer: 10,
+                recipient: 20,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            });
+            assert_ok!(Mod::execute(Mod::unchecked_transfer(100, data, 20)));
+            let data = <Names<Test>>::get(100).unwrap();
+            assert_eq!(data.owner, 20);
+            assert_eq!(data.value, 42);
+        });
+    }
+
     #[test]
     fn events() {
         new_test_ext().execute_with(|| {
@@ -592,17 +1378,31 @@ mod execute_function {
                 operation: OperationType::Registration,
                 name: 100,
                 value: 42,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 100,
                 value: 50,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 20,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
 
             let name_events = vec![
@@ -617,6 +1417,8 @@ mod execute_function {
                         value: 42,
                         owner: 10,
                         expiration: Some(101),
+                        nonce: 0,
+                        held_since: 1,
                     })),
                     topics: vec![],
                 },
@@ -626,6 +1428,8 @@ mod execute_function {
                         value: 50,
                         owner: 20,
                         expiration: Some(101),
+                        nonce: 1,
+                        held_since: 1,
                     })),
                     topics: vec![],
                 },
@@ -652,9 +1456,16 @@ mod expiration {
                 operation: OperationType::Registration,
                 name: 10,
                 value: 42,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_eq!(get_expiring_names(110), vec![10]);
             let prior_events = System::events();
@@ -669,6 +1480,11 @@ mod expiration {
                     event: TestEvent::names(RawEvent::NameExpired(10)),
                     topics: vec![],
                 },
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: TestEvent::names(RawEvent::ExpirationsProcessed(1, 0, 0)),
+                    topics: vec![],
+                },
             ];
             assert_eq!(System::events(),
                        [&prior_events[..], &expire_events[..]].concat());
@@ -684,36 +1500,62 @@ mod expiration {
                 operation: OperationType::Registration,
                 name: 10,
                 value: 42,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             System::set_block_number(105);
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 10,
                 value: 50,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
 
             assert_eq!(get_expiring_names(110), vec![10]);
             Mod::expire_names(110);
             assert_eq!(get_expiring_names(110), vec![]);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ExpirationsProcessed(0, 1, 0))
+            }));
             assert_eq!(<Names<Test>>::get(10), Some(NameData::<Test> {
                 value: 50,
                 owner: 10,
                 expiration: Some(115),
+                nonce: 1,
+                held_since: 100,
             }));
 
             assert_ok!(Mod::execute(Operation {
                 operation: OperationType::Update,
                 name: 10,
                 value: 0,
+                old_data: None,
                 sender: 10,
+                payer: 10,
                 recipient: 10,
                 fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
             }));
             assert_eq!(get_expiring_names(115), vec![10]);
             Mod::expire_names(115);
@@ -722,6 +1564,6746 @@ mod expiration {
                 value: 0,
                 owner: 10,
                 expiration: None,
+                nonce: 2,
+                held_since: 100,
+            }));
+        });
+    }
+
+    #[test]
+    fn per_block_limit() {
+        new_test_ext().execute_with(|| {
+            for name in 1..=4 {
+                Mod::schedule_expiration(&name, 50);
+            }
+            assert_eq!(Mod::schedule_expiration(&2, 50), 50);
+            assert_eq!(get_expiring_names(50), vec![1, 2, 3]);
+            assert_eq!(get_expiring_names(51), vec![4]);
+
+            /* Names that do not fit into their block expire later.  */
+            <ExpirationQueue<Test>>::insert(110, vec![1, 2, 3]);
+            System::set_block_number(100);
+            assert_ok!(Mod::execute(Operation {
+                operation: OperationType::Registration,
+                name: 10,
+                value: 42,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            }));
+            assert_eq!(Mod::lookup(10).unwrap().expiration, Some(111));
+            assert_eq!(get_expiring_names(111), vec![10]);
+        });
+    }
+
+    #[test]
+    fn buckets() {
+        new_test_ext().execute_with(|| {
+            set_bucket_size(10);
+            assert_eq!(Mod::schedule_expiration(&1, 50), 50);
+            assert_eq!(Mod::schedule_expiration(&2, 51), 60);
+            assert_eq!(Mod::schedule_expiration(&2, 59), 60);
+            assert_eq!(get_expiring_names(60), vec![2]);
+
+            System::set_block_number(100);
+            assert_ok!(Mod::execute(Operation {
+                operation: OperationType::Registration,
+                name: 15,
+                value: 42,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            }));
+            assert_eq!(Mod::lookup(15).unwrap().expiration, Some(120));
+        });
+    }
+
+    #[test]
+    fn legacy_index() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(100);
+            assert_ok!(Mod::execute(Operation {
+                operation: OperationType::Registration,
+                name: 10,
+                value: 42,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            }));
+
+            /* Simulate an entry from before the expiration queue.  It is
+               not processed anymore by itself, only once migrated.  */
+            <ExpirationQueue<Test>>::remove(110);
+            <Expirations<Test>>::insert(110, 10, 10);
+            <Expirations<Test>>::insert(105, 10, 10);
+
+            <migration::MigrateExpirations as migration::NameMigration<Test>>::migrate_name(&10);
+            assert!(!<Expirations<Test>>::exists(110, 10));
+            assert_eq!(Mod::expiring_at(110), vec![10]);
+            assert_eq!(Mod::lookup(10).unwrap().expiration, Some(110));
+
+            Mod::expire_names(110);
+            assert_eq!(<Names<Test>>::get(10), None);
+        });
+    }
+
+    #[test]
+    fn late_legacy_migration() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(100);
+            assert_ok!(Mod::execute(Operation {
+                operation: OperationType::Registration,
+                name: 10,
+                value: 42,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            }));
+            <ExpirationQueue<Test>>::remove(110);
+            <Expirations<Test>>::insert(110, 10, 10);
+
+            /* The legacy entry's block has passed without processing it,
+               so the name is scheduled for the next block.  */
+            System::set_block_number(115);
+            Mod::expire_names(110);
+            assert!(Mod::lookup(10).is_some());
+
+            <migration::MigrateExpirations as migration::NameMigration<Test>>::migrate_name(&10);
+            assert_eq!(Mod::lookup(10).unwrap().expiration, Some(116));
+            assert_eq!(Mod::expiring_at(116), vec![10]);
+            Mod::expire_names(116);
+            assert_eq!(<Names<Test>>::get(10), None);
+        });
+    }
+
+    fn register(name: u64) {
+        assert_ok!(Mod::execute(Operation {
+            operation: OperationType::Registration,
+            name: name,
+            value: 42,
+            old_data: None,
+            sender: 10,
+            payer: 10,
+            recipient: 10,
+            fee: 0,
+            deposit: 0,
+            free_update: false,
+            registrar: None,
+            renewal: false,
+            never_expires: false,
+        }));
+    }
+
+    #[test]
+    fn grace_period() {
+        new_test_ext().execute_with(|| {
+            register(700);
+            register(701);
+
+            assert!(Mod::active_at(&700, 704));
+            assert!(!Mod::active_at(&700, 705));
+
+            System::set_block_number(700);
+            Mod::expire_names(700);
+            assert_eq!(Mod::lookup(700).unwrap().expiration, Some(705));
+            assert!(Mod::active_at(&700, 704));
+            assert!(!Mod::active_at(&700, 705));
+            assert!(Mod::in_grace_period(700));
+            assert_eq!(get_expiring_names(705), vec![700]);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameInGracePeriod(700, 705))
+            }));
+
+            /* Renewing the name during the grace period keeps it.  */
+            System::set_block_number(701);
+            Mod::expire_names(701);
+            assert!(Mod::in_grace_period(701));
+            System::set_block_number(702);
+            assert_ok!(Mod::execute(Operation {
+                operation: OperationType::Update,
+                name: 701,
+                value: 50,
+                old_data: None,
+                sender: 10,
+                payer: 10,
+                recipient: 10,
+                fee: 0,
+                deposit: 0,
+                free_update: false,
+                registrar: None,
+                renewal: false,
+                never_expires: false,
+            }));
+            assert!(!Mod::in_grace_period(701));
+
+            System::set_block_number(705);
+            Mod::expire_names(705);
+            assert_eq!(Mod::lookup(700), None);
+            assert!(!Mod::in_grace_period(700));
+            System::set_block_number(706);
+            Mod::expire_names(706);
+            assert_eq!(Mod::lookup(701).unwrap().expiration, Some(1403));
+        });
+    }
+
+    #[test]
+    fn transfer_on_expiration() {
+        new_test_ext().execute_with(|| {
+            register(800);
+
+            System::set_block_number(800);
+            Mod::expire_names(800);
+            assert_eq!(Mod::lookup(800), Some(NameData::<Test> {
+                value: 42,
+                owner: REAPED_FALLBACK,
+                expiration: Some(1600),
+                nonce: 1,
+                held_since: 800,
+            }));
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameTransferred(800, 10, REAPED_FALLBACK))
+            }));
+        });
+    }
+
+    #[test]
+    fn query_helpers() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(100);
+            for (name, value) in vec![(10, 42), (11, 0)] {
+                assert_ok!(Mod::execute(Operation {
+                    operation: OperationType::Registration,
+                    name: name,
+                    value: value,
+                    old_data: None,
+                    sender: 10,
+                    payer: 10,
+                    recipient: 10,
+                    fee: 0,
+                    deposit: 0,
+                    free_update: false,
+                    registrar: None,
+                    renewal: false,
+                    never_expires: false,
+                }));
+            }
+
+            assert_eq!(Mod::next_expiration(&10), Some(110));
+            assert!(Mod::active_at(&10, 109));
+            assert!(!Mod::active_at(&10, 110));
+
+            assert_eq!(Mod::next_expiration(&11), None);
+            assert!(Mod::active_at(&11, 1_000_000));
+
+            assert_eq!(Mod::next_expiration(&12), None);
+            assert!(!Mod::active_at(&12, 100));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the per-name operation nonce.
+mod name_nonces {
+    use super::*;
+
+    #[test]
+    fn bumped_by_operations() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::lookup(100).unwrap().nonce, 0);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 50));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().nonce, 2);
+
+            /* After expiration, a new registration starts from zero.  */
+            Mod::expire_names(101);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::lookup(100).unwrap().nonce, 0);
+        });
+    }
+
+    #[test]
+    fn set_value_at_nonce() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_noop!(Mod::set_value_at_nonce(Origin::signed(10), 100, 42, 0),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::set_value_at_nonce(Origin::signed(10), 100, 50, 0));
+            assert_noop!(Mod::set_value_at_nonce(Origin::signed(10), 100, 60, 0),
+                         "name nonce mismatch");
+            assert_noop!(Mod::set_value_at_nonce(Origin::signed(20), 100, 60, 1),
+                         "non-owner name update");
+            assert_ok!(Mod::set_value_at_nonce(Origin::signed(10), 100, 60, 1));
+            assert_eq!(Mod::lookup(100).unwrap().value, 60);
+        });
+    }
+
+    #[test]
+    fn signed_operations() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            let order = SignedOperation::<Test> {
+                owner: 10,
+                name: 100,
+                value: None,
+                recipient: Some(20),
+                nonce: 0,
+                name_nonce: Some(0),
+            };
+            let signature = TestSignature(10, Mod::signed_operation_message(&order));
+
+            /* Any change to the name invalidates the signed order.  */
+            assert_ok!(Mod::update(Origin::signed(10), 100, 50));
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(20), order.clone(),
+                                                       signature.clone()),
+                         "name nonce mismatch");
+
+            let order = SignedOperation::<Test> {
+                name_nonce: Some(1),
+                ..order
+            };
+            let signature = TestSignature(10, Mod::signed_operation_message(&order));
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(20), order, signature));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the versioned encoding of NameData.
+mod name_data_encoding {
+    use super::*;
+    use codec::{Decode, Encode};
+
+    fn test_data(nonce: u64) -> NameData<Test> {
+        NameData::<Test> {
+            value: 42,
+            owner: 10,
+            expiration: Some(100),
+            nonce: nonce,
+            held_since: 0,
+        }
+    }
+
+    #[test]
+    fn layout() {
+        /* This guards against accidental changes to the encoding, which
+           would break all names stored already.  */
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&[42, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[10, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 100, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[3, 64, 5, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[9, 0, 0, 0, 0, 0, 0, 0]);
+        let data = NameData::<Test> {
+            held_since: 9,
+            ..test_data(5)
+        };
+        assert_eq!(data.encode(), expected);
+        assert_eq!(NameData::<Test>::decode(&mut &expected[..]), Ok(data));
+    }
+
+    #[test]
+    fn legacy_entries() {
+        let legacy = (42u64, 10u64, Some(100u64)).encode();
+        assert_eq!(NameData::<Test>::decode(&mut &legacy[..]), Ok(test_data(0)));
+
+        let mut version_one = legacy.clone();
+        version_one.extend_from_slice(&[1, 0]);
+        assert_eq!(NameData::<Test>::decode(&mut &version_one[..]), Ok(test_data(0)));
+
+        let mut version_two = legacy.clone();
+        version_two.push(2);
+        version_two.extend(5u64.encode().encode());
+        assert_eq!(NameData::<Test>::decode(&mut &version_two[..]), Ok(test_data(5)));
+    }
+
+    #[test]
+    fn future_versions() {
+        /* A later version with additional extension data, followed by other
+           data that must not be consumed.  */
+        let mut future = (42u64, 10u64, Some(100u64)).encode();
+        future.push(4);
+        future.extend((7u64, 8u64, 1u8, 2u8).encode().encode());
+        future.push(99);
+
+        let mut input = &future[..];
+        assert_eq!(NameData::<Test>::decode(&mut input), Ok(NameData::<Test> {
+            held_since: 8,
+            ..test_data(7)
+        }));
+        assert_eq!(input, &[99]);
+    }
+
+    #[test]
+    fn storage_roundtrip() {
+        new_test_ext().execute_with(|| {
+            <Names<Test>>::insert(5, test_data(3));
+            assert_eq!(Mod::lookup(5), Some(test_data(3)));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the genesis config.
+mod genesis {
+    use super::*;
+
+    #[test]
+    fn initial_names() {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            names: vec![
+                (10, 42, 1, None),
+                (20, 50, 2, Some(100)),
+                (30, 60, 3, Some(0)),
+            ],
+            system_names: vec![],
+            reserved_prefixes: vec![],
+            topic_prefixes: vec![],
+        }.assimilate_storage(&mut t).unwrap();
+
+        sp_io::TestExternalities::from(t).execute_with(|| {
+            assert_eq!(Mod::lookup(10), Some(NameData::<Test> {
+                value: 42,
+                owner: 1,
+                expiration: None,
+                nonce: 0,
+                held_since: 0,
+            }));
+            assert_eq!(Mod::lookup(20), Some(NameData::<Test> {
+                value: 50,
+                owner: 2,
+                expiration: Some(100),
+                nonce: 0,
+                held_since: 0,
+            }));
+            assert_eq!(Mod::lookup(30), Some(NameData::<Test> {
+                value: 60,
+                owner: 3,
+                expiration: Some(1),
+                nonce: 0,
+                held_since: 0,
+            }));
+
+            assert_eq!(get_expiring_names(1), vec![30]);
+            assert_eq!(get_expiring_names(100), vec![20]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the namehash index.
+mod namehash_index {
+    use super::*;
+    use crate::namehash::{EnsNamehash, Namehash};
+    use hex_literal::hex;
+
+    fn ens(name: &str) -> H256 {
+        <EnsNamehash as Namehash<Vec<u8>>>::namehash(&name.as_bytes().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn ens_namehash() {
+        /* Test vectors from EIP-137.  */
+        assert_eq!(ens(""), H256::default());
+        assert_eq!(ens("eth"), H256::from(
+            hex!("93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")));
+        assert_eq!(ens("foo.eth"), H256::from(
+            hex!("de9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")));
+    }
+
+    #[test]
+    fn registration_and_expiration() {
+        new_test_ext().execute_with(|| {
+            System::set_block_number(10);
+            for name in &[5, 20, 2_000] {
+                assert_ok!(Mod::execute(Mod::unchecked_operation(*name, 42, 10)));
+            }
+            assert_ok!(Mod::execute(Mod::unchecked_operation(20, 50, 20)));
+
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(5)), Some(5));
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(20)), Some(20));
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(2_000)), None);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(42)), None);
+
+            Mod::expire_names(15);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(5)), None);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(20)), Some(20));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for hidden registrations.
+mod hidden_registrations {
+    use super::*;
+    use sp_runtime::traits::Hash;
+
+    fn hash(name: u64) -> H256 {
+        BlakeTwo256::hash_of(&name)
+    }
+
+    #[test]
+    fn registration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(20);
+
+            assert_noop!(Mod::register_hidden(Origin::ROOT, hash(100)),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(100)));
+            assert_noop!(Mod::register_hidden(Origin::signed(20), hash(100)),
+                         "name hash is registered already");
+
+            assert_eq!(Mod::lookup_hidden(hash(100)), Some(HiddenRegistration::<Test> {
+                owner: 10,
+                expiration: 30,
+            }));
+            expect_balance(FEE_RECEIVER, 1050);
+            expect_balance(10, 4950);
+
+            /* The name cannot be registered by others in the mean time.  */
+            add_balance(20, 5000);
+            assert_noop!(Mod::update(Origin::signed(20), 100, 42),
+                         "name is reserved by a hidden registration");
+        });
+    }
+
+    #[test]
+    fn reveal_validity_window() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(100)));
+
+            assert_noop!(Mod::reveal_name(Origin::signed(10), 100, Some(5)),
+                         "transaction is past its validity window");
+            assert_ok!(Mod::reveal_name(Origin::signed(10), 100, Some(20)));
+        });
+    }
+
+    #[test]
+    fn reveal() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(100)));
+
+            assert_noop!(Mod::reveal_name(Origin::signed(10), 200, None),
+                         "no hidden registration for the name");
+            assert_noop!(Mod::reveal_name(Origin::signed(20), 100, None),
+                         "non-owner name reveal");
+            assert_ok!(Mod::reveal_name(Origin::signed(10), 100, None));
+
+            assert_eq!(Mod::lookup(100), Some(NameData::<Test> {
+                value: 0,
+                owner: 10,
+                expiration: None,
+                nonce: 0,
+                held_since: 1,
+            }));
+            assert_eq!(Mod::lookup_hidden(hash(100)), None);
+            expect_balance(FEE_RECEIVER, 1150);
+            expect_balance(10, 4850);
+
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameRevealed(hash(100), 100))
+            }));
+        });
+    }
+
+    #[test]
+    fn normal_registration_by_owner() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(100)));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::lookup_hidden(hash(100)), None);
+        });
+    }
+
+    #[test]
+    fn expiration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(20);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(100)));
+            assert_ok!(Mod::register_hidden(Origin::signed(10), hash(200)));
+            assert_ok!(Mod::reveal_name(Origin::signed(10), 200, None));
+
+            Mod::expire_hidden(29);
+            assert!(Mod::lookup_hidden(hash(100)).is_some());
+            let prior_events = System::events();
+
+            Mod::expire_hidden(30);
+            assert_eq!(Mod::lookup_hidden(hash(100)), None);
+            let expire_events = vec![
+                EventRecord {
+                    phase: Phase::ApplyExtrinsic(0),
+                    event: TestEvent::names(RawEvent::HiddenRegistrationExpired(hash(100))),
+                    topics: vec![],
+                },
+            ];
+            assert_eq!(System::events(),
+                       [&prior_events[..], &expire_events[..]].concat());
+
+            assert_ok!(Mod::update(Origin::signed(20), 100, 42));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for verification of signatures by name owners.
+mod name_signatures {
+    use super::*;
+
+    #[test]
+    fn verification() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            let msg = b"log in".to_vec();
+            assert!(Mod::verify_name_signature(&100, &msg, &TestSignature(10, msg.clone())));
+            assert!(!Mod::verify_name_signature(&100, b"other", &TestSignature(10, msg.clone())));
+            assert!(!Mod::verify_name_signature(&100, &msg, &TestSignature(20, msg.clone())));
+            assert!(!Mod::verify_name_signature(&200, &msg, &TestSignature(10, msg.clone())));
+
+            /* After a transfer, the new owner's signature is required.  */
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert!(!Mod::verify_name_signature(&100, &msg, &TestSignature(10, msg.clone())));
+            assert!(Mod::verify_name_signature(&100, &msg, &TestSignature(20, msg.clone())));
+        });
+    }
+
+    #[test]
+    fn signing_key() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::set_signing_key(Origin::signed(10), 200, Some(30)),
+                         "name does not exist");
+            assert_noop!(Mod::set_signing_key(Origin::signed(20), 100, Some(30)),
+                         "non-owner signing key update");
+
+            let msg = b"payload".to_vec();
+            assert!(Mod::verify_with_signing_key(&100, &msg, &TestSignature(10, msg.clone())));
+
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 100, Some(30)));
+            assert_eq!(Mod::signing_key(100), Some(30));
+            assert!(Mod::verify_with_signing_key(&100, &msg, &TestSignature(30, msg.clone())));
+            assert!(!Mod::verify_with_signing_key(&100, &msg, &TestSignature(10, msg.clone())));
+            /* The owner check itself is not affected by the signing key.  */
+            assert!(Mod::verify_name_signature(&100, &msg, &TestSignature(10, msg.clone())));
+
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 100, None));
+            assert_eq!(Mod::signing_key(100), None);
+            assert!(Mod::verify_with_signing_key(&100, &msg, &TestSignature(10, msg.clone())));
+        });
+    }
+
+    #[test]
+    fn signing_key_cleared() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 100, Some(30)));
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 200, Some(30)));
+
+            /* Updates keep the key, transfers clear it.  */
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_eq!(Mod::signing_key(100), Some(30));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::signing_key(100), None);
+
+            /* Expiration clears it as well.  */
+            Mod::expire_names(200);
+            assert_eq!(Mod::lookup(200), None);
+            assert_eq!(Mod::signing_key(200), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for identity links.
+mod identity_links {
+    use super::*;
+
+    #[test]
+    fn linking() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(200, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 101, 42));
+            assert_ok!(Mod::update(Origin::signed(200), 102, 42));
+
+            assert_noop!(Mod::link_identity(Origin::signed(10), 200),
+                         "name does not exist");
+            assert_noop!(Mod::link_identity(Origin::signed(200), 100),
+                         "non-owner identity link");
+            assert_noop!(Mod::link_identity(Origin::signed(200), 102),
+                         "sender has no identity");
+            assert_noop!(Mod::unlink_identity(Origin::signed(10), 100),
+                         "name is not linked");
+
+            assert_ok!(Mod::link_identity(Origin::signed(10), 100));
+            assert_eq!(Mod::identity_of_name(100), Some(10));
+            assert_eq!(Mod::name_of_identity(10), Some(100));
+
+            /* Linking another name replaces the previous link.  */
+            assert_ok!(Mod::link_identity(Origin::signed(10), 101));
+            assert_eq!(Mod::identity_of_name(100), None);
+            assert_eq!(Mod::identity_of_name(101), Some(10));
+            assert_eq!(Mod::name_of_identity(10), Some(101));
+
+            assert_ok!(Mod::unlink_identity(Origin::signed(10), 101));
+            assert_eq!(Mod::identity_of_name(101), None);
+            assert_eq!(Mod::name_of_identity(10), None);
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::link_identity(Origin::signed(10), 100));
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_eq!(Mod::identity_of_name(100), Some(10));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::identity_of_name(100), None);
+            assert_eq!(Mod::name_of_identity(10), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::IdentityUnlinked(100, 10))
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the administrative extrinsics.
+mod force_calls {
+    use super::*;
+    use crate::weights::WeightInfo;
+    use frame_support::weights::{DispatchClass, GetDispatchInfo};
+
+    #[test]
+    fn transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 100, Some(30)));
+
+            assert_noop!(Mod::force_transfer(Origin::signed(10), 100, 20),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::force_transfer(Origin::ROOT, 200, 20),
+                         "name does not exist");
+            assert_ok!(Mod::force_transfer(Origin::ROOT, 100, 20));
+
+            assert_eq!(Mod::lookup(100), Some(NameData::<Test> {
+                value: 42,
+                owner: 20,
+                expiration: Some(100),
+                nonce: 1,
+                held_since: 1,
+            }));
+            assert_eq!(Mod::signing_key(100), None);
+            expect_balance(10, 4900);
+        });
+    }
+
+    #[test]
+    fn remove() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::force_remove(Origin::signed(10), 100),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::force_remove(Origin::ROOT, 200),
+                         "name does not exist");
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(100)), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameRemoved(100))
+            }));
+
+            /* The stale expiration index entry is harmless.  */
+            Mod::expire_names(100);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            Mod::expire_names(100);
+            assert!(Mod::lookup(100).is_some());
+        });
+    }
+
+    #[test]
+    fn register_batch() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::force_register_batch(Origin::signed(10), vec![(200, 1, 20, None)]),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::force_register_batch(Origin::ROOT, vec![
+                (200, 1, 20, None), (300, 1, 20, None), (400, 1, 20, None), (500, 1, 20, None),
+            ]), "too many names to register");
+            assert_noop!(Mod::force_register_batch(Origin::ROOT, vec![
+                (200, 1, 20, None), (100, 1, 20, None),
+            ]), "name exists already");
+            assert_noop!(Mod::force_register_batch(Origin::ROOT, vec![
+                (200, 1, 20, None), (200, 2, 30, None),
+            ]), "duplicate name in batch");
+
+            assert_ok!(Mod::force_register_batch(Origin::ROOT, vec![
+                (200, 1, 20, None), (300, 2, 30, Some(5)), (901, 3, 20, Some(50)),
+            ]));
+            assert_eq!(Mod::lookup(200).map(|d| (d.value, d.owner, d.expiration)),
+                       Some((1, 20, None)));
+            assert_eq!(Mod::lookup(300).map(|d| (d.value, d.owner, d.expiration)),
+                       Some((2, 30, Some(11))));
+            assert_eq!(Mod::lookup(901).map(|d| (d.value, d.owner, d.expiration)),
+                       Some((3, 20, Some(50))));
+            assert_eq!(Mod::total_names(), 4);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameRegistered(300))
+            }));
+        });
+    }
+
+    #[test]
+    fn operational() {
+        let info = Call::<Test>::force_transfer(100, 20).get_dispatch_info();
+        assert_eq!(info.class, DispatchClass::Operational);
+        let info = Call::<Test>::force_remove(100).get_dispatch_info();
+        assert_eq!(info.class, DispatchClass::Operational);
+        let info = Call::<Test>::force_register_batch(vec![(200, 1, 20, None); 3])
+            .get_dispatch_info();
+        assert_eq!(info.class, DispatchClass::Operational);
+        assert_eq!(info.weight, 3 * TestWeights::force_register_batch());
+        let info = Call::<Test>::update(100, 42).get_dispatch_info();
+        assert_eq!(info.class, DispatchClass::Normal);
+    }
+
+    #[test]
+    fn bulk_transfer_weight() {
+        let info = Call::<Test>::transfer_all_names(20, 0).get_dispatch_info();
+        assert_eq!(info.weight, 2 * TestWeights::transfer_all_names());
+        let info = Call::<Test>::transfer_all_names(20, 10).get_dispatch_info();
+        assert_eq!(info.weight, 2 * TestWeights::transfer_all_names()
+                                + 10 * TestWeights::transfer_all_names_skipped());
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for dispatching calls as a name.
+mod name_dispatch {
+    use super::*;
+    use crate::weights::WeightInfo;
+    use frame_support::weights::{DispatchClass, GetDispatchInfo};
+
+    #[test]
+    fn weight_of_inner_call() {
+        let inner = Call::<Test>::update(200, 1);
+        let info = Call::<Test>::dispatch_as_name(100, Box::new(inner.clone()))
+            .get_dispatch_info();
+        assert_eq!(info.weight,
+                   inner.get_dispatch_info().weight + TestWeights::dispatch_as_name());
+        assert_eq!(info.class, DispatchClass::Normal);
+
+        let inner = Call::<Test>::force_register_batch(vec![(200, 1, 20, None); 3]);
+        let info = Call::<Test>::dispatch_as_name(100, Box::new(inner))
+            .get_dispatch_info();
+        assert_eq!(info.weight,
+                   3 * TestWeights::force_register_batch() + TestWeights::dispatch_as_name());
+        assert_eq!(info.class, DispatchClass::Operational);
+    }
+
+    #[test]
+    fn name_accounts() {
+        assert_eq!(Mod::name_account(&100), Mod::name_account(&100));
+        assert_ne!(Mod::name_account(&100), Mod::name_account(&101));
+        assert_ne!(Mod::name_account(&100), Mod::account_id());
+    }
+
+    #[test]
+    fn dispatch_checks() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let call = |name, value| Box::new(Call::update(name, value));
+            assert_noop!(Mod::dispatch_as_name(Origin::signed(10), 100, call(200, 5)),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::dispatch_as_name(Origin::signed(20), 100, call(200, 5)),
+                         "non-owner name dispatch");
+            assert_noop!(Mod::dispatch_as_name(Origin::signed(10), 100,
+                                               Box::new(Call::transfer(100, 20))),
+                         "call is not allowed for dispatch as a name");
+        });
+    }
+
+    #[test]
+    fn resources_follow_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let account = Mod::name_account(&100);
+            add_balance(account, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::dispatch_as_name(Origin::signed(10), 100,
+                                             Box::new(Call::update(200, 5))));
+            assert_eq!(Mod::lookup(200).map(|d| (d.owner, d.value)), Some((account, 5)));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_noop!(Mod::dispatch_as_name(Origin::signed(10), 100,
+                                               Box::new(Call::update(200, 6))),
+                         "non-owner name dispatch");
+            assert_ok!(Mod::dispatch_as_name(Origin::signed(20), 100,
+                                             Box::new(Call::update(200, 6))));
+            assert_eq!(Mod::lookup(200).map(|d| (d.owner, d.value)), Some((account, 6)));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the name-owner origin checks.
+mod owner_origins {
+    use super::*;
+
+    parameter_types! {
+        pub const GatedName: u64 = 100;
+    }
+
+    #[test]
+    fn ensure_name_owner() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_noop!(Mod::ensure_name_owner(Origin::signed(10), &100),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_eq!(Mod::ensure_name_owner(Origin::signed(10), &100), Ok(10));
+            assert_noop!(Mod::ensure_name_owner(Origin::signed(20), &100),
+                         "caller does not own the name");
+            assert_noop!(Mod::ensure_name_owner(Origin::ROOT, &100),
+                         DispatchError::BadOrigin);
+        });
+    }
+
+    #[test]
+    fn ensure_origin_adapter() {
+        type Gate = EnsureNameOwner<Test, GatedName>;
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert!(Gate::try_origin(Origin::signed(10)).is_err());
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_eq!(Gate::try_origin(Origin::signed(10)).ok(), Some(10));
+            assert!(Gate::try_origin(Origin::signed(20)).is_err());
+            assert!(Gate::try_origin(Origin::ROOT).is_err());
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Gate::try_origin(Origin::signed(20)).ok(), Some(20));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for system names owned by the pallet account.
+mod system_names {
+    use super::*;
+
+    #[test]
+    fn genesis() {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            names: vec![(10, 42, 1, None)],
+            system_names: vec![(20, 50)],
+            reserved_prefixes: vec![],
+            topic_prefixes: vec![],
+        }.assimilate_storage(&mut t).unwrap();
+
+        sp_io::TestExternalities::from(t).execute_with(|| {
+            assert_eq!(Mod::lookup(20), Some(NameData::<Test> {
+                value: 50,
+                owner: Mod::account_id(),
+                expiration: None,
+                nonce: 0,
+                held_since: 0,
+            }));
+            assert!(Mod::is_system_name(20));
+            assert!(!Mod::is_system_name(10));
+            assert_eq!(Mod::total_names(), 2);
+            assert_eq!(Mod::names_of(&Mod::account_id()), vec![20]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate name in genesis config")]
+    fn duplicate_genesis_names() {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            names: vec![(10, 42, 1, None), (10, 43, 2, Some(100))],
+            system_names: vec![],
+            reserved_prefixes: vec![],
+            topic_prefixes: vec![],
+        }.assimilate_storage(&mut t).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate name in genesis config")]
+    fn system_name_duplicates_genesis_name() {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            names: vec![(10, 42, 1, None)],
+            system_names: vec![(10, 50)],
+            reserved_prefixes: vec![],
+            topic_prefixes: vec![],
+        }.assimilate_storage(&mut t).unwrap();
+    }
+
+    #[test]
+    fn set_system_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::set_system_name(Origin::signed(10), 200, 1),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::set_system_name(Origin::ROOT, 100, 1),
+                         "name is not a system name");
+
+            assert_ok!(Mod::set_system_name(Origin::ROOT, 200, 1));
+            assert_ok!(Mod::set_system_name(Origin::ROOT, 200, 2));
+            assert_eq!(Mod::lookup(200), Some(NameData::<Test> {
+                value: 2,
+                owner: Mod::account_id(),
+                expiration: None,
+                nonce: 1,
+                held_since: 1,
+            }));
+            assert!(Mod::is_system_name(200));
+
+            assert_noop!(Mod::update(Origin::signed(10), 200, 3), "non-owner name update");
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(Mod::set_system_name(Origin::ROOT, 200, 1));
+            assert_ok!(Mod::force_transfer(Origin::ROOT, 200, 10));
+            assert!(!Mod::is_system_name(200));
+            assert_noop!(Mod::set_system_name(Origin::ROOT, 200, 2),
+                         "name is not a system name");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for reserved namespaces.
+mod reserved_namespaces {
+    use super::*;
+
+    fn new_reserved_ext() -> sp_io::TestExternalities {
+        let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        GenesisConfig::<Test> {
+            names: vec![],
+            system_names: vec![],
+            reserved_prefixes: vec![b"9".to_vec()],
+            topic_prefixes: vec![],
+        }.assimilate_storage(&mut t).unwrap();
+        t.into()
+    }
+
+    #[test]
+    fn registration_rejected() {
+        new_reserved_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert!(Mod::is_reserved_name(&9));
+            assert!(Mod::is_reserved_name(&95));
+            assert!(!Mod::is_reserved_name(&59));
+
+            assert_noop!(Mod::check_assuming_signed(10, 95, None, None),
+                         "name is in a reserved namespace");
+            assert_noop!(Mod::update(Origin::signed(10), 9, 1),
+                         "name is in a reserved namespace");
+            assert_ok!(Mod::check_assuming_signed(10, 59, None, None));
+        });
+    }
+
+    #[test]
+    fn force_register() {
+        new_reserved_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert_noop!(Mod::force_register(Origin::signed(10), 95, 42, 10),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::force_register(Origin::ROOT, 95, 42, 10));
+            assert_noop!(Mod::force_register(Origin::ROOT, 95, 50, 10),
+                         "name exists already");
+            assert_eq!(Mod::lookup(95).unwrap().owner, 10);
+
+            /* Once registered, the owner can update the name normally.  */
+            assert_ok!(Mod::update(Origin::signed(10), 95, 50));
+            assert_eq!(Mod::lookup(95).unwrap().value, 50);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for donating names to the community account.
+mod donations {
+    use super::*;
+
+    #[test]
+    fn donate_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::lookup(100).unwrap().expiration, Some(100));
+
+            assert_noop!(Mod::donate_name(Origin::signed(10), 200), "name does not exist");
+            assert_noop!(Mod::donate_name(Origin::signed(20), 100), "non-owner name donation");
+            assert_ok!(Mod::donate_name(Origin::signed(10), 100));
+
+            assert_eq!(Mod::lookup(100), Some(NameData::<Test> {
+                value: 42,
+                owner: CommunityAccount::get(),
+                expiration: None,
+                nonce: 1,
+                held_since: 1,
+            }));
+            assert_eq!(Mod::names_of(&10), vec![]);
+            expect_balance(10, 4900);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameDonated(100, 10))
+            }));
+
+            Mod::expire_names(100);
+            assert!(Mod::lookup(100).is_some());
+        });
+    }
+
+    #[test]
+    fn usual_transfer_checks() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 100, true));
+            assert_noop!(Mod::donate_name(Origin::signed(10), 100), "name is frozen");
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 100, false));
+
+            assert_ok!(Mod::set_block_all_transfers(Origin::signed(CommunityAccount::get()),
+                                                    true));
+            assert_noop!(Mod::donate_name(Origin::signed(10), 100),
+                         "recipient does not accept transfers from the sender");
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+        });
+    }
+
+    #[test]
+    fn no_expiration_scheduled() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(get_expiring_names(105), vec![100]);
+
+            System::set_block_number(10);
+            assert_ok!(Mod::donate_name(Origin::signed(10), 100));
+            assert_eq!(Mod::lookup(100).unwrap().expiration, None);
+            assert_eq!(get_expiring_names(110), vec![]);
+            assert_eq!(Mod::receipts(10, None::<u32>).last().unwrap().expiration, None);
+            expect_balance(10, 4900);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for locking names as collateral.
+mod collateral {
+    use super::*;
+
+    #[test]
+    fn lock_checks() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 30),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(20), 100, 30),
+                         "non-owner collateral lock");
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 10),
+                         "cannot lock collateral for the owner");
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 20),
+                         "name is locked as collateral");
+            assert_eq!(Mod::collateral_beneficiary(100), Some(30));
+        });
+    }
+
+    #[test]
+    fn locked_name_cannot_be_moved() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "name is locked as collateral");
+            assert_noop!(Mod::donate_name(Origin::signed(10), 100),
+                         "name is locked as collateral");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+
+            assert_noop!(Mod::release_collateral(Origin::signed(10), 100),
+                         "sender is not the collateral beneficiary");
+            assert_ok!(Mod::release_collateral(Origin::signed(30), 100));
+            assert_eq!(Mod::collateral_beneficiary(100), None);
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+        });
+    }
+
+    #[test]
+    fn claim_collateral() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+
+            assert_noop!(Mod::claim_collateral(Origin::signed(20), 100),
+                         "sender is not the collateral beneficiary");
+            assert_ok!(Mod::claim_collateral(Origin::signed(30), 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 30);
+            assert_eq!(Mod::collateral_beneficiary(100), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::CollateralClaimed(100, 30))
+            }));
+
+            assert_ok!(Mod::transfer(Origin::signed(30), 100, 20));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for payout routing of name income.
+mod payouts {
+    use super::*;
+
+    #[test]
+    fn set_payout() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let p = Perbill::from_percent;
+            assert_noop!(Mod::set_payout(Origin::signed(10), 100, vec![(20, p(10))]),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::set_payout(Origin::signed(20), 100, vec![(20, p(10))]),
+                         "non-owner payout change");
+            assert_noop!(Mod::set_payout(Origin::signed(10), 100,
+                                         vec![(20, p(10)), (30, p(10)), (40, p(10))]),
+                         "too many payout accounts");
+            assert_noop!(Mod::set_payout(Origin::signed(10), 100,
+                                         vec![(20, p(60)), (30, p(50))]),
+                         "payout fractions exceed the whole");
+
+            assert_ok!(Mod::set_payout(Origin::signed(10), 100, vec![(20, p(10))]));
+            assert_eq!(Mod::payout_of(100), vec![(20, p(10))]);
+            assert_ok!(Mod::set_payout(Origin::signed(10), 100, vec![]));
+            assert_eq!(Mod::payout_of(100), vec![]);
+
+            assert_ok!(Mod::set_payout(Origin::signed(10), 100, vec![(20, p(10))]));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+            assert_eq!(Mod::payout_of(100), vec![]);
+        });
+    }
+
+    #[test]
+    fn pay_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+            add_balance(40, 5000);
+            assert_noop!(Mod::pay_name(Origin::signed(40), 100, 1000), "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::pay_name(Origin::signed(40), 100, 1000));
+            expect_balance(10, 5900);
+            expect_balance(40, 4000);
+
+            assert_ok!(Mod::set_payout(Origin::signed(10), 100,
+                                       vec![(20, Perbill::from_percent(10)),
+                                            (30, Perbill::from_percent(25))]));
+            assert_ok!(Mod::pay_name(Origin::signed(40), 100, 1000));
+            expect_balance(10, 6550);
+            expect_balance(20, 5100);
+            expect_balance(30, 5250);
+            expect_balance(40, 3000);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NamePaid(100, 40, 1000))
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for standing approvals of operators.
+mod operators {
+    use super::*;
+
+    fn t(n: u64) -> H256 {
+        H256::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn approve_operator() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_noop!(Mod::approve_operator(Origin::signed(10), 100, 20, vec![t(5)]),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::approve_operator(Origin::signed(20), 100, 20, vec![t(5)]),
+                         "non-owner operator approval");
+            assert_noop!(Mod::approve_operator(Origin::signed(10), 100, 20,
+                                               vec![t(1), t(2), t(3)]),
+                         "too many templates");
+            assert_ok!(Mod::approve_operator(Origin::signed(10), 100, 20, vec![t(5)]));
+            assert_eq!(Mod::operator_of(100), Some((20, vec![t(5)])));
+
+            assert_ok!(Mod::approve_operator(Origin::signed(10), 100, 20, vec![]));
+            assert_eq!(Mod::operator_of(100), None);
+        });
+    }
+
+    #[test]
+    fn operator_update() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_noop!(Mod::operator_update(Origin::signed(20), 100, 10),
+                         "name has no approved operator");
+            assert_ok!(Mod::approve_operator(Origin::signed(10), 100, 20, vec![t(5), t(7)]));
+
+            assert_noop!(Mod::operator_update(Origin::signed(30), 100, 10),
+                         "sender is not the approved operator");
+            assert_noop!(Mod::operator_update(Origin::signed(20), 100, 11),
+                         "value does not match any approved template");
+            assert_ok!(Mod::operator_update(Origin::signed(20), 100, 10));
+            assert_ok!(Mod::operator_update(Origin::signed(20), 100, 14));
+            assert_eq!(Mod::lookup(100).unwrap().value, 14);
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+            assert_eq!(Mod::operator_of(100), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for rent-to-own agreements.
+mod rent_to_own {
+    use super::*;
+
+    /// Sets up name 100 owned by 10 with an agreement for tenant 20 to
+    /// pay three installments of 500 every 10 blocks.
+    fn setup_agreement() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        add_balance(20, 5000);
+        System::set_block_number(50);
+        assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+        assert_ok!(Mod::offer_rent_to_own(Origin::signed(10), 100, 20, 500, 3, 10,
+                                          HandoverTerms::default()));
+    }
+
+    #[test]
+    fn offer_checks() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let offer = |who, tenant, payments, period| {
+                Mod::offer_rent_to_own(Origin::signed(who), 100, tenant, 500, payments, period,
+                                       HandoverTerms::default())
+            };
+            assert_noop!(offer(10, 20, 3, 10), "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(offer(20, 30, 3, 10), "non-owner rent-to-own offer");
+            assert_noop!(offer(10, 10, 3, 10), "cannot rent a name to its owner");
+            assert_noop!(offer(10, 20, 0, 10), "invalid rent-to-own terms");
+            assert_noop!(offer(10, 20, 3, 0), "invalid rent-to-own terms");
+            assert_ok!(offer(10, 20, 3, 10));
+            assert_noop!(offer(10, 30, 3, 10), "name is under a rent-to-own agreement");
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 30),
+                         "name is under a rent-to-own agreement");
+        });
+    }
+
+    #[test]
+    fn paid_in_full() {
+        new_test_ext().execute_with(|| {
+            setup_agreement();
+            assert_noop!(Mod::pay_installment(Origin::signed(30), 100),
+                         "sender is not the tenant");
+
+            assert_ok!(Mod::pay_installment(Origin::signed(20), 100));
+            System::set_block_number(59);
+            assert_ok!(Mod::pay_installment(Origin::signed(20), 100));
+            Mod::cancel_overdue_rents(60);
+            assert_eq!(Mod::rent_agreement(100).unwrap().next_due, 69);
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+
+            assert_ok!(Mod::pay_installment(Origin::signed(20), 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::rent_agreement(100), None);
+            expect_balance(10, 6400);
+            expect_balance(20, 3500);
+        });
+    }
+
+    #[test]
+    fn missed_installment() {
+        new_test_ext().execute_with(|| {
+            setup_agreement();
+            assert_ok!(Mod::pay_installment(Origin::signed(20), 100));
+
+            Mod::cancel_overdue_rents(59);
+            assert!(Mod::rent_agreement(100).is_some());
+            Mod::cancel_overdue_rents(60);
+            assert_eq!(Mod::rent_agreement(100), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::RentToOwnCancelled(100))
+            }));
+
+            assert_noop!(Mod::pay_installment(Origin::signed(20), 100),
+                         "no rent-to-own agreement for the name");
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+        });
+    }
+
+    #[test]
+    fn handover_terms() {
+        let sell = |name, terms| {
+            assert_ok!(Mod::update(Origin::signed(10), name, 42));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), name, 1, 5));
+            assert_ok!(Mod::offer_rent_to_own(Origin::signed(10), name, 20, 500, 1, 10, terms));
+            assert_ok!(Mod::pay_installment(Origin::signed(20), name));
+            assert_eq!(Mod::lookup(name).unwrap().owner, 20);
+        };
+
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+
+            sell(100, HandoverTerms::default());
+            assert_eq!(Mod::lookup(100).unwrap().value, 42);
+            assert_eq!(Mod::records_of(&100), vec![(1, 5)]);
+
+            sell(200, HandoverTerms {
+                clear_value_on_transfer: true,
+                preserve_records: false,
+            });
+            assert_eq!(Mod::lookup(200).unwrap().value, 0);
+            assert_eq!(Mod::records_of(&200), vec![]);
+            assert_eq!(Balances::reserved_balance(&10), 10);
+
+            /* Names 900 to 999 have the owner as default value.  */
+            sell(900, HandoverTerms {
+                clear_value_on_transfer: true,
+                preserve_records: true,
+            });
+            assert_eq!(Mod::lookup(900).unwrap().value, 20);
+            assert_eq!(Mod::records_of(&900), vec![(1, 5)]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for names split into shares.
+mod shares {
+    use super::*;
+
+    /// Sets up name 100 split into 10 shares with a 60% threshold,
+    /// held as 4 / 3 / 3 by accounts 10, 20 and 30.
+    fn setup_shared_name() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        add_balance(20, 5000);
+        assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+        assert_ok!(Mod::split_into_shares(Origin::signed(10), 100, 10,
+                                          Perbill::from_percent(60)));
+        assert_ok!(Mod::transfer_shares(Origin::signed(10), 100, 20, 3));
+        assert_ok!(Mod::transfer_shares(Origin::signed(10), 100, 30, 3));
+    }
+
+    #[test]
+    fn split_checks() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let split = |who, total| {
+                Mod::split_into_shares(Origin::signed(who), 100, total,
+                                       Perbill::from_percent(50))
+            };
+            assert_noop!(split(10, 10), "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_noop!(split(20, 10), "non-owner share split");
+            assert_noop!(split(10, 0), "cannot split a name into zero shares");
+
+            assert_ok!(split(10, 10));
+            assert_eq!(Mod::lookup(100).unwrap().owner, Mod::name_account(&100));
+            assert_eq!(Mod::share_info(100).unwrap().holders, vec![(10, 10)]);
+            assert_noop!(Mod::update(Origin::signed(10), 100, 43), "non-owner name update");
+        });
+    }
+
+    #[test]
+    fn transfer_shares() {
+        new_test_ext().execute_with(|| {
+            setup_shared_name();
+            assert_eq!(Mod::share_info(100).unwrap().holders, vec![(10, 4), (20, 3), (30, 3)]);
+
+            assert_noop!(Mod::transfer_shares(Origin::signed(20), 100, 10, 5),
+                         "insufficient shares");
+            assert_noop!(Mod::transfer_shares(Origin::signed(10), 100, 40, 1),
+                         "too many share holders");
+            assert_noop!(Mod::transfer_shares(Origin::signed(10), 200, 20, 1),
+                         "name is not split into shares");
+
+            assert_ok!(Mod::transfer_shares(Origin::signed(20), 100, 40, 3));
+            assert_eq!(Mod::share_info(100).unwrap().holders, vec![(10, 4), (30, 3), (40, 3)]);
+        });
+    }
+
+    #[test]
+    fn value_approval() {
+        new_test_ext().execute_with(|| {
+            setup_shared_name();
+            assert_noop!(Mod::approve_value(Origin::signed(20), 100),
+                         "no value proposal for the name");
+            assert_noop!(Mod::propose_value(Origin::signed(50), 100, 55),
+                         "sender holds no shares");
+
+            assert_ok!(Mod::propose_value(Origin::signed(20), 100, 55));
+            assert_eq!(Mod::value_proposal(100), Some((55, vec![20])));
+            assert_eq!(Mod::lookup(100).unwrap().value, 42);
+
+            assert_ok!(Mod::approve_value(Origin::signed(30), 100));
+            assert_eq!(Mod::lookup(100).unwrap().value, 55);
+            assert_eq!(Mod::value_proposal(100), None);
+        });
+    }
+
+    #[test]
+    fn reclaim_name() {
+        new_test_ext().execute_with(|| {
+            setup_shared_name();
+            assert_noop!(Mod::reclaim_name(Origin::signed(10), 100),
+                         "sender does not hold all shares");
+
+            assert_ok!(Mod::transfer_shares(Origin::signed(20), 100, 10, 3));
+            assert_ok!(Mod::transfer_shares(Origin::signed(30), 100, 10, 3));
+            assert_ok!(Mod::reclaim_name(Origin::signed(10), 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::share_info(100), None);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for ownership attestations.
+mod attestations {
+    use super::*;
+
+    #[test]
+    fn attest_ownership() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_eq!(Mod::attest_ownership(&100), None);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_eq!(Mod::attest_ownership(&100), Some(OwnershipAttestation::<Test> {
+                name: 100,
+                owner: 10,
+                block: 5,
+                storage_key: <Names<Test>>::hashed_key_for(100),
+            }));
+            let key = Mod::attest_ownership(&100).unwrap().storage_key;
+            let raw = sp_io::storage::get(&key).unwrap();
+            assert_eq!(NameData::<Test>::decode(&mut &raw[..]).ok(), Mod::lookup(100));
+        });
+    }
+
+    #[test]
+    fn name_storage_key() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let key = Mod::name_storage_key(&100);
+            assert_ne!(key, Mod::name_storage_key(&200));
+            assert_eq!(sp_io::storage::get(&key), None);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::attest_ownership(&100).unwrap().storage_key, key);
+            assert_eq!(sp_io::storage::get(&key), Some(Mod::lookup(100).unwrap().encode()));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the registrar statistics.
+mod statistics {
+    use super::*;
+
+    /// Returns the statistics as tuples of era, registrations, renewals,
+    /// expirations and fees.
+    fn stats() -> Vec<(u32, u32, u32, u32, u128)> {
+        Mod::statistics().into_iter().map(|s| {
+            (s.era, s.registrations, s.renewals, s.expirations, s.fees)
+        }).collect()
+    }
+
+    #[test]
+    fn counters() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_eq!(stats(), vec![(0, 0, 0, 0, 0)]);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 20, 43));
+            assert_ok!(Mod::transfer(Origin::signed(10), 20, 30));
+
+            System::set_block_number(25);
+            Mod::expire_names(25);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(stats(), vec![
+                (0, 1, 1, 0, 100),
+                (1, 0, 0, 0, 0),
+                (2, 1, 0, 1, 100),
+            ]);
+        });
+    }
+
+    #[test]
+    fn ring_buffer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            System::set_block_number(35);
+            assert_eq!(stats(), vec![(1, 0, 0, 0, 0), (2, 0, 0, 0, 0), (3, 0, 0, 0, 0)]);
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_eq!(stats(), vec![(1, 0, 0, 0, 0), (2, 0, 0, 0, 0), (3, 1, 0, 0, 100)]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for batches of name operations.
+mod batches {
+    use super::*;
+
+    #[test]
+    fn check_and_execute() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            let ops = Mod::check_many_assuming_signed(vec![
+                (10, 100, Some(1), Some(20)),
+                (10, 200, Some(2), None),
+                (20, 300, Some(3), None),
+            ]).unwrap();
+            assert_eq!(ops.len(), 3);
+            assert_ok!(Mod::execute_many(ops));
+
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::lookup(200).unwrap().value, 2);
+            assert_eq!(Mod::lookup(300).unwrap().owner, 20);
+            expect_balance(10, 4800);
+            expect_balance(20, 4900);
+        });
+    }
+
+    #[test]
+    fn invalid_batches() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert_noop!(Mod::check_many_assuming_signed(vec![
+                (10, 100, Some(1), None),
+                (10, 100, Some(2), None),
+            ]), "duplicate name in batch");
+            assert_noop!(Mod::check_many_assuming_signed(vec![
+                (10, 100, Some(1), None),
+                (10, 200, Some(2_000), None),
+            ]), "operation violates name policy");
+        });
+    }
+
+    /// Locks the given amount of an account's balance against all
+    /// withdrawals and reserves.
+    fn lock(who: u64, amount: u128) {
+        <Balances as LockableCurrency<u64>>::set_lock(
+            [1, 2, 3, 4, 5, 6, 7, 8], &who, amount, 100, WithdrawReasons::all());
+    }
+
+    #[test]
+    fn total_fee() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            lock(10, 4850);
+
+            /* Each operation alone is affordable, but not both together.  */
+            assert_ok!(Mod::check_many_assuming_signed(vec![(10, 100, None, None)]));
+            assert_noop!(Mod::check_many_assuming_signed(vec![
+                (10, 100, None, None),
+                (10, 200, None, None),
+            ]), "cannot withdraw name fee from sender");
+        });
+    }
+
+    #[test]
+    fn total_fee_and_deposit() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_value_deposit(1);
+
+            /* The fees (200) and deposits (16) are affordable separately,
+               but not together.  */
+            lock(10, 4790);
+            assert_noop!(Mod::check_many_assuming_signed(vec![
+                (10, 100, Some(1), None),
+                (10, 200, Some(2), None),
+            ]), "insufficient balance for value deposit");
+
+            lock(10, 4784);
+            let ops = Mod::check_many_assuming_signed(vec![
+                (10, 100, Some(1), None),
+                (10, 200, Some(2), None),
+            ]).unwrap();
+            assert_ok!(Mod::execute_many(ops));
+            assert_eq!(Mod::value_deposit(100), Some((10, 8)));
+            assert_eq!(Mod::value_deposit(200), Some((10, 8)));
+            assert_eq!(Balances::free_balance(&10), 4784);
+        });
+    }
+
+    #[test]
+    fn total_first_update_holds() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            set_first_update_period(Some(10));
+            lock(10, 4850);
+
+            /* Half of each fee is held instead of paid, but both count
+               towards the total.  */
+            assert_noop!(Mod::check_many_assuming_signed(vec![
+                (10, 100, None, None),
+                (10, 200, None, None),
+            ]), "cannot withdraw name fee from sender");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for backorders.
+mod backorders {
+    use super::*;
+
+    #[test]
+    fn place_and_cancel() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+            add_balance(40, 5000);
+
+            assert_noop!(Mod::place_backorder(Origin::signed(20), 100, 50),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_noop!(Mod::place_backorder(Origin::signed(10), 100, 50),
+                         "cannot backorder an owned name");
+            assert_noop!(Mod::place_backorder(Origin::signed(20), 100, 5),
+                         "backorder bond is too low");
+
+            assert_ok!(Mod::place_backorder(Origin::signed(20), 100, 20));
+            assert_ok!(Mod::place_backorder(Origin::signed(30), 100, 50));
+            assert_noop!(Mod::place_backorder(Origin::signed(20), 100, 30),
+                         "name is backordered already");
+            assert_noop!(Mod::place_backorder(Origin::signed(40), 100, 30),
+                         "too many backorders");
+            assert_eq!(Mod::backorders(100), vec![(30, 50), (20, 20)]);
+            assert_eq!(Balances::reserved_balance(&30), 50);
+
+            assert_noop!(Mod::cancel_backorder(Origin::signed(40), 100),
+                         "no backorder for the name");
+            assert_ok!(Mod::cancel_backorder(Origin::signed(30), 100));
+            assert_eq!(Mod::backorders(100), vec![(20, 20)]);
+            expect_balance(30, 5000);
+        });
+    }
+
+    #[test]
+    fn exercised_on_expiration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::place_backorder(Origin::signed(20), 100, 20));
+            assert_ok!(Mod::place_backorder(Origin::signed(30), 100, 50));
+
+            System::set_block_number(100);
+            Mod::expire_names(100);
+            assert_eq!(Mod::lookup(100).unwrap().owner, 30);
+            assert_eq!(Mod::backorders(100), Vec::<(u64, u128)>::new());
+            expect_balance(20, 5000);
+            expect_balance(30, 4850);
+            expect_balance(FEE_RECEIVER, 1250);
+
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::BackorderRefunded(100, 20, 20))
+            }));
+        });
+    }
+
+    #[test]
+    fn invalid_backorder_skipped() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::place_backorder(Origin::signed(20), 100, 20));
+            assert_ok!(Mod::place_backorder(Origin::signed(30), 100, 50));
+
+            /* Account 30 cannot pay the registration fee anymore.  */
+            assert_ok!(<Balances as ReservableCurrency<u64>>::reserve(&30, 4900));
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Balances::reserved_balance(&30), 4900);
+            expect_balance(20, 4880);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the extra weight of name operations.
+mod operation_weights {
+    use super::*;
+    use crate::weights::WeightInfo;
+
+    #[test]
+    fn extra_weight() {
+        assert_eq!(ValueOperationWeight::<Test>::new(10_000).weigh_data((&100, &42)), 10_000);
+        assert_eq!(ValueOperationWeight::<Test>::new(10_000).weigh_data((&300, &42)), 11_042);
+        assert_eq!(TransferOperationWeight::<Test>::new(10_000).weigh_data((&100, &20)), 10_000);
+        assert_eq!(TransferOperationWeight::<Test>::new(10_000).weigh_data((&300, &20)), 11_000);
+    }
+
+    #[test]
+    fn calls_include_extra_weight() {
+        use frame_support::weights::{DispatchClass, GetDispatchInfo};
+
+        let weight = |call: Call<Test>| call.get_dispatch_info().weight;
+        assert_eq!(weight(Call::set_value_at_nonce(300, 42, 0)),
+                   TestWeights::set_value_at_nonce() + 1_042);
+        assert_eq!(weight(Call::operator_update(300, 42)), TestWeights::operator_update() + 1_042);
+        assert_eq!(weight(Call::update_patch(300, vec![1, 2, 3])),
+                   TestWeights::update_patch() + 1_000);
+        assert_eq!(weight(Call::reveal_name(300, None)), TestWeights::reveal_name() + 1_000);
+        assert_eq!(weight(Call::renew(300)), TestWeights::renew() + 1_000);
+        assert_eq!(weight(Call::renew(100)), TestWeights::renew());
+
+        let payload = SponsoredUpdate::<Test> {
+            owner: 10,
+            name: 300,
+            value: 42,
+            nonce: 0,
+        };
+        assert_eq!(weight(Call::sponsored_update(payload, TestSignature(10, vec![]))),
+                   TestWeights::sponsored_update() + 1_042);
+
+        let info = Call::<Test>::force_register(300, 42, 10).get_dispatch_info();
+        assert_eq!((info.weight, info.class),
+                   (TestWeights::force_register() + 1_042, DispatchClass::Operational));
+        let info = Call::<Test>::force_transfer(300, 20).get_dispatch_info();
+        assert_eq!((info.weight, info.class),
+                   (TestWeights::force_transfer() + 1_000, DispatchClass::Operational));
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the base weights of calls.
+mod weight_info {
+    use super::*;
+    use crate::weights::WeightInfo;
+    use frame_support::dispatch::DecodeDifferent;
+    use frame_support::weights::GetDispatchInfo;
+
+    fn signed_operation() -> (SignedOperation<Test>, TestSignature) {
+        let payload = SignedOperation::<Test> {
+            owner: 10,
+            name: 100,
+            value: Some(42),
+            recipient: None,
+            nonce: 0,
+            name_nonce: None,
+        };
+        (payload, TestSignature(10, vec![]))
+    }
+
+    /// Checks that every call is weighed by its own entry of the weight
+    /// info, so that a new call cannot silently use the default weight.
+    #[test]
+    fn every_call_has_an_entry() {
+        let mut checked = Vec::new();
+        macro_rules! check {
+            ($call:ident($($arg:expr),*)) => {
+                check!($call($($arg),*), TestWeights::$call())
+            };
+            ($call:ident($($arg:expr),*), $weight:expr) => {
+                let info = Call::<Test>::$call($($arg),*).get_dispatch_info();
+                assert_eq!(info.weight, $weight, "weight of {}", stringify!($call));
+                checked.push(stringify!($call));
+            };
+        }
+
+        let attestation = IdentityAttestation::<Test> {
+            identity: vec![1],
+            signature: TestSignature(50, vec![]),
+        };
+        let envelope = Envelope {
+            recipient: H256::from_low_u64_be(1),
+            ciphertext: vec![1],
+        };
+        let terms = HandoverTerms {
+            clear_value_on_transfer: false,
+            preserve_records: false,
+        };
+        let session = SessionUpdate::<Test> {
+            owner: 10,
+            key: 30,
+            name: 100,
+            value: 42,
+            nonce: 0,
+        };
+        let sponsored = SponsoredUpdate::<Test> {
+            owner: 10,
+            name: 100,
+            value: 42,
+            nonce: 0,
+        };
+        let (payload, signature) = signed_operation();
+
+        check!(update(100, 42));
+        check!(update_via_registrar(100, 42, 50));
+        check!(register(100, 42, None));
+        check!(register_attested(100, 42, attestation));
+        check!(renew(100));
+        check!(set_value(100, 42));
+        check!(set_value_at_nonce(100, 42, 0));
+        check!(update_patch(100, vec![1]));
+        check!(schedule_value(100, 42, 5));
+        check!(cancel_scheduled_value(100));
+        check!(set_subvalue(100, 1, 42));
+        check!(clear_subvalue(100, 1));
+        check!(publish_envelope(100, envelope));
+        check!(remove_envelope(100, H256::from_low_u64_be(1)));
+        check!(watch_name(100));
+        check!(unwatch_name(100));
+        check!(transfer(100, 20));
+        check!(transfer_existing(100, 20));
+        check!(set_transfer_preference(true));
+        check!(block_sender(20));
+        check!(unblock_sender(20));
+        check!(set_block_all_transfers(true));
+        check!(claim_name(100));
+        check!(cancel_transfer(100));
+        check!(donate_name(100));
+        check!(lock_as_collateral(100, 20));
+        check!(release_collateral(100));
+        check!(claim_collateral(100));
+        check!(propose_swap(20, vec![100], vec![200], 0));
+        check!(cancel_swap());
+        check!(accept_swap(10), 3 * TestWeights::accept_swap());
+        check!(set_payout(100, vec![(20, Perbill::from_percent(50))]));
+        check!(pay_name(100, 10));
+        check!(approve_operator(100, 20, vec![H256::from_low_u64_be(1)]));
+        check!(operator_update(100, 42));
+        check!(offer_rent_to_own(100, 20, 10, 3, 5, terms));
+        check!(pay_installment(100));
+        check!(split_into_shares(100, 10, Perbill::from_percent(50)));
+        check!(transfer_shares(100, 20, 1));
+        check!(propose_value(100, 42));
+        check!(approve_value(100));
+        check!(reclaim_name(100));
+        check!(dispatch_as_name(100, Box::new(Call::update(100, 42))),
+               TestWeights::dispatch_as_name() + TestWeights::update());
+        check!(repair_index(100));
+        check!(transfer_all_names(20, 1),
+               2 * TestWeights::transfer_all_names() + TestWeights::transfer_all_names_skipped());
+        check!(register_hidden(H256::from_low_u64_be(1)));
+        check!(reveal_name(100, None));
+        check!(set_signing_key(100, Some(20)));
+        check!(set_controller(100, Some(20)));
+        check!(set_note(100, Some(vec![1])));
+        check!(set_redirect(100, Some(200)));
+        check!(set_guardian(100, Some(20)));
+        check!(request_guarded_transfer(100, 20));
+        check!(confirm_guarded_transfer(100));
+        check!(cancel_guarded_transfer(100));
+        check!(set_sale_ready(100, true));
+        check!(set_retention_policy(100, RetentionPolicy::default()));
+        check!(authorize_session_key(30, 10, vec![100]));
+        check!(revoke_session_key(30));
+        check!(session_update(session, TestSignature(30, vec![])));
+        check!(link_identity(100));
+        check!(unlink_identity(100));
+        check!(force_transfer(100, 20));
+        check!(report_name(100));
+        check!(decide_report(100, true));
+        check!(force_remove(100));
+        check!(freeze_name(100, true));
+        check!(force_register(100, 42, 10));
+        check!(force_register_batch(vec![(100, 42, 10, None)]));
+        check!(queue_migration(vec![100]));
+        check!(force_total_names(1));
+        check!(set_namespace_registrar(b"1".to_vec(), Some(50)));
+        check!(delegate_namespace(b"1".to_vec(), 50, true));
+        check!(set_topic_prefixes(vec![b"1".to_vec()]));
+        check!(set_attestation_oracle(b"1".to_vec(), Some(50)));
+        check!(set_account_fee_exemption(10, true));
+        check!(set_name_fee_exemption(100, true));
+        check!(set_system_name(100, 42));
+        check!(import_names(10, vec![]));
+        check!(register_intent(100, 42));
+        check!(preorder(H256::from_low_u64_be(1), 100));
+        check!(place_backorder(100, 100));
+        check!(cancel_backorder(100));
+        check!(insure_name(100, 1));
+        check!(set_insurance_premium(10));
+        check!(renew_namespace(b"1".to_vec(), vec![100]));
+        check!(sponsored_update(sponsored, TestSignature(10, vec![])));
+        check!(execute_signed_operation(payload, signature));
+        check!(execute_signed_batch(vec![signed_operation()]));
+        check!(sweep_expirations(1, 1));
+
+        for function in Mod::call_functions() {
+            let name = match function.name {
+                DecodeDifferent::Encode(name) => name,
+                DecodeDifferent::Decoded(_) => unreachable!(),
+            };
+            assert!(checked.contains(&name), "no weight check for {}", name);
+        }
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for deferred execution of operations.
+mod deferred_operations {
+    use super::*;
+
+    #[test]
+    fn weight_limit() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            for name in &[100, 200, 300] {
+                Mod::enqueue(Mod::check_assuming_signed(10, *name, Some(42), None).unwrap());
+            }
+            assert_eq!(Mod::deferred_queue_end(), 3);
+            assert_eq!(Mod::lookup(100), None);
+
+            Mod::process_deferred();
+            assert_eq!(Mod::lookup(100).unwrap().value, 42);
+            assert_eq!(Mod::lookup(200).unwrap().value, 42);
+            assert_eq!(Mod::lookup(300), None);
+            assert_eq!(Mod::deferred_cursor(), 2);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::DeferredOperationsProcessed(2, 1))
+            }));
+
+            Mod::process_deferred();
+            assert_eq!(Mod::lookup(300).unwrap().owner, 10);
+            assert_eq!(Mod::deferred_cursor(), 0);
+            assert_eq!(Mod::deferred_queue_end(), 0);
+            expect_balance(10, 4700);
+
+            /* Nothing happens once the queue is empty.  */
+            let events = System::events().len();
+            Mod::process_deferred();
+            assert_eq!(System::events().len(), events);
+        });
+    }
+
+    #[test]
+    fn stale_operation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            Mod::enqueue(Mod::check_assuming_signed(10, 100, None, Some(20)).unwrap());
+            assert_ok!(Mod::update(Origin::signed(10), 100, 2));
+
+            Mod::process_deferred();
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::DeferredOperationFailed(100))
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for scheduled value updates.
+mod scheduled_values {
+    use super::*;
+
+    #[test]
+    fn schedule_and_activate() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            System::set_block_number(5);
+
+            assert_noop!(Mod::schedule_value(Origin::signed(20), 100, 2, 10),
+                         "non-owner name update");
+            assert_noop!(Mod::schedule_value(Origin::signed(10), 100, 2, 5),
+                         "invalid effective block for scheduled value");
+            assert_noop!(Mod::schedule_value(Origin::signed(10), 100, 2, 16),
+                         "invalid effective block for scheduled value");
+            assert_noop!(Mod::schedule_value(Origin::signed(10), 100, 2_000, 10),
+                         "operation violates name policy");
+
+            assert_ok!(Mod::schedule_value(Origin::signed(10), 100, 2, 10));
+            assert_ok!(Mod::schedule_value(Origin::signed(10), 100, 3, 12));
+            assert_eq!(Mod::scheduled_value(100), Some((12, 3)));
+
+            /* The first schedule has been replaced.  */
+            Mod::activate_scheduled_values(10);
+            assert_eq!(Mod::lookup(100).unwrap().value, 1);
+
+            System::set_block_number(12);
+            Mod::activate_scheduled_values(12);
+            assert_eq!(Mod::lookup(100).unwrap().value, 3);
+            assert_eq!(Mod::scheduled_value(100), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ScheduledValueActivated(100))
+            }));
+        });
+    }
+
+    #[test]
+    fn cancel_and_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::cancel_scheduled_value(Origin::signed(10), 100),
+                         "no scheduled value for the name");
+            assert_ok!(Mod::schedule_value(Origin::signed(10), 100, 2, 5));
+            assert_noop!(Mod::cancel_scheduled_value(Origin::signed(20), 100),
+                         "non-owner name update");
+            assert_ok!(Mod::cancel_scheduled_value(Origin::signed(10), 100));
+            assert_eq!(Mod::scheduled_value(100), None);
+
+            /* Schedules do not carry over to a new owner.  */
+            assert_ok!(Mod::schedule_value(Origin::signed(10), 100, 2, 5));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::scheduled_value(100), None);
+            Mod::activate_scheduled_values(5);
+            assert_eq!(Mod::lookup(100).unwrap().value, 1);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for encrypted envelopes.
+mod envelopes {
+    use super::*;
+
+    fn envelope(recipient: u64, ciphertext: &[u8]) -> Envelope {
+        Envelope {
+            recipient: H256::from_low_u64_be(recipient),
+            ciphertext: ciphertext.to_vec(),
+        }
+    }
+
+    #[test]
+    fn structure() {
+        assert_ok!(envelope(1, &[1, 2, 3, 4]).check_structure(4));
+        assert_eq!(envelope(0, &[1]).check_structure(4), Err("envelope has no recipient"));
+        assert_eq!(envelope(1, &[]).check_structure(4), Err("envelope has no ciphertext"));
+        assert_eq!(envelope(1, &[1, 2, 3, 4, 5]).check_structure(4),
+                   Err("envelope ciphertext is too large"));
+    }
+
+    #[test]
+    fn publish_and_remove() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::publish_envelope(Origin::signed(20), 100, envelope(1, &[1])),
+                         "non-owner envelope update");
+            assert_noop!(Mod::publish_envelope(Origin::signed(10), 100, envelope(1, &[])),
+                         "envelope has no ciphertext");
+            assert_ok!(Mod::publish_envelope(Origin::signed(10), 100, envelope(1, &[1])));
+            assert_ok!(Mod::publish_envelope(Origin::signed(10), 100, envelope(2, &[2])));
+            assert_ok!(Mod::publish_envelope(Origin::signed(10), 100, envelope(1, &[3])));
+            assert_noop!(Mod::publish_envelope(Origin::signed(10), 100, envelope(3, &[1])),
+                         "too many envelopes");
+            assert_eq!(Mod::envelope(&100, &H256::from_low_u64_be(1)), Some(envelope(1, &[3])));
+            assert_eq!(Mod::envelope_count(100), 2);
+            expect_balance(10, 4840);
+
+            assert_noop!(Mod::remove_envelope(Origin::signed(10), 100, H256::from_low_u64_be(3)),
+                         "envelope does not exist");
+            assert_ok!(Mod::remove_envelope(Origin::signed(10), 100, H256::from_low_u64_be(2)));
+            assert_eq!(Mod::envelope(&100, &H256::from_low_u64_be(2)), None);
+            assert_eq!(Mod::envelope_count(100), 1);
+
+            /* Envelopes are addressed by the owner, so they are removed
+               when the name is transferred.  */
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::envelope(&100, &H256::from_low_u64_be(1)), None);
+            assert_eq!(Mod::envelope_count(100), 0);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the free updates quota.
+mod free_updates {
+    use super::*;
+
+    #[test]
+    fn quota_per_era() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 400, 1));
+            expect_balance(10, 4900);
+            assert_eq!(Mod::free_updates_left(&400), 2);
+
+            assert_ok!(Mod::update(Origin::signed(10), 400, 2));
+            assert_ok!(Mod::update(Origin::signed(10), 400, 3));
+            assert_eq!(Mod::free_updates_left(&400), 0);
+            expect_balance(10, 4900);
+            assert_ok!(Mod::update(Origin::signed(10), 400, 4));
+            expect_balance(10, 4890);
+
+            /* Transfers are not covered by the quota.  */
+            assert!(!Mod::check_assuming_signed(10, 400, None, Some(20)).unwrap().free_update);
+
+            System::set_block_number(10);
+            assert_eq!(Mod::free_updates_left(&400), 2);
+            assert_ok!(Mod::update(Origin::signed(10), 400, 5));
+            expect_balance(10, 4890);
+            assert_eq!(Mod::free_updates_left(&400), 1);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for reporting squatted names.
+mod squatting_reports {
+    use super::*;
+
+    #[test]
+    fn reporting() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+
+            assert_noop!(Mod::report_name(Origin::signed(20), 100),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_noop!(Mod::report_name(Origin::signed(10), 100),
+                         "cannot report an owned name");
+
+            System::set_block_number(5);
+            assert_ok!(Mod::report_name(Origin::signed(20), 100));
+            assert_noop!(Mod::report_name(Origin::signed(30), 100),
+                         "name is reported already");
+            assert_eq!(Mod::report_of(100), Some(SquattingReport::<Test> {
+                reporter: 20,
+                bond: 500,
+                decidable_at: 15,
+            }));
+            assert_eq!(Balances::reserved_balance(&20), 500);
+        });
+    }
+
+    #[test]
+    fn upheld() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::report_name(Origin::signed(20), 100));
+
+            assert_noop!(Mod::decide_report(Origin::signed(30), 100, true),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::decide_report(Origin::ROOT, 200, true),
+                         "name is not reported");
+            assert_noop!(Mod::decide_report(Origin::ROOT, 100, true),
+                         "report is still in its challenge period");
+
+            System::set_block_number(11);
+            assert_ok!(Mod::decide_report(Origin::ROOT, 100, true));
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::report_of(100), None);
+            expect_balance(20, 5000);
+            expect_balance(10, 4900);
+        });
+    }
+
+    #[test]
+    fn rejected() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::report_name(Origin::signed(20), 100));
+
+            System::set_block_number(11);
+            assert_ok!(Mod::decide_report(Origin::ROOT, 100, false));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::report_of(100), None);
+            expect_balance(20, 4500);
+            expect_balance(10, 5400);
+        });
+    }
+
+    #[test]
+    fn refunded_when_name_is_removed() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::report_name(Origin::signed(20), 100));
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert_eq!(Mod::report_of(100), None);
+            expect_balance(20, 5000);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the conversion of name fees.
+mod fee_conversion {
+    use super::*;
+
+    #[test]
+    fn converted_fee() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_fee_rate(150, 1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            expect_balance(10, 4850);
+            expect_balance(FEE_RECEIVER, 1150);
+        });
+    }
+
+    #[test]
+    fn stale_rate() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            set_fee_rate(150, 1);
+            System::set_block_number(11);
+            assert_ok!(Mod::check_assuming_signed(10, 100, Some(42), None));
+
+            System::set_block_number(12);
+            assert_noop!(Mod::update(Origin::signed(10), 100, 42),
+                         "fee conversion rate is stale");
+        });
+    }
+
+    #[test]
+    fn no_rate() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            set_fee_rate(0, 1);
+            assert_noop!(Mod::update(Origin::signed(10), 100, 42),
+                         "no fee conversion rate available");
+        });
+    }
+
+    #[test]
+    fn free_operations_need_no_rate() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            set_fee_rate(0, 1);
+            System::set_block_number(50);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the expiration insurance.
+mod insurance {
+    use super::*;
+
+    #[test]
+    fn insuring() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(Mod::insurance_pool(), 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::set_insurance_premium(Origin::ROOT, 20));
+
+            assert_noop!(Mod::insure_name(Origin::signed(10), 100, 1),
+                         "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_noop!(Mod::insure_name(Origin::signed(20), 100, 1),
+                         "non-owner name insurance");
+            assert_noop!(Mod::insure_name(Origin::signed(10), 100, 0),
+                         "no renewals to insure");
+
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 2));
+            assert_noop!(Mod::insure_name(Origin::signed(10), 100, 2),
+                         "too many insured renewals");
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 1));
+            assert_eq!(Mod::insured_renewals(100), 3);
+            expect_balance(10, 4840);
+            expect_balance(Mod::insurance_pool(), 1060);
+        });
+    }
+
+    #[test]
+    fn renewal_on_expiration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 1));
+
+            System::set_block_number(100);
+            Mod::expire_names(100);
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 42);
+            assert_eq!(data.expiration, Some(200));
+            assert_eq!(Mod::insured_renewals(100), 0);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::InsuranceClaimed(100, 0))
+            }));
+
+            System::set_block_number(200);
+            Mod::expire_names(200);
+            assert_eq!(Mod::lookup(100), None);
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 2));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::insured_renewals(100), 0);
+        });
+    }
+
+    #[test]
+    fn premiums_held_by_pallet_account() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(Mod::insurance_pool(), 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::set_insurance_premium(Origin::ROOT, 20));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            let issuance = Balances::total_issuance();
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 2));
+            assert_eq!(Balances::total_issuance(), issuance);
+
+            let pool: u64 = NamesModuleId::get().into_sub_account(&b"insurance"[..]);
+            assert_eq!(Mod::insurance_pool(), pool);
+            assert_eq!(Mod::insurance_pool(), Mod::sub_account_id(b"insurance"));
+            expect_balance(pool, 1040);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for session keys.
+mod session_keys {
+    use super::*;
+
+    fn payload(name: u64, value: u64, nonce: u64) -> SessionUpdate<Test> {
+        SessionUpdate::<Test> {
+            owner: 10,
+            key: 30,
+            name: name,
+            value: value,
+            nonce: nonce,
+        }
+    }
+
+    fn sign(payload: &SessionUpdate<Test>) -> TestSignature {
+        TestSignature(payload.key, Mod::session_update_message(payload))
+    }
+
+    #[test]
+    fn authorisation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_ok!(Mod::update(Origin::signed(20), 300, 42));
+
+            System::set_block_number(10);
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 9, vec![100]),
+                         "session key is already expired");
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 111, vec![100]),
+                         "session key lasts too long");
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![]),
+                         "session key has no names");
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 20,
+                                                    vec![100, 200, 100]),
+                         "session key has too many names");
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![400]),
+                         "name does not exist");
+            assert_noop!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![300]),
+                         "non-owner session key");
+
+            assert_ok!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![100, 200]));
+            assert_eq!(Mod::session_key(10, 30), Some(SessionGrant::<Test> {
+                expires: 20,
+                names: vec![100, 200],
+            }));
+
+            assert_noop!(Mod::revoke_session_key(Origin::signed(10), 40),
+                         "no such session key");
+            assert_ok!(Mod::revoke_session_key(Origin::signed(10), 30));
+            assert_eq!(Mod::session_key(10, 30), None);
+        });
+    }
+
+    #[test]
+    fn updates() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_ok!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![100]));
+
+            let p = payload(100, 50, 0);
+            assert_noop!(Mod::session_update(Origin::signed(40), p.clone(),
+                                             TestSignature(10, p.encode())),
+                         "invalid session key signature");
+            assert_noop!(Mod::session_update(Origin::signed(40), p.clone(),
+                                             TestSignature(30, p.encode())),
+                         "invalid session key signature");
+            assert_noop!(Mod::session_update(Origin::signed(40), payload(100, 50, 1),
+                                             sign(&payload(100, 50, 1))),
+                         "invalid operation nonce");
+            assert_noop!(Mod::session_update(Origin::signed(40), payload(200, 50, 0),
+                                             sign(&payload(200, 50, 0))),
+                         "session key does not cover the name");
+
+            assert_ok!(Mod::session_update(Origin::signed(40), p.clone(), sign(&p)));
+            assert_eq!(Mod::lookup(100).unwrap().value, 50);
+            assert_eq!(Mod::operation_nonce(10), 1);
+            assert_noop!(Mod::session_update(Origin::signed(40), p.clone(), sign(&p)),
+                         "invalid operation nonce");
+
+            System::set_block_number(21);
+            let p = payload(100, 60, 1);
+            assert_noop!(Mod::session_update(Origin::signed(40), p.clone(), sign(&p)),
+                         "session key is expired");
+        });
+    }
+
+    #[test]
+    fn only_owner_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::authorize_session_key(Origin::signed(10), 30, 20, vec![100]));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            let p = payload(100, 50, 0);
+            assert_noop!(Mod::session_update(Origin::signed(40), p.clone(), sign(&p)),
+                         "non-owner name update");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for bulk renewals by namespace registrars.
+mod namespace_renewals {
+    use super::*;
+
+    #[test]
+    fn registrar_record() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::set_namespace_registrar(Origin::signed(10), b"4".to_vec(), Some(30)),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::set_namespace_registrar(Origin::ROOT, b"4".to_vec(), Some(30)));
+            assert_eq!(Mod::namespace_registrar(b"4".to_vec()), Some(30));
+            assert_ok!(Mod::set_namespace_registrar(Origin::ROOT, b"4".to_vec(), None));
+            assert_eq!(Mod::namespace_registrar(b"4".to_vec()), None);
+        });
+    }
+
+    #[test]
+    fn renewal() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            add_balance(30, 5000);
+            assert_ok!(Mod::set_namespace_registrar(Origin::ROOT, b"4".to_vec(), Some(30)));
+            assert_ok!(Mod::update(Origin::signed(10), 410, 42));
+            assert_ok!(Mod::update(Origin::signed(20), 420, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            /* Use up the free updates, so that renewals pay a fee.  */
+            for name in &[410, 420] {
+                let owner = Mod::lookup(name).unwrap().owner;
+                assert_ok!(Mod::update(Origin::signed(owner), *name, 42));
+                assert_ok!(Mod::update(Origin::signed(owner), *name, 42));
+            }
+
+            System::set_block_number(5);
+            assert_noop!(Mod::renew_namespace(Origin::signed(20), b"4".to_vec(), vec![410]),
+                         "sender is not the registrar of the namespace");
+            assert_noop!(Mod::renew_namespace(Origin::signed(30), b"4".to_vec(),
+                                              vec![410, 420, 430, 440]),
+                         "too many names to renew");
+            assert_noop!(Mod::renew_namespace(Origin::signed(30), b"4".to_vec(), vec![410, 100]),
+                         "name is outside the namespace");
+            assert_noop!(Mod::renew_namespace(Origin::signed(30), b"4".to_vec(), vec![410, 410]),
+                         "duplicate name in batch");
+            assert_noop!(Mod::renew_namespace(Origin::signed(30), b"4".to_vec(), vec![410, 430]),
+                         "name does not exist");
+
+            assert_ok!(Mod::renew_namespace(Origin::signed(30), b"4".to_vec(), vec![410, 420]));
+            let data = Mod::lookup(410).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 42);
+            assert_eq!(data.expiration, Some(415));
+            assert_eq!(Mod::lookup(420).unwrap().owner, 20);
+            assert_eq!(Mod::lookup(420).unwrap().expiration, Some(425));
+            expect_balance(10, 4800);
+            expect_balance(20, 4900);
+            expect_balance(30, 4980);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the first-update requirement of new names.
+mod first_updates {
+    use super::*;
+
+    #[test]
+    fn not_required() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::pending_first_update(100), None);
+            expect_balance(FEE_RECEIVER, 1100);
+        });
+    }
+
+    #[test]
+    fn completed() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_first_update_period(Some(10));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::pending_first_update(100), Some(PendingFirstUpdate::<Test> {
+                deadline: 10,
+                payer: 10,
+                held: 50,
+            }));
+            assert_eq!(Balances::reserved_balance(&10), 50);
+            expect_balance(FEE_RECEIVER, 1050);
+
+            /* Updates that keep the value do not count.  */
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert!(Mod::pending_first_update(100).is_some());
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_eq!(Mod::pending_first_update(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 4900);
+            expect_balance(FEE_RECEIVER, 1100);
+
+            System::set_block_number(10);
+            Mod::release_unused_names(10);
+            assert_eq!(Mod::lookup(100).unwrap().value, 43);
+        });
+    }
+
+    #[test]
+    fn released() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_first_update_period(Some(10));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            System::set_block_number(9);
+            Mod::release_unused_names(9);
+            assert!(Mod::lookup(100).is_some());
+
+            System::set_block_number(10);
+            Mod::release_unused_names(10);
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::pending_first_update(100), None);
+            expect_balance(10, 4950);
+            expect_balance(FEE_RECEIVER, 1050);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameReleased(100, 50))
+            }));
+        });
+    }
+
+    #[test]
+    fn forfeited_on_removal() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_first_update_period(Some(10));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert_eq!(Mod::pending_first_update(100), None);
+            expect_balance(10, 4900);
+            expect_balance(FEE_RECEIVER, 1100);
+        });
+    }
+
+    #[test]
+    fn hold_returned_if_withdrawal_fails() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_first_update_period(Some(10));
+
+            let op = Mod::check_assuming_signed(10, 100, Some(42), None).unwrap();
+            /* The lock only applies to fees, so that holding part of the
+               fee still works before the withdrawal of the rest fails.  */
+            let mut reasons = WithdrawReasons::none();
+            reasons.set(WithdrawReason::Fee);
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &10, 5000, 100, reasons);
+            assert!(Mod::execute(op).is_err());
+
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::pending_first_update(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 5000);
+            expect_balance(FEE_RECEIVER, 1000);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for signalling rejected operations.
+mod rejections {
+    use super::*;
+
+    #[test]
+    fn event_and_error() {
+        new_test_ext().execute_with(|| {
+            let res: DispatchResult = Err(Mod::reject_operation(&100, 7, "value is invalid").into());
+            assert_eq!(res, Err("value is invalid".into()));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::OperationRejected(100, 7)));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for event topics of namespaces.
+mod namespace_topics {
+    use super::*;
+
+    /// Returns the topics of all events about the given name.
+    fn topics_of(name: u64) -> Vec<Vec<H256>> {
+        System::events().into_iter()
+            .filter(|r| match &r.event {
+                TestEvent::names(RawEvent::NameRegistered(n))
+                    | TestEvent::names(RawEvent::NameUpdated(n, _))
+                    | TestEvent::names(RawEvent::NameTransferred(n, _, _)) => *n == name,
+                _ => false,
+            })
+            .map(|r| r.topics)
+            .collect()
+    }
+
+    #[test]
+    fn set_topic_prefixes() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::set_topic_prefixes(Origin::signed(10), vec![b"4".to_vec()]),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::set_topic_prefixes(Origin::ROOT, vec![vec![1], vec![2], vec![3]]),
+                         "too many topic prefixes");
+            assert_ok!(Mod::set_topic_prefixes(Origin::ROOT, vec![b"4".to_vec(), b"45".to_vec()]));
+            assert_eq!(Mod::topic_prefixes(), vec![b"4".to_vec(), b"45".to_vec()]);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::TopicPrefixesSet(
+                    vec![b"4".to_vec(), b"45".to_vec()]))
+            }));
+        });
+    }
+
+    #[test]
+    fn events_are_indexed() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::set_topic_prefixes(Origin::ROOT, vec![b"4".to_vec(), b"45".to_vec()]));
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 400, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 450, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 450, 20));
+
+            let four = Mod::namespace_topic(b"4");
+            let four_five = Mod::namespace_topic(b"45");
+            assert_ne!(four, four_five);
+            assert_eq!(topics_of(100), vec![Vec::<H256>::new(); 2]);
+            assert_eq!(topics_of(400), vec![vec![four]; 2]);
+            assert_eq!(topics_of(450), vec![vec![four, four_five]; 4]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for delegated administration of namespaces.
+mod namespace_delegation {
+    use super::*;
+
+    #[test]
+    fn delegate_namespace() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::delegate_namespace(Origin::signed(30), b"4".to_vec(), 30, true),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"4".to_vec(), 30, true));
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"4".to_vec(), 30, true));
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"5".to_vec(), 30, true));
+            assert_noop!(Mod::delegate_namespace(Origin::ROOT, b"6".to_vec(), 30, true),
+                         "too many delegated namespaces");
+            assert_eq!(Mod::delegated_namespaces(30), vec![b"4".to_vec(), b"5".to_vec()]);
+            assert!(Mod::administers(&30, &400));
+            assert!(Mod::administers(&30, &5));
+            assert!(!Mod::administers(&30, &600));
+            assert!(!Mod::administers(&20, &400));
+
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"4".to_vec(), 30, false));
+            assert!(!Mod::administers(&30, &400));
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"5".to_vec(), 30, false));
+            assert!(!<DelegatedNamespaces<Test>>::exists(30));
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NamespaceDelegated(b"5".to_vec(), 30, false))
+            }));
+        });
+    }
+
+    #[test]
+    fn admin_calls() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 400, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 500, 42));
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"4".to_vec(), 30, true));
+
+            assert_noop!(Mod::force_transfer(Origin::signed(30), 500, 20),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::force_remove(Origin::signed(30), 500),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::force_register(Origin::signed(30), 501, 1, 20),
+                         DispatchError::BadOrigin);
+
+            assert_ok!(Mod::force_transfer(Origin::signed(30), 400, 20));
+            assert_eq!(Mod::lookup(400).unwrap().owner, 20);
+            assert_ok!(Mod::force_register(Origin::signed(30), 401, 1, 20));
+            assert_eq!(Mod::lookup(401).unwrap().owner, 20);
+            assert_ok!(Mod::force_remove(Origin::signed(30), 401));
+            assert_eq!(Mod::lookup(401), None);
+
+            /* Governance can still act inside delegated namespaces.  */
+            assert_ok!(Mod::force_transfer(Origin::ROOT, 400, 10));
+            assert_eq!(Mod::lookup(400).unwrap().owner, 10);
+        });
+    }
+
+    #[test]
+    fn freeze_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 400, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 500, 42));
+            assert_ok!(Mod::delegate_namespace(Origin::ROOT, b"4".to_vec(), 30, true));
+
+            assert_noop!(Mod::freeze_name(Origin::signed(30), 500, true),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::freeze_name(Origin::signed(30), 401, true),
+                         "name does not exist");
+            assert_ok!(Mod::freeze_name(Origin::signed(30), 400, true));
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 500, true));
+            assert!(Mod::is_frozen(400));
+
+            assert_noop!(Mod::update(Origin::signed(10), 400, 43), "name is frozen");
+            assert_noop!(Mod::transfer(Origin::signed(10), 500, 20), "name is frozen");
+            assert_ok!(Mod::force_transfer(Origin::signed(30), 400, 20));
+            assert!(Mod::is_frozen(400));
+
+            add_balance(20, 5000);
+            assert_ok!(Mod::freeze_name(Origin::signed(30), 400, false));
+            assert_ok!(Mod::update(Origin::signed(20), 400, 43));
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameFrozen(400, false))
+            }));
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 500));
+            assert!(!Mod::is_frozen(500));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the retention policies of names on transfer.
+mod retention_policies {
+    use super::*;
+
+    /// Registers name 100 for account 10 with a record, an operator and
+    /// an insured renewal.
+    fn setup() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        add_balance(20, 5000);
+        assert_ok!(Mod::set_insurance_premium(Origin::ROOT, 20));
+        assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+        assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 5));
+        assert_ok!(Mod::approve_operator(Origin::signed(10), 100, 30,
+                                          vec![H256::repeat_byte(1)]));
+        assert_ok!(Mod::insure_name(Origin::signed(10), 100, 1));
+    }
+
+    #[test]
+    fn set_retention_policy() {
+        new_test_ext().execute_with(|| {
+            setup();
+            let policy = RetentionPolicy {
+                keep_records: false,
+                keep_operator: true,
+                keep_insurance: true,
+            };
+            assert_noop!(Mod::set_retention_policy(Origin::signed(10), 200, policy),
+                         "name does not exist");
+            assert_noop!(Mod::set_retention_policy(Origin::signed(20), 100, policy),
+                         "non-owner retention policy update");
+            assert_eq!(Mod::retention_policy(100), RetentionPolicy::default());
+            assert_ok!(Mod::set_retention_policy(Origin::signed(10), 100, policy));
+            assert_eq!(Mod::retention_policy(100), policy);
+
+            assert_ok!(Mod::set_retention_policy(Origin::signed(10), 100,
+                                                 RetentionPolicy::default()));
+            assert!(!<RetentionPolicies<Test>>::exists(100));
+        });
+    }
+
+    #[test]
+    fn default_policy() {
+        new_test_ext().execute_with(|| {
+            setup();
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            assert_eq!(Mod::records_of(&100), vec![(1, 5)]);
+            assert_eq!(Mod::operator_of(100), None);
+            assert_eq!(Mod::insured_renewals(100), 0);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::OwnerDataCleared(100, false, true, true))
+            }));
+        });
+    }
+
+    #[test]
+    fn custom_policy() {
+        new_test_ext().execute_with(|| {
+            setup();
+            let policy = RetentionPolicy {
+                keep_records: false,
+                keep_operator: true,
+                keep_insurance: true,
+            };
+            assert_ok!(Mod::set_retention_policy(Origin::signed(10), 100, policy));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            assert_eq!(Mod::records_of(&100), vec![]);
+            assert_eq!(Mod::subvalue_count(100), 0);
+            expect_balance(10, 5000 - 100 - 20);
+            assert_eq!(Mod::operator_of(100), Some((30, vec![H256::repeat_byte(1)])));
+            assert_eq!(Mod::insured_renewals(100), 1);
+            assert_eq!(Mod::retention_policy(100), RetentionPolicy::default());
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::OwnerDataCleared(100, true, false, false))
+            }));
+        });
+    }
+
+    #[test]
+    fn nothing_to_clear() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert!(!System::events().iter().any(|r| match r.event {
+                TestEvent::names(RawEvent::OwnerDataCleared(..)) => true,
+                _ => false,
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for registrar commissions on name fees.
+mod registrar_commission {
+    use super::*;
+
+    #[test]
+    fn commission_paid() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(50, 1000);
+            assert_ok!(Mod::update_via_registrar(Origin::signed(10), 100, 42, 50));
+            expect_balance(10, 4900);
+            expect_balance(50, 1010);
+            expect_balance(FEE_RECEIVER, 1090);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::RegistrarCommissionPaid(100, 50, 10))
+            }));
+
+            /* Without a fee, there is no commission.  */
+            assert_ok!(Mod::update_via_registrar(Origin::signed(10), 100, 43, 50));
+            expect_balance(50, 1010);
+            assert_eq!(Mod::lookup(100).unwrap().value, 43);
+
+            assert_noop!(Mod::update_via_registrar(Origin::signed(20), 100, 44, 50),
+                         "non-owner name update");
+        });
+    }
+
+    #[test]
+    fn without_registrar() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let mut op = Mod::check_assuming_signed(10, 100, Some(42), None).unwrap();
+            op.set_registrar(None);
+            assert_ok!(Mod::execute(op));
+            expect_balance(FEE_RECEIVER, 1100);
+            assert!(!System::events().iter().any(|r| match r.event {
+                TestEvent::names(RawEvent::RegistrarCommissionPaid(..)) => true,
+                _ => false,
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the quarantine of expired names.
+mod quarantine {
+    use super::*;
+
+    #[test]
+    fn blocks_registration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            set_quarantine(2);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::lookup(20), None);
+            assert_eq!(Mod::quarantined_until(20), Some(32));
+            assert_noop!(Mod::update(Origin::signed(20), 20, 1),
+                         "name is quarantined after expiration");
+
+            System::set_block_number(31);
+            Mod::end_quarantines(31);
+            assert_noop!(Mod::update(Origin::signed(20), 20, 1),
+                         "name is quarantined after expiration");
+
+            System::set_block_number(32);
+            Mod::end_quarantines(32);
+            assert_eq!(Mod::quarantined_until(20), None);
+            assert_ok!(Mod::update(Origin::signed(20), 20, 1));
+            assert_eq!(Mod::lookup(20).unwrap().owner, 20);
+        });
+    }
+
+    #[test]
+    fn disabled() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::quarantined_until(20), None);
+            assert_ok!(Mod::update(Origin::signed(20), 20, 1));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for controllers of names.
+mod controllers {
+    use super::*;
+
+    #[test]
+    fn set_controller() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::set_controller(Origin::signed(10), 200, Some(20)),
+                         "name does not exist");
+            assert_noop!(Mod::set_controller(Origin::signed(20), 100, Some(20)),
+                         "non-owner controller update");
+
+            assert_ok!(Mod::set_controller(Origin::signed(10), 100, Some(20)));
+            assert_eq!(Mod::controller(100), Some(20));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ControllerUpdated(100, Some(20))));
+
+            assert_ok!(Mod::set_controller(Origin::signed(10), 100, None));
+            assert_eq!(Mod::controller(100), None);
+        });
+    }
+
+    #[test]
+    fn controller_updates() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_noop!(Mod::set_value(Origin::signed(20), 100, 2),
+                         "non-owner name update");
+
+            assert_ok!(Mod::set_controller(Origin::signed(10), 100, Some(20)));
+            assert_ok!(Mod::set_value(Origin::signed(20), 100, 2));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 2);
+            assert_eq!(Mod::controller(100), Some(20));
+
+            assert_noop!(Mod::check_assuming_signed(20, 100, None, Some(30)),
+                         "controller cannot transfer the name");
+            assert_noop!(Mod::check_assuming_signed(20, 100, Some(3), Some(20)),
+                         "controller cannot transfer the name");
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_controller(Origin::signed(10), 100, Some(20)));
+
+            let op = Mod::check_assuming_signed(10, 100, None, Some(30)).unwrap();
+            assert_ok!(Mod::execute(op));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 30);
+            assert_eq!(Mod::controller(100), None);
+            assert_noop!(Mod::set_value(Origin::signed(20), 100, 2),
+                         "non-owner name update");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for simulated execution of operations.
+#[cfg(feature = "simulation")]
+mod simulation {
+    use super::*;
+
+    #[test]
+    fn reports_changes() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            let op = Mod::check_assuming_signed(10, 100, Some(42), None).unwrap();
+
+            let sim = Mod::simulate_execute(op);
+            assert_ok!(sim.result);
+            assert_eq!(sim.old_data, None);
+            assert_eq!(sim.new_data, Mod::lookup(100));
+            assert_eq!(sim.new_data.unwrap().value, 42);
+            assert_eq!(sim.balances, vec![(10, (5000, 0), (4900, 0))]);
+            assert!(sim.events.contains(&TestEvent::names(RawEvent::NameRegistered(100))));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the structured errors of operation checks.
+mod operation_errors {
+    use super::*;
+
+    #[test]
+    fn context() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_eq!(Mod::check_detailed(20, 20, 100, Some(2), None).err(),
+                       Some(OperationError::OwnedBy(10)));
+            assert_eq!(Mod::check_detailed(20, 20, 200, Some(2), None).err(),
+                       Some(OperationError::RequiredFee(100)));
+            assert_eq!(Mod::check_detailed(10, 10, 100, None, Some(10)).err(), None);
+        });
+    }
+
+    #[test]
+    fn deposit() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1050);
+            set_value_deposit(200);
+            assert_eq!(Mod::check_detailed(10, 10, 200, Some(2), None).err(),
+                       Some(OperationError::RequiredDeposit(8 * 200 + 100)));
+        });
+    }
+
+    #[test]
+    fn messages() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_noop!(Mod::check_assuming_signed(20, 100, None, None),
+                         "non-owner name update");
+            assert_eq!(OperationError::<Test>::RequiredFee(100).message(),
+                       "insufficient balance for name fee");
+            assert_eq!(OperationError::<Test>::from("name is frozen"),
+                       OperationError::Other("name is frozen"));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for how operations may change the expiration of names.
+mod expiration_updates {
+    use super::*;
+
+    fn setup() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+    }
+
+    fn expiration(name: u64) -> Option<u64> {
+        Mod::lookup(name).unwrap().expiration
+    }
+
+    #[test]
+    fn may_shorten() {
+        new_test_ext().execute_with(|| {
+            setup();
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_eq!(expiration(100), None);
+
+            System::set_block_number(10);
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 1));
+            assert_eq!(expiration(100), Some(110));
+        });
+    }
+
+    #[test]
+    fn extend_only() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_expiration_updates(ExpirationUpdate::ExtendOnly);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 1));
+
+            System::set_block_number(10);
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 1));
+            assert_eq!(expiration(100), None);
+            assert_ok!(Mod::set_value(Origin::signed(10), 200, 2));
+            assert_eq!(expiration(200), Some(210));
+            assert_ok!(Mod::set_value(Origin::signed(10), 200, 0));
+            assert_eq!(expiration(200), None);
+        });
+    }
+
+    #[test]
+    fn preserve() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_expiration_updates(ExpirationUpdate::Preserve);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_eq!(expiration(100), Some(101));
+
+            System::set_block_number(10);
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 0));
+            assert_eq!(expiration(100), Some(101));
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 2));
+            assert_eq!(expiration(100), Some(101));
+
+            System::set_block_number(20);
+            assert_noop!(Mod::renew(Origin::signed(10), 200), "name does not exist");
+            assert_noop!(Mod::renew(Origin::signed(20), 100), "non-owner name update");
+            assert_ok!(Mod::renew(Origin::signed(10), 100));
+            assert_eq!(Mod::lookup(100).unwrap().value, 2);
+            assert_eq!(expiration(100), Some(120));
+        });
+    }
+
+    #[test]
+    fn preserved_expiration_is_processed() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_expiration_updates(ExpirationUpdate::Preserve);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            System::set_block_number(10);
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 2));
+
+            System::set_block_number(101);
+            Mod::expire_names(101);
+            assert_eq!(Mod::lookup(100), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for marking names as ready for sale.
+mod sale_ready {
+    use super::*;
+
+    #[test]
+    fn set_mark() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::set_sale_ready(Origin::signed(10), 200, true),
+                         "name does not exist");
+            assert_noop!(Mod::set_sale_ready(Origin::signed(20), 100, true),
+                         "non-owner sale mark update");
+
+            assert_ok!(Mod::set_sale_ready(Origin::signed(10), 100, true));
+            assert!(Mod::is_sale_ready(100));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::SaleReadySet(100, true)));
+
+            assert_ok!(Mod::set_sale_ready(Origin::signed(10), 100, false));
+            assert!(!Mod::is_sale_ready(100));
+        });
+    }
+
+    #[test]
+    fn value_locked() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_sale_ready(Origin::signed(10), 100, true));
+
+            assert_noop!(Mod::set_value(Origin::signed(10), 100, 2),
+                         "name value is locked for sale");
+            assert_noop!(Mod::check_assuming_signed(10, 100, Some(2), Some(20)),
+                         "name value is locked for sale");
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 1));
+            assert_eq!(Mod::lookup(100).unwrap().value, 1);
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_sale_ready(Origin::signed(10), 100, true));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert!(!Mod::is_sale_ready(100));
+            assert_ok!(Mod::set_value(Origin::signed(20), 100, 2));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the availability status of names.
+mod availability {
+    use super::*;
+    use sp_runtime::traits::Hash;
+
+    #[test]
+    fn registered_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(1);
+            assert_eq!(Mod::availability(&100), Availability::Available);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 0));
+            assert_eq!(Mod::availability(&100), Availability::Taken { expires: Some(101) });
+            assert_eq!(Mod::availability(&200), Availability::Taken { expires: None });
+
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 200, true));
+            assert_eq!(Mod::availability(&200), Availability::Frozen);
+        });
+    }
+
+    #[test]
+    fn grace_period() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 700, 1));
+
+            System::set_block_number(701);
+            Mod::expire_names(701);
+            assert_eq!(Mod::availability(&700), Availability::GracePeriod { until: 706 });
+        });
+    }
+
+    #[test]
+    fn reserved() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), BlakeTwo256::hash_of(&100u64)));
+            assert_eq!(Mod::availability(&100), Availability::Reserved);
+
+            set_quarantine(5);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::availability(&20), Availability::Reserved);
+            Mod::end_quarantines(35);
+            assert_eq!(Mod::availability(&20), Availability::Available);
+        });
+    }
+
+    #[test]
+    fn bulk_check() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::register_hidden(Origin::signed(10), BlakeTwo256::hash_of(&300u64)));
+
+            assert_eq!(Mod::check_available(&[]), vec![]);
+            assert_eq!(Mod::check_available(&[200, 100, 300, 200]), vec![
+                Availability::Available,
+                Availability::Taken { expires: None },
+                Availability::Reserved,
+                Availability::Available,
+            ]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for swapping names between two owners.
+mod swaps {
+    use super::*;
+
+    fn setup() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        add_balance(20, 5000);
+        assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+        assert_ok!(Mod::update(Origin::signed(10), 101, 1));
+        assert_ok!(Mod::update(Origin::signed(20), 200, 2));
+    }
+
+    #[test]
+    fn invalid_offers() {
+        new_test_ext().execute_with(|| {
+            setup();
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 10, vec![100], vec![], 0),
+                         "cannot swap names with oneself");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![], vec![], 0),
+                         "swap offer is empty");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100, 101],
+                                           vec![200, 201], 0),
+                         "too many names in swap offer");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100, 100], vec![], 0),
+                         "duplicate name in swap offer");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![200], vec![], 0),
+                         "swapped name has a different owner");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![201], 0),
+                         "name does not exist");
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![101], 0),
+                         "swapped name has a different owner");
+        });
+    }
+
+    #[test]
+    fn offer_locks_names() {
+        new_test_ext().execute_with(|| {
+            setup();
+            assert_ok!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![200], 500));
+            assert_eq!(Mod::swap_lock(100), Some(10));
+            assert_eq!(Balances::reserved_balance(&10), 500);
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 30, vec![101], vec![], 0),
+                         "sender already has an open swap offer");
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 30),
+                         "name is offered in a swap");
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 5));
+
+            assert_noop!(Mod::cancel_swap(Origin::signed(20)), "no open swap offer");
+            assert_ok!(Mod::cancel_swap(Origin::signed(10)));
+            assert_eq!(Mod::swap_offer(10), None);
+            assert_eq!(Mod::swap_lock(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+        });
+    }
+
+    #[test]
+    fn accept() {
+        new_test_ext().execute_with(|| {
+            setup();
+            assert_ok!(Mod::propose_swap(Origin::signed(10), 20, vec![100, 101],
+                                         vec![200], 500));
+            assert_noop!(Mod::accept_swap(Origin::signed(30), 10),
+                         "sender is not the swap counterparty");
+            assert_noop!(Mod::accept_swap(Origin::signed(20), 30), "no open swap offer");
+
+            assert_ok!(Mod::accept_swap(Origin::signed(20), 10));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::lookup(101).unwrap().owner, 20);
+            assert_eq!(Mod::lookup(200).unwrap().owner, 10);
+            assert_eq!(Mod::swap_offer(10), None);
+            assert_eq!(Mod::swap_lock(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::free_balance(&10), 5000 - 200 - 500);
+            assert_eq!(Balances::free_balance(&20), 5000 - 100 + 500);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::SwapCompleted(10, 20)));
+        });
+    }
+
+    #[test]
+    fn accept_checks_everything_first() {
+        new_test_ext().execute_with(|| {
+            setup();
+            assert_ok!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![200], 500));
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(20), 200, 30));
+
+            assert_noop!(Mod::accept_swap(Origin::signed(20), 10),
+                         "name is locked as collateral");
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::swap_lock(100), Some(10));
+            assert_eq!(Balances::reserved_balance(&10), 500);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for inspecting pending processes of names.
+mod pending_entries {
+    use super::*;
+
+    #[test]
+    fn pending_for() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(1);
+            assert_eq!(Mod::pending_for(&100), vec![]);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_eq!(Mod::pending_for(&100), vec![]);
+
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+            assert_ok!(Mod::schedule_value(Origin::signed(10), 100, 5, 8));
+            assert_ok!(Mod::place_backorder(Origin::signed(20), 100, 10));
+            assert_eq!(Mod::pending_for(&100), vec![
+                PendingEntry::Collateral { beneficiary: 30 },
+                PendingEntry::ScheduledValue { at: 8 },
+                PendingEntry::Backorders(1),
+            ]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the fee exemption list.
+mod fee_exemptions {
+    use super::*;
+
+    #[test]
+    fn admin_only() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::set_account_fee_exemption(Origin::signed(10), 10, true),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::set_name_fee_exemption(Origin::signed(10), 100, true),
+                         DispatchError::BadOrigin);
+
+            assert_ok!(Mod::set_account_fee_exemption(Origin::ROOT, 10, true));
+            assert!(Mod::is_fee_exempt_account(10));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::AccountFeeExemptionSet(10, true)));
+            assert_ok!(Mod::set_name_fee_exemption(Origin::ROOT, 100, true));
+            assert!(Mod::is_fee_exempt_name(100));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::NameFeeExemptionSet(100, true)));
+
+            assert_ok!(Mod::set_account_fee_exemption(Origin::ROOT, 10, false));
+            assert!(!Mod::is_fee_exempt_account(10));
+        });
+    }
+
+    #[test]
+    fn exempt_account() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::set_account_fee_exemption(Origin::ROOT, 10, true));
+
+            assert_eq!(Mod::check_assuming_signed(10, 100, Some(1), None).unwrap().fee, 0);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_eq!(Balances::free_balance(&10), 5000);
+            assert_noop!(Mod::update(Origin::signed(10), 200, 2_000),
+                         "operation violates name policy");
+
+            assert_eq!(Mod::check_assuming_signed(20, 200, Some(1), None).unwrap().fee, 100);
+        });
+    }
+
+    #[test]
+    fn exempt_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::set_name_fee_exemption(Origin::ROOT, 100, true));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_eq!(Balances::free_balance(&10), 5000);
+            assert_eq!(Mod::check_assuming_signed(10, 200, Some(1), None).unwrap().fee, 100);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the per-block limit of registrations.
+mod registrations_per_block {
+    use super::*;
+    use sp_runtime::traits::OnInitialize;
+
+    #[test]
+    fn limits_registrations() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_registrations_per_block(Some(2));
+            System::set_block_number(1);
+            Mod::on_initialize(1);
+            assert_ok!(Mod::update(Origin::signed(10), 1, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 2, 1));
+            assert_eq!(Mod::registrations_this_block(), 2);
+
+            assert_eq!(Mod::check_detailed(10, 10, 3, Some(1), None).unwrap_err(),
+                       OperationError::RetryNextBlock);
+            assert_noop!(Mod::update(Origin::signed(10), 3, 1),
+                         "too many registrations in this block");
+
+            /* Updates of existing names are not affected.  */
+            assert_ok!(Mod::update(Origin::signed(10), 1, 2));
+            assert_eq!(Mod::registrations_this_block(), 2);
+
+            System::set_block_number(2);
+            Mod::on_initialize(2);
+            assert_eq!(Mod::registrations_this_block(), 0);
+            assert_ok!(Mod::update(Origin::signed(10), 3, 1));
+        });
+    }
+
+    #[test]
+    fn unlimited() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            for name in 1..=5 {
+                assert_ok!(Mod::update(Origin::signed(10), name, 1));
+            }
+            assert_eq!(Mod::registrations_this_block(), 5);
+            assert!(Mod::can_register_in_block());
+        });
+    }
+
+    #[test]
+    fn validity_hint() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_registrations_per_block(Some(1));
+            assert_ok!(Mod::validate_registration(&Call::update(2, 1)));
+            assert_ok!(Mod::update(Origin::signed(10), 1, 1));
+
+            assert_eq!(Mod::validate_registration(&Call::update(2, 1)),
+                       Err(InvalidTransaction::ExhaustsResources.into()));
+            assert_eq!(Mod::validate_registration(&Call::register(2, 1, None)),
+                       Err(InvalidTransaction::ExhaustsResources.into()));
+            assert_ok!(Mod::validate_registration(&Call::update(1, 2)));
+            assert_ok!(Mod::validate_registration(&Call::renew(1)));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for registrations in attested namespaces.
+mod attestations {
+    use super::*;
+
+    fn attest(name: u64, identity: &[u8], registrant: u64, oracle: u64) -> IdentityAttestation<Test> {
+        IdentityAttestation {
+            identity: identity.to_vec(),
+            signature: TestSignature(oracle, Mod::attestation_message(&name, identity, &registrant)),
+        }
+    }
+
+    #[test]
+    fn set_oracle() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::set_attestation_oracle(Origin::signed(10), b"5".to_vec(), Some(50)),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"5".to_vec(), Some(50)));
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"6".to_vec(), Some(60)));
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"5".to_vec(), Some(51)));
+            assert_eq!(Mod::attested_namespaces(),
+                       vec![(b"6".to_vec(), 60), (b"5".to_vec(), 51)]);
+            assert_eq!(Mod::attestation_oracle(&55), Some(51));
+            assert_eq!(Mod::attestation_oracle(&10), None);
+
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"6".to_vec(), None));
+            assert_eq!(Mod::attested_namespaces(), vec![(b"5".to_vec(), 51)]);
+        });
+    }
+
+    #[test]
+    fn requires_attestation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"5".to_vec(), Some(50)));
+
+            assert_noop!(Mod::update(Origin::signed(10), 55, 1),
+                         "name requires an oracle attestation");
+            assert_noop!(Mod::register_attested(Origin::signed(10), 55, 1,
+                                                attest(55, b"@domob", 10, 51)),
+                         "invalid oracle attestation");
+            assert_noop!(Mod::register_attested(Origin::signed(10), 55, 1,
+                                                attest(55, b"@domob", 20, 50)),
+                         "invalid oracle attestation");
+            assert_noop!(Mod::register_attested(Origin::signed(10), 55, 1,
+                                                attest(56, b"@domob", 10, 50)),
+                         "invalid oracle attestation");
+
+            /* Names outside of the namespace need no attestation.  */
+            assert_ok!(Mod::update(Origin::signed(10), 65, 1));
+        });
+    }
+
+    #[test]
+    fn registration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::set_attestation_oracle(Origin::ROOT, b"5".to_vec(), Some(50)));
+
+            let attestation = attest(55, b"@domob", 10, 50);
+            let hash = BlakeTwo256::hash_of(&attestation);
+            assert_ok!(Mod::register_attested(Origin::signed(10), 55, 1, attestation.clone()));
+            assert_eq!(Mod::lookup(55).unwrap().owner, 10);
+            assert_eq!(Mod::attestation_hash(55), Some(hash));
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameAttested(55, hash))
+            }));
+
+            assert_noop!(Mod::register_attested(Origin::signed(10), 55, 1, attestation),
+                         "name already exists");
+
+            /* Updates of the name do not need the attestation again, but
+               it is cleared when the name changes owner.  */
+            assert_ok!(Mod::update(Origin::signed(10), 55, 2));
+            assert_eq!(Mod::attestation_hash(55), Some(hash));
+            assert_ok!(Mod::transfer(Origin::signed(10), 55, 20));
+            assert_eq!(Mod::attestation_hash(55), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for guardians that confirm transfers of names.
+mod guardians {
+    use super::*;
+
+    #[test]
+    fn set_guardian() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::set_guardian(Origin::signed(10), 200, Some(50)),
+                         "name does not exist");
+            assert_noop!(Mod::set_guardian(Origin::signed(20), 100, Some(50)),
+                         "non-owner guardian update");
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+            assert_eq!(Mod::guardian(100), Some(50));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::GuardianUpdated(100, Some(50))));
+
+            /* Once set, only the guardian can remove itself.  */
+            assert_noop!(Mod::set_guardian(Origin::signed(10), 100, None),
+                         "only the guardian can change it");
+            assert_ok!(Mod::set_guardian(Origin::signed(50), 100, None));
+            assert_eq!(Mod::guardian(100), None);
+        });
+    }
+
+    #[test]
+    fn confirmed_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "transfer needs confirmation by the guardian");
+            assert_noop!(Mod::request_guarded_transfer(Origin::signed(20), 100, 30),
+                         "non-owner transfer request");
+            assert_ok!(Mod::request_guarded_transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::guarded_transfer(100), Some(GuardedTransfer {
+                recipient: 20,
+                deadline: 15,
+                confirmed: false,
+            }));
+
+            /* Before confirmation, the transfer is still blocked.  */
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "transfer needs confirmation by the guardian");
+            assert_noop!(Mod::confirm_guarded_transfer(Origin::signed(10), 100),
+                         "only the guardian can confirm");
+
+            assert_ok!(Mod::confirm_guarded_transfer(Origin::signed(50), 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::guardian(100), None);
+            assert_eq!(Mod::guarded_transfer(100), None);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::GuardedTransferConfirmed(100, 20)));
+        });
+    }
+
+    #[test]
+    fn expired_request() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+            assert_ok!(Mod::request_guarded_transfer(Origin::signed(10), 100, 20));
+
+            System::set_block_number(16);
+            assert_noop!(Mod::confirm_guarded_transfer(Origin::signed(50), 100),
+                         "transfer request has expired");
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+        });
+    }
+
+    #[test]
+    fn failed_confirmation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+            assert_ok!(Mod::request_guarded_transfer(Origin::signed(10), 100, 20));
+            assert_ok!(Mod::set_block_all_transfers(Origin::signed(20), true));
+
+            assert!(Mod::confirm_guarded_transfer(Origin::signed(50), 100).is_err());
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert!(!Mod::guarded_transfer(100).unwrap().confirmed);
+        });
+    }
+
+    #[test]
+    fn cancel() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_noop!(Mod::request_guarded_transfer(Origin::signed(10), 100, 20),
+                         "name has no guardian");
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+
+            assert_noop!(Mod::cancel_guarded_transfer(Origin::signed(10), 100),
+                         "no transfer requested");
+            assert_ok!(Mod::request_guarded_transfer(Origin::signed(10), 100, 20));
+            assert_noop!(Mod::cancel_guarded_transfer(Origin::signed(20), 100),
+                         "only the owner or guardian can cancel");
+            assert_ok!(Mod::cancel_guarded_transfer(Origin::signed(50), 100));
+            assert_eq!(Mod::guarded_transfer(100), None);
+        });
+    }
+
+    #[test]
+    fn no_transfers_around_guardian() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(20), 200, 2));
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+
+            let err = "transfer needs confirmation by the guardian";
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 20), err);
+            assert_noop!(Mod::offer_rent_to_own(Origin::signed(10), 100, 20, 500, 1, 10,
+                                                HandoverTerms::default()),
+                         err);
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![], 0), err);
+            assert_noop!(Mod::propose_swap(Origin::signed(20), 10, vec![200], vec![100], 0),
+                         err);
+            assert_noop!(Mod::split_into_shares(Origin::signed(10), 100, 10,
+                                                Perbill::from_percent(50)),
+                         err);
+            assert_noop!(Mod::donate_name(Origin::signed(10), 100), err);
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+        });
+    }
+
+    #[test]
+    fn guardian_added_to_requested_swap_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(20), 200, 2));
+            assert_ok!(Mod::propose_swap(Origin::signed(20), 10, vec![200], vec![100], 0));
+
+            assert_ok!(Mod::set_guardian(Origin::signed(10), 100, Some(50)));
+            assert_noop!(Mod::accept_swap(Origin::signed(10), 20),
+                         "transfer needs confirmation by the guardian");
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+        });
+    }
+
+    #[test]
+    fn no_guardian_for_encumbered_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 101, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 102, 1));
+
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+            assert_noop!(Mod::set_guardian(Origin::signed(10), 100, Some(50)),
+                         "name is locked as collateral");
+            assert_ok!(Mod::claim_collateral(Origin::signed(30), 100));
+
+            assert_ok!(Mod::offer_rent_to_own(Origin::signed(10), 101, 20, 500, 1, 10,
+                                              HandoverTerms::default()));
+            assert_noop!(Mod::set_guardian(Origin::signed(10), 101, Some(50)),
+                         "name is under a rent-to-own agreement");
+
+            assert_ok!(Mod::propose_swap(Origin::signed(10), 20, vec![102], vec![], 0));
+            assert_noop!(Mod::set_guardian(Origin::signed(10), 102, Some(50)),
+                         "name is offered in a swap");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the archive of expired names.
+mod archive {
+    use super::*;
+
+    #[test]
+    fn tombstone_and_pruning() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_archive_retention(Some(5));
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::lookup(20), None);
+            assert_eq!(Mod::archived(20), Some(Tombstone {
+                owner: 10,
+                value_hash: BlakeTwo256::hash_of(&1u64),
+                expired_at: 30,
+            }));
+
+            Mod::prune_archive(34);
+            assert!(Mod::archived(20).is_some());
+            Mod::prune_archive(35);
+            assert_eq!(Mod::archived(20), None);
+        });
+    }
+
+    #[test]
+    fn replaced_tombstone() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            set_archive_retention(Some(10));
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 5, 1));
+            System::set_block_number(15);
+            Mod::expire_names(15);
+
+            /* The name is registered again and expires a second time before
+               the first tombstone is pruned.  */
+            assert_ok!(Mod::update(Origin::signed(20), 5, 2));
+            System::set_block_number(20);
+            Mod::expire_names(20);
+            assert_eq!(Mod::archived(5).unwrap().owner, 20);
+
+            Mod::prune_archive(25);
+            assert_eq!(Mod::archived(5).unwrap().expired_at, 20);
+            Mod::prune_archive(30);
+            assert_eq!(Mod::archived(5), None);
+        });
+    }
+
+    #[test]
+    fn disabled() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::lookup(20), None);
+            assert_eq!(Mod::archived(20), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for owner notes on names.
+mod notes {
+    use super::*;
+
+    #[test]
+    fn set_and_clear() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::set_note(Origin::signed(10), 200, Some(b"ref".to_vec())),
+                         "name does not exist");
+            assert_noop!(Mod::set_note(Origin::signed(20), 100, Some(b"ref".to_vec())),
+                         "non-owner note update");
+            assert_noop!(Mod::set_note(Origin::signed(10), 100, Some(b"too long!".to_vec())),
+                         "note is too large");
+            assert_noop!(Mod::set_note(Origin::signed(10), 100, None), "name has no note");
+
+            assert_ok!(Mod::set_note(Origin::signed(10), 100, Some(b"ref".to_vec())));
+            assert_eq!(Mod::note(100), Some(Note {
+                data: b"ref".to_vec(),
+                depositor: 10,
+                deposit: 20,
+            }));
+            assert_eq!(Balances::reserved_balance(&10), 20);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::NoteUpdated(100, true)));
+
+            /* Replacing the note keeps the existing deposit.  */
+            assert_ok!(Mod::set_note(Origin::signed(10), 100, Some(b"acct-42".to_vec())));
+            assert_eq!(Mod::note(100).unwrap().data, b"acct-42".to_vec());
+            assert_eq!(Balances::reserved_balance(&10), 20);
+
+            assert_ok!(Mod::set_note(Origin::signed(10), 100, None));
+            assert_eq!(Mod::note(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_note(Origin::signed(10), 100, Some(b"ref".to_vec())));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::note(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Mod::lookup(100).unwrap().value, 1);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for locks placed on names by other pallets.
+mod external_locks {
+    use super::*;
+    use crate::lock::NameLocks;
+
+    const COURT: LockIdentifier = *b"court   ";
+    const ESCROW: LockIdentifier = *b"escrow  ";
+
+    #[test]
+    fn blocks_transfers() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+
+            assert_noop!(Mod::set_external_lock(&200, COURT), "name does not exist");
+            assert_ok!(Mod::set_external_lock(&100, COURT));
+            assert_ok!(Mod::set_external_lock(&100, ESCROW));
+            assert_ok!(Mod::set_external_lock(&100, COURT));
+            assert_eq!(Mod::external_locks(100), vec![COURT, ESCROW]);
+            assert!(Mod::is_externally_locked(&100));
+
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "name is locked by another pallet");
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 2));
+
+            Mod::remove_external_lock(&100, COURT);
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "name is locked by another pallet");
+            Mod::remove_external_lock(&100, ESCROW);
+            assert!(!Mod::is_externally_locked(&100));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ExternalLockRemoved(100, ESCROW)));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+        });
+    }
+
+    #[test]
+    fn no_new_agreements() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::set_external_lock(&100, COURT));
+
+            let err = "name is locked by another pallet";
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 20), err);
+            assert_noop!(Mod::offer_rent_to_own(Origin::signed(10), 100, 20, 500, 1, 10,
+                                                HandoverTerms::default()),
+                         err);
+            assert_noop!(Mod::propose_swap(Origin::signed(10), 20, vec![100], vec![], 0), err);
+            assert_noop!(Mod::split_into_shares(Origin::signed(10), 100, 10,
+                                                Perbill::from_percent(50)),
+                         err);
+            assert_noop!(Mod::donate_name(Origin::signed(10), 100), err);
+        });
+    }
+
+    #[test]
+    fn existing_agreements_wait() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 101, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 102, 1));
+            assert_ok!(Mod::lock_as_collateral(Origin::signed(10), 100, 30));
+            assert_ok!(Mod::offer_rent_to_own(Origin::signed(10), 101, 20, 500, 1, 10,
+                                              HandoverTerms::default()));
+            assert_ok!(Mod::propose_swap(Origin::signed(10), 20, vec![102], vec![], 0));
+            for name in &[100, 101, 102] {
+                assert_ok!(Mod::set_external_lock(name, COURT));
+            }
+
+            let err = "name is locked by another pallet";
+            assert_noop!(Mod::claim_collateral(Origin::signed(30), 100), err);
+            assert_noop!(Mod::pay_installment(Origin::signed(20), 101), err);
+            assert_noop!(Mod::accept_swap(Origin::signed(20), 10), err);
+            for name in &[100, 101, 102] {
+                assert_eq!(Mod::lookup(name).unwrap().owner, 10);
+                Mod::remove_external_lock(name, COURT);
+            }
+
+            assert_ok!(Mod::claim_collateral(Origin::signed(30), 100));
+            assert_ok!(Mod::pay_installment(Origin::signed(20), 101));
+            assert_ok!(Mod::accept_swap(Origin::signed(20), 10));
+            for name in &[101, 102] {
+                assert_eq!(Mod::lookup(name).unwrap().owner, 20);
+            }
+        });
+    }
+
+    #[test]
+    fn cleared_on_removal() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+            assert_ok!(Mod::set_external_lock(&20, COURT));
+
+            System::set_block_number(30);
+            Mod::expire_names(30);
+            assert_eq!(Mod::lookup(20), None);
+            assert!(!Mod::is_externally_locked(&20));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the hook that is notified of executed operations.
+mod operation_hook {
+    use super::*;
+
+    #[test]
+    fn called_for_operations() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 2));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_noop!(Mod::update(Origin::signed(10), 100, 3), "non-owner name update");
+
+            assert_eq!(executed_operations(), vec![
+                (OperationType::Registration, 100, 10),
+                (OperationType::Update, 100, 10),
+                (OperationType::Transfer, 100, 20),
+            ]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for redirects of names to other names.
+mod redirects {
+    use super::*;
+
+    /// Registers the given names for account 10.
+    fn setup(names: &[u64]) {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        for n in names {
+            assert_ok!(Mod::update(Origin::signed(10), *n, 1));
+        }
+    }
+
+    #[test]
+    fn set_and_clear() {
+        new_test_ext().execute_with(|| {
+            setup(&[100, 200]);
+
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 300, Some(100)),
+                         "name does not exist");
+            assert_noop!(Mod::set_redirect(Origin::signed(20), 100, Some(200)),
+                         "non-owner redirect update");
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 100, Some(300)),
+                         "name does not exist");
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 100, None),
+                         "name has no redirect");
+
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, Some(200)));
+            assert_eq!(Mod::redirect(100), Some(200));
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::RedirectUpdated(100, Some(200))));
+
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, None));
+            assert_eq!(Mod::redirect(100), None);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::RedirectUpdated(100, None)));
+        });
+    }
+
+    #[test]
+    fn resolution() {
+        new_test_ext().execute_with(|| {
+            setup(&[100, 200, 300]);
+            assert_ok!(Mod::update(Origin::signed(10), 300, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 300, 20));
+
+            assert_eq!(Mod::resolve(&100).unwrap().0, 100);
+            assert_eq!(Mod::resolve(&400), Err(ResolveError::NotFound));
+
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 200, Some(300)));
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, Some(200)));
+            let (name, data) = Mod::resolve(&100).unwrap();
+            assert_eq!(name, 300);
+            assert_eq!(data.value, 42);
+            assert_eq!(Mod::resolve_address(&100), Ok(20));
+            assert_eq!(Mod::resolve_address(&200), Ok(20));
+
+            /* The redirecting names themselves are unchanged.  */
+            assert_eq!(Mod::lookup(100).unwrap().value, 1);
+        });
+    }
+
+    #[test]
+    fn cycles() {
+        new_test_ext().execute_with(|| {
+            setup(&[100, 200, 300]);
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 100, Some(100)),
+                         "redirects form a cycle");
+
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, Some(200)));
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 200, Some(300)));
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 300, Some(100)),
+                         "redirects form a cycle");
+        });
+    }
+
+    #[test]
+    fn depth_limit() {
+        new_test_ext().execute_with(|| {
+            setup(&[100, 200, 300, 400, 500]);
+
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 200, Some(300)));
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 300, Some(400)));
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, Some(200)));
+            assert_noop!(Mod::set_redirect(Origin::signed(10), 500, Some(100)),
+                         "too many redirects");
+            assert_eq!(Mod::resolve(&100).unwrap().0, 400);
+
+            /* Extending the end of a chain is allowed, but then resolving
+               its start fails.  */
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 400, Some(500)));
+            assert_eq!(Mod::resolve(&100), Err(ResolveError::TooDeep));
+            assert_eq!(Mod::resolve(&200).unwrap().0, 500);
+        });
+    }
+
+    #[test]
+    fn cleared_on_transfer() {
+        new_test_ext().execute_with(|| {
+            setup(&[100, 200]);
+            assert_ok!(Mod::set_redirect(Origin::signed(10), 100, Some(200)));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::redirect(100), None);
+            assert_eq!(Mod::resolve(&100).unwrap().0, 100);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the default (currency-based) payment of name fees.
+mod payment {
+    use super::*;
+    use crate::payment::NamePayment;
+
+    type Payment = ();
+
+    #[test]
+    fn check() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            assert_eq!(<Payment as NamePayment<Test>>::check(&10, 5000), Ok(()));
+            assert_eq!(<Payment as NamePayment<Test>>::check(&10, 5001),
+                       Err(OperationError::RequiredFee(5001)));
+        });
+    }
+
+    #[test]
+    fn withdraw_with_commission() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(50, 1000);
+
+            assert_eq!(<Payment as NamePayment<Test>>::withdraw(&10, 100, None), Ok(0));
+            assert_eq!(Balances::free_balance(&10), 4900);
+            assert_eq!(Balances::free_balance(&FEE_RECEIVER), 1100);
+
+            assert_eq!(<Payment as NamePayment<Test>>::withdraw(&10, 100, Some(&50)), Ok(10));
+            assert_eq!(Balances::free_balance(&10), 4800);
+            assert_eq!(Balances::free_balance(&50), 1010);
+            assert_eq!(Balances::free_balance(&FEE_RECEIVER), 1190);
+
+            assert!(<Payment as NamePayment<Test>>::withdraw(&10, 10000, None).is_err());
+            assert_eq!(Balances::free_balance(&10), 4800);
+        });
+    }
+
+    #[test]
+    fn hold_settle_refund() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+
+            assert_ok!(<Payment as NamePayment<Test>>::hold(&10, 300));
+            assert_eq!(Balances::reserved_balance(&10), 300);
+
+            <Payment as NamePayment<Test>>::settle(&10, 100);
+            assert_eq!(Balances::reserved_balance(&10), 200);
+            assert_eq!(Balances::free_balance(&FEE_RECEIVER), 1100);
+
+            <Payment as NamePayment<Test>>::refund(&10, 200);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::free_balance(&10), 4900);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the lifecycle state of names.
+mod name_state {
+    use super::*;
+    use sp_runtime::traits::Hash;
+
+    #[test]
+    fn states() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(1);
+            assert_eq!(Mod::state_of(&100), NameState::Available);
+            assert_ok!(Mod::register_hidden(Origin::signed(10), BlakeTwo256::hash_of(&200u64)));
+            assert_eq!(Mod::state_of(&200), NameState::Reserved);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(Mod::state_of(&100), NameState::Registered(data.clone()));
+
+            assert_ok!(Mod::set_transfer_preference(Origin::signed(20), true));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(Mod::state_of(&100), NameState::PendingTransfer {
+                data: data.clone(),
+                recipient: 20,
+            });
+
+            /* Freezing takes precedence over everything else.  */
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 100, true));
+            assert_eq!(Mod::state_of(&100), NameState::Frozen(data));
+            assert_eq!(Mod::availability(&100), Availability::Frozen);
+
+            assert_ok!(Mod::update(Origin::signed(10), 700, 1));
+            System::set_block_number(701);
+            Mod::expire_names(701);
+            let data = Mod::lookup(700).unwrap();
+            assert_eq!(Mod::state_of(&700), NameState::GracePeriod(data));
+        });
+    }
+
+    #[test]
+    fn frozen_names_cannot_be_encumbered() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::freeze_name(Origin::ROOT, 100, true));
+
+            assert_noop!(Mod::lock_as_collateral(Origin::signed(10), 100, 30),
+                         "name is frozen");
+            assert_noop!(Mod::split_into_shares(Origin::signed(10), 100, 10,
+                                                Perbill::from_percent(50)),
+                         "name is frozen");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for limited automatic expiration and sweeping.
+mod sweeping {
+    use super::*;
+
+    /// Registers three names that all expire at block 101.
+    fn setup() {
+        add_balance(FEE_RECEIVER, 1000);
+        add_balance(10, 5000);
+        add_balance(30, 1000);
+        for (h, name) in [(1, 100), (2, 99), (3, 98)].iter() {
+            System::set_block_number(*h);
+            assert_ok!(Mod::update(Origin::signed(10), *name, 1));
+        }
+        assert_eq!(Mod::expiring_at(101), vec![100, 99, 98]);
+    }
+
+    #[test]
+    fn limited_auto_expiration() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_auto_expirations(Some(1));
+
+            System::set_block_number(101);
+            Mod::expire_names(101);
+            assert_eq!(Mod::lookup(100), None);
+            assert!(Mod::lookup(99).is_some());
+            assert_eq!(Mod::expiring_at(101), vec![99, 98]);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ExpirationsProcessed(1, 0, 2)));
+        });
+    }
+
+    #[test]
+    fn sweep() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_auto_expirations(Some(0));
+            System::set_block_number(101);
+            Mod::expire_names(101);
+            assert!(Mod::lookup(100).is_some());
+
+            assert_noop!(Mod::sweep_expirations(Origin::signed(30), 101, 1),
+                         "can only sweep past blocks");
+            System::set_block_number(102);
+            assert_noop!(Mod::sweep_expirations(Origin::signed(30), 101, 0),
+                         "nothing to sweep");
+            assert_noop!(Mod::sweep_expirations(Origin::signed(30), 101, 4),
+                         "too many expirations to sweep");
+            assert_noop!(Mod::sweep_expirations(Origin::signed(30), 50, 1),
+                         "no expirations to sweep");
+
+            /* Without funds in the pot, no bounty is paid.  */
+            assert_ok!(Mod::sweep_expirations(Origin::signed(30), 101, 1));
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Balances::free_balance(&30), 1000);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ExpirationsSwept(30, 101, 1, 2, 0)));
+
+            add_balance(Mod::sweep_pot(), 2000);
+            assert_ok!(Mod::sweep_expirations(Origin::signed(30), 101, 3));
+            assert_eq!(Mod::lookup(99), None);
+            assert_eq!(Mod::lookup(98), None);
+            assert_eq!(Mod::expiring_at(101), Vec::<u64>::new());
+            assert_eq!(Balances::free_balance(&30), 1010);
+            assert_eq!(Balances::free_balance(&Mod::sweep_pot()), 1990);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ExpirationsSwept(30, 101, 2, 0, 10)));
+
+            assert_noop!(Mod::sweep_expirations(Origin::signed(30), 101, 1),
+                         "no expirations to sweep");
+        });
+    }
+
+    #[test]
+    fn late_sweep_schedules_ahead() {
+        new_test_ext().execute_with(|| {
+            setup();
+            set_quarantine(5);
+            set_archive_retention(Some(5));
+            set_auto_expirations(Some(0));
+            System::set_block_number(101);
+            Mod::expire_names(101);
+
+            System::set_block_number(200);
+            assert_ok!(Mod::sweep_expirations(Origin::signed(30), 101, 1));
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::archived(100).unwrap().expired_at, 101);
+            assert_eq!(Mod::quarantined_until(100), Some(201));
+
+            System::set_block_number(201);
+            Mod::end_quarantines(201);
+            Mod::prune_archive(201);
+            assert_eq!(Mod::quarantined_until(100), None);
+            assert_eq!(Mod::archived(100), None);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+        });
+    }
+
+    #[test]
+    fn late_sweep_with_grace_period() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(30, 1000);
+            set_auto_expirations(Some(0));
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 700, 1));
+            System::set_block_number(701);
+            Mod::expire_names(701);
+
+            System::set_block_number(800);
+            assert_ok!(Mod::sweep_expirations(Origin::signed(30), 701, 1));
+            assert_eq!(Mod::lookup(700).unwrap().expiration, Some(801));
+            assert_eq!(Mod::expiring_at(801), vec![700]);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameInGracePeriod(700, 801))
+            }));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the content rules on values after the compliance window.
+mod compliance {
+    use super::*;
+
+    #[test]
+    fn window_and_warning() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_compliance(Some(10), ComplianceAction::Warn);
+
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_eq!(Mod::compliance_deadline(100), Some(11));
+
+            /* During the window, any value is fine.  */
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 3));
+
+            System::set_block_number(11);
+            Mod::check_compliance(11);
+            assert_eq!(System::events().last().unwrap().event,
+                       TestEvent::names(RawEvent::ValueNotCompliant(100, false)));
+            assert_eq!(Mod::lookup(100).unwrap().value, 3);
+
+            assert_noop!(Mod::update(Origin::signed(10), 100, 5),
+                         "value does not satisfy the content rules");
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "value does not satisfy the content rules");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 4));
+        });
+    }
+
+    #[test]
+    fn expiring_non_compliant_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_compliance(Some(10), ComplianceAction::Expire);
+
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 2));
+
+            System::set_block_number(11);
+            Mod::check_compliance(11);
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::compliance_deadline(100), None);
+            assert!(Mod::lookup(200).is_some());
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ValueNotCompliant(100, true))
+            }));
+            assert!(!System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ValueNotCompliant(200, true))
+            }));
+        });
+    }
+
+    #[test]
+    fn no_insurance_or_grace_period() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_compliance(Some(10), ComplianceAction::Expire);
+
+            /* Name 100 is insured, and 700 would get a grace period on
+               normal expiration.  Neither applies to non-compliance.  */
+            System::set_block_number(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::insure_name(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 700, 1));
+
+            System::set_block_number(11);
+            Mod::check_compliance(11);
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::lookup(700), None);
+            assert_eq!(Mod::insured_renewals(100), 0);
+            assert!(!Mod::in_grace_period(&700));
+            for name in &[100, 700] {
+                assert!(System::events().iter().any(|r| {
+                    r.event == TestEvent::names(RawEvent::NameExpired(*name))
+                }));
+            }
+        });
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 1));
+            assert_eq!(Mod::compliance_deadline(100), None);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the receipts of name operations.
+mod receipts {
+    use super::*;
+
+    #[test]
+    fn receipts_are_stored() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            let hash = BlakeTwo256::hash_of(&100u64);
+            assert_eq!(Mod::receipts(5, None::<u32>), vec![
+                Receipt::<Test> {
+                    name_hash: hash,
+                    operation: OperationType::Registration,
+                    fee: 100,
+                    payer: 10,
+                    expiration: Some(105),
+                    previous_owner: None,
+                },
+                Receipt::<Test> {
+                    name_hash: hash,
+                    operation: OperationType::Transfer,
+                    fee: 0,
+                    payer: 10,
+                    expiration: Some(105),
+                    previous_owner: Some(10),
+                },
+            ]);
+            assert_eq!(Mod::receipts(4, None::<u32>), vec![]);
+        });
+    }
+
+    #[test]
+    fn receipts_are_pruned() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(5);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            Mod::prune_receipts(14);
+            assert_eq!(Mod::receipts(5, None::<u32>).len(), 1);
+            Mod::prune_receipts(15);
+            assert_eq!(Mod::receipts(5, None::<u32>), vec![]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for repairing the derived index entries of names.
+mod index_repair {
+    use super::*;
+
+    #[test]
+    fn repair_existing_name() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            let h = Mod::lookup(100).unwrap().expiration.unwrap();
+
+            <OwnerIndex<Test>>::remove(&10, &100);
+            <OwnerIndex<Test>>::insert(&20, &100, &100);
+            <NamehashIndex<Test>>::remove(H256::from_low_u64_be(100));
+            <ExpirationQueue<Test>>::remove(h);
+
+            assert_ok!(Mod::repair_index(Origin::signed(30), 100));
+            assert_eq!(Mod::names_of(&10), vec![100]);
+            assert_eq!(Mod::names_of(&20), vec![100]);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(100)), Some(100));
+            assert_eq!(Mod::expiring_at(h), vec![100]);
+
+            assert_ok!(Mod::repair_index(Origin::signed(20), 100));
+            assert_eq!(Mod::names_of(&20), vec![]);
+            assert_ok!(Mod::repair_index(Origin::signed(10), 100));
+            assert_eq!(Mod::names_of(&10), vec![100]);
+            assert_eq!(Mod::expiring_at(h), vec![100]);
+        });
+    }
+
+    #[test]
+    fn repair_removed_name() {
+        new_test_ext().execute_with(|| {
+            <OwnerIndex<Test>>::insert(&20, &100, &100);
+            <NamehashIndex<Test>>::insert(H256::from_low_u64_be(100), 100);
+
+            <migration::RepairIndex as migration::NameMigration<Test>>::migrate_name(&100);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(100)), None);
+            assert_eq!(Mod::names_of(&20), vec![100]);
+
+            assert_ok!(Mod::repair_index(Origin::signed(20), 100));
+            assert_eq!(Mod::names_of(&20), vec![]);
+        });
+    }
+
+    #[test]
+    fn force_total_names() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::force_total_names(Origin::signed(10), 5),
+                         DispatchError::BadOrigin);
+            assert_ok!(Mod::force_total_names(Origin::ROOT, 5));
+            assert_eq!(Mod::total_names(), 5);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for stepped migrations.
+mod migrations {
+    use super::*;
+
+    #[test]
+    fn queue_migration() {
+        new_test_ext().execute_with(|| {
+            assert_noop!(Mod::queue_migration(Origin::signed(10), vec![1]),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::queue_migration(Origin::ROOT, vec![1, 2, 3]),
+                         "too many names to queue");
+            assert_ok!(Mod::queue_migration(Origin::ROOT, vec![1, 2]));
+            assert_ok!(Mod::queue_migration(Origin::ROOT, vec![3, 4]));
+            assert_eq!(Mod::migration_queue_end(), 4);
+            assert_eq!(Mod::migration_cursor(), 0);
+        });
+    }
+
+    #[test]
+    fn weight_limit() {
+        new_test_ext().execute_with(|| {
+            assert_ok!(Mod::queue_migration(Origin::ROOT, vec![1, 2]));
+            assert_ok!(Mod::queue_migration(Origin::ROOT, vec![3, 4]));
+
+            Mod::step_migration();
+            assert_eq!(take_migrated(), vec![1, 2, 3]);
+            assert_eq!(Mod::migration_cursor(), 3);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NamesMigrated(3, 1))
+            }));
+
+            Mod::step_migration();
+            assert_eq!(take_migrated(), vec![4]);
+            assert_eq!(Mod::migration_cursor(), 0);
+            assert_eq!(Mod::migration_queue_end(), 0);
+
+            /* Nothing happens once the queue is empty.  */
+            let events = System::events().len();
+            Mod::step_migration();
+            assert_eq!(take_migrated(), vec![]);
+            assert_eq!(System::events().len(), events);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the call classification helpers.
+mod call_filters {
+    use super::*;
+
+    #[test]
+    fn name_management() {
+        assert!(Call::<Test>::update(100, 42).is_name_management_call());
+        assert!(Call::<Test>::register(100, 42, None).is_name_management_call());
+        assert!(Call::<Test>::set_value(100, 42).is_name_management_call());
+        assert!(Call::<Test>::transfer(100, 20).is_name_management_call());
+        assert!(Call::<Test>::transfer_existing(100, 20).is_name_management_call());
+        assert!(Call::<Test>::transfer_all_names(20, 0).is_name_management_call());
+        assert!(Call::<Test>::donate_name(100).is_name_management_call());
+        assert!(Call::<Test>::lock_as_collateral(100, 20).is_name_management_call());
+        assert!(Call::<Test>::register_hidden(H256::zero()).is_name_management_call());
+        assert!(Call::<Test>::reveal_name(100, None).is_name_management_call());
+        assert!(Call::<Test>::set_signing_key(100, None).is_name_management_call());
+        assert!(Call::<Test>::link_identity(100).is_name_management_call());
+        assert!(Call::<Test>::unlink_identity(100).is_name_management_call());
+        assert!(!Call::<Test>::force_transfer(100, 20).is_name_management_call());
+    }
+
+    #[test]
+    fn value_update() {
+        assert!(Call::<Test>::update(100, 42).is_value_update_call());
+        assert!(Call::<Test>::register(100, 42, None).is_value_update_call());
+        assert!(Call::<Test>::set_value(100, 42).is_value_update_call());
+        assert!(Call::<Test>::update_patch(100, vec![1]).is_value_update_call());
+        assert!(Call::<Test>::set_value_at_nonce(100, 42, 0).is_value_update_call());
+        assert!(!Call::<Test>::transfer(100, 20).is_value_update_call());
+        assert!(!Call::<Test>::transfer_existing(100, 20).is_value_update_call());
+        assert!(!Call::<Test>::transfer_all_names(20, 0).is_value_update_call());
+        assert!(!Call::<Test>::register_hidden(H256::zero()).is_value_update_call());
+        assert!(!Call::<Test>::reveal_name(100, None).is_value_update_call());
+        assert!(!Call::<Test>::set_signing_key(100, None).is_value_update_call());
+    }
+
+    #[test]
+    fn cheap_currency_admin() {
+        assert!(Call::<Test>::block_sender(20).is_read_cheap());
+        assert!(!Call::<Test>::block_sender(20).touches_currency());
+        assert!(!Call::<Test>::update(100, 42).is_read_cheap());
+        assert!(Call::<Test>::update(100, 42).touches_currency());
+        assert!(Call::<Test>::watch_name(100).touches_currency());
+        assert!(Call::<Test>::pay_name(100, 10).touches_currency());
+        assert!(!Call::<Test>::transfer_all_names(20, 0).is_read_cheap());
+
+        assert!(Call::<Test>::force_transfer(100, 20).is_admin());
+        assert!(Call::<Test>::force_remove(100).is_admin());
+        assert!(Call::<Test>::queue_migration(vec![]).is_admin());
+        assert!(Call::<Test>::force_total_names(0).is_admin());
+        assert!(!Call::<Test>::update(100, 42).is_admin());
+        assert!(!Call::<Test>::donate_name(100).is_admin());
+    }
+
+    /// The classifications must be consistent with each other for all
+    /// calls.  New calls have to be added to this list.
+    #[test]
+    fn consistent_classification() {
+        let calls = vec![
+            Call::<Test>::update(100, 42),
+            Call::<Test>::register(100, 42, None),
+            Call::<Test>::set_value(100, 42),
+            Call::<Test>::set_value_at_nonce(100, 42, 0),
+            Call::<Test>::update_patch(100, vec![1]),
+            Call::<Test>::schedule_value(100, 42, 10),
+            Call::<Test>::cancel_scheduled_value(100),
+            Call::<Test>::transfer(100, 20),
+            Call::<Test>::transfer_existing(100, 20),
+            Call::<Test>::transfer_all_names(20, 0),
+            Call::<Test>::donate_name(100),
+            Call::<Test>::lock_as_collateral(100, 20),
+            Call::<Test>::set_payout(100, vec![]),
+            Call::<Test>::pay_name(100, 10),
+            Call::<Test>::register_hidden(H256::zero()),
+            Call::<Test>::reveal_name(100, None),
+            Call::<Test>::set_signing_key(100, None),
+            Call::<Test>::repair_index(100),
+            Call::<Test>::dispatch_as_name(100, Box::new(Call::update(200, 1))),
+            Call::<Test>::force_transfer(100, 20),
+            Call::<Test>::force_remove(100),
+            Call::<Test>::force_register(100, 42, 10),
+            Call::<Test>::force_total_names(0),
+            Call::<Test>::queue_migration(vec![]),
+        ];
+        for c in calls {
+            assert!(!(c.is_admin() && c.is_name_management_call()), "{:?}", c);
+            assert!(!c.is_value_update_call() || c.is_name_management_call(), "{:?}", c);
+            assert!(!c.is_value_update_call() || c.touches_currency(), "{:?}", c);
+        }
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the provided name policy building blocks.
+mod policies {
+    use super::*;
+    use policy::{
+        Composed, ExpirationPolicy, FeePolicy, FixedPeriod, FlatFee, NeverExpire,
+    };
+
+    parameter_types! {
+        pub const RegistrationFee: u128 = 100;
+        pub const UpdateFee: u128 = 10;
+        pub const Period: u64 = 42;
+        pub const LoyaltyPeriod: u64 = 10;
+        pub const LoyaltySteps: &'static [u32] = &[100, 80, 50];
+        pub const MaxJitter: u64 = 5;
+        pub const NoJitter: u64 = 0;
+    }
+
+    type Policy = Composed<FlatFee<RegistrationFee, UpdateFee>, FixedPeriod<Period>, ()>;
+
+    #[test]
+    fn flat_fee() {
+        new_test_ext().execute_with(|| {
+            let op = Mod::unchecked_operation(100, 5, 10);
+            assert_eq!(<FlatFee<RegistrationFee, UpdateFee> as FeePolicy<Test>>::get_name_fee(&op),
+                       Some(100));
+
+            assert_ok!(Mod::execute(op));
+            let op = Mod::unchecked_operation(100, 6, 10);
+            assert_eq!(<FlatFee<RegistrationFee, UpdateFee> as FeePolicy<Test>>::get_name_fee(&op),
+                       Some(10));
+        });
+    }
+
+    #[test]
+    fn expiration() {
+        new_test_ext().execute_with(|| {
+            let op = Mod::unchecked_operation(100, 5, 10);
+            assert_eq!(<NeverExpire as ExpirationPolicy<Test>>::get_expiration(&op), None);
+            assert_eq!(<FixedPeriod<Period> as ExpirationPolicy<Test>>::get_expiration(&op),
+                       Some(42));
+        });
+    }
+
+    #[test]
+    fn jittered_expiration() {
+        use policy::Jittered;
+        new_test_ext().execute_with(|| {
+            let expiration = |name| {
+                let op = Mod::unchecked_operation(name, 5, 10);
+                <Jittered<FixedPeriod<Period>, MaxJitter> as ExpirationPolicy<Test>>
+                    ::get_expiration(&op).unwrap()
+            };
+
+            let heights: Vec<u64> = (1..=20).map(expiration).collect();
+            assert!(heights.iter().all(|h| *h >= 42 && *h <= 47));
+            assert!(heights.iter().any(|h| *h != heights[0]));
+            assert_eq!((1..=20).map(expiration).collect::<Vec<_>>(), heights);
+
+            let op = Mod::unchecked_operation(100, 5, 10);
+            assert_eq!(<Jittered<FixedPeriod<Period>, NoJitter> as ExpirationPolicy<Test>>
+                           ::get_expiration(&op), Some(42));
+            assert_eq!(<Jittered<NeverExpire, MaxJitter> as ExpirationPolicy<Test>>
+                           ::get_expiration(&op), None);
+        });
+    }
+
+    #[test]
+    fn utf8_classifier() {
+        use policy::{NameClass, NameClassifier, Utf8Classifier};
+        let classify = |name: &str| {
+            <Utf8Classifier as NameClassifier<Vec<u8>>>::classify(&name.as_bytes().to_vec())
+        };
+
+        assert_eq!(classify(""), NameClass::Ascii);
+        assert_eq!(classify("domob"), NameClass::Ascii);
+        assert_eq!(classify("d/xn--caf-dma"), NameClass::Ascii);
+        assert_eq!(classify("café"), NameClass::Unicode);
+        assert_eq!(classify("名前"), NameClass::Unicode);
+        assert_eq!(classify("🚀"), NameClass::Emoji);
+        assert_eq!(classify("❤️🔥"), NameClass::Emoji);
+        assert_eq!(classify("👨‍👩‍👧"), NameClass::Emoji);
+        assert_eq!(classify("rocket🚀"), NameClass::Unicode);
+        assert_eq!(classify("\u{200D}"), NameClass::Unicode);
+        assert_eq!(<Utf8Classifier as NameClassifier<Vec<u8>>>::classify(&vec![0xff, 0x00]),
+                   NameClass::Binary);
+    }
+
+    #[test]
+    fn loyalty_pricing() {
+        use policy::LoyaltyPricing;
+        type Loyalty = LoyaltyPricing<FlatFee<RegistrationFee, UpdateFee>, LoyaltyPeriod, LoyaltySteps>;
+        let fee = |name| {
+            <Loyalty as FeePolicy<Test>>::get_name_fee(&Mod::unchecked_operation(name, 1, 10))
+        };
+
+        new_test_ext().execute_with(|| {
+            System::set_block_number(5);
+            assert_eq!(fee(100), Some(100));
+            assert_ok!(Mod::execute(Mod::unchecked_operation(100, 5, 10)));
+            assert_eq!(Mod::lookup(100).unwrap().held_since, 5);
+            assert_eq!(fee(100), Some(10));
+
+            System::set_block_number(14);
+            assert_eq!(fee(100), Some(10));
+            System::set_block_number(15);
+            assert_eq!(fee(100), Some(8));
+
+            /* Updates by the same owner keep the holding period.  */
+            assert_ok!(Mod::execute(Mod::unchecked_operation(100, 6, 10)));
+            assert_eq!(Mod::lookup(100).unwrap().held_since, 5);
+            System::set_block_number(25);
+            assert_eq!(fee(100), Some(5));
+            System::set_block_number(1000);
+            assert_eq!(fee(100), Some(5));
+
+            let mut op = Mod::unchecked_operation(100, 6, 10);
+            op.operation = OperationType::Transfer;
+            assert_eq!(<Loyalty as FeePolicy<Test>>::get_name_fee(&op), Some(10));
+
+            /* A new owner starts from scratch.  */
+            op.recipient = 20;
+            assert_ok!(Mod::execute(op));
+            assert_eq!(Mod::lookup(100).unwrap().held_since, 1000);
+            assert_eq!(fee(100), Some(10));
+        });
+    }
+
+    #[test]
+    fn composed() {
+        new_test_ext().execute_with(|| {
+            let op = Mod::unchecked_operation(100, 5, 10);
+            assert_eq!(<Policy as NamePolicy<Test>>::get_name_fee(&op), Some(100));
+            assert_eq!(<Policy as NamePolicy<Test>>::get_expiration(&op), Some(42));
+
+            /* The deposit handler () burns the fee.  */
+            add_balance(10, 5000);
+            let issuance = Balances::total_issuance();
+            let imbalance = Balances::withdraw(&10, 100, WithdrawReasons::all(),
+                                               ExistenceRequirement::AllowDeath).unwrap();
+            <Policy as NamePolicy<Test>>::deposit_fee(imbalance);
+            assert_eq!(Balances::total_issuance(), issuance - 100);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the owner index and handling of reaped accounts.
+mod owner_index {
+    use super::*;
+
+    /// Returns the sorted list of names owned by an account.
+    fn names_of(owner: u64) -> Vec<u64> {
+        let mut names = Mod::names_of(&owner);
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn index_updates() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_ok!(Mod::update(Origin::signed(20), 300, 0));
+            assert_eq!(names_of(10), vec![100, 200]);
+            assert_eq!(names_of(20), vec![300]);
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 200, 20));
+            assert_eq!(names_of(10), vec![100]);
+            assert_eq!(names_of(20), vec![200, 300]);
+
+            Mod::expire_names(100);
+            assert_eq!(names_of(10), Vec::<u64>::new());
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 300));
+            assert_eq!(names_of(20), vec![200]);
+        });
+    }
+
+    #[test]
+    fn transfer_all() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 300, 42));
+            assert_ok!(Mod::set_signing_key(Origin::signed(10), 100, Some(30)));
+
+            assert_noop!(Mod::transfer_all_names(Origin::signed(10), 10, 0),
+                         "cannot transfer names to the sender");
+
+            /* At most two names are moved per call.  */
+            assert_ok!(Mod::transfer_all_names(Origin::signed(10), 20, 0));
+            assert_eq!(names_of(10).len(), 1);
+            assert_eq!(names_of(20).len(), 2);
+            assert_ok!(Mod::transfer_all_names(Origin::signed(10), 20, 0));
+            assert_eq!(names_of(10), Vec::<u64>::new());
+            assert_eq!(names_of(20), vec![100, 200, 300]);
+            assert_eq!(Mod::lookup(300).unwrap().owner, 20);
+            assert_eq!(Mod::signing_key(100), None);
+
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NamesTransferred(10, 20, 1))
+            }));
+        });
+    }
+
+    #[test]
+    fn transfer_all_skips_failing_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            /* These names' values violate the name policy, so they cannot
+               be transferred normally.  */
+            assert_ok!(Mod::execute(Mod::unchecked_operation(200, 2_000, 10)));
+            assert_ok!(Mod::execute(Mod::unchecked_operation(300, 2_000, 10)));
+
+            let skipped = || System::events().iter().filter(|r| {
+                match r.event {
+                    TestEvent::names(RawEvent::BulkTransferSkipped(_)) => true,
+                    _ => false,
+                }
+            }).count() as u32;
+
+            /* The failing names stay in the owner index, so the second
+               call passes over the ones skipped by the first.  */
+            assert_ok!(Mod::transfer_all_names(Origin::signed(10), 20, 0));
+            assert_ok!(Mod::transfer_all_names(Origin::signed(10), 20, skipped()));
+            assert_eq!(names_of(10), vec![200, 300]);
+            assert_eq!(names_of(20), vec![100]);
+            assert_eq!(Mod::lookup(200).unwrap().value, 2_000);
+
+            assert_eq!(skipped(), 2);
+            for name in &[200, 300] {
+                assert!(System::events().iter().any(|r| {
+                    r.event == TestEvent::names(RawEvent::BulkTransferSkipped(*name))
+                }));
+            }
+
+            assert_ok!(Mod::transfer_all_names(Origin::signed(10), 20, 2));
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NamesTransferred(10, 20, 0))
+            }));
+        });
+    }
+
+    #[test]
+    fn export_and_import() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(10), 200, 0));
+
+            let mut exported = Mod::export_names(&10);
+            exported.sort();
+            assert_eq!(exported, vec![(100, 42, Some(110)), (200, 0, None)]);
+
+            /* Import into a different account, with one name that will be
+               newly registered and expirations in the past.  */
+            let portfolio: Portfolio<Test> = vec![
+                (100, 43, Some(5)),
+                (300, 1, Some(50)),
+            ];
+            assert_noop!(Mod::import_names(Origin::signed(10), 20, portfolio.encode()),
+                         DispatchError::BadOrigin);
+            assert_noop!(Mod::import_names(Origin::ROOT, 20, vec![1, 2, 3]),
+                         "invalid portfolio data");
+            let too_many: Portfolio<Test> = vec![(1, 0, None), (2, 0, None), (3, 0, None)];
+            assert_noop!(Mod::import_names(Origin::ROOT, 20, too_many.encode()),
+                         "too many names to import");
+            assert_ok!(Mod::import_names(Origin::ROOT, 20, portfolio.encode()));
+
+            assert_eq!(Mod::lookup(100), Some(NameData::<Test> {
+                value: 43,
+                owner: 20,
+                expiration: Some(11),
+                nonce: 1,
+                held_since: 10,
+            }));
+            assert_eq!(Mod::lookup(300), Some(NameData::<Test> {
+                value: 1,
+                owner: 20,
+                expiration: Some(50),
+                nonce: 0,
+                held_since: 10,
+            }));
+            assert_eq!(names_of(10), vec![200]);
+            assert_eq!(names_of(20), vec![100, 300]);
+            assert_eq!(get_expiring_names(50), vec![300]);
+            assert_eq!(Mod::resolve_namehash(H256::from_low_u64_be(300)), Some(300));
+        });
+    }
+
+    #[test]
+    fn reaped_owner() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 0));
+            assert_ok!(Mod::update(Origin::signed(10), 500, 0));
+            assert_ok!(Mod::update(Origin::signed(10), 600, 0));
+            assert_ok!(Mod::update(Origin::signed(10), 601, 1));
+            expect_balance(10, 4600);
+
+            /* Reap the account by transferring its whole balance away.  */
+            assert_ok!(Balances::transfer(Origin::signed(10), 20, 4600));
+            expect_balance(10, 0);
+
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::lookup(500), Some(NameData::<Test> {
+                value: 0,
+                owner: REAPED_FALLBACK,
+                expiration: None,
+                nonce: 1,
+                held_since: 10,
+            }));
+            assert_eq!(Mod::lookup(600), Some(NameData::<Test> {
+                value: 0,
+                owner: 10,
+                expiration: Some(15),
+                nonce: 0,
+                held_since: 10,
+            }));
+            /* Name 601 would have expired in block 611 anyway, but the
+               accelerated expiration is earlier.  */
+            assert_eq!(Mod::lookup(601).unwrap().expiration, Some(15));
+            assert_eq!(names_of(REAPED_FALLBACK), vec![500]);
+
+            Mod::expire_names(15);
+            assert_eq!(Mod::lookup(600), None);
+            assert_eq!(Mod::lookup(601), None);
+            assert_eq!(names_of(10), vec![100]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the total name count, its limit and congestion pricing.
+mod name_limits {
+    use super::*;
+    use sp_runtime::traits::OnInitialize;
+
+    /// Registers the given names with value zero (so they never expire)
+    /// from account 10.
+    fn register(names: impl Iterator<Item = u64>) {
+        for name in names {
+            assert_ok!(Mod::update(Origin::signed(10), name, 0));
+        }
+    }
+
+    #[test]
+    fn total_names() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 100_000);
+            register(1..=3);
+            assert_ok!(Mod::update(Origin::signed(10), 1, 5));
+            assert_eq!(Mod::total_names(), 3);
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 2));
+            assert_eq!(Mod::total_names(), 2);
+        });
+    }
+
+    #[test]
+    fn maximum() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 100_000);
+            register(1..=20);
+            assert_noop!(Mod::update(Origin::signed(10), 21, 0),
+                         "maximum number of names reached");
+
+            /* Updates are still possible, and new names can be registered
+               once others are gone.  */
+            assert_ok!(Mod::update(Origin::signed(10), 20, 1));
+            assert_ok!(Mod::force_remove(Origin::ROOT, 1));
+            assert_ok!(Mod::update(Origin::signed(10), 21, 0));
+        });
+    }
+
+    #[test]
+    fn minimum_expiration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 100_000);
+
+            /* The test policy uses the name as expiration period (for
+               values that are not zero).  */
+            assert_noop!(Mod::update(Origin::signed(10), 0, 5),
+                         "name expiration period is too short");
+            assert_ok!(Mod::update(Origin::signed(10), 0, 0));
+            assert_ok!(Mod::update(Origin::signed(10), 1, 5));
+        });
+    }
+
+    #[test]
+    fn congestion_pricing() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 100_000);
+            register(1..=4);
+            Mod::on_initialize(1);
+            assert_eq!(Mod::registration_fee_multiplier(), 100);
+
+            register(5..=5);
+            /* The multiplier is only updated with the next block.  */
+            assert_eq!(Mod::registration_fee_multiplier(), 100);
+            Mod::on_initialize(2);
+            assert_eq!(Mod::registration_fee_multiplier(), 200);
+            assert_eq!(Mod::check_assuming_signed(10, 100, None, None).unwrap().fee, 200);
+            assert_eq!(Mod::check_assuming_signed(10, 1, None, None).unwrap().fee, 0);
+
+            register(6..=10);
+            Mod::on_initialize(3);
+            assert_eq!(Mod::registration_fee_multiplier(), 1000);
+            assert_eq!(Mod::check_assuming_signed(10, 100, None, None).unwrap().fee, 1000);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for registration intents.
+mod registration_intents {
+    use super::*;
+    use sp_runtime::traits::OnFinalize;
+
+    #[test]
+    fn submission() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_noop!(Mod::register_intent(Origin::signed(20), 100, 1),
+                         "name exists already");
+            assert_noop!(Mod::register_intent(Origin::signed(30), 200, 1),
+                         "insufficient balance for name fee");
+            assert_noop!(Mod::register_intent(Origin::signed(20), 200, 2_000),
+                         "operation violates name policy");
+
+            assert_ok!(Mod::register_intent(Origin::signed(20), 200, 1));
+            assert_noop!(Mod::register_intent(Origin::signed(20), 200, 2),
+                         "intent submitted already");
+            assert_ok!(Mod::register_intent(Origin::signed(10), 200, 3));
+            assert_eq!(Mod::registration_intents(200), vec![(20, 1), (10, 3)]);
+
+            /* Nothing is registered (or paid) before settlement, but the
+               bond is held.  */
+            assert_eq!(Mod::lookup(200), None);
+            expect_balance(20, 5000);
+            assert_eq!(Balances::reserved_balance(20), 10);
+        });
+    }
+
+    #[test]
+    fn bond_and_fee_required() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &10, 4895, 100, WithdrawReasons::all());
+
+            /* The fee alone could be paid, but not together with the bond.  */
+            assert_noop!(Mod::register_intent(Origin::signed(10), 100, 1),
+                         "cannot withdraw name fee from sender");
+        });
+    }
+
+    #[test]
+    fn intents_per_name_limited() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            for who in 10..16 {
+                add_balance(who, 5000);
+            }
+            for who in 10..15 {
+                assert_ok!(Mod::register_intent(Origin::signed(who), 100, 1));
+            }
+            assert_noop!(Mod::register_intent(Origin::signed(15), 100, 1),
+                         "too many intents for the name");
+            assert_ok!(Mod::register_intent(Origin::signed(15), 200, 1));
+        });
+    }
+
+    #[test]
+    fn settlement() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            for who in 10..15 {
+                add_balance(who, 5000);
+                assert_ok!(Mod::register_intent(Origin::signed(who), 100, who));
+            }
+            assert_ok!(Mod::register_intent(Origin::signed(10), 200, 1));
+            Mod::on_finalize(1);
+
+            /* Exactly one of the senders wins, and only they pay.  */
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.value, data.owner);
+            for who in 10..15 {
+                let mut expected = 5000;
+                if who == data.owner {
+                    expected -= 100;
+                }
+                /* Account 10 also registered name 200.  */
+                if who == 10 {
+                    expected -= 100;
+                }
+                expect_balance(who, expected);
+                assert_eq!(Balances::reserved_balance(who), 0);
+            }
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::RegistrationIntentSettled(100, data.owner))
+            }));
+            assert_eq!(Mod::lookup(200).unwrap().owner, 10);
+
+            assert!(Mod::registration_intents(100).is_empty());
+            assert!(<IntentNames<Test>>::get().is_empty());
+        });
+    }
+
+    #[test]
+    fn settlement_is_order_independent() {
+        let winner = |senders: &[u64]| {
+            new_test_ext().execute_with(|| {
+                add_balance(FEE_RECEIVER, 1000);
+                for who in senders {
+                    add_balance(*who, 5000);
+                    assert_ok!(Mod::register_intent(Origin::signed(*who), 100, 1));
+                }
+                Mod::on_finalize(1);
+                Mod::lookup(100).unwrap().owner
+            })
+        };
+        assert_eq!(winner(&[10, 20, 30, 40]), winner(&[40, 30, 20, 10]));
+    }
+
+    #[test]
+    fn invalid_winner_skipped() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::register_intent(Origin::signed(10), 100, 1));
+            assert_ok!(Mod::register_intent(Origin::signed(20), 100, 1));
+
+            /* Account 20 cannot pay the fee anymore, so 10 wins.  */
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &20, 4950, 100, WithdrawReasons::all());
+            Mod::on_finalize(1);
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            expect_balance(10, 4900);
+            assert_eq!(Balances::reserved_balance(10), 0);
+            assert_eq!(Balances::reserved_balance(20), 0);
+        });
+    }
+
+    #[test]
+    fn invalid_intent_forfeits_bond() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(20, 5000);
+            assert_ok!(Mod::register_intent(Origin::signed(20), 100, 1));
+
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &20, 4950, 100, WithdrawReasons::all());
+            Mod::on_finalize(1);
+            assert_eq!(Mod::lookup(100), None);
+            expect_balance(20, 4990);
+            assert_eq!(Balances::reserved_balance(20), 0);
+            expect_balance(FEE_RECEIVER, 1010);
+        });
+    }
+
+    #[test]
+    fn intents_only() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            set_intents_only(true);
+
+            assert_noop!(Mod::update(Origin::signed(10), 200, 42),
+                         "names must be registered through intents");
+            assert_noop!(Mod::transfer(Origin::signed(10), 200, 20),
+                         "names must be registered through intents");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_ok!(Mod::register_intent(Origin::signed(10), 200, 42));
+            Mod::on_finalize(1);
+            assert_eq!(Mod::lookup(200).unwrap().owner, 10);
+
+            set_intents_only(false);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for preorders.
+mod preorders {
+    use super::*;
+
+    fn hash(name: u64) -> H256 {
+        BlakeTwo256::hash_of(&name)
+    }
+
+    #[test]
+    fn preorder() {
+        new_test_ext().execute_with(|| {
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(10);
+
+            assert_noop!(Mod::preorder(Origin::signed(10), hash(100), 99),
+                         "preorder deposit is too low");
+            assert_ok!(Mod::preorder(Origin::signed(10), hash(100), 200));
+            assert_noop!(Mod::preorder(Origin::signed(20), hash(100), 200),
+                         "name hash is preordered already");
+
+            assert_eq!(Mod::lookup_preorder(hash(100)), Some(Preorder::<Test> {
+                owner: 10,
+                deposit: 200,
+                matures: 15,
+                expiration: 25,
+            }));
+            assert_eq!(Balances::reserved_balance(&10), 200);
+            assert_eq!(Balances::free_balance(&10), 4800);
+        });
+    }
+
+    #[test]
+    fn exclusive_window() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::preorder(Origin::signed(10), hash(100), 200));
+
+            /* Before maturity, the name is not reserved yet.  */
+            System::set_block_number(14);
+            assert_ok!(Mod::check_assuming_signed(20, 100, None, None));
+
+            System::set_block_number(15);
+            assert_noop!(Mod::update(Origin::signed(20), 100, 42),
+                         "name is reserved by a preorder");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::lookup_preorder(hash(100)), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 4900);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::PreorderExercised(hash(100)))
+            }));
+        });
+    }
+
+    #[test]
+    fn expiration() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            System::set_block_number(10);
+            assert_ok!(Mod::preorder(Origin::signed(10), hash(100), 200));
+
+            Mod::expire_preorders(24);
+            assert!(Mod::lookup_preorder(hash(100)).is_some());
+            Mod::expire_preorders(25);
+            assert_eq!(Mod::lookup_preorder(hash(100)), None);
+
+            /* Half of the deposit is forfeited to the fee receiver.  */
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 4900);
+            expect_balance(FEE_RECEIVER, 1100);
+
+            assert_ok!(Mod::update(Origin::signed(20), 100, 42));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for sponsored name updates.
+mod sponsored_updates {
+    use super::*;
+
+    fn payload(owner: u64, name: u64, value: u64, nonce: u64) -> SponsoredUpdate<Test> {
+        SponsoredUpdate::<Test> {
+            owner: owner,
+            name: name,
+            value: value,
+            nonce: nonce,
+        }
+    }
+
+    fn sign(payload: &SponsoredUpdate<Test>) -> TestSignature {
+        TestSignature(payload.owner, Mod::sponsored_update_message(payload))
+    }
+
+    #[test]
+    fn sponsor_pays_fee() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(20, 1000);
+
+            let p = payload(10, 100, 42, 0);
+            assert_ok!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&p)));
+
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 42);
+            expect_balance(10, 0);
+            expect_balance(20, 900);
+            expect_balance(FEE_RECEIVER, 1100);
+            assert_eq!(Mod::operation_nonce(10), 1);
+        });
+    }
+
+    #[test]
+    fn invalid_signature() {
+        new_test_ext().execute_with(|| {
+            add_balance(20, 1000);
+
+            let p = payload(10, 100, 42, 0);
+            let other = payload(10, 100, 43, 0);
+            assert_noop!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&other)),
+                         "invalid owner signature");
+            assert_noop!(Mod::sponsored_update(Origin::signed(20), p.clone(),
+                                               TestSignature(20, p.encode())),
+                         "invalid owner signature");
+            assert_noop!(Mod::sponsored_update(Origin::signed(20), p.clone(),
+                                               TestSignature(10, p.encode())),
+                         "invalid owner signature");
+        });
+    }
+
+    #[test]
+    fn replay_protection() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(20, 1000);
+
+            let p = payload(10, 100, 42, 0);
+            assert_ok!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&p)));
+            assert_noop!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&p)),
+                         "invalid operation nonce");
+
+            let p = payload(10, 100, 50, 1);
+            assert_ok!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&p)));
+            assert_eq!(Mod::lookup(100).unwrap().value, 50);
+        });
+    }
+
+    #[test]
+    fn owner_authority() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            /* The sponsor cannot pay for updates of a name the signer
+               does not own, and needs enough balance for the fee.  */
+            let p = payload(30, 100, 50, 0);
+            assert_noop!(Mod::sponsored_update(Origin::signed(20), p.clone(), sign(&p)),
+                         "non-owner name update");
+            let p = payload(10, 200, 50, 0);
+            assert_noop!(Mod::sponsored_update(Origin::signed(40), p.clone(), sign(&p)),
+                         "insufficient balance for name fee");
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for owner-signed operations submitted by relayers.
+mod signed_operations {
+    use super::*;
+
+    fn payload(owner: u64, name: u64, value: Option<u64>, recipient: Option<u64>,
+               nonce: u64) -> SignedOperation<Test> {
+        SignedOperation::<Test> {
+            owner: owner,
+            name: name,
+            value: value,
+            recipient: recipient,
+            nonce: nonce,
+            name_nonce: None,
+        }
+    }
+
+    fn sign(payload: &SignedOperation<Test>) -> TestSignature {
+        TestSignature(payload.owner, Mod::signed_operation_message(payload))
+    }
+
+    #[test]
+    fn relayed_operations() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            let p = payload(10, 100, Some(42), None, 0);
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 42);
+            expect_balance(10, 900);
+            expect_balance(50, 0);
+
+            let p = payload(10, 100, None, Some(20), 1);
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 20);
+            assert_eq!(data.value, 42);
+            assert_eq!(Mod::operation_nonce(10), 2);
+        });
+    }
+
+    #[test]
+    fn invalid_authorisation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            let p = payload(10, 100, Some(42), None, 0);
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(50), p.clone(),
+                                                       TestSignature(50, p.encode())),
+                         "invalid owner signature");
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(50), p.clone(),
+                                                       TestSignature(10, p.encode())),
+                         "invalid owner signature");
+            let wrong_nonce = payload(10, 100, Some(42), None, 1);
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(50), wrong_nonce.clone(),
+                                                       sign(&wrong_nonce)),
+                         "invalid operation nonce");
+
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)));
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)),
+                         "invalid operation nonce");
+        });
+    }
+
+    #[test]
+    fn bound_to_chain() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            /* A signature from a chain with another genesis block.  */
+            let p = payload(10, 100, Some(42), None, 0);
+            let signature = sign(&p);
+            <system::BlockHash<Test>>::insert(0, H256::repeat_byte(1));
+            assert_noop!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), signature),
+                         "invalid owner signature");
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)));
+        });
+    }
+
+    #[test]
+    fn shared_nonce_with_sponsored_updates() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            let p = payload(10, 100, Some(42), None, 0);
+            assert_ok!(Mod::execute_signed_operation(Origin::signed(50), p.clone(), sign(&p)));
+
+            let sponsored = SponsoredUpdate::<Test> {
+                owner: 10,
+                name: 100,
+                value: 50,
+                nonce: 1,
+            };
+            let signature = TestSignature(10, Mod::sponsored_update_message(&sponsored));
+            assert_ok!(Mod::sponsored_update(Origin::signed(10), sponsored, signature));
+            assert_eq!(Mod::operation_nonce(10), 2);
+        });
+    }
+
+    #[test]
+    fn batch() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 1000);
+
+            let a = payload(10, 100, Some(42), None, 0);
+            let b = payload(20, 100, Some(50), None, 0);
+            let c = payload(20, 200, Some(50), None, 0);
+            let items = vec![
+                (a.clone(), sign(&a)),
+                (b.clone(), sign(&b)),
+                (c.clone(), TestSignature(10, Mod::signed_operation_message(&c))),
+                (c.clone(), sign(&c)),
+            ];
+            assert_ok!(Mod::execute_signed_batch(Origin::signed(50), items));
+
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::lookup(200).unwrap().owner, 20);
+            assert_eq!(Mod::operation_nonce(20), 1);
+
+            let events: Vec<_> = System::events().into_iter().map(|r| r.event).collect();
+            assert!(events.contains(&TestEvent::names(RawEvent::SignedOperationFailed(1))));
+            assert!(events.contains(&TestEvent::names(RawEvent::SignedOperationFailed(2))));
+            assert!(!events.contains(&TestEvent::names(RawEvent::SignedOperationFailed(3))));
+            assert!(events.contains(&TestEvent::names(RawEvent::SignedBatchExecuted(2))));
+        });
+    }
+
+    #[test]
+    fn batch_weight() {
+        let items = vec![(payload(10, 100, None, None, 0), TestSignature(10, vec![])); 3];
+        assert_eq!(WeightPerItem(10_000).weigh_data((&items,)), 30_000);
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for partial value updates with patches.
+mod patch_updates {
+    use super::*;
+
+    #[test]
+    fn update_patch() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            assert_noop!(Mod::update_patch(Origin::signed(10), 100, vec![1]),
+                         "name does not exist");
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_noop!(Mod::update_patch(Origin::signed(10), 100, vec![]),
+                         "invalid value patch");
+            assert_noop!(Mod::update_patch(Origin::signed(20), 100, vec![1]),
+                         "non-owner name update");
+            assert_noop!(Mod::update_patch(Origin::signed(10), 100, vec![255; 4]),
+                         "operation violates name policy");
+
+            assert_ok!(Mod::update_patch(Origin::signed(10), 100, vec![1, 2]));
+            let data = Mod::lookup(100).unwrap();
+            assert_eq!(data.owner, 10);
+            assert_eq!(data.value, 45);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for subvalues of names.
+mod subvalues {
+    use super::*;
+
+    #[test]
+    fn set_and_clear() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            expect_balance(10, 900);
+
+            assert_noop!(Mod::set_subvalue(Origin::signed(10), 200, 1, 5),
+                         "name does not exist");
+            assert_noop!(Mod::set_subvalue(Origin::signed(20), 100, 1, 5),
+                         "non-owner subvalue update");
+
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 5));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 6));
+            assert_eq!(Mod::subvalue(100, 1), Some(Subvalue::<Test> {
+                value: 6,
+                depositor: 10,
+                deposit: 10,
+            }));
+            assert_eq!(Mod::subvalue_count(100), 1);
+            assert_eq!(Balances::reserved_balance(&10), 10);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::SubvalueUpdated(100, 1))
+            }));
+
+            assert_noop!(Mod::clear_subvalue(Origin::signed(10), 100, 2),
+                         "subvalue does not exist");
+            assert_ok!(Mod::clear_subvalue(Origin::signed(10), 100, 1));
+            assert_eq!(Mod::subvalue(100, 1), None);
+            assert_eq!(Mod::subvalue_count(100), 0);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 900);
+        });
+    }
+
+    #[test]
+    fn limit() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 5));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 2, 5));
+            assert_noop!(Mod::set_subvalue(Origin::signed(10), 100, 3, 5),
+                         "too many subvalues");
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 2, 6));
+        });
+    }
+
+    #[test]
+    fn transfer_and_removal() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 5));
+
+            /* After a transfer, the subvalues stay with the name (and the
+               deposit with the original depositor).  */
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_ok!(Mod::set_subvalue(Origin::signed(20), 100, 2, 5));
+            assert_eq!(Mod::subvalue(100, 1).unwrap().depositor, 10);
+            assert_eq!(Balances::reserved_balance(&10), 10);
+            assert_eq!(Balances::reserved_balance(&20), 10);
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert_eq!(Mod::subvalue(100, 1), None);
+            assert_eq!(Mod::subvalue(100, 2), None);
+            assert_eq!(Mod::subvalue_count(100), 0);
+            assert_eq!(Mod::subvalue_keys(100), Vec::<u64>::new());
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::reserved_balance(&20), 0);
+        });
+    }
+
+    #[test]
+    fn records() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::records_of(&100), vec![]);
+            assert_eq!(Mod::record_proof_keys(&100).len(), 2);
+
+            /* Records are ordered by the encoding of their subkeys, which
+               is little-endian for integers.  */
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 5));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 256, 7));
+            assert_ok!(Mod::set_subvalue(Origin::signed(10), 100, 1, 6));
+            assert_eq!(Mod::records_of(&100), vec![(256, 7), (1, 6)]);
+
+            let keys = Mod::record_proof_keys(&100);
+            assert_eq!(keys.len(), 4);
+            assert_eq!(sp_io::storage::get(&keys[2]),
+                       Mod::subvalue(100, 256).map(|e| e.encode()));
+
+            assert_ok!(Mod::clear_subvalue(Origin::signed(10), 100, 256));
+            assert_eq!(Mod::records_of(&100), vec![(1, 6)]);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for watchers of names.
+mod watchers {
+    use super::*;
+
+    fn triggered(who: u64, name: u64, change: NameChange) -> bool {
+        let expected = TestEvent::names(RawEvent::NameWatchTriggered(who, name, change));
+        System::events().iter().any(|r| r.event == expected)
+    }
+
+    #[test]
+    fn watch_and_unwatch() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 100);
+            add_balance(30, 100);
+            add_balance(40, 100);
+
+            assert_noop!(Mod::watch_name(Origin::signed(20), 100), "name does not exist");
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::watch_name(Origin::signed(20), 100));
+            assert_noop!(Mod::watch_name(Origin::signed(20), 100), "name is watched already");
+            assert_ok!(Mod::watch_name(Origin::signed(30), 100));
+            assert_noop!(Mod::watch_name(Origin::signed(40), 100), "too many watchers");
+            assert_eq!(Balances::reserved_balance(&20), 5);
+
+            assert_noop!(Mod::unwatch_name(Origin::signed(40), 100), "name is not watched");
+            assert_ok!(Mod::unwatch_name(Origin::signed(20), 100));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_eq!(Mod::watchers(100), vec![(30, 5)]);
+        });
+    }
+
+    #[test]
+    fn triggers() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 100);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::watch_name(Origin::signed(20), 100));
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert!(triggered(20, 100, NameChange::Update));
+            assert!(!triggered(20, 100, NameChange::Transfer));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+            assert!(triggered(20, 100, NameChange::Transfer));
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert!(triggered(20, 100, NameChange::Removal));
+            assert!(Mod::watchers(100).is_empty());
+            assert_eq!(Balances::reserved_balance(&20), 0);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for value deposits.
+mod value_deposits {
+    use super::*;
+
+    /// Encoded size of the test values (u64).
+    const VALUE_SIZE: u128 = 8;
+
+    #[test]
+    fn reconciliation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            set_value_deposit(2);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(Mod::value_deposit(100), Some((10, 2 * VALUE_SIZE)));
+            assert_eq!(Balances::reserved_balance(&10), 2 * VALUE_SIZE);
+
+            /* Since the test values all have the same size, we change
+               the per-byte deposit instead to simulate shrinking and
+               growing values.  */
+            set_value_deposit(1);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 43));
+            assert_eq!(Balances::reserved_balance(&10), VALUE_SIZE);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ValueDepositReleased(100, 10, VALUE_SIZE))
+            }));
+
+            set_value_deposit(3);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 44));
+            assert_eq!(Balances::reserved_balance(&10), 3 * VALUE_SIZE);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::ValueDepositReserved(100, 10, 2 * VALUE_SIZE))
+            }));
+            expect_balance(10, 900);
+        });
+    }
+
+    #[test]
+    fn new_depositor() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 1000);
+            set_value_deposit(1);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::value_deposit(100), Some((10, VALUE_SIZE)));
+
+            assert_ok!(Mod::update(Origin::signed(20), 100, 43));
+            assert_eq!(Mod::value_deposit(100), Some((20, VALUE_SIZE)));
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::reserved_balance(&20), VALUE_SIZE);
+
+            assert_ok!(Mod::force_remove(Origin::ROOT, 100));
+            assert_eq!(Mod::value_deposit(100), None);
+            assert_eq!(Balances::reserved_balance(&20), 0);
+        });
+    }
+
+    #[test]
+    fn insufficient_balance() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 105);
+            set_value_deposit(1);
+
+            assert_noop!(Mod::update(Origin::signed(10), 100, 42),
+                         "insufficient balance for value deposit");
+            set_value_deposit(0);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+        });
+    }
+
+    #[test]
+    fn fee_kept_if_deposit_fails() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_value_deposit(1);
+
+            let op = Mod::check_assuming_signed(10, 100, Some(42), None).unwrap();
+            /* The lock only applies to reserves, so that the fee could
+               be paid but the deposit fails.  */
+            let mut reasons = WithdrawReasons::none();
+            reasons.set(WithdrawReason::Reserve);
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &10, 5000, 100, reasons);
+            assert!(Mod::execute(op).is_err());
+
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::value_deposit(100), None);
+            assert_eq!(Balances::free_balance(&10), 5000);
+            expect_balance(FEE_RECEIVER, 1000);
+        });
+    }
+
+    #[test]
+    fn deposit_released_if_fee_fails() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            set_value_deposit(1);
+
+            let op = Mod::check_assuming_signed(10, 100, Some(42), None).unwrap();
+            /* The lock only applies to fees, so that the deposit can still
+               be reserved before the fee withdrawal fails.  */
+            let mut reasons = WithdrawReasons::none();
+            reasons.set(WithdrawReason::Fee);
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &10, 5000, 100, reasons);
+            assert!(Mod::execute(op).is_err());
+
+            assert_eq!(Mod::lookup(100), None);
+            assert_eq!(Mod::value_deposit(100), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            expect_balance(10, 5000);
+            expect_balance(FEE_RECEIVER, 1000);
+        });
+    }
+
+    #[test]
+    fn deposit_and_fee_together() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 5000);
+            add_balance(20, 5000);
+            set_value_deposit(1);
+
+            /* The deposit alone can be reserved and the fee alone paid,
+               but not both together.  */
+            <Balances as LockableCurrency<u64>>::set_lock(
+                [1, 2, 3, 4, 5, 6, 7, 8], &10, 4896, 100, WithdrawReasons::all());
+            assert_eq!(Mod::check_detailed(10, 10, 100, Some(42), None).err(),
+                       Some(OperationError::RequiredDeposit(100 + VALUE_SIZE)));
+
+            /* With a sponsor, the sender only needs the deposit.  */
+            assert_ok!(Mod::check_sponsored(10, 20, 100, Some(42), None));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the strict register and set_value calls.
+mod strict_updates {
+    use super::*;
+
+    #[test]
+    fn register() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            assert_ok!(Mod::register(Origin::signed(10), 100, 42, None));
+            assert_eq!(Mod::lookup(100).unwrap().value, 42);
+            expect_balance(10, 900);
+
+            assert_noop!(Mod::register(Origin::signed(10), 100, 43, None), "name already exists");
+
+            set_intents_only(true);
+            assert_noop!(Mod::register(Origin::signed(10), 200, 42, None),
+                         "names must be registered through intents");
+        });
+    }
+
+    #[test]
+    fn validity_window() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            System::set_block_number(10);
+
+            assert_noop!(Mod::register(Origin::signed(10), 100, 42, Some(9)),
+                         "transaction is past its validity window");
+            assert_ok!(Mod::register(Origin::signed(10), 100, 42, Some(10)));
+        });
+    }
+
+    #[test]
+    fn set_value() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            assert_noop!(Mod::set_value(Origin::signed(10), 100, 42), "name does not exist");
+
+            assert_ok!(Mod::register(Origin::signed(10), 100, 42, None));
+            assert_noop!(Mod::set_value(Origin::signed(20), 100, 43), "non-owner name update");
+            assert_ok!(Mod::set_value(Origin::signed(10), 100, 43));
+            assert_eq!(Mod::lookup(100).unwrap().value, 43);
+        });
+    }
+
+    #[test]
+    fn transfer_existing() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            assert_noop!(Mod::transfer_existing(Origin::signed(10), 100, 20),
+                         "name does not exist");
+            assert_ok!(Mod::register(Origin::signed(10), 100, 42, None));
+            assert_ok!(Mod::transfer_existing(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+        });
+    }
+
+    #[test]
+    fn strict_transfers() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            set_strict_transfers(true);
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20), "name does not exist");
+            assert_ok!(Mod::register(Origin::signed(10), 100, 42, None));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            set_strict_transfers(false);
+            assert_ok!(Mod::transfer(Origin::signed(10), 200, 20));
+            assert_eq!(Mod::lookup(200).unwrap().owner, 20);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for transfers to accounts that require claiming names.
+mod transfer_claims {
+    use super::*;
+
+    #[test]
+    fn immediate_by_default() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::pending_transfer(100), None);
+        });
+    }
+
+    #[test]
+    fn pending_and_claim() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::set_transfer_preference(Origin::signed(20), true));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 10);
+            assert_eq!(Mod::pending_transfer(100), Some(20));
+
+            assert_noop!(Mod::claim_name(Origin::signed(30), 100),
+                         "no pending transfer to the sender");
+            assert_ok!(Mod::claim_name(Origin::signed(20), 100));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+            assert_eq!(Mod::names_of(&20), vec![100]);
+            assert_eq!(Mod::pending_transfer(100), None);
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::TransferClaimed(100, 20))
+            }));
+
+            /* New names transferred to the account are registered to
+               the sender and pending as well.  */
+            assert_ok!(Mod::transfer(Origin::signed(10), 200, 20));
+            assert_eq!(Mod::lookup(200).unwrap().owner, 10);
+            assert_eq!(Mod::pending_transfer(200), Some(20));
+        });
+    }
+
+    #[test]
+    fn cancellation() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::set_transfer_preference(Origin::signed(20), true));
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            assert_noop!(Mod::cancel_transfer(Origin::signed(20), 100),
+                         "non-owner transfer cancellation");
+            assert_ok!(Mod::cancel_transfer(Origin::signed(10), 100));
+            assert_noop!(Mod::cancel_transfer(Origin::signed(10), 100), "no pending transfer");
+            assert_noop!(Mod::claim_name(Origin::signed(20), 100),
+                         "no pending transfer to the sender");
+
+            /* A direct transfer to someone else also drops the pending
+               transfer.  */
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 30));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 30);
+            assert_eq!(Mod::pending_transfer(100), None);
+
+            assert_ok!(Mod::set_transfer_preference(Origin::signed(20), false));
+            assert_ok!(Mod::transfer(Origin::signed(30), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for blocking incoming transfers.
+mod transfer_blocks {
+    use super::*;
+
+    #[test]
+    fn blocked_sender() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(30, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::update(Origin::signed(30), 300, 42));
+
+            assert_ok!(Mod::block_sender(Origin::signed(20), 10));
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "recipient does not accept transfers from the sender");
+            assert_noop!(Mod::transfer(Origin::signed(10), 200, 20),
+                         "recipient does not accept transfers from the sender");
+            assert_ok!(Mod::transfer(Origin::signed(30), 300, 20));
+
+            assert_ok!(Mod::unblock_sender(Origin::signed(20), 10));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+            assert_eq!(Mod::lookup(100).unwrap().owner, 20);
+        });
+    }
+
+    #[test]
+    fn block_all() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            add_balance(20, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+
+            assert_ok!(Mod::set_block_all_transfers(Origin::signed(20), true));
+            assert_noop!(Mod::transfer(Origin::signed(10), 100, 20),
+                         "recipient does not accept transfers from the sender");
+
+            /* The account can still register and update its own names.  */
+            assert_ok!(Mod::update(Origin::signed(20), 200, 42));
+            assert_ok!(Mod::update(Origin::signed(20), 200, 43));
+
+            assert_ok!(Mod::set_block_all_transfers(Origin::signed(20), false));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+        });
+    }
+
+}
+
+/* ************************************************************************** */
+
+/// Unit tests for the classification of transfers as separate operation type.
+mod transfer_operations {
+    use super::*;
+
+    #[test]
+    fn operation_type() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+
+            let typ = |value, recipient| {
+                Mod::check_assuming_signed(10, 100, value, recipient).unwrap().operation
+            };
+            assert_eq!(typ(None, Some(20)), OperationType::Registration);
+
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_eq!(typ(None, Some(20)), OperationType::Transfer);
+            assert_eq!(typ(Some(42), Some(20)), OperationType::Transfer);
+            assert_eq!(typ(Some(50), Some(20)), OperationType::Update);
+            assert_eq!(typ(None, None), OperationType::Update);
+            assert_eq!(typ(None, Some(10)), OperationType::Update);
+        });
+    }
+
+    #[test]
+    fn transfer_event() {
+        new_test_ext().execute_with(|| {
+            add_balance(FEE_RECEIVER, 1000);
+            add_balance(10, 1000);
+            assert_ok!(Mod::update(Origin::signed(10), 100, 42));
+            assert_ok!(Mod::transfer(Origin::signed(10), 100, 20));
+
+            assert!(System::events().iter().any(|r| {
+                r.event == TestEvent::names(RawEvent::NameTransferred(100, 10, 20))
             }));
         });
     }
@@ -0,0 +1,424 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Dispatch weights of the pallet's calls.  The base weight of every call
+//! comes from the runtime's [`WeightInfo`].  On top of that, runtimes whose
+//! name policy or other hooks do expensive work (e.g. Unicode validation of
+//! names) can charge [extra weight](OperationWeight) for name operations,
+//! so that blocks are not overfilled.
+
+use crate::Trait;
+
+use frame_support::traits::Get;
+use frame_support::weights::Weight;
+use sp_std::marker::PhantomData;
+
+/// Base weights of the pallet's calls.  Each function returns the weight
+/// of the call with the same name, with these exceptions:
+///
+/// - For calls that process a list of items
+///   ([`force_register_batch`](crate::Module::force_register_batch),
+///   [`renew_namespace`](crate::Module::renew_namespace),
+///   [`execute_signed_batch`](crate::Module::execute_signed_batch) and
+///   [`sweep_expirations`](crate::Module::sweep_expirations)), the weight
+///   is per item.
+/// - For [`accept_swap`](crate::Module::accept_swap), the weight is per
+///   name that may be swapped.
+/// - For [`transfer_all_names`](crate::Module::transfer_all_names), the
+///   weight is per transferred name, and `transfer_all_names_skipped` per
+///   name that is skipped.
+/// - For [`dispatch_as_name`](crate::Module::dispatch_as_name), the weight
+///   is the overhead on top of the inner call.
+///
+/// The unit type implements this with the fixed weights that the pallet
+/// has always used, which is fine for tests.  Production runtimes should
+/// use [`ReferenceWeights`] or their own values.
+pub trait WeightInfo {
+
+    fn update() -> Weight;
+    fn update_via_registrar() -> Weight;
+    fn register() -> Weight;
+    fn register_attested() -> Weight;
+    fn renew() -> Weight;
+    fn set_value() -> Weight;
+    fn set_value_at_nonce() -> Weight;
+    fn update_patch() -> Weight;
+    fn schedule_value() -> Weight;
+    fn cancel_scheduled_value() -> Weight;
+    fn set_subvalue() -> Weight;
+    fn clear_subvalue() -> Weight;
+    fn publish_envelope() -> Weight;
+    fn remove_envelope() -> Weight;
+    fn watch_name() -> Weight;
+    fn unwatch_name() -> Weight;
+    fn transfer() -> Weight;
+    fn transfer_existing() -> Weight;
+    fn set_transfer_preference() -> Weight;
+    fn block_sender() -> Weight;
+    fn unblock_sender() -> Weight;
+    fn set_block_all_transfers() -> Weight;
+    fn claim_name() -> Weight;
+    fn cancel_transfer() -> Weight;
+    fn donate_name() -> Weight;
+    fn lock_as_collateral() -> Weight;
+    fn release_collateral() -> Weight;
+    fn claim_collateral() -> Weight;
+    fn propose_swap() -> Weight;
+    fn cancel_swap() -> Weight;
+    fn accept_swap() -> Weight;
+    fn set_payout() -> Weight;
+    fn pay_name() -> Weight;
+    fn approve_operator() -> Weight;
+    fn operator_update() -> Weight;
+    fn offer_rent_to_own() -> Weight;
+    fn pay_installment() -> Weight;
+    fn split_into_shares() -> Weight;
+    fn transfer_shares() -> Weight;
+    fn propose_value() -> Weight;
+    fn approve_value() -> Weight;
+    fn reclaim_name() -> Weight;
+    fn dispatch_as_name() -> Weight;
+    fn repair_index() -> Weight;
+    fn transfer_all_names() -> Weight;
+    fn transfer_all_names_skipped() -> Weight;
+    fn register_hidden() -> Weight;
+    fn reveal_name() -> Weight;
+    fn set_signing_key() -> Weight;
+    fn set_controller() -> Weight;
+    fn set_note() -> Weight;
+    fn set_redirect() -> Weight;
+    fn set_guardian() -> Weight;
+    fn request_guarded_transfer() -> Weight;
+    fn confirm_guarded_transfer() -> Weight;
+    fn cancel_guarded_transfer() -> Weight;
+    fn set_sale_ready() -> Weight;
+    fn set_retention_policy() -> Weight;
+    fn authorize_session_key() -> Weight;
+    fn revoke_session_key() -> Weight;
+    fn session_update() -> Weight;
+    fn link_identity() -> Weight;
+    fn unlink_identity() -> Weight;
+    fn force_transfer() -> Weight;
+    fn report_name() -> Weight;
+    fn decide_report() -> Weight;
+    fn force_remove() -> Weight;
+    fn freeze_name() -> Weight;
+    fn force_register() -> Weight;
+    fn force_register_batch() -> Weight;
+    fn queue_migration() -> Weight;
+    fn force_total_names() -> Weight;
+    fn set_namespace_registrar() -> Weight;
+    fn delegate_namespace() -> Weight;
+    fn set_topic_prefixes() -> Weight;
+    fn set_attestation_oracle() -> Weight;
+    fn set_account_fee_exemption() -> Weight;
+    fn set_name_fee_exemption() -> Weight;
+    fn set_system_name() -> Weight;
+    fn import_names() -> Weight;
+    fn register_intent() -> Weight;
+    fn preorder() -> Weight;
+    fn place_backorder() -> Weight;
+    fn cancel_backorder() -> Weight;
+    fn insure_name() -> Weight;
+    fn set_insurance_premium() -> Weight;
+    fn renew_namespace() -> Weight;
+    fn sponsored_update() -> Weight;
+    fn execute_signed_operation() -> Weight;
+    fn execute_signed_batch() -> Weight;
+    fn sweep_expirations() -> Weight;
+
+}
+
+/// Fixed weights, which do not depend on any runtime parameters.
+impl WeightInfo for () {
+    fn update() -> Weight { 10_000 }
+    fn update_via_registrar() -> Weight { 10_000 }
+    fn register() -> Weight { 10_000 }
+    fn register_attested() -> Weight { 10_000 }
+    fn renew() -> Weight { 10_000 }
+    fn set_value() -> Weight { 10_000 }
+    fn set_value_at_nonce() -> Weight { 10_000 }
+    fn update_patch() -> Weight { 10_000 }
+    fn schedule_value() -> Weight { 10_000 }
+    fn cancel_scheduled_value() -> Weight { 10_000 }
+    fn set_subvalue() -> Weight { 10_000 }
+    fn clear_subvalue() -> Weight { 10_000 }
+    fn publish_envelope() -> Weight { 10_000 }
+    fn remove_envelope() -> Weight { 10_000 }
+    fn watch_name() -> Weight { 10_000 }
+    fn unwatch_name() -> Weight { 10_000 }
+    fn transfer() -> Weight { 10_000 }
+    fn transfer_existing() -> Weight { 10_000 }
+    fn set_transfer_preference() -> Weight { 10_000 }
+    fn block_sender() -> Weight { 10_000 }
+    fn unblock_sender() -> Weight { 10_000 }
+    fn set_block_all_transfers() -> Weight { 10_000 }
+    fn claim_name() -> Weight { 10_000 }
+    fn cancel_transfer() -> Weight { 10_000 }
+    fn donate_name() -> Weight { 10_000 }
+    fn lock_as_collateral() -> Weight { 10_000 }
+    fn release_collateral() -> Weight { 10_000 }
+    fn claim_collateral() -> Weight { 10_000 }
+    fn propose_swap() -> Weight { 10_000 }
+    fn cancel_swap() -> Weight { 10_000 }
+    fn accept_swap() -> Weight { 20_000 }
+    fn set_payout() -> Weight { 10_000 }
+    fn pay_name() -> Weight { 10_000 }
+    fn approve_operator() -> Weight { 10_000 }
+    fn operator_update() -> Weight { 10_000 }
+    fn offer_rent_to_own() -> Weight { 10_000 }
+    fn pay_installment() -> Weight { 10_000 }
+    fn split_into_shares() -> Weight { 10_000 }
+    fn transfer_shares() -> Weight { 10_000 }
+    fn propose_value() -> Weight { 10_000 }
+    fn approve_value() -> Weight { 10_000 }
+    fn reclaim_name() -> Weight { 10_000 }
+    fn dispatch_as_name() -> Weight { 20_000 }
+    fn repair_index() -> Weight { 10_000 }
+    fn transfer_all_names() -> Weight { 100_000 }
+    fn transfer_all_names_skipped() -> Weight { 5_000 }
+    fn register_hidden() -> Weight { 10_000 }
+    fn reveal_name() -> Weight { 10_000 }
+    fn set_signing_key() -> Weight { 10_000 }
+    fn set_controller() -> Weight { 10_000 }
+    fn set_note() -> Weight { 10_000 }
+    fn set_redirect() -> Weight { 10_000 }
+    fn set_guardian() -> Weight { 10_000 }
+    fn request_guarded_transfer() -> Weight { 10_000 }
+    fn confirm_guarded_transfer() -> Weight { 10_000 }
+    fn cancel_guarded_transfer() -> Weight { 10_000 }
+    fn set_sale_ready() -> Weight { 10_000 }
+    fn set_retention_policy() -> Weight { 10_000 }
+    fn authorize_session_key() -> Weight { 10_000 }
+    fn revoke_session_key() -> Weight { 10_000 }
+    fn session_update() -> Weight { 20_000 }
+    fn link_identity() -> Weight { 10_000 }
+    fn unlink_identity() -> Weight { 10_000 }
+    fn force_transfer() -> Weight { 10_000 }
+    fn report_name() -> Weight { 10_000 }
+    fn decide_report() -> Weight { 10_000 }
+    fn force_remove() -> Weight { 10_000 }
+    fn freeze_name() -> Weight { 10_000 }
+    fn force_register() -> Weight { 10_000 }
+    fn force_register_batch() -> Weight { 10_000 }
+    fn queue_migration() -> Weight { 100_000 }
+    fn force_total_names() -> Weight { 10_000 }
+    fn set_namespace_registrar() -> Weight { 10_000 }
+    fn delegate_namespace() -> Weight { 10_000 }
+    fn set_topic_prefixes() -> Weight { 10_000 }
+    fn set_attestation_oracle() -> Weight { 10_000 }
+    fn set_account_fee_exemption() -> Weight { 10_000 }
+    fn set_name_fee_exemption() -> Weight { 10_000 }
+    fn set_system_name() -> Weight { 10_000 }
+    fn import_names() -> Weight { 100_000 }
+    fn register_intent() -> Weight { 50_000 }
+    fn preorder() -> Weight { 10_000 }
+    fn place_backorder() -> Weight { 10_000 }
+    fn cancel_backorder() -> Weight { 10_000 }
+    fn insure_name() -> Weight { 10_000 }
+    fn set_insurance_premium() -> Weight { 10_000 }
+    fn renew_namespace() -> Weight { 10_000 }
+    fn sponsored_update() -> Weight { 20_000 }
+    fn execute_signed_operation() -> Weight { 20_000 }
+    fn execute_signed_batch() -> Weight { 10_000 }
+    fn sweep_expirations() -> Weight { 10_000 }
+}
+
+/// Weight of verifying a signature in [`ReferenceWeights`].
+const SIGNATURE: Weight = 5_000;
+/// Weight of reading a storage item in [`ReferenceWeights`].
+const READ: Weight = 500;
+/// Weight of writing a storage item in [`ReferenceWeights`].
+const WRITE: Weight = 2_000;
+/// Base weight of executing any call in [`ReferenceWeights`].
+const BASE: Weight = 2_000;
+
+/// Storage reads done by checking a name operation.
+const CHECK_READS: Weight = 16;
+/// Storage writes done by executing a name operation.
+const EXECUTE_WRITES: Weight = 8;
+
+/// Returns the weight of the given storage accesses.
+fn accesses(reads: Weight, writes: Weight) -> Weight {
+    reads.saturating_mul(READ).saturating_add(writes.saturating_mul(WRITE))
+}
+
+/// Returns the weight of a call with the given storage accesses.
+fn rw(reads: Weight, writes: Weight) -> Weight {
+    BASE.saturating_add(accesses(reads, writes))
+}
+
+/// Returns the weight of a call that checks (but does not execute) a name
+/// operation, plus the given other storage accesses.
+fn check(reads: Weight, writes: Weight) -> Weight {
+    rw(CHECK_READS.saturating_add(reads), writes)
+}
+
+/// Returns the weight of a call that checks and executes a name operation,
+/// plus the given other storage accesses.
+fn operation(reads: Weight, writes: Weight) -> Weight {
+    check(reads, EXECUTE_WRITES.saturating_add(writes))
+}
+
+/// Reference weights for production runtimes.  This Substrate version
+/// has no benchmarking framework, so they are estimated from the storage
+/// items each call reads and writes in the worst case (with a fixed cost
+/// per access and per signature check) rather than measured.  Calls whose
+/// work is bounded by a runtime limit (e.g. [`Trait::MaxSwapNames`]) are
+/// weighed for that limit.
+pub struct ReferenceWeights<T>(PhantomData<T>);
+
+impl<T: Trait> ReferenceWeights<T> {
+    /// Returns the weight of removing a name (clearing all its data) and
+    /// processing its backorders.
+    fn removal() -> Weight {
+        let backorders = T::MaxBackorders::get() as Weight;
+        rw(20, 20).saturating_add(accesses(CHECK_READS, 1).saturating_mul(backorders))
+            .saturating_add(operation(0, 0))
+    }
+}
+
+impl<T: Trait> WeightInfo for ReferenceWeights<T> {
+    fn update() -> Weight { operation(1, 0) }
+    fn update_via_registrar() -> Weight { operation(2, 1) }
+    fn register() -> Weight { operation(2, 0) }
+    fn register_attested() -> Weight { operation(3, 1) + SIGNATURE }
+    fn renew() -> Weight { operation(1, 0) }
+    fn set_value() -> Weight { operation(1, 0) }
+    fn set_value_at_nonce() -> Weight { operation(1, 0) }
+    fn update_patch() -> Weight { operation(1, 0) }
+    fn schedule_value() -> Weight { check(1, 2) }
+    fn cancel_scheduled_value() -> Weight { rw(2, 1) }
+    fn set_subvalue() -> Weight { rw(4, 4) }
+    fn clear_subvalue() -> Weight { rw(4, 4) }
+    fn publish_envelope() -> Weight { rw(4, 4) }
+    fn remove_envelope() -> Weight { rw(3, 2) }
+    fn watch_name() -> Weight { rw(3, 2) }
+    fn unwatch_name() -> Weight { rw(2, 2) }
+    fn transfer() -> Weight { operation(1, 0) }
+    fn transfer_existing() -> Weight { operation(1, 0) }
+    fn set_transfer_preference() -> Weight { rw(0, 1) }
+    fn block_sender() -> Weight { rw(0, 1) }
+    fn unblock_sender() -> Weight { rw(0, 1) }
+    fn set_block_all_transfers() -> Weight { rw(0, 1) }
+    fn claim_name() -> Weight { operation(2, 0) }
+    fn cancel_transfer() -> Weight { rw(2, 1) }
+    fn donate_name() -> Weight { operation(1, 0) }
+    fn lock_as_collateral() -> Weight { rw(8, 1) }
+    fn release_collateral() -> Weight { rw(1, 1) }
+    fn claim_collateral() -> Weight { operation(3, 0) }
+    fn propose_swap() -> Weight {
+        let max_swap_names = T::MaxSwapNames::get() as Weight;
+        rw(2, 2).saturating_add(accesses(7, 1).saturating_mul(max_swap_names))
+    }
+    fn cancel_swap() -> Weight {
+        let max_swap_names = T::MaxSwapNames::get() as Weight;
+        rw(1, 2).saturating_add(accesses(1, 1).saturating_mul(max_swap_names))
+    }
+    fn accept_swap() -> Weight { operation(7, 1) }
+    fn set_payout() -> Weight { rw(1, 1) }
+    fn pay_name() -> Weight { rw(4, 4) }
+    fn approve_operator() -> Weight { rw(1, 1) }
+    fn operator_update() -> Weight { operation(2, 0) }
+    fn offer_rent_to_own() -> Weight { rw(8, 2) }
+    fn pay_installment() -> Weight { operation(4, 4) }
+    fn split_into_shares() -> Weight { operation(8, 1) }
+    fn transfer_shares() -> Weight { rw(1, 1) }
+    fn propose_value() -> Weight { rw(3, 1) }
+    fn approve_value() -> Weight { operation(3, 1) }
+    fn reclaim_name() -> Weight { operation(3, 0) }
+    fn dispatch_as_name() -> Weight { rw(1, 0) }
+    fn repair_index() -> Weight { rw(3, 2) }
+    fn transfer_all_names() -> Weight { operation(1, 0) }
+    fn transfer_all_names_skipped() -> Weight { check(1, 0) }
+    fn register_hidden() -> Weight { rw(2, 4) }
+    fn reveal_name() -> Weight { operation(1, 0) }
+    fn set_signing_key() -> Weight { rw(1, 1) }
+    fn set_controller() -> Weight { rw(1, 1) }
+    fn set_note() -> Weight { rw(3, 2) }
+    fn set_redirect() -> Weight {
+        rw(1, 1)
+            .saturating_add(accesses(1, 0).saturating_mul(T::MaxRedirectDepth::get() as Weight))
+    }
+    fn set_guardian() -> Weight { rw(6, 2) }
+    fn request_guarded_transfer() -> Weight { rw(2, 1) }
+    fn confirm_guarded_transfer() -> Weight { operation(3, 2) }
+    fn cancel_guarded_transfer() -> Weight { rw(3, 1) }
+    fn set_sale_ready() -> Weight { rw(1, 1) }
+    fn set_retention_policy() -> Weight { rw(1, 1) }
+    fn authorize_session_key() -> Weight {
+        rw(0, 1)
+            .saturating_add(accesses(1, 0).saturating_mul(T::MaxSessionNames::get() as Weight))
+    }
+    fn revoke_session_key() -> Weight { rw(1, 1) }
+    fn session_update() -> Weight { operation(3, 1) + SIGNATURE }
+    fn link_identity() -> Weight { rw(3, 3) }
+    fn unlink_identity() -> Weight { rw(3, 2) }
+    fn force_transfer() -> Weight { operation(2, 0) }
+    fn report_name() -> Weight { rw(3, 2) }
+    fn decide_report() -> Weight { Self::removal().saturating_add(accesses(3, 2)) }
+    fn force_remove() -> Weight { Self::removal().saturating_add(accesses(2, 0)) }
+    fn freeze_name() -> Weight { rw(2, 1) }
+    fn force_register() -> Weight { operation(2, 0) }
+    fn force_register_batch() -> Weight { rw(3, 6) }
+    fn queue_migration() -> Weight {
+        let max_bulk_transfer = T::MaxBulkTransfer::get() as Weight;
+        rw(1, 1).saturating_add(accesses(0, 1).saturating_mul(max_bulk_transfer))
+    }
+    fn force_total_names() -> Weight { rw(0, 1) }
+    fn set_namespace_registrar() -> Weight { rw(0, 1) }
+    fn delegate_namespace() -> Weight { rw(1, 1) }
+    fn set_topic_prefixes() -> Weight { rw(0, 1) }
+    fn set_attestation_oracle() -> Weight { rw(1, 1) }
+    fn set_account_fee_exemption() -> Weight { rw(0, 1) }
+    fn set_name_fee_exemption() -> Weight { rw(0, 1) }
+    fn set_system_name() -> Weight { rw(4, 6) }
+    fn import_names() -> Weight {
+        let max_bulk_transfer = T::MaxBulkTransfer::get() as Weight;
+        rw(1, 0).saturating_add(accesses(3, 6).saturating_mul(max_bulk_transfer))
+    }
+    fn register_intent() -> Weight { check(3, 3) }
+    fn preorder() -> Weight { rw(2, 3) }
+    fn place_backorder() -> Weight { rw(3, 2) }
+    fn cancel_backorder() -> Weight { rw(2, 2) }
+    fn insure_name() -> Weight { rw(4, 3) }
+    fn set_insurance_premium() -> Weight { rw(0, 1) }
+    fn renew_namespace() -> Weight { operation(1, 0) }
+    fn sponsored_update() -> Weight { operation(3, 1) + SIGNATURE }
+    fn execute_signed_operation() -> Weight { operation(3, 1) + SIGNATURE }
+    fn execute_signed_batch() -> Weight { operation(3, 1) + SIGNATURE }
+    fn sweep_expirations() -> Weight { rw(8, 8) }
+}
+
+/// Computes the weight that is added to the base weight of calls
+/// performing a name operation.
+pub trait OperationWeight<Name, Value> {
+
+    /// Returns the extra weight for an operation on the given name.  The
+    /// value is `None` for transfers, which keep the existing value.
+    fn extra_weight(name: &Name, value: Option<&Value>) -> Weight;
+
+}
+
+/// The unit type adds no extra weight.
+impl<Name, Value> OperationWeight<Name, Value> for () {
+    fn extra_weight(_name: &Name, _value: Option<&Value>) -> Weight {
+        0
+    }
+}
@@ -0,0 +1,176 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! Conversion between names of the pallet and the JSON format used by
+//! [Xaya Core](https://github.com/xaya/xaya)'s RPC interface, so that
+//! existing Xaya tooling and game engines can talk to a Substrate chain
+//! with minimal changes.  This is only available with `std`.
+//!
+//! [`NameShow`] corresponds to the result of `name_show`, and
+//! [`NameUpdate`] to the arguments of `name_update`.  Names and values
+//! are encoded as `utf8` strings if possible, and as `hex` otherwise.
+//! When reading, `ascii`, `utf8` and `hex` are understood.
+
+use crate::{Module, NameData, Operation, Trait};
+
+use serde::{Deserialize, Serialize};
+use sp_runtime::traits::{SaturatedConversion, Saturating};
+
+use std::fmt::Write;
+
+/// A name with its data as returned by `name_show`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NameShow {
+    /// The name, encoded according to `name_encoding`.
+    pub name: String,
+    /// The encoding of the name.
+    pub name_encoding: String,
+    /// The value, encoded according to `value_encoding`.
+    pub value: String,
+    /// The encoding of the value.
+    pub value_encoding: String,
+    /// The owner of the name, as formatted by its `Display` implementation
+    /// (e.g. SS58 for the usual account IDs).
+    pub address: String,
+    /// Number of blocks until the name expires.  Names on Xaya never
+    /// expire, so this is omitted if the name does not either.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_in: Option<i64>,
+}
+
+/// The arguments of `name_update`:  The name, the new value and an
+/// optional recipient address for transfers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NameUpdate {
+    /// The name, encoded according to `name_encoding`.
+    pub name: String,
+    /// The encoding of the name, or `utf8` if not given.
+    #[serde(default)]
+    pub name_encoding: Option<String>,
+    /// The new value, encoded according to `value_encoding`.
+    pub value: String,
+    /// The encoding of the value, or `utf8` if not given.
+    #[serde(default)]
+    pub value_encoding: Option<String>,
+    /// The recipient of the name, if it is transferred.
+    #[serde(default, rename = "destAddress")]
+    pub dest_address: Option<String>,
+}
+
+/// Encodes raw name or value data as a string, returning the string
+/// and its encoding.
+pub fn encode_data(data: &[u8]) -> (String, String) {
+    match std::str::from_utf8(data) {
+        Ok(s) => (s.to_string(), "utf8".to_string()),
+        Err(_) => {
+            let mut hex = String::with_capacity(2 * data.len());
+            for b in data {
+                let _ = write!(hex, "{:02x}", b);
+            }
+            (hex, "hex".to_string())
+        },
+    }
+}
+
+/// Decodes a name or value string according to the given encoding
+/// (with `None` meaning `utf8`).
+pub fn decode_data(data: &str, encoding: Option<&str>) -> Result<Vec<u8>, String> {
+    match encoding {
+        None | Some("ascii") | Some("utf8") => Ok(data.as_bytes().to_vec()),
+        Some("hex") => {
+            if data.len() % 2 != 0 {
+                return Err("invalid hex data: odd length".to_string());
+            }
+            (0..data.len()).step_by(2)
+                .map(|i| {
+                    data.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                        .ok_or_else(|| format!("invalid hex data: {}", data))
+                })
+                .collect()
+        },
+        Some(enc) => Err(format!("unsupported data encoding: {}", enc)),
+    }
+}
+
+impl NameShow {
+
+    /// Constructs the `name_show` result for a name with the given data,
+    /// as of the given block height.
+    pub fn new<T: Trait>(name: &T::Name, data: &NameData<T>, now: T::BlockNumber) -> NameShow
+        where T::Name: AsRef<[u8]>, T::Value: AsRef<[u8]>
+    {
+        let (name, name_encoding) = encode_data(name.as_ref());
+        let (value, value_encoding) = encode_data(data.value.as_ref());
+        NameShow {
+            name: name,
+            name_encoding: name_encoding,
+            value: value,
+            value_encoding: value_encoding,
+            address: data.owner.to_string(),
+            expires_in: data.expiration.map(|h| {
+                let blocks: u64 = h.saturating_sub(now).saturated_into();
+                blocks.min(i64::max_value() as u64) as i64
+            }),
+        }
+    }
+
+    /// Looks up a name in the pallet's database and returns its `name_show`
+    /// result, or `None` if it does not exist.
+    pub fn lookup<T: Trait>(name: &T::Name) -> Option<NameShow>
+        where T::Name: AsRef<[u8]>, T::Value: AsRef<[u8]>
+    {
+        let now = system::Module::<T>::block_number();
+        Module::<T>::lookup(name).map(|data| NameShow::new::<T>(name, &data, now))
+    }
+
+}
+
+impl NameUpdate {
+
+    /// Returns the raw name and value of the update.
+    pub fn decode(&self) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let name = decode_data(&self.name, self.name_encoding.as_deref())?;
+        let value = decode_data(&self.value, self.value_encoding.as_deref())?;
+        Ok((name, value))
+    }
+
+    /// Checks the update as an operation of the given sender, with
+    /// [`check_assuming_signed`](Module::check_assuming_signed).  The
+    /// destination address (if any) is converted to an account with
+    /// `parse_address`.  The result can then be
+    /// [executed](Module::execute).
+    pub fn check<T: Trait, F>(&self, sender: T::AccountId,
+                              parse_address: F) -> Result<Operation<T>, String>
+        where T::Name: From<Vec<u8>>, T::Value: From<Vec<u8>>,
+              F: Fn(&str) -> Option<T::AccountId>
+    {
+        let (name, value) = self.decode()?;
+        let recipient = match &self.dest_address {
+            None => None,
+            Some(addr) => match parse_address(addr) {
+                None => return Err(format!("invalid destination address: {}", addr)),
+                Some(account) => Some(account),
+            },
+        };
+        Module::<T>::check_assuming_signed(sender, name.into(), Some(value.into()), recipient)
+            .map_err(|e| e.to_string())
+    }
+
+}
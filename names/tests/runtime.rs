@@ -0,0 +1,749 @@
+/*
+    names - a pallet for Substrate blockchains implementing naming
+    Copyright (C) 2020  Autonomous Worlds Ltd
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU General Public License for more details.
+
+    You should have received a copy of the GNU General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Integration tests that build a full runtime (with `construct_runtime!`)
+//! around the names pallet and run multi-block scenarios through the
+//! dispatched calls and block hooks, as a real chain would.
+//!
+//! The runtime has no scheduler pallet, since there is none at the
+//! Substrate revision used here.  Expirations are processed by the names
+//! pallet's own block hooks, up to its per-block limit.
+
+use frame_support::{
+    assert_noop, assert_ok, construct_runtime, parameter_types,
+    traits::{Currency, Randomness},
+    weights::Weight,
+};
+use sp_core::H256;
+use sp_runtime::{
+    BuildStorage, ModuleId, Perbill,
+    testing::{Block as TestBlock, Header, TestSignature, TestXt, UintAuthorityId},
+    traits::{BlakeTwo256, Dispatchable, Hash, IdentityLookup, OnFinalize, OnInitialize},
+};
+use system::EventRecord;
+
+use names::{NameData, Operation};
+use names::policy::{
+    ByNameClass, Composed, ExpirationPolicy, FeePolicy, LengthTiered, NameClass,
+    ShortNamePremium, Utf8Classifier,
+};
+
+type AccountId = u64;
+type Balance = u128;
+type BlockNumber = u64;
+
+/// The opaque extrinsic and block types.  We never actually build blocks
+/// with extrinsics, but construct_runtime needs them to be defined.
+type UncheckedExtrinsic = TestXt<Call, ()>;
+type Block = TestBlock<UncheckedExtrinsic>;
+
+construct_runtime!(
+    pub enum Runtime where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic
+    {
+        System: system::{Module, Call, Storage, Config, Event},
+        Balances: balances::{Module, Call, Storage, Config<T>, Event<T>},
+        Names: names::{Module, Call, Storage, Event<T>},
+    }
+);
+
+parameter_types! {
+    pub const BlockHashCount: BlockNumber = 250;
+    pub const MaximumBlockWeight: Weight = 1_000_000;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const ExistentialDeposit: Balance = 10;
+    pub const HiddenRegistrationFee: Balance = 50;
+    pub const HiddenRegistrationPeriod: BlockNumber = 5;
+    pub const MaxBulkTransfer: u32 = 10;
+    pub const MaxSwapNames: u32 = 10;
+    pub const MaxForceRegistrations: u32 = 3;
+    pub const StatsEraLength: BlockNumber = 100;
+    pub const StatsHistory: u32 = 10;
+    pub const FreeUpdatesPerEra: u32 = 0;
+    pub const MaxTemplates: u32 = 5;
+    pub const ReceiptRetention: BlockNumber = 100;
+    pub const MaxPayoutSplits: u32 = 5;
+    pub const MaxShareHolders: u32 = 10;
+    pub const MigrationWeightLimit: Weight = 100_000;
+    pub const DeferredWeightLimit: Weight = 100_000;
+    pub const MaxMigratedNameLength: u32 = 10;
+    pub const MaxMigratedValueLength: u32 = 10;
+    pub const CommunityAccount: AccountId = 777;
+    pub const NamesModuleId: ModuleId = ModuleId(*b"py/names");
+    pub const ExpirationBucketSize: BlockNumber = 1;
+    pub const MaxExpirationsPerBlock: u32 = 10;
+    pub const MaxAutoExpirations: Option<u32> = Some(2);
+    pub const SweepBounty: Balance = 5;
+    pub const MaxValueLookahead: BlockNumber = 100;
+    pub const StrictTransfers: bool = false;
+    pub const ValueDepositPerByte: Balance = 0;
+    pub const MinExpirationBlocks: BlockNumber = 1;
+    pub const ExpirationUpdates: names::ExpirationUpdate = names::ExpirationUpdate::MayShorten;
+    pub const ComplianceWindow: Option<BlockNumber> = None;
+    pub const NonComplianceAction: names::ComplianceAction = names::ComplianceAction::Warn;
+    pub const WatchDeposit: Balance = 5;
+    pub const MaxWatchers: u32 = 10;
+    pub const MaxSubvalues: u32 = 10;
+    pub const SubvalueDeposit: Balance = 10;
+    pub const NoteDeposit: Balance = 10;
+    pub const MaxNoteSize: u32 = 64;
+    pub const MaxRedirectDepth: u32 = 4;
+    pub const EnvelopeFee: Balance = 10;
+    pub const MaxEnvelopeSize: u32 = 256;
+    pub const MaxEnvelopes: u32 = 10;
+    pub const MinPreorderDeposit: Balance = 100;
+    pub const PreorderMaturity: BlockNumber = 2;
+    pub const PreorderWindow: BlockNumber = 5;
+    pub const PreorderForfeit: Perbill = Perbill::from_percent(50);
+    pub const FirstUpdatePeriod: Option<BlockNumber> = None;
+    pub const FirstUpdateRefund: Perbill = Perbill::from_percent(50);
+    pub const ExpirationQuarantine: BlockNumber = 0;
+    pub const RegistrarCommission: Perbill = Perbill::from_percent(10);
+    pub const MinBackorderBond: Balance = 100;
+    pub const MaxFeeRateAge: BlockNumber = 10;
+    pub const ReportBond: Balance = 500;
+    pub const ReportChallengePeriod: BlockNumber = 10;
+    pub const ArchiveRetention: Option<BlockNumber> = None;
+    pub const GuardianConfirmationPeriod: BlockNumber = 10;
+    pub const MaxBackorders: u32 = 10;
+    pub const MaxBulkRenewals: u32 = 3;
+    pub const MaxTopicPrefixes: u32 = 2;
+    pub const MaxDelegatedNamespaces: u32 = 2;
+    pub const MaxSessionDuration: BlockNumber = 100;
+    pub const MaxSessionNames: u32 = 2;
+    pub const MaxInsuredRenewals: u32 = 3;
+    pub const RegistrationIntentsOnly: bool = false;
+    pub const IntentBond: Balance = 10;
+    pub const MaxIntentsPerName: u32 = 10;
+    pub const MaxTotalNames: Option<u32> = None;
+    pub const MaxRegistrationsPerBlock: Option<u32> = None;
+    pub const CongestionMultipliers: &'static [(u32, u32)] = &[];
+    /// Names must be at least two bytes long and cost 100 to register.
+    pub const RegistrationFees: &'static [(usize, Balance)] = &[(2, 100)];
+    pub const UpdateFee: Balance = 10;
+    pub const PremiumBase: Balance = 100;
+    pub const PremiumFactor: u32 = 10;
+    pub const PremiumThreshold: u32 = 5;
+    /// Emoji names cost ten times as much, binary names are not allowed.
+    pub const ClassMultipliers: &'static [(NameClass, u32)] = &[
+        (NameClass::Ascii, 100),
+        (NameClass::Unicode, 100),
+        (NameClass::Emoji, 1000),
+    ];
+}
+
+impl system::Trait for Runtime {
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = BlockNumber;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+    type ModuleToIndex = ModuleToIndex;
+}
+
+impl balances::Trait for Runtime {
+    type Balance = Balance;
+    type OnFreeBalanceZero = ();
+    type OnNewAccount = ();
+    type Event = Event;
+    type DustRemoval = ();
+    type TransferPayment = ();
+    type ExistentialDeposit = ExistentialDeposit;
+    type TransferFee = ();
+    type CreationFee = ();
+}
+
+/// Number of blocks after which short names expire.
+const SHORT_EXPIRATION: BlockNumber = 10;
+
+/// Randomness for testing, which is just a hash of the subject.
+pub struct TestRandomness;
+impl Randomness<H256> for TestRandomness {
+    fn random(subject: &[u8]) -> H256 {
+        BlakeTwo256::hash(subject)
+    }
+}
+
+impl names::Trait for Runtime {
+
+    type Name = Vec<u8>;
+    type Value = Vec<u8>;
+    type DefaultValue = ();
+
+    type Currency = Balances;
+    type Event = Event;
+
+    type Namehash = names::namehash::EnsNamehash;
+
+    type HiddenRegistrationFee = HiddenRegistrationFee;
+    type HiddenRegistrationPeriod = HiddenRegistrationPeriod;
+
+    type Identity = ();
+    type ForceOrigin = system::EnsureRoot<AccountId>;
+    type ReportOrigin = system::EnsureRoot<AccountId>;
+    type Namespaces = names::namespace::BytePrefix;
+    type CommunityAccount = CommunityAccount;
+    type ModuleId = NamesModuleId;
+    type Call = Call;
+    type NameCallFilter = names::dispatch::AllowAll;
+    type MaxShareHolders = MaxShareHolders;
+    type MaxPayoutSplits = MaxPayoutSplits;
+    type MaxBulkTransfer = MaxBulkTransfer;
+    type MaxSwapNames = MaxSwapNames;
+    type MaxForceRegistrations = MaxForceRegistrations;
+    type MaxTotalNames = MaxTotalNames;
+    type MaxRegistrationsPerBlock = MaxRegistrationsPerBlock;
+    type CongestionMultipliers = CongestionMultipliers;
+    type Randomness = TestRandomness;
+    type StrictTransfers = StrictTransfers;
+    type RegistrationIntentsOnly = RegistrationIntentsOnly;
+    type IntentBond = IntentBond;
+    type MaxIntentsPerName = MaxIntentsPerName;
+
+    type MinPreorderDeposit = MinPreorderDeposit;
+    type PreorderMaturity = PreorderMaturity;
+    type PreorderWindow = PreorderWindow;
+    type PreorderForfeit = PreorderForfeit;
+    type FirstUpdatePeriod = FirstUpdatePeriod;
+    type FirstUpdateRefund = FirstUpdateRefund;
+    type ExpirationQuarantine = ExpirationQuarantine;
+    type RegistrarCommission = RegistrarCommission;
+    type MinBackorderBond = MinBackorderBond;
+    type ReportBond = ReportBond;
+    type ReportChallengePeriod = ReportChallengePeriod;
+    type ArchiveRetention = ArchiveRetention;
+    type GuardianConfirmationPeriod = GuardianConfirmationPeriod;
+    type MaxBackorders = MaxBackorders;
+    type MaxBulkRenewals = MaxBulkRenewals;
+    type MaxTopicPrefixes = MaxTopicPrefixes;
+    type MaxDelegatedNamespaces = MaxDelegatedNamespaces;
+    type MaxSessionDuration = MaxSessionDuration;
+    type MaxSessionNames = MaxSessionNames;
+    type MaxInsuredRenewals = MaxInsuredRenewals;
+
+    /// Policy similar to the example node:  Names must be at least two bytes
+    /// long, registrations cost 100 and updates 10.  Fees are burnt.
+    type Policy = Composed<LengthTiered<RegistrationFees, UpdateFee>, ShortNamesExpire, ()>;
+    type ExtraWeight = ();
+    type WeightInfo = ();
+    type FeeConverter = ();
+    type Payment = ();
+    type MaxFeeRateAge = MaxFeeRateAge;
+    type MinExpirationBlocks = MinExpirationBlocks;
+    type ExpirationUpdates = ExpirationUpdates;
+    type ComplianceWindow = ComplianceWindow;
+    type NonComplianceAction = NonComplianceAction;
+    type MaxExpirationsPerBlock = MaxExpirationsPerBlock;
+    type MaxAutoExpirations = MaxAutoExpirations;
+    type SweepBounty = SweepBounty;
+    type MaxValueLookahead = MaxValueLookahead;
+    type ExpirationBucketSize = ExpirationBucketSize;
+    type ValueDepositPerByte = ValueDepositPerByte;
+    type OnOwnerReaped = ();
+    type OnExpiration = ();
+    type ValueCompliance = ();
+    type OnNameOperation = ();
+    type Signature = TestSignature;
+    type Signer = UintAuthorityId;
+    type Patch = ();
+    type SubKey = Vec<u8>;
+    type MaxSubvalues = MaxSubvalues;
+    type SubvalueDeposit = SubvalueDeposit;
+    type NoteDeposit = NoteDeposit;
+    type MaxNoteSize = MaxNoteSize;
+    type MaxRedirectDepth = MaxRedirectDepth;
+    type EnvelopeFee = EnvelopeFee;
+    type MaxEnvelopeSize = MaxEnvelopeSize;
+    type MaxEnvelopes = MaxEnvelopes;
+    type WatchDeposit = WatchDeposit;
+    type MaxWatchers = MaxWatchers;
+    type Templates = names::template::ExactValue;
+    type MaxTemplates = MaxTemplates;
+    type Migration = names::migration::TruncateOversized<MaxMigratedNameLength,
+                                                         MaxMigratedValueLength>;
+    type MigrationWeightLimit = MigrationWeightLimit;
+    type DeferredWeightLimit = DeferredWeightLimit;
+    type ReceiptRetention = ReceiptRetention;
+    type StatsEraLength = StatsEraLength;
+    type StatsHistory = StatsHistory;
+    type FreeUpdatesPerEra = FreeUpdatesPerEra;
+
+}
+
+/// Names up to three bytes long expire, longer ones do not.
+struct ShortNamesExpire;
+
+impl ExpirationPolicy<Runtime> for ShortNamesExpire {
+    fn get_expiration(op: &Operation<Runtime>) -> Option<BlockNumber> {
+        if op.name.len() <= 3 {
+            Some(SHORT_EXPIRATION)
+        } else {
+            None
+        }
+    }
+}
+
+/// Account that is endowed at genesis.
+const ALICE: AccountId = 1;
+/// Another endowed account.
+const BOB: AccountId = 2;
+
+fn new_test_ext() -> sp_io::TestExternalities {
+    GenesisConfig {
+        system: Some(Default::default()),
+        balances: Some(balances::GenesisConfig::<Runtime> {
+            balances: vec![(ALICE, 1_000), (BOB, 1_000)],
+            vesting: vec![],
+        }),
+    }.build_storage().unwrap().into()
+}
+
+/// Runs the block hooks of all modules to process blocks up to (and
+/// including) the given height.
+fn run_to_block(n: BlockNumber) {
+    while System::block_number() < n {
+        let cur = System::block_number();
+        AllModules::on_finalize(cur);
+        System::set_block_number(cur + 1);
+        AllModules::on_initialize(cur + 1);
+    }
+}
+
+/// Dispatches a call to the names module from the given signed account.
+fn dispatch(who: AccountId, call: names::Call<Runtime>) -> frame_support::dispatch::DispatchResult {
+    Call::Names(call).dispatch(Origin::signed(who))
+}
+
+/// Returns all events from the names module deposited so far.
+fn name_events() -> Vec<names::Event<Runtime>> {
+    System::events().into_iter()
+        .filter_map(|r: EventRecord<Event, H256>| match r.event {
+            Event::names(e) => Some(e),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn registration_and_fees() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), b"x".to_vec())));
+        assert_ok!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), b"y".to_vec())));
+        assert_ok!(dispatch(BOB, names::Call::update(b"longname".to_vec(), b"z".to_vec())));
+        assert_noop!(dispatch(BOB, names::Call::update(b"foo".to_vec(), b"bad".to_vec())),
+                     "non-owner name update");
+        assert_noop!(dispatch(BOB, names::Call::update(b"x".to_vec(), b"bad".to_vec())),
+                     "operation violates name policy");
+
+        assert_eq!(Names::lookup(b"foo".to_vec()), Some(NameData::<Runtime> {
+            value: b"y".to_vec(),
+            owner: ALICE,
+            expiration: Some(1 + SHORT_EXPIRATION),
+            nonce: 1,
+            held_since: 1,
+        }));
+        assert_eq!(Names::lookup(b"longname".to_vec()), Some(NameData::<Runtime> {
+            value: b"z".to_vec(),
+            owner: BOB,
+            expiration: None,
+            nonce: 0,
+            held_since: 1,
+        }));
+
+        assert_eq!(Balances::free_balance(&ALICE), 890);
+        assert_eq!(Balances::free_balance(&BOB), 900);
+        assert_eq!(Balances::total_issuance(), 1_790);
+    });
+}
+
+#[test]
+fn expiration_across_blocks() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), b"x".to_vec())));
+        assert_ok!(dispatch(ALICE, names::Call::update(b"bar".to_vec(), b"x".to_vec())));
+
+        /* Renew one of the names shortly before it would expire.  */
+        run_to_block(SHORT_EXPIRATION);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"bar".to_vec(), b"y".to_vec())));
+
+        /* The other name expires exactly at its expiration height.  */
+        assert!(Names::lookup(b"foo".to_vec()).is_some());
+        run_to_block(1 + SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"foo".to_vec()), None);
+        assert!(Names::lookup(b"bar".to_vec()).is_some());
+
+        /* Once expired, the name can be registered by someone else.  */
+        assert_ok!(dispatch(BOB, names::Call::update(b"foo".to_vec(), b"z".to_vec())));
+        assert_eq!(Names::lookup(b"foo".to_vec()).unwrap().owner, BOB);
+
+        /* The renewed name expires later.  */
+        run_to_block(2 * SHORT_EXPIRATION - 1);
+        assert!(Names::lookup(b"bar".to_vec()).is_some());
+        run_to_block(2 * SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"bar".to_vec()), None);
+
+        assert!(name_events().contains(&names::RawEvent::NameExpired(b"foo".to_vec())));
+        assert!(name_events().contains(&names::RawEvent::NameExpired(b"bar".to_vec())));
+    });
+}
+
+#[test]
+fn transfer_and_expiration_events() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(dispatch(ALICE, names::Call::transfer(b"foo".to_vec(), BOB)));
+        assert_noop!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), b"x".to_vec())),
+                     "non-owner name update");
+        assert_ok!(dispatch(BOB, names::Call::update(b"foo".to_vec(), b"x".to_vec())));
+        run_to_block(1 + SHORT_EXPIRATION);
+
+        let data_bob = |value: &[u8], nonce: u64| NameData::<Runtime> {
+            value: value.to_vec(),
+            owner: BOB,
+            expiration: Some(1 + SHORT_EXPIRATION),
+            nonce: nonce,
+            held_since: 1,
+        };
+        assert_eq!(name_events(), vec![
+            names::RawEvent::NameRegistered(b"foo".to_vec()),
+            names::RawEvent::NameUpdated(b"foo".to_vec(), data_bob(b"", 0)),
+            names::RawEvent::NameUpdated(b"foo".to_vec(), data_bob(b"x", 1)),
+            names::RawEvent::NameExpired(b"foo".to_vec()),
+            names::RawEvent::ExpirationsProcessed(1, 0, 0),
+        ]);
+    });
+}
+
+#[test]
+fn expiration_limit_across_blocks() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        for name in &[b"a", b"b", b"c"] {
+            assert_ok!(dispatch(ALICE, names::Call::update(name.to_vec(), b"x".to_vec())));
+        }
+        run_to_block(2);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"d".to_vec(), b"x".to_vec())));
+
+        /* The renewal leaves a stale index entry, which still counts
+           against the limit of two entries per block.  */
+        run_to_block(SHORT_EXPIRATION);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"b".to_vec(), b"y".to_vec())));
+        run_to_block(1 + SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"a".to_vec()), None);
+        assert!(Names::lookup(b"b".to_vec()).is_some());
+        assert!(Names::lookup(b"c".to_vec()).is_some());
+        assert_eq!(name_events().last(), Some(&names::RawEvent::ExpirationsProcessed(1, 1, 1)));
+
+        /* The next block only processes its own entries.  */
+        run_to_block(2 + SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"d".to_vec()), None);
+        assert!(Names::lookup(b"c".to_vec()).is_some());
+        assert_eq!(name_events().last(), Some(&names::RawEvent::ExpirationsProcessed(1, 0, 0)));
+
+        run_to_block(2 * SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"b".to_vec()), None);
+    });
+}
+
+#[test]
+fn sweeping_left_over_expirations() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        for name in &[b"a", b"b", b"c"] {
+            assert_ok!(dispatch(ALICE, names::Call::update(name.to_vec(), b"x".to_vec())));
+        }
+
+        /* Only two of the names expire automatically.  */
+        run_to_block(1 + SHORT_EXPIRATION);
+        assert_eq!(Names::lookup(b"a".to_vec()), None);
+        assert_eq!(Names::lookup(b"b".to_vec()), None);
+        assert!(Names::lookup(b"c".to_vec()).is_some());
+        assert!(name_events().contains(&names::RawEvent::ExpirationsProcessed(2, 0, 1)));
+
+        /* The last one stays until someone sweeps it.  */
+        run_to_block(5 + SHORT_EXPIRATION);
+        assert!(Names::lookup(b"c".to_vec()).is_some());
+        assert_noop!(dispatch(BOB, names::Call::sweep_expirations(5 + SHORT_EXPIRATION, 1)),
+                     "can only sweep past blocks");
+        Balances::make_free_balance_be(&Names::sweep_pot(), 100);
+        assert_ok!(dispatch(BOB, names::Call::sweep_expirations(1 + SHORT_EXPIRATION, 1)));
+        assert_eq!(Names::lookup(b"c".to_vec()), None);
+        assert_eq!(Balances::free_balance(&BOB), 1_005);
+        assert_eq!(name_events().last(), Some(&names::RawEvent::ExpirationsSwept(
+            BOB, 1 + SHORT_EXPIRATION, 1, 0, 5)));
+
+        /* The name can be registered again right away.  */
+        assert_ok!(dispatch(BOB, names::Call::update(b"c".to_vec(), b"y".to_vec())));
+        assert_eq!(Names::lookup(b"c".to_vec()).unwrap().owner, BOB);
+    });
+}
+
+/// Example of how a runtime can define proxy types based on the call
+/// classification helpers of the names module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ProxyType {
+    /// Can do everything the account itself can.
+    Any,
+    /// Can perform any name operation, including transfers.
+    NameOwner,
+    /// Can update values of names, but not transfer them.
+    NameManager,
+}
+
+impl ProxyType {
+    fn filter(&self, c: &Call) -> bool {
+        match (self, c) {
+            (ProxyType::Any, _) => true,
+            (ProxyType::NameOwner, Call::Names(nc)) => nc.is_name_management_call(),
+            (ProxyType::NameManager, Call::Names(nc)) => nc.is_value_update_call(),
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn proxy_type_filter() {
+    let update = Call::Names(names::Call::update(b"foo".to_vec(), b"x".to_vec()));
+    let transfer = Call::Names(names::Call::transfer(b"foo".to_vec(), BOB));
+    let balance = Call::Balances(balances::Call::transfer(BOB, 10));
+
+    assert!(ProxyType::Any.filter(&update));
+    assert!(ProxyType::Any.filter(&transfer));
+    assert!(ProxyType::Any.filter(&balance));
+
+    assert!(ProxyType::NameOwner.filter(&update));
+    assert!(ProxyType::NameOwner.filter(&transfer));
+    assert!(!ProxyType::NameOwner.filter(&balance));
+
+    assert!(ProxyType::NameManager.filter(&update));
+    assert!(!ProxyType::NameManager.filter(&transfer));
+    assert!(!ProxyType::NameManager.filter(&balance));
+}
+
+#[test]
+fn short_name_premium() {
+    type Premium = ShortNamePremium<PremiumBase, PremiumFactor, PremiumThreshold, UpdateFee>;
+    let fee = |name: &[u8]| {
+        let op = Names::unchecked_operation(name.to_vec(), vec![], ALICE);
+        <Premium as FeePolicy<Runtime>>::get_name_fee(&op)
+    };
+
+    new_test_ext().execute_with(|| {
+        assert_eq!(fee(b""), None);
+        assert_eq!(fee(b"a"), Some(1_000_000));
+        assert_eq!(fee(b"abcd"), Some(1_000));
+        assert_eq!(fee(b"abcde"), Some(100));
+        assert_eq!(fee(b"abcdefghij"), Some(100));
+
+        assert_ok!(dispatch(ALICE, names::Call::update(b"ab".to_vec(), vec![])));
+        assert_eq!(fee(b"ab"), Some(10));
+    });
+}
+
+#[test]
+fn name_class_pricing() {
+    type Pricing = ByNameClass<Utf8Classifier, LengthTiered<RegistrationFees, UpdateFee>,
+                               ClassMultipliers>;
+    let fee = |name: &[u8]| {
+        let op = Names::unchecked_operation(name.to_vec(), vec![], ALICE);
+        <Pricing as FeePolicy<Runtime>>::get_name_fee(&op)
+    };
+
+    new_test_ext().execute_with(|| {
+        assert_eq!(fee(b"x"), None);
+        assert_eq!(fee(b"domob"), Some(100));
+        assert_eq!(fee("café".as_bytes()), Some(100));
+        assert_eq!(fee("🚀".as_bytes()), Some(1_000));
+        assert_eq!(fee(&[0xff, 0xfe]), None);
+
+        assert_ok!(dispatch(ALICE, names::Call::update("🚀".as_bytes().to_vec(), vec![])));
+        assert_eq!(fee("🚀".as_bytes()), Some(10));
+    });
+}
+
+#[test]
+fn migration_of_oversized_names() {
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        let long_value = b"0123456789abc".to_vec();
+        assert_ok!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), b"x".to_vec())));
+        assert_ok!(dispatch(ALICE, names::Call::update(b"bar".to_vec(), long_value.clone())));
+        assert_ok!(dispatch(BOB, names::Call::update(b"verylongname".to_vec(), b"z".to_vec())));
+
+        let queue = names::Call::queue_migration(vec![
+            b"foo".to_vec(), b"bar".to_vec(), b"verylongname".to_vec(),
+        ]);
+        assert_ok!(Call::Names(queue).dispatch(Origin::ROOT));
+        run_to_block(2);
+
+        assert_eq!(Names::lookup(b"foo".to_vec()).unwrap().value, b"x".to_vec());
+        assert_eq!(Names::lookup(b"bar".to_vec()).unwrap().value, b"0123456789".to_vec());
+        assert!(name_events().contains(
+            &names::RawEvent::ValueTruncated(b"bar".to_vec(), long_value)));
+
+        /* Names that are too long themselves are kept for governance.  */
+        assert_eq!(Names::lookup(b"verylongname".to_vec()).unwrap().owner, BOB);
+        assert!(name_events().contains(
+            &names::RawEvent::OversizedName(b"verylongname".to_vec())));
+        assert!(name_events().contains(&names::RawEvent::NamesMigrated(3, 0)));
+    });
+}
+
+#[test]
+fn xaya_rpc_format() {
+    use names::xaya::{NameShow, NameUpdate};
+
+    new_test_ext().execute_with(|| {
+        run_to_block(1);
+        assert_ok!(dispatch(ALICE, names::Call::update(b"foo".to_vec(), vec![0xff, 0x00])));
+        assert_ok!(dispatch(ALICE, names::Call::update(b"longname".to_vec(), b"{}".to_vec())));
+        run_to_block(3);
+
+        assert_eq!(NameShow::lookup::<Runtime>(&b"foo".to_vec()), Some(NameShow {
+            name: "foo".to_string(),
+            name_encoding: "utf8".to_string(),
+            value: "ff00".to_string(),
+            value_encoding: "hex".to_string(),
+            address: "1".to_string(),
+            expires_in: Some(SHORT_EXPIRATION as i64 - 2),
+        }));
+        assert_eq!(NameShow::lookup::<Runtime>(&b"longname".to_vec()).unwrap().expires_in,
+                   None);
+        assert_eq!(NameShow::lookup::<Runtime>(&b"missing".to_vec()), None);
+
+        let update = NameUpdate {
+            name: "6c6f6e676e616d65".to_string(),
+            name_encoding: Some("hex".to_string()),
+            value: "{\"x\":1}".to_string(),
+            value_encoding: None,
+            dest_address: Some("2".to_string()),
+        };
+        let op = update.check::<Runtime, _>(ALICE, |addr| addr.parse().ok()).unwrap();
+        assert_ok!(Names::execute(op));
+        let data = Names::lookup(b"longname".to_vec()).unwrap();
+        assert_eq!(data.owner, BOB);
+        assert_eq!(data.value, b"{\"x\":1}".to_vec());
+
+        let bad = NameUpdate {
+            dest_address: Some("nobody".to_string()),
+            ..update
+        };
+        assert_eq!(bad.check::<Runtime, _>(BOB, |addr| addr.parse().ok()).err(),
+                   Some("invalid destination address: nobody".to_string()));
+    });
+}
+
+/// File with the golden SCALE encodings that downstream indexers and wallets
+/// rely on.  It can be regenerated by running the tests with the
+/// `NAMES_UPDATE_GOLDEN` environment variable set, which should only be
+/// done for intended (and announced) changes to the layout.
+const GOLDEN_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/encodings.txt");
+
+/// Encodes a value for the golden file, making sure that it also decodes
+/// back to the same value.
+fn golden<V>(label: &str, value: &V) -> (String, String)
+    where V: codec::Encode + codec::Decode + PartialEq + std::fmt::Debug
+{
+    let encoded = value.encode();
+    assert_eq!(V::decode(&mut &encoded[..]).ok().as_ref(), Some(value),
+               "{} does not decode to the encoded value", label);
+    (label.to_string(), format!("{}", sp_core::hexdisplay::HexDisplay::from(&encoded)))
+}
+
+/// Returns the encodings of fixed name data, events and calls.
+fn golden_vectors() -> Vec<(String, String)> {
+    let data = NameData::<Runtime> {
+        value: b"val".to_vec(),
+        owner: ALICE,
+        expiration: Some(100),
+        nonce: 3,
+        held_since: 7,
+    };
+    let data_no_expiration = NameData::<Runtime> {
+        value: vec![],
+        owner: BOB,
+        expiration: None,
+        nonce: 0,
+        held_since: 0,
+    };
+    let name = b"foo".to_vec();
+
+    vec![
+        golden("name_data", &data),
+        golden("name_data_no_expiration", &data_no_expiration),
+        golden("event_name_registered",
+               &Event::names(names::RawEvent::NameRegistered(name.clone()))),
+        golden("event_name_updated",
+               &Event::names(names::RawEvent::NameUpdated(name.clone(), data.clone()))),
+        golden("event_name_transferred",
+               &Event::names(names::RawEvent::NameTransferred(name.clone(), ALICE, BOB))),
+        golden("call_update",
+               &Call::Names(names::Call::update(name.clone(), b"val".to_vec()))),
+        golden("call_register",
+               &Call::Names(names::Call::register(name.clone(), b"val".to_vec(), Some(50)))),
+    ]
+}
+
+#[test]
+fn golden_encodings() {
+    let vectors = golden_vectors();
+
+    if std::env::var_os("NAMES_UPDATE_GOLDEN").is_some() {
+        let mut content = String::from(
+            "# SCALE encodings checked by the golden_encodings test in runtime.rs.\n\
+             # Regenerate with NAMES_UPDATE_GOLDEN=1 only for intended layout changes.\n");
+        for (label, hex) in &vectors {
+            content.push_str(&format!("{} {}\n", label, hex));
+        }
+        std::fs::write(GOLDEN_FILE, content).unwrap();
+        return;
+    }
+
+    let expected: Vec<(String, String)> = std::fs::read_to_string(GOLDEN_FILE).unwrap()
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.splitn(2, ' ');
+            let label = parts.next().unwrap().to_string();
+            let hex = parts.next().unwrap_or("").to_string();
+            (label, hex)
+        })
+        .collect();
+
+    for (label, hex) in &vectors {
+        let golden = expected.iter().find(|(l, _)| l == label)
+            .unwrap_or_else(|| panic!("{} is missing from the golden file", label));
+        assert_eq!(&golden.1, hex, "encoding of {} has changed", label);
+    }
+    assert_eq!(expected.len(), vectors.len(), "golden file has unchecked entries");
+}
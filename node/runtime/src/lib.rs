@@ -9,13 +9,15 @@
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
 use sp_std::prelude::*;
+use codec::{Decode, Encode};
 use sp_core::OpaqueMetadata;
 use sp_runtime::{
     ApplyExtrinsicResult, transaction_validity::TransactionValidity, generic, create_runtime_str,
-    impl_opaque_keys, MultiSignature
+    impl_opaque_keys, MultiSignature, ModuleId
 };
 use sp_runtime::traits::{
-    NumberFor, BlakeTwo256, Block as BlockT, StaticLookup, Verify, ConvertInto, IdentifyAccount
+    NumberFor, BlakeTwo256, Block as BlockT, StaticLookup, Verify, ConvertInto, IdentifyAccount,
+    AccountIdConversion
 };
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -201,7 +203,7 @@ impl balances::Trait for Runtime {
     /// The type for recording an account's balance.
     type Balance = Balance;
     /// What to do if an account's free balance gets zeroed.
-    type OnFreeBalanceZero = ();
+    type OnFreeBalanceZero = Names;
     /// What to do if a new account is created.
     type OnNewAccount = Indices;
     /// The ubiquitous event type.
@@ -232,41 +234,223 @@ impl sudo::Trait for Runtime {
     type Proposal = Call;
 }
 
+parameter_types! {
+    pub const HiddenRegistrationFee: Balance = 100;
+    pub const HiddenRegistrationPeriod: BlockNumber = HOURS;
+    // Bulk transfers are weighed for this many names, which must fit into a block.
+    pub const MaxBulkTransfer: u32 = 20;
+    pub const MaxSwapNames: u32 = 20;
+    pub const MaxForceRegistrations: u32 = 1000;
+    pub const StatsEraLength: BlockNumber = DAYS;
+    pub const StatsHistory: u32 = 30;
+    pub const FreeUpdatesPerEra: u32 = 0;
+    pub const MaxTemplates: u32 = 10;
+    pub const ReceiptRetention: BlockNumber = 7 * DAYS;
+    pub const MaxPayoutSplits: u32 = 10;
+    pub const MaxShareHolders: u32 = 20;
+    pub const MigrationWeightLimit: Weight = 100_000_000;
+    pub const DeferredWeightLimit: Weight = 1_000_000;
+    pub const ExpirationBucketSize: BlockNumber = 1;
+    pub const MaxValueLookahead: BlockNumber = DAYS;
+    pub const MaxExpirationsPerBlock: u32 = 1000;
+    pub const MaxAutoExpirations: Option<u32> = None;
+    pub const SweepBounty: Balance = 1;
+    pub const StrictTransfers: bool = false;
+    pub const ValueDepositPerByte: Balance = 1;
+    pub const MinExpirationBlocks: BlockNumber = 10;
+    pub const ExpirationUpdates: names::ExpirationUpdate = names::ExpirationUpdate::MayShorten;
+    pub const ComplianceWindow: Option<BlockNumber> = None;
+    pub const NonComplianceAction: names::ComplianceAction = names::ComplianceAction::Warn;
+    pub const WatchDeposit: Balance = 10;
+    pub const MaxWatchers: u32 = 16;
+    pub const MaxSubvalues: u32 = 32;
+    pub const SubvalueDeposit: Balance = 100;
+    pub const NoteDeposit: Balance = 100;
+    pub const MaxNoteSize: u32 = 256;
+    pub const MaxRedirectDepth: u32 = 4;
+    pub const EnvelopeFee: Balance = 10;
+    pub const MaxEnvelopeSize: u32 = 1024;
+    pub const MaxEnvelopes: u32 = 20;
+    pub const MinPreorderDeposit: Balance = 1000;
+    pub const PreorderMaturity: BlockNumber = MINUTES;
+    pub const PreorderWindow: BlockNumber = HOURS;
+    pub const PreorderForfeit: Perbill = Perbill::from_percent(20);
+    pub const FirstUpdatePeriod: Option<BlockNumber> = None;
+    pub const FirstUpdateRefund: Perbill = Perbill::from_percent(50);
+    pub const ExpirationQuarantine: BlockNumber = 1;
+    pub const RegistrarCommission: Perbill = Perbill::from_percent(5);
+    pub const MinBackorderBond: Balance = 1000;
+    pub const NamesModuleId: ModuleId = ModuleId(*b"py/names");
+    pub const MaxFeeRateAge: BlockNumber = 1 * HOURS;
+    pub const ReportBond: Balance = 10_000;
+    pub const ReportChallengePeriod: BlockNumber = 2 * DAYS;
+    pub const ArchiveRetention: Option<BlockNumber> = Some(90 * DAYS);
+    pub const GuardianConfirmationPeriod: BlockNumber = DAYS;
+    pub const MaxBackorders: u32 = 20;
+    pub const MaxBulkRenewals: u32 = 100;
+    pub const MaxTopicPrefixes: u32 = 100;
+    pub const MaxDelegatedNamespaces: u32 = 10;
+    pub const MaxSessionDuration: BlockNumber = 1 * DAYS;
+    pub const MaxSessionNames: u32 = 16;
+    pub const MaxInsuredRenewals: u32 = 5;
+    pub const RegistrationIntentsOnly: bool = false;
+    pub const IntentBond: Balance = 100;
+    pub const MaxIntentsPerName: u32 = 16;
+    pub const MaxTotalNames: Option<u32> = None;
+    pub const MaxRegistrationsPerBlock: Option<u32> = Some(50);
+    pub const CongestionMultipliers: &'static [(u32, u32)] = &[(1_000_000, 200), (10_000_000, 1000)];
+    /// Single-letter names are not allowed (nor the empty name).  Everything
+    /// else costs the same.  Name fees are burnt.
+    pub const NameRegistrationFees: &'static [(usize, Balance)] = &[(2, 1000)];
+    pub const NameUpdateFee: Balance = 100;
+    /// Names of reaped accounts expire after one day.
+    pub const ReapedNameExpiration: BlockNumber = DAYS;
+}
+
 impl names::Trait for Runtime {
 
     type Name = Vec<u8>;
     type Value = Vec<u8>;
+    type DefaultValue = ();
 
     type Currency = balances::Module<Self>;
     type Event = Event;
 
-    fn get_name_fee(op: &names::Operation<Self>) -> Option<Balance> {
-        /* Single-letter names are not allowed (nor the empty name).  Everything
-           else is fine.  */
-        if op.name.len() < 2 {
-            return None
-        }
+    type Namehash = names::namehash::EnsNamehash;
 
-        Some(match op.operation {
-            names::OperationType::Registration => 1000,
-            names::OperationType::Update => 100,
-        })
-    }
+    type HiddenRegistrationFee = HiddenRegistrationFee;
+    type HiddenRegistrationPeriod = HiddenRegistrationPeriod;
+
+    type Identity = ();
+    type ForceOrigin = system::EnsureRoot<AccountId>;
+    type ReportOrigin = system::EnsureRoot<AccountId>;
+    type Namespaces = names::namespace::BytePrefix;
+    type CommunityAccount = CommunityAccount;
+    type ModuleId = NamesModuleId;
+    type Call = Call;
+    type NameCallFilter = NameCallFilter;
+    type MaxShareHolders = MaxShareHolders;
+    type MaxPayoutSplits = MaxPayoutSplits;
+    type MaxBulkTransfer = MaxBulkTransfer;
+    type MaxSwapNames = MaxSwapNames;
+    type MaxForceRegistrations = MaxForceRegistrations;
+    type MaxTotalNames = MaxTotalNames;
+    type MaxRegistrationsPerBlock = MaxRegistrationsPerBlock;
+    type CongestionMultipliers = CongestionMultipliers;
+    type Randomness = RandomnessCollectiveFlip;
+    type StrictTransfers = StrictTransfers;
+    type RegistrationIntentsOnly = RegistrationIntentsOnly;
+    type IntentBond = IntentBond;
+    type MaxIntentsPerName = MaxIntentsPerName;
+
+    type MinPreorderDeposit = MinPreorderDeposit;
+    type PreorderMaturity = PreorderMaturity;
+    type PreorderWindow = PreorderWindow;
+    type PreorderForfeit = PreorderForfeit;
+    type FirstUpdatePeriod = FirstUpdatePeriod;
+    type FirstUpdateRefund = FirstUpdateRefund;
+    type ExpirationQuarantine = ExpirationQuarantine;
+    type RegistrarCommission = RegistrarCommission;
+    type MinBackorderBond = MinBackorderBond;
+    type ReportBond = ReportBond;
+    type ReportChallengePeriod = ReportChallengePeriod;
+    type ArchiveRetention = ArchiveRetention;
+    type GuardianConfirmationPeriod = GuardianConfirmationPeriod;
+    type MaxBackorders = MaxBackorders;
+    type MaxBulkRenewals = MaxBulkRenewals;
+    type MaxTopicPrefixes = MaxTopicPrefixes;
+    type MaxDelegatedNamespaces = MaxDelegatedNamespaces;
+    type MaxSessionDuration = MaxSessionDuration;
+    type MaxSessionNames = MaxSessionNames;
+    type MaxInsuredRenewals = MaxInsuredRenewals;
+
+    type Policy = names::policy::Composed<
+        names::policy::LengthTiered<NameRegistrationFees, NameUpdateFee>,
+        ShortNamesExpire,
+        (),
+    >;
+    type ExtraWeight = ();
+    type WeightInfo = names::weights::ReferenceWeights<Runtime>;
+    type FeeConverter = ();
+    type Payment = ();
+    type MaxFeeRateAge = MaxFeeRateAge;
+    type MinExpirationBlocks = MinExpirationBlocks;
+    type ExpirationUpdates = ExpirationUpdates;
+    type ComplianceWindow = ComplianceWindow;
+    type NonComplianceAction = NonComplianceAction;
+    type MaxExpirationsPerBlock = MaxExpirationsPerBlock;
+    type MaxAutoExpirations = MaxAutoExpirations;
+    type SweepBounty = SweepBounty;
+    type ExpirationBucketSize = ExpirationBucketSize;
+    type MaxValueLookahead = MaxValueLookahead;
+    type ValueDepositPerByte = ValueDepositPerByte;
+    type OnOwnerReaped = names::policy::ExpireAfter<ReapedNameExpiration>;
+    type OnExpiration = ();
+    type ValueCompliance = ();
+    type OnNameOperation = ();
+    type Signature = Signature;
+    type Signer = <Signature as Verify>::Signer;
+    type Patch = ();
+    type SubKey = Vec<u8>;
+    type MaxSubvalues = MaxSubvalues;
+    type SubvalueDeposit = SubvalueDeposit;
+    type NoteDeposit = NoteDeposit;
+    type MaxNoteSize = MaxNoteSize;
+    type MaxRedirectDepth = MaxRedirectDepth;
+    type EnvelopeFee = EnvelopeFee;
+    type MaxEnvelopeSize = MaxEnvelopeSize;
+    type MaxEnvelopes = MaxEnvelopes;
+    type WatchDeposit = WatchDeposit;
+    type MaxWatchers = MaxWatchers;
+    type Templates = names::template::ExactValue;
+    type MaxTemplates = MaxTemplates;
+    type Migration = ();
+    type MigrationWeightLimit = MigrationWeightLimit;
+    type DeferredWeightLimit = DeferredWeightLimit;
+    type ReceiptRetention = ReceiptRetention;
+    type StatsEraLength = StatsEraLength;
+    type StatsHistory = StatsHistory;
+    type FreeUpdatesPerEra = FreeUpdatesPerEra;
 
-    fn get_expiration(op: &names::Operation<Self>) -> Option<BlockNumber> {
-        /* Short names (up to three characters) will expire after 10 blocks.
-           Longer names will stick around forever.  */
+}
+
+/// Expiration policy of the names module:  Short names (up to three
+/// characters) will expire after 10 blocks.  Longer names will stick
+/// around forever.
+pub struct ShortNamesExpire;
+
+impl names::policy::ExpirationPolicy<Runtime> for ShortNamesExpire {
+    fn get_expiration(op: &names::Operation<Runtime>) -> Option<BlockNumber> {
         if op.name.len() <= 3 {
             Some(10)
         } else {
             None
         }
     }
+}
 
-    fn deposit_fee(_b: <Self::Currency as Currency<AccountId>>::NegativeImbalance) {
-        /* Just burn the name fee by dropping the imbalance.  */
+/// Calls that name owners can dispatch from the sovereign account of
+/// their names:  Balance transfers and name operations.
+pub struct NameCallFilter;
+
+impl names::dispatch::CallFilter<Vec<u8>, Call> for NameCallFilter {
+    fn allow_call(_name: &Vec<u8>, call: &Call) -> bool {
+        match call {
+            Call::Balances(_) => true,
+            Call::Names(nc) => nc.is_name_management_call(),
+            _ => false,
+        }
     }
+}
 
+/// The account that receives donated names.  This is the account a
+/// treasury pallet would use, so that it can be added later on.
+pub struct CommunityAccount;
+
+impl frame_support::traits::Get<AccountId> for CommunityAccount {
+    fn get() -> AccountId {
+        ModuleId(*b"py/trsry").into_account()
+    }
 }
 
 construct_runtime!(
@@ -283,7 +467,7 @@ construct_runtime!(
         Balances: balances,
         TransactionPayment: transaction_payment::{Module, Storage},
         Sudo: sudo,
-        Names: names::{Module, Call, Storage, Event<T>},
+        Names: names::{Module, Call, Storage, Event<T>, Config<T>},
         RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
     }
 );
@@ -305,7 +489,8 @@ pub type SignedExtra = (
     system::CheckEra<Runtime>,
     system::CheckNonce<Runtime>,
     system::CheckWeight<Runtime>,
-    transaction_payment::ChargeTransactionPayment<Runtime>
+    transaction_payment::ChargeTransactionPayment<Runtime>,
+    names::LimitRegistrations<Runtime>
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -393,4 +578,91 @@ impl_runtime_apis! {
             Grandpa::grandpa_authorities()
         }
     }
+
+    impl names::runtime_api::NamesApi<Block, Vec<u8>, AccountId, Signature, BlockNumber> for Runtime {
+        fn resolve_namehash(hash: Hash) -> Option<Vec<u8>> {
+            Names::resolve_namehash(hash)
+        }
+
+        fn resolve(name: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), names::ResolveError> {
+            Names::resolve(&name).map(|(name, data)| (name, data.value.encode()))
+        }
+
+        fn resolve_address(name: Vec<u8>) -> Result<AccountId, names::ResolveError> {
+            Names::resolve_address(&name)
+        }
+
+        fn verify_name_signature(name: Vec<u8>, message: Vec<u8>, signature: Signature) -> bool {
+            Names::verify_name_signature(&name, &message, &signature)
+        }
+
+        fn export_names(owner: AccountId) -> Vec<u8> {
+            Names::export_names(&owner).encode()
+        }
+
+        fn next_expiration(name: Vec<u8>) -> Option<BlockNumber> {
+            Names::next_expiration(&name)
+        }
+
+        fn active_at(name: Vec<u8>, block: BlockNumber) -> bool {
+            Names::active_at(&name, block)
+        }
+
+        fn receipts(block: BlockNumber, extrinsic: Option<u32>) -> Vec<u8> {
+            Names::receipts(block, extrinsic).encode()
+        }
+
+        fn attest_ownership(name: Vec<u8>) -> Option<Vec<u8>> {
+            Names::attest_ownership(&name).map(|a| a.encode())
+        }
+
+        fn owner_at(name: Vec<u8>) -> Option<AccountId> {
+            Names::lookup(name).map(|data| data.owner)
+        }
+
+        fn name_storage_key(name: Vec<u8>) -> Vec<u8> {
+            Names::name_storage_key(&name)
+        }
+
+        fn statistics() -> Vec<u8> {
+            Names::statistics().encode()
+        }
+
+        fn records_of(name: Vec<u8>) -> Vec<u8> {
+            Names::records_of(&name).encode()
+        }
+
+        fn record_proof_keys(name: Vec<u8>) -> Vec<Vec<u8>> {
+            Names::record_proof_keys(&name)
+        }
+
+        fn check_operation(sender: AccountId, name: Vec<u8>, value: Option<Vec<u8>>,
+                           recipient: Option<AccountId>) -> Result<(), Vec<u8>> {
+            let value = match value {
+                None => None,
+                Some(v) => match Decode::decode(&mut &v[..]) {
+                    Ok(v) => Some(v),
+                    Err(_) => {
+                        let err = names::OperationError::<Runtime>::Other("invalid value encoding");
+                        return Err(err.encode());
+                    },
+                },
+            };
+            Names::check_detailed(sender.clone(), sender, name, value, recipient)
+                .map(|_| ())
+                .map_err(|e| e.encode())
+        }
+
+        fn availability(name: Vec<u8>) -> names::Availability<BlockNumber> {
+            Names::availability(&name)
+        }
+
+        fn check_available(names: Vec<Vec<u8>>) -> Vec<names::Availability<BlockNumber>> {
+            Names::check_available(&names)
+        }
+
+        fn pending_for(name: Vec<u8>) -> Vec<u8> {
+            Names::pending_for(&name).encode()
+        }
+    }
 }
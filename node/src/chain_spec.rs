@@ -1,7 +1,7 @@
 use sp_core::{Pair, Public, sr25519};
 use node_with_names_runtime::{
 	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig,
-	SudoConfig, IndicesConfig, SystemConfig, WASM_BINARY, Signature
+	SudoConfig, IndicesConfig, NamesConfig, SystemConfig, WASM_BINARY, Signature
 };
 use sp_consensus_aura::sr25519::{AuthorityId as AuraId};
 use grandpa_primitives::{AuthorityId as GrandpaId};
@@ -139,5 +139,11 @@ fn testnet_genesis(initial_authorities: Vec<(AuraId, GrandpaId)>,
 		grandpa: Some(GrandpaConfig {
 			authorities: initial_authorities.iter().map(|x| (x.1.clone(), 1)).collect(),
 		}),
+		names: Some(NamesConfig {
+			names: vec![],
+			system_names: vec![],
+			reserved_prefixes: vec![],
+			topic_prefixes: vec![],
+		}),
 	}
 }